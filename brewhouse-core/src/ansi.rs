@@ -0,0 +1,204 @@
+//! Terminal escape sequences in brew output.
+//!
+//! Brew colors its headings and warnings, and curl and the bottle pourer redraw progress
+//! bars with carriage returns. [`parse`] turns that into plain text split into styled
+//! spans for display; [`strip`] keeps only the text.
+
+/// One of the eight basic terminal colors; the bright variants map to the same color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+}
+
+impl Color {
+    fn from_index(index: u32) -> Option<Color> {
+        Some(match index {
+            0 => Color::Black,
+            1 => Color::Red,
+            2 => Color::Green,
+            3 => Color::Yellow,
+            4 => Color::Blue,
+            5 => Color::Magenta,
+            6 => Color::Cyan,
+            7 => Color::White,
+            _ => return None,
+        })
+    }
+}
+
+/// Text attributes set by SGR sequences (`ESC [ … m`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    pub bold: bool,
+    pub underline: bool,
+    /// Foreground color; None is the default text color
+    pub color: Option<Color>,
+}
+
+impl Style {
+    fn apply_sgr(&mut self, params: &str) {
+        let mut codes = params.split(';').map(|code| code.parse::<u32>().unwrap_or(0));
+        while let Some(code) = codes.next() {
+            match code {
+                0 => *self = Style::default(),
+                1 => self.bold = true,
+                22 => self.bold = false,
+                4 => self.underline = true,
+                24 => self.underline = false,
+                30..=37 => self.color = Color::from_index(code - 30),
+                90..=97 => self.color = Color::from_index(code - 90),
+                39 => self.color = None,
+                // 256-color and true-color foregrounds and backgrounds: skip their arguments
+                38 | 48 => match codes.next() {
+                    Some(5) => {
+                        codes.next();
+                    }
+                    Some(2) => {
+                        codes.nth(2);
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+    }
+}
+
+/// A run of text in one style
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub style: Style,
+}
+
+/// Split `text` into styled spans, dropping every escape sequence and control character
+/// except newlines and tabs. A line redrawn with carriage returns keeps only its last version.
+pub fn parse(text: &str) -> Vec<Span> {
+    let mut spans: Vec<Span> = Vec::new();
+    let mut style = Style::default();
+    let mut push = |text: &str, style: Style| match spans.last_mut() {
+        Some(last) if last.style == style => last.text.push_str(text),
+        _ if text.is_empty() => {}
+        _ => spans.push(Span { text: text.to_string(), style }),
+    };
+
+    let mut lines = text.split('\n').peekable();
+    while let Some(line) = lines.next() {
+        let line = last_redraw(line);
+        let mut chars = line.char_indices().peekable();
+        let mut plain_start = 0;
+        while let Some((index, c)) = chars.next() {
+            if c != '\u{1b}' && (!c.is_control() || c == '\t') {
+                continue;
+            }
+            push(&line[plain_start..index], style);
+            if c == '\u{1b}' {
+                match chars.next() {
+                    // CSI: parameters up to a final byte in @..~; only SGR changes the style
+                    Some((params_start, '[')) => {
+                        let params_start = params_start + 1;
+                        let mut params_end = line.len();
+                        let mut final_byte = None;
+                        for (index, c) in chars.by_ref() {
+                            if ('@'..='~').contains(&c) {
+                                params_end = index;
+                                final_byte = Some(c);
+                                break;
+                            }
+                        }
+                        if final_byte == Some('m') {
+                            style.apply_sgr(&line[params_start..params_end]);
+                        }
+                    }
+                    // OSC (e.g. hyperlinks): up to BEL or ESC \
+                    Some((_, ']')) => {
+                        while let Some((_, c)) = chars.next() {
+                            if c == '\u{7}' {
+                                break;
+                            }
+                            if c == '\u{1b}' && chars.peek().is_some_and(|(_, c)| *c == '\\') {
+                                chars.next();
+                                break;
+                            }
+                        }
+                    }
+                    // Two-character sequences such as ESC 7
+                    _ => {}
+                }
+            }
+            plain_start = chars.peek().map_or(line.len(), |(index, _)| *index);
+        }
+        push(&line[plain_start..], style);
+        if lines.peek().is_some() {
+            push("\n", style);
+        }
+    }
+    spans
+}
+
+/// `text` without escape sequences, control characters or overwritten progress lines
+pub fn strip(text: &str) -> String {
+    parse(text).into_iter().map(|span| span.text).collect()
+}
+
+/// What is left of a line after the terminal has drawn it: the part after its last carriage
+/// return, ignoring one that only ends the line
+fn last_redraw(line: &str) -> &str {
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    line.rsplit('\r').next().unwrap_or(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(text: &str, style: Style) -> Span {
+        Span { text: text.to_string(), style }
+    }
+
+    #[test]
+    fn test_plain_text_is_one_span() {
+        assert_eq!(parse("==> Pouring wget\n"), vec![span("==> Pouring wget\n", Style::default())]);
+        assert!(parse("").is_empty());
+    }
+
+    #[test]
+    fn test_sgr_styles() {
+        let bold = Style { bold: true, ..Default::default() };
+        let blue = Style { color: Some(Color::Blue), ..Default::default() };
+        assert_eq!(
+            parse("\u{1b}[34m==>\u{1b}[0m \u{1b}[1mPouring\u{1b}[0m wget"),
+            vec![
+                span("==>", blue),
+                span(" ", Style::default()),
+                span("Pouring", bold),
+                span(" wget", Style::default()),
+            ]
+        );
+
+        // Bright colors, combined codes and skipped 256-color arguments
+        let spans = parse("\u{1b}[1;91mError:\u{1b}[38;5;196;4m x");
+        let bold_red = Style { bold: true, color: Some(Color::Red), underline: false };
+        assert_eq!(spans, vec![span("Error:", bold_red), span(" x", Style { underline: true, ..bold_red })]);
+    }
+
+    #[test]
+    fn test_strip() {
+        assert_eq!(strip("\u{1b}[4mhttps://brew.sh\u{1b}[24m\n"), "https://brew.sh\n");
+        // Cursor movement and OSC hyperlinks are dropped, keeping the link text
+        assert_eq!(strip("a\u{1b}[2Kb\u{1b}]8;;https://brew.sh\u{7}link\u{1b}]8;;\u{1b}\\"), "ablink");
+        assert_eq!(strip("tab\there\u{7}"), "tab\there");
+    }
+
+    #[test]
+    fn test_progress_redraws_keep_last() {
+        assert_eq!(strip("#   10%\r### 30%\r#####100.0%\nDone\r\n"), "#####100.0%\nDone\n");
+    }
+}
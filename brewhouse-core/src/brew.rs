@@ -0,0 +1,5201 @@
+use crate::metadata_cache;
+use crate::runner::{self, BrewRunner};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Package {
+    pub name: String,
+    pub version: Option<String>,
+    pub desc: Option<String>,
+    pub homepage: Option<String>,
+    pub installed: bool,
+    /// Version currently symlinked into the Homebrew prefix, if any
+    #[serde(default)]
+    pub linked_keg: Option<String>,
+    /// Keg-only formulae are not linked by default and need `--force` to link
+    #[serde(default)]
+    pub keg_only: bool,
+    /// Every installed keg of this formula, in the order brew reports them
+    #[serde(default)]
+    pub installed_versions: Vec<String>,
+    /// True for casks (GUI apps, fonts, drivers) as opposed to formulae
+    #[serde(default)]
+    pub cask: bool,
+    /// Unix time the newest keg or the cask was installed, which an upgrade also sets
+    #[serde(default)]
+    pub installed_time: Option<i64>,
+    /// Tap the package comes from, e.g. homebrew/core
+    #[serde(default)]
+    pub tap: Option<String>,
+}
+
+impl Package {
+    /// Human-readable link state for the details pane
+    pub fn link_state_label(&self) -> String {
+        match (&self.linked_keg, self.keg_only) {
+            (Some(version), _) => format!("Linked: {}", version),
+            (None, true) => "Not linked (keg-only)".to_string(),
+            (None, false) => "Not linked".to_string(),
+        }
+    }
+
+    /// The keg in use: the linked one, or otherwise the newest installed
+    pub fn current_version(&self) -> Option<&str> {
+        self.linked_keg
+            .as_deref()
+            .filter(|linked| self.installed_versions.iter().any(|v| v == linked))
+            .or_else(|| self.installed_versions.last().map(String::as_str))
+    }
+
+    /// Installed kegs other than the current one
+    pub fn old_versions(&self) -> Vec<&str> {
+        let current = self.current_version();
+        self.installed_versions
+            .iter()
+            .map(String::as_str)
+            .filter(|v| Some(*v) != current)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BrewInfoFormula {
+    pub name: String,
+    pub full_name: Option<String>,
+    pub tap: Option<String>,
+    pub oldname: Option<String>,
+    /// Names the formula had before being renamed; newer brew lists them all here
+    pub oldnames: Option<Vec<String>>,
+    pub aliases: Option<Vec<String>>,
+    pub versioned_formulae: Option<Vec<String>>,
+    pub desc: Option<String>,
+    pub license: Option<String>,
+    pub homepage: Option<String>,
+    #[serde(default)]
+    pub versions: BrewVersions,
+    pub urls: Option<BrewUrls>,
+    pub revision: Option<i32>,
+    pub version_scheme: Option<i32>,
+    pub bottle: Option<serde_json::Value>,
+    pub keg_only: Option<bool>,
+    pub keg_only_reason: Option<serde_json::Value>,
+    pub options: Option<Vec<serde_json::Value>>,
+    pub build_dependencies: Option<Vec<String>>,
+    pub dependencies: Option<Vec<String>>,
+    pub test_dependencies: Option<Vec<String>>,
+    pub recommended_dependencies: Option<Vec<String>>,
+    pub optional_dependencies: Option<Vec<String>>,
+    pub uses_from_macos: Option<Vec<serde_json::Value>>,
+    pub requirements: Option<Vec<serde_json::Value>>,
+    pub conflicts_with: Option<Vec<String>>,
+    pub conflicts_with_reasons: Option<Vec<String>>,
+    pub link_overwrite: Option<Vec<String>>,
+    pub caveats: Option<String>,
+    #[serde(default, deserialize_with = "lenient_vec_option")]
+    pub installed: Option<Vec<BrewInstalled>>,
+    pub linked_keg: Option<String>,
+    pub pinned: Option<bool>,
+    pub outdated: Option<bool>,
+    pub deprecated: Option<bool>,
+    pub deprecation_date: Option<String>,
+    pub deprecation_reason: Option<String>,
+    pub disabled: Option<bool>,
+    pub disable_date: Option<String>,
+    pub disable_reason: Option<String>,
+    pub analytics: Option<serde_json::Value>,
+}
+
+impl BrewInfoFormula {
+    /// Version to show: the stable one, or for HEAD-only formulae the head
+    /// version or newest installed keg
+    pub fn display_version(&self) -> Option<&str> {
+        self.versions
+            .stable
+            .as_deref()
+            .or(self.versions.head.as_deref())
+            .or_else(|| self.installed.as_ref()?.last().map(|keg| keg.version.as_str()))
+    }
+
+    /// Aliases and former names that also find this formula, e.g. "python3" for python@3.13
+    pub fn other_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        let candidates = self.aliases.iter().flatten().chain(self.oldnames.iter().flatten()).chain(&self.oldname);
+        for name in candidates {
+            if *name != self.name && !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+        names
+    }
+
+    /// Build options declared by the formula (e.g. `--with-openssl`)
+    pub fn install_options(&self) -> Vec<FormulaOption> {
+        self.options
+            .iter()
+            .flatten()
+            .filter_map(|value| serde_json::from_value(value.clone()).ok())
+            .collect()
+    }
+}
+
+/// One entry of the `options` array in `brew info`
+#[derive(Debug, Clone, Deserialize)]
+pub struct FormulaOption {
+    pub option: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct BrewVersions {
+    /// None for HEAD-only formulae
+    pub stable: Option<String>,
+    pub head: Option<String>,
+    pub bottle: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BrewUrls {
+    pub stable: Option<BrewUrl>,
+    pub head: Option<BrewUrl>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BrewUrl {
+    #[serde(default)]
+    pub url: String,
+    pub tag: Option<String>,
+    pub revision: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BrewInstalled {
+    pub version: String,
+    pub used_options: Vec<String>,
+    pub built_as_bottle: bool,
+    pub poured_from_bottle: bool,
+    pub time: Option<i64>,
+    pub runtime_dependencies: Option<Vec<serde_json::Value>>,
+    pub installed_as_dependency: bool,
+    pub installed_on_request: bool,
+}
+
+/// One entry of the `casks` array in `brew info --json=v2`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BrewInfoCask {
+    pub token: String,
+    pub full_token: Option<String>,
+    pub tap: Option<String>,
+    /// Display names, e.g. "Firefox"
+    #[serde(default)]
+    pub name: Vec<String>,
+    pub desc: Option<String>,
+    pub homepage: Option<String>,
+    /// Latest version, or "latest" for casks that aren't versioned
+    pub version: Option<String>,
+    /// Installed version; None when the cask isn't installed
+    pub installed: Option<String>,
+    /// Unix time the installed version was installed
+    pub installed_time: Option<i64>,
+    pub outdated: Option<bool>,
+    /// What the cask installs (apps, binaries, pkgs, ...), in brew's own shape
+    #[serde(default)]
+    pub artifacts: Vec<serde_json::Value>,
+    pub caveats: Option<String>,
+    #[serde(default)]
+    pub depends_on: CaskDependsOn,
+    /// The app updates itself, so `brew upgrade` normally skips it
+    pub auto_updates: Option<bool>,
+    pub deprecated: Option<bool>,
+    pub disabled: Option<bool>,
+}
+
+impl BrewInfoCask {
+    /// Human-readable name, falling back to the token
+    pub fn display_name(&self) -> &str {
+        self.name.first().map(String::as_str).unwrap_or(&self.token)
+    }
+}
+
+/// The `depends_on` block of a cask; macOS version and architecture requirements are ignored
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CaskDependsOn {
+    pub formula: Vec<String>,
+    pub cask: Vec<String>,
+}
+
+/// `brew info` for a single name, which may be a formula or a cask
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageInfo {
+    Formula(Box<BrewInfoFormula>),
+    Cask(Box<BrewInfoCask>),
+}
+
+impl PackageInfo {
+    /// The fields formulae and casks have in common, in one shape
+    pub fn details(&self) -> PackageDetails {
+        match self {
+            PackageInfo::Formula(formula) => PackageDetails {
+                kind: PackageKind::Formula,
+                name: formula.name.clone(),
+                display_name: formula.name.clone(),
+                version: formula.display_version().map(str::to_string),
+                desc: formula.desc.clone(),
+                homepage: formula.homepage.clone(),
+                dependencies: formula.dependencies.clone().unwrap_or_default(),
+                build_dependencies: formula.build_dependencies.clone().unwrap_or_default(),
+                caveats: formula.caveats.clone(),
+                tap: formula.tap.clone(),
+            },
+            PackageInfo::Cask(cask) => PackageDetails {
+                kind: PackageKind::Cask,
+                name: cask.token.clone(),
+                display_name: cask.display_name().to_string(),
+                version: cask.version.clone(),
+                desc: cask.desc.clone(),
+                homepage: cask.homepage.clone(),
+                dependencies: cask.depends_on.formula.iter().chain(&cask.depends_on.cask).cloned().collect(),
+                build_dependencies: Vec::new(),
+                caveats: cask.caveats.clone(),
+                tap: cask.tap.clone(),
+            },
+        }
+    }
+
+    /// The info without what changes as packages are installed, upgraded and pinned,
+    /// which is all the disk cache keeps
+    fn static_metadata(&self) -> PackageInfo {
+        self.clone().with_install_state(None)
+    }
+
+    /// The info with its install state (kegs, link, pin, outdated) replaced by what brew
+    /// reported for the package in `snapshot`; none if it isn't installed
+    fn with_install_state(self, snapshot: Option<&InstalledSnapshot>) -> PackageInfo {
+        match self {
+            PackageInfo::Formula(mut formula) => {
+                let installed = snapshot.and_then(|s| s.formulae.iter().find(|f| f.name == formula.name));
+                formula.installed = installed.and_then(|f| f.installed.clone());
+                formula.linked_keg = installed.and_then(|f| f.linked_keg.clone());
+                formula.pinned = installed.and_then(|f| f.pinned);
+                formula.outdated = installed.and_then(|f| f.outdated);
+                PackageInfo::Formula(formula)
+            }
+            PackageInfo::Cask(mut cask) => {
+                let installed = snapshot.and_then(|s| s.casks.iter().find(|c| c.token == cask.token));
+                cask.installed = installed.and_then(|c| c.installed.clone());
+                cask.installed_time = installed.and_then(|c| c.installed_time);
+                cask.outdated = installed.and_then(|c| c.outdated);
+                PackageInfo::Cask(cask)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageKind {
+    Formula,
+    Cask,
+}
+
+/// What the UI shows about a package, whether brew described it as a formula or a cask
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageDetails {
+    pub kind: PackageKind,
+    /// Name to pass to brew: the formula name or cask token
+    pub name: String,
+    /// Human-readable name; differs from `name` for most casks
+    pub display_name: String,
+    pub version: Option<String>,
+    pub desc: Option<String>,
+    pub homepage: Option<String>,
+    /// Runtime dependencies; for casks, the formulae and casks it depends on
+    pub dependencies: Vec<String>,
+    /// Only formulae have build dependencies
+    pub build_dependencies: Vec<String>,
+    pub caveats: Option<String>,
+    /// Tap it comes from, e.g. "homebrew/core" or "hashicorp/tap"
+    pub tap: Option<String>,
+}
+
+/// Split a tap-qualified name such as `hashicorp/tap/terraform` into its tap and
+/// package name. Plain names give None.
+pub fn split_tap_name(name: &str) -> Option<(&str, &str)> {
+    let (tap, package) = name.rsplit_once('/')?;
+    let mut parts = tap.split('/');
+    let valid = parts.next().is_some_and(|user| !user.is_empty())
+        && parts.next().is_some_and(|repo| !repo.is_empty())
+        && parts.next().is_none();
+    (valid && !package.is_empty()).then_some((tap, package))
+}
+
+/// Whether packages from `tap` can be used with the taps in `tapped` (from `get_taps`).
+/// The official taps don't need tapping since brew reads them from its API.
+pub fn is_tapped(tap: &str, tapped: &[String]) -> bool {
+    let tap = tap.to_lowercase();
+    // brew accepts "user/repo" for the repository "user/homebrew-repo"
+    let tap = tap.replace("/homebrew-", "/");
+    matches!(tap.as_str(), "homebrew/core" | "homebrew/cask") || tapped.iter().any(|t| t.to_lowercase() == tap)
+}
+
+/// Tap a tap-qualified `name` needs before it can be looked up, if it isn't tapped yet
+pub fn missing_tap(name: &str, tapped: &[String]) -> Option<String> {
+    let (tap, _) = split_tap_name(name)?;
+    (!is_tapped(tap, tapped)).then(|| tap.to_string())
+}
+
+#[derive(Debug)]
+pub enum BrewError {
+    CommandFailed(String),
+    ParseError(String),
+    NotInstalled,
+    /// Downloading failed (DNS, connection, HTTP errors)
+    NetworkError(String),
+    /// A file or directory in the Homebrew prefix isn't writable
+    PermissionDenied(String),
+    /// Another brew process holds the lock
+    Locked(String),
+    /// The formula or cask doesn't exist
+    FormulaNotFound(String),
+    /// A download didn't match its expected checksum
+    ChecksumMismatch(String),
+    /// Simulate-only mode is on, so nothing was changed. Holds what brew said it would do,
+    /// or the command that would have run if brew can't preview it.
+    DryRun(String),
+}
+
+type ErrorVariant = fn(String) -> BrewError;
+
+// stderr patterns for classifying failures, checked in order
+const ERROR_PATTERNS: &[(&str, ErrorVariant)] = &[
+    ("SHA256 mismatch", BrewError::ChecksumMismatch),
+    ("Checksum mismatch", BrewError::ChecksumMismatch),
+    ("has already locked", BrewError::Locked),
+    ("process is already running", BrewError::Locked),
+    ("Another active Homebrew", BrewError::Locked),
+    ("No available formula", BrewError::FormulaNotFound),
+    ("No available cask", BrewError::FormulaNotFound),
+    ("No formulae or casks found", BrewError::FormulaNotFound),
+    ("No such keg", BrewError::FormulaNotFound),
+    ("Permission denied", BrewError::PermissionDenied),
+    ("Operation not permitted", BrewError::PermissionDenied),
+    ("is not writable", BrewError::PermissionDenied),
+    ("Could not resolve host", BrewError::NetworkError),
+    ("Failed to connect", BrewError::NetworkError),
+    ("Connection timed out", BrewError::NetworkError),
+    ("Network is unreachable", BrewError::NetworkError),
+    ("Failed to download", BrewError::NetworkError),
+    ("curl: (", BrewError::NetworkError),
+];
+
+impl BrewError {
+    /// Error for a failed brew command, classified by what it printed to stderr
+    pub fn from_stderr(stderr: String) -> Self {
+        ERROR_PATTERNS
+            .iter()
+            .find(|(pattern, _)| stderr.contains(pattern))
+            .map(|(_, variant)| variant(stderr.clone()))
+            .unwrap_or(BrewError::CommandFailed(stderr))
+    }
+
+    /// What the user can do about the error, if there's anything specific
+    pub fn suggestion(&self) -> Option<&'static str> {
+        match self {
+            BrewError::NotInstalled => Some("Install Homebrew or set its location under Tools > Homebrew Location."),
+            BrewError::NetworkError(_) => Some("Check your internet connection and try again."),
+            BrewError::PermissionDenied(_) => {
+                Some("Make sure your user owns the Homebrew prefix. `brew doctor` lists the affected paths.")
+            }
+            BrewError::Locked(_) => Some("Wait for the other brew process to finish, then try again."),
+            BrewError::FormulaNotFound(_) => {
+                Some("Check the name for typos, or run Update so Homebrew knows about recently added formulae.")
+            }
+            BrewError::ChecksumMismatch(_) => Some(
+                "The download may be incomplete or the formula changed upstream. Update Homebrew and try again.",
+            ),
+            BrewError::DryRun(_) => Some("Turn off Simulate Only in Preferences to run it for real."),
+            BrewError::CommandFailed(_) | BrewError::ParseError(_) => None,
+        }
+    }
+
+    /// One-line description of what went wrong: brew's first "Error:" line if it printed one
+    pub fn summary(&self) -> String {
+        let message = match self {
+            BrewError::CommandFailed(msg)
+            | BrewError::ParseError(msg)
+            | BrewError::NetworkError(msg)
+            | BrewError::PermissionDenied(msg)
+            | BrewError::Locked(msg)
+            | BrewError::FormulaNotFound(msg)
+            | BrewError::ChecksumMismatch(msg) => msg.as_str(),
+            BrewError::NotInstalled => return self.to_string(),
+            BrewError::DryRun(_) => return "Simulated; nothing was changed".to_string(),
+        };
+        let line = message
+            .lines()
+            .map(str::trim)
+            .find_map(|line| line.strip_prefix("Error:"))
+            .or_else(|| message.lines().map(str::trim).find(|line| !line.is_empty()))
+            .unwrap_or("Unknown error");
+        sanitize_error(line.trim())
+    }
+
+    /// Sanitized message followed by the recovery suggestion, for showing in the UI
+    pub fn user_message(&self) -> String {
+        let message = sanitize_error(&self.to_string());
+        match self.suggestion() {
+            Some(suggestion) => format!("{}\n\n{}", message, suggestion),
+            None => message,
+        }
+    }
+}
+
+impl std::fmt::Display for BrewError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BrewError::CommandFailed(msg) => write!(f, "Brew command failed: {}", msg),
+            BrewError::ParseError(msg) => write!(f, "Failed to parse brew output: {}", msg),
+            BrewError::NotInstalled => write!(f, "Homebrew is not installed or not in PATH"),
+            BrewError::NetworkError(msg) => write!(f, "Network error: {}", msg),
+            BrewError::PermissionDenied(msg) => write!(f, "Permission denied: {}", msg),
+            BrewError::Locked(msg) => write!(f, "Another brew process is running: {}", msg),
+            BrewError::FormulaNotFound(msg) => write!(f, "Formula not found: {}", msg),
+            BrewError::ChecksumMismatch(msg) => write!(f, "Checksum mismatch: {}", msg),
+            BrewError::DryRun(msg) => write!(f, "Simulated; nothing was changed:\n{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for BrewError {}
+
+pub type BrewResult<T> = Result<T, BrewError>;
+
+/// Simple rate limiter to prevent rapid repeated operations.
+/// Uses atomic operations for thread safety.
+pub struct RateLimiter {
+    last_operation: AtomicU64,
+    min_interval_ms: u64,
+}
+
+impl RateLimiter {
+    pub const fn new(min_interval_ms: u64) -> Self {
+        Self {
+            last_operation: AtomicU64::new(0),
+            min_interval_ms,
+        }
+    }
+
+    /// Check if an operation is allowed. Returns Ok(()) if allowed,
+    /// or Err with the number of milliseconds to wait.
+    pub fn check(&self) -> Result<(), u64> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+
+        let last = self.last_operation.load(Ordering::SeqCst);
+        let elapsed = now.saturating_sub(last);
+
+        if elapsed >= self.min_interval_ms {
+            self.last_operation.store(now, Ordering::SeqCst);
+            Ok(())
+        } else {
+            Err(self.min_interval_ms - elapsed)
+        }
+    }
+
+    /// Milliseconds until the next operation would be allowed, without claiming it
+    pub fn remaining_ms(&self) -> u64 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+
+        let elapsed = now.saturating_sub(self.last_operation.load(Ordering::SeqCst));
+        self.min_interval_ms.saturating_sub(elapsed)
+    }
+
+    /// Check rate limit and return a BrewError if rate limited
+    pub fn check_or_error(&self) -> BrewResult<()> {
+        self.check().map_err(|wait_ms| {
+            BrewError::CommandFailed(format!(
+                "Rate limited. Please wait {} ms before retrying.",
+                wait_ms
+            ))
+        })
+    }
+}
+
+// Global rate limiters for different operation types
+// Install/uninstall operations: minimum 2 seconds between operations
+pub static INSTALL_RATE_LIMITER: RateLimiter = RateLimiter::new(2000);
+// Search operations: minimum 500ms between operations
+pub static SEARCH_RATE_LIMITER: RateLimiter = RateLimiter::new(500);
+// Info operations: minimum 200ms between operations
+pub static INFO_RATE_LIMITER: RateLimiter = RateLimiter::new(200);
+
+// Configured brew executable; None means look up "brew" on PATH
+static BREW_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Run brew from `path` instead of looking it up on PATH. None restores the default.
+pub fn set_brew_path(path: Option<PathBuf>) {
+    *BREW_PATH.lock().unwrap() = path;
+}
+
+// Whether changes are only previewed; see set_dry_run
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+/// Only preview operations that change the installation. Installs, upgrades, cleanups and
+/// (un)linking run with `--dry-run`; uninstalls, taps, Brewfile installs and deleting
+/// downloads, which brew can't preview, don't run at all. Either way they fail with
+/// `BrewError::DryRun` describing what would have happened, so no caller acts as if
+/// something changed. `brew update` still runs, since it only refreshes Homebrew itself.
+pub fn set_dry_run(dry_run: bool) {
+    DRY_RUN.store(dry_run, Ordering::Relaxed);
+}
+
+pub fn is_dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+/// In simulate-only mode, the `DryRun` error to return instead of running `brew <args>`.
+/// With `can_preview`, brew is asked what it would do with `--dry-run`.
+async fn simulate(args: &[&str], can_preview: bool) -> Option<BrewError> {
+    if !is_dry_run() {
+        return None;
+    }
+    let command = format!("brew {}", args.join(" "));
+    if !can_preview {
+        return Some(BrewError::DryRun(format!("Would run `{}`, which has no preview", command)));
+    }
+    Some(match run_brew(dry_run_args(args)).await {
+        Ok(output) if output.status.success() => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            BrewError::DryRun(format!("`{}` would do:\n{}{}", command, stdout, stderr).trim_end().to_string())
+        }
+        // A preview that fails shows why the real run would have failed
+        Ok(output) => BrewError::from_stderr(String::from_utf8_lossy(&output.stderr).to_string()),
+        Err(e) => BrewError::CommandFailed(e.to_string()),
+    })
+}
+
+/// `args` with `--dry-run` after the subcommand, e.g. `install --dry-run wget`
+fn dry_run_args<'a>(args: &[&'a str]) -> Vec<&'a str> {
+    let mut preview = args.to_vec();
+    preview.insert(preview.len().min(1), "--dry-run");
+    preview
+}
+
+/// `args` to run in a terminal, which `simulate` can't stand in front of. With `dry_run`,
+/// installs and upgrades become a `--dry-run` preview, and other commands the `DryRun` error.
+pub fn terminal_args(args: &[String], dry_run: bool) -> BrewResult<Vec<String>> {
+    if !dry_run {
+        return Ok(args.to_vec());
+    }
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.first() {
+        Some(&"install") | Some(&"upgrade") => Ok(dry_run_args(&args).into_iter().map(str::to_string).collect()),
+        _ => Err(BrewError::DryRun(format!("Would run `brew {}`, which has no preview", args.join(" ")))),
+    }
+}
+
+// Whether installs and upgrades run with --verbose
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+
+/// Run installs and upgrades with `--verbose`, so their output shows every step of a build.
+/// Useful when diagnosing a failing build; off by default.
+pub fn set_verbose(verbose: bool) {
+    VERBOSE.store(verbose, Ordering::Relaxed);
+}
+
+// Whether every cask installs with --no-quarantine, whatever its InstallOptions say
+static NO_QUARANTINE: AtomicBool = AtomicBool::new(false);
+
+/// Install casks without the macOS Gatekeeper quarantine, so unsigned apps open without a
+/// warning. Off by default.
+pub fn set_no_quarantine(no_quarantine: bool) {
+    NO_QUARANTINE.store(no_quarantine, Ordering::Relaxed);
+}
+
+/// The brew executable all commands are run with
+pub fn brew_program() -> PathBuf {
+    BREW_PATH.lock().unwrap().clone().unwrap_or_else(|| PathBuf::from("brew"))
+}
+
+/// Like `brew_program`, but with a bare "brew" resolved against PATH
+pub fn resolved_brew_program() -> PathBuf {
+    let program = brew_program();
+    if program.components().count() > 1 {
+        return program;
+    }
+    std::env::var_os("PATH")
+        .and_then(|paths| {
+            std::env::split_paths(&paths)
+                .map(|dir| dir.join(&program))
+                .find(|candidate| candidate.is_file())
+        })
+        .unwrap_or(program)
+}
+
+/// brew for a command whose output goes straight to a log view. brew only colors output
+/// on a terminal, so ask for colors anyway, since the view renders them; output that
+/// gets parsed stays plain.
+fn log_view_brew_command() -> tokio::process::Command {
+    brew_command_with_env(&[("HOMEBREW_COLOR", "1")])
+}
+
+/// `brew_command` with extra environment variables for this one invocation
+fn brew_command_with_env(env: &[(&str, &str)]) -> tokio::process::Command {
+    runner::tokio_command_with_env(&brew_program(), &brew_prefix_of(&resolved_brew_program()), env)
+}
+
+/// Run brew with `args` to completion. Goes through the runner installed with
+/// `runner::with_runner`, if any, so tests can stand in for Homebrew.
+async fn run_brew<I, S>(args: I) -> std::io::Result<std::process::Output>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let args = args.into_iter().map(|arg| arg.as_ref().to_owned()).collect();
+    match runner::scoped_runner() {
+        Some(runner) => runner.output(args).await,
+        None => {
+            let runner = runner::ProcessRunner::new(brew_program(), brew_prefix_of(&resolved_brew_program()));
+            runner.output(args).await
+        }
+    }
+}
+
+/// Receives download progress as a percentage, once per update curl prints
+pub type ProgressSender = tokio::sync::mpsc::UnboundedSender<f64>;
+
+/// Like `run_brew`, but reads stderr as it arrives and sends each curl progress
+/// update to `progress`. A scoped runner has no live output, so it reports none.
+///
+/// brew tells curl to stay silent unless its stdout is a terminal, so stdout goes to a
+/// pseudo-terminal here; stderr, where curl draws its bar, stays a pipe.
+async fn run_brew_with_progress(
+    args: &[&str],
+    progress: Option<&ProgressSender>,
+) -> std::io::Result<std::process::Output> {
+    use tokio::io::AsyncReadExt;
+
+    let Some(progress) = progress.filter(|_| runner::scoped_runner().is_none()) else {
+        return run_brew(args).await;
+    };
+
+    let (terminal, terminal_for_brew) = open_pty()?;
+    // Colors would end up in the captured output, which callers parse
+    let mut child = brew_command_with_env(&[("HOMEBREW_NO_COLOR", "1")])
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::from(terminal_for_brew))
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut stderr_pipe = child.stderr.take().expect("stderr is piped");
+
+    let read_stdout = async {
+        let mut terminal = tokio::fs::File::from_std(terminal);
+        let mut stdout = Vec::new();
+        let mut chunk = [0u8; 4096];
+        loop {
+            match terminal.read(&mut chunk).await {
+                Ok(0) => break,
+                Ok(n) => stdout.extend_from_slice(&chunk[..n]),
+                // Linux reports EIO once brew, the only holder of the other end, has exited
+                Err(e) if e.raw_os_error() == Some(libc::EIO) => break,
+                Err(e) => return Err(e),
+            }
+        }
+        // The terminal turns each newline into "\r\n"
+        let stdout = String::from_utf8_lossy(&stdout).replace("\r\n", "\n").into_bytes();
+        Ok::<_, std::io::Error>(stdout)
+    };
+    let read_stderr = async {
+        let mut stderr = Vec::new();
+        let mut chunk = [0u8; 4096];
+        let mut line_start = 0;
+        loop {
+            let n = stderr_pipe.read(&mut chunk).await?;
+            if n == 0 {
+                break;
+            }
+            stderr.extend_from_slice(&chunk[..n]);
+            line_start = send_download_progress(&stderr, line_start, progress);
+        }
+        Ok::<_, std::io::Error>(stderr)
+    };
+    let (stdout, stderr) = tokio::join!(read_stdout, read_stderr);
+
+    Ok(std::process::Output {
+        status: child.wait().await?,
+        stdout: stdout?,
+        stderr: stderr?,
+    })
+}
+
+/// A new pseudo-terminal as (our end, the end to give a child process)
+fn open_pty() -> std::io::Result<(std::fs::File, std::fs::File)> {
+    use std::os::fd::FromRawFd;
+
+    let (mut ours, mut theirs) = (0, 0);
+    let null = std::ptr::null_mut();
+    // SAFETY: openpty only writes the two descriptors; the name, settings and size are optional
+    if unsafe { libc::openpty(&mut ours, &mut theirs, null, null as _, null as _) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // SAFETY: openpty has just opened both descriptors, and nothing else owns them
+    Ok(unsafe { (std::fs::File::from_raw_fd(ours), std::fs::File::from_raw_fd(theirs)) })
+}
+
+/// Send each curl progress update in the complete lines of `stderr` from `line_start` on,
+/// returning where the unfinished line starts. curl redraws its bar after each '\r'.
+fn send_download_progress(stderr: &[u8], mut line_start: usize, progress: &ProgressSender) -> usize {
+    while let Some(end) = stderr[line_start..].iter().position(|&b| b == b'\r' || b == b'\n') {
+        let line = String::from_utf8_lossy(&stderr[line_start..line_start + end]);
+        if let Some(percent) = parse_download_progress(&line) {
+            let _ = progress.send(percent);
+        }
+        line_start += end + 1;
+    }
+    line_start
+}
+
+/// The percentage in a curl progress bar line, e.g. "#####          45.3%"
+pub fn parse_download_progress(line: &str) -> Option<f64> {
+    let line = line.trim();
+    let (bar, percent) = line.rsplit_once(|c: char| c.is_whitespace()).unwrap_or(("", line));
+    if !bar.chars().all(|c| c == '#' || c.is_whitespace()) {
+        return None;
+    }
+    let percent: f64 = percent.strip_suffix('%')?.parse().ok()?;
+    (0.0..=100.0).contains(&percent).then_some(percent)
+}
+
+/// Full argv (program first) to run brew, e.g. for spawning it in a terminal.
+/// The `runner::env` variables still have to be set on the process.
+pub fn brew_argv() -> Vec<String> {
+    let wrapping = runner::current_wrapping(&brew_prefix_of(&resolved_brew_program()));
+    runner::command_argv(&brew_program(), wrapping, &runner::env())
+        .into_iter()
+        .map(|arg| arg.to_string_lossy().to_string())
+        .collect()
+}
+
+// Well-known brew locations: Apple Silicon, Intel macOS, and Linux (system-wide / per-user)
+const KNOWN_BREW_PATHS: &[&str] = &[
+    "/opt/homebrew/bin/brew",
+    "/usr/local/bin/brew",
+    "/home/linuxbrew/.linuxbrew/bin/brew",
+];
+
+/// Find every Homebrew installation in the standard prefixes
+pub fn detect_brew_installations() -> Vec<PathBuf> {
+    let mut candidates: Vec<PathBuf> = KNOWN_BREW_PATHS.iter().map(PathBuf::from).collect();
+    if let Some(home) = std::env::var_os("HOME") {
+        candidates.push(PathBuf::from(home).join(".linuxbrew/bin/brew"));
+    }
+    candidates.into_iter().filter(|path| path.is_file()).collect()
+}
+
+/// The prefix a brew executable belongs to, e.g. /opt/homebrew for /opt/homebrew/bin/brew
+pub fn brew_prefix_of(program: &Path) -> PathBuf {
+    program
+        .parent()
+        .filter(|dir| dir.ends_with("bin"))
+        .and_then(Path::parent)
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| program.to_path_buf())
+}
+
+/// Check that `path` is a working brew executable. Returns its version line.
+pub fn validate_brew_path(path: &Path) -> BrewResult<String> {
+    if !path.is_file() {
+        return Err(BrewError::CommandFailed(format!("{} is not a file", path.display())));
+    }
+
+    let output = runner::std_command(path, &brew_prefix_of(path))
+        .arg("--version")
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| BrewError::CommandFailed(format!("{}: {}", path.display(), e)))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let version = stdout.lines().next().unwrap_or("").trim().to_string();
+    if !output.status.success() || !version.starts_with("Homebrew") {
+        return Err(BrewError::CommandFailed(format!(
+            "{} does not look like a Homebrew executable",
+            path.display()
+        )));
+    }
+
+    Ok(version)
+}
+
+/// Check if brew is installed and accessible
+pub fn is_brew_installed() -> bool {
+    runner::std_command(&brew_program(), &brew_prefix_of(&resolved_brew_program()))
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+/// Homebrew's official install script
+pub const INSTALL_SCRIPT_URL: &str = "https://raw.githubusercontent.com/Homebrew/install/HEAD/install.sh";
+
+/// Shell command line the Homebrew docs give for installing it
+pub fn install_script_command() -> String {
+    format!("/bin/bash -c \"$(curl -fsSL {})\"", INSTALL_SCRIPT_URL)
+}
+
+/// Run the official install script non-interactively, sending each output line to `tx`.
+/// On Linux the script needs sudo, which only works here if no password is required.
+pub async fn install_homebrew(tx: tokio::sync::mpsc::UnboundedSender<String>) -> BrewResult<()> {
+    let mut cmd = runner::tokio_command(Path::new("/bin/bash"), Path::new("/"));
+    cmd.arg("-c").arg(install_script_command()).env("NONINTERACTIVE", "1");
+    stream_output(cmd, tx).await
+}
+
+/// Taps offered during first-run setup: (name, description)
+pub const RECOMMENDED_TAPS: &[(&str, &str)] = &[
+    ("homebrew/services", "Start and stop background services with brew services"),
+    ("homebrew/command-not-found", "Suggest the formula that provides a missing command"),
+];
+
+/// Names of the currently tapped repositories
+pub async fn get_taps() -> BrewResult<Vec<String>> {
+    let output = run_brew(["tap"])
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(BrewError::from_stderr(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// Tap a repository (`brew tap user/repo`)
+pub async fn add_tap(name: &str) -> BrewResult<String> {
+    validate_package_name(name)?;
+
+    if let Some(simulated) = simulate(&["tap", name], false).await {
+        return Err(simulated);
+    }
+
+    let output = run_brew(["tap", name])
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(BrewError::from_stderr(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Install everything listed in a Brewfile, sending output lines to `tx`
+pub async fn bundle_install(
+    brewfile: &Path,
+    tx: tokio::sync::mpsc::UnboundedSender<String>,
+) -> BrewResult<()> {
+    INSTALL_RATE_LIMITER.check_or_error()?;
+    if !brewfile.is_file() {
+        return Err(BrewError::CommandFailed(format!("{} is not a file", brewfile.display())));
+    }
+    let brewfile_arg = brewfile.to_string_lossy();
+    if let Some(simulated) = simulate(&["bundle", "install", "--file", &brewfile_arg], false).await {
+        return Err(simulated);
+    }
+
+    let mut cmd = log_view_brew_command();
+    cmd.args(["bundle", "install", "--file"]).arg(brewfile);
+    let result = stream_output(cmd, tx).await;
+    invalidate_caches(None).await;
+    result
+}
+
+/// What `brew bundle check` found missing from a Brewfile
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BundleCheck {
+    /// One line per missing entry, e.g. "Formula wget needs to be installed or updated."
+    pub missing: Vec<String>,
+}
+
+impl BundleCheck {
+    pub fn is_satisfied(&self) -> bool {
+        self.missing.is_empty()
+    }
+
+    /// "satisfied" or e.g. "4 missing"
+    pub fn label(&self) -> String {
+        match self.missing.len() {
+            0 => "satisfied".to_string(),
+            count => format!("{} missing", count),
+        }
+    }
+}
+
+/// Check whether everything in a Brewfile is installed (`brew bundle check`), without
+/// counting outdated packages as missing
+pub async fn bundle_check(brewfile: &Path) -> BrewResult<BundleCheck> {
+    if !brewfile.is_file() {
+        return Err(BrewError::CommandFailed(format!("{} is not a file", brewfile.display())));
+    }
+    let args = ["bundle", "check", "--verbose", "--no-upgrade", "--file"].map(OsStr::new);
+    let output = run_brew(args.into_iter().chain([brewfile.as_os_str()]))
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+
+    // Missing entries make it exit with an error, listing them on stdout
+    let check = parse_bundle_check(&String::from_utf8_lossy(&output.stdout));
+    if !output.status.success() && check.is_satisfied() {
+        return Err(BrewError::from_stderr(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+    Ok(check)
+}
+
+fn parse_bundle_check(stdout: &str) -> BundleCheck {
+    let missing = stdout
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix('→'))
+        .map(|line| line.trim().to_string())
+        .collect();
+    BundleCheck { missing }
+}
+
+/// Write a Brewfile listing everything installed (`brew bundle dump`), replacing `brewfile` if it exists
+pub async fn bundle_dump(brewfile: &Path) -> BrewResult<String> {
+    let args = [OsStr::new("bundle"), OsStr::new("dump"), OsStr::new("--force"), OsStr::new("--file")];
+    let output = run_brew(args.into_iter().chain([brewfile.as_os_str()]))
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(BrewError::from_stderr(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Download a package into the Homebrew cache without installing it (`brew fetch <pkg>`),
+/// sending download progress to `progress`. A later install then needs no download.
+pub async fn fetch_package(package_name: &str, progress: ProgressSender) -> BrewResult<String> {
+    validate_package_name(package_name)?;
+
+    let output = run_brew_with_progress(&["fetch", package_name], Some(&progress))
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+    if !output.status.success() {
+        return Err(BrewError::from_stderr(String::from_utf8_lossy(&output.stderr).to_string()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Generate a formula for the source archive at `url` in `tap` (`brew create`), creating
+/// the tap locally if it doesn't exist. `name` overrides the one brew guesses from the URL.
+/// Returns the path of the new formula, for opening in an editor.
+pub async fn create_formula(url: &str, name: Option<&str>, tap: &str) -> BrewResult<PathBuf> {
+    validate_formula_url(url)?;
+    validate_tap_name(tap)?;
+    if let Some(name) = name {
+        validate_package_name(name)?;
+        if name.contains('/') {
+            return Err(BrewError::ParseError(format!("Invalid formula name: {}", name)));
+        }
+    }
+
+    let mut args = vec!["create", "--tap", tap];
+    if let Some(name) = name {
+        args.extend(["--set-name", name]);
+    }
+    args.push(url);
+    if let Some(simulated) = simulate(&args, false).await {
+        return Err(simulated);
+    }
+
+    ensure_local_tap(tap).await?;
+
+    // brew create ends by opening $EDITOR, which would wait forever without a terminal;
+    // the caller opens the file instead
+    let output = brew_command_with_env(&[("HOMEBREW_EDITOR", "true"), ("HOMEBREW_NO_INSTALL_FROM_API", "1")])
+        .args(&args)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(BrewError::from_stderr(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    created_formula_path(&format!("{}{}", stdout, stderr))
+        .ok_or_else(|| BrewError::ParseError("brew create did not say where it wrote the formula".to_string()))
+}
+
+/// The formula file in `brew create`'s output, which ends by suggesting an audit of it,
+/// e.g. "Editing /home/linuxbrew/.linuxbrew/Homebrew/Library/Taps/me/homebrew-tap/Formula/hello.rb"
+fn created_formula_path(output: &str) -> Option<PathBuf> {
+    output
+        .split_whitespace()
+        .rfind(|word| word.starts_with('/') && word.ends_with(".rb"))
+        .map(PathBuf::from)
+}
+
+/// Create `tap` as an empty local tap (`brew tap-new --no-git`) unless it is already tapped
+async fn ensure_local_tap(tap: &str) -> BrewResult<()> {
+    if is_tapped(tap, &get_taps().await?) {
+        return Ok(());
+    }
+    let output = run_brew(["tap-new", "--no-git", tap])
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+    if !output.status.success() {
+        return Err(BrewError::from_stderr(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Run the tests an installed formula ships with (`brew test <pkg>`), sending output lines
+/// to `tx`. Fails if the test does; casks have no tests.
+pub async fn test_formula(
+    package_name: &str,
+    tx: tokio::sync::mpsc::UnboundedSender<String>,
+) -> BrewResult<()> {
+    validate_package_name(package_name)?;
+
+    let mut cmd = log_view_brew_command();
+    cmd.args(["test", package_name]);
+    stream_output(cmd, tx).await
+}
+
+/// Shell startup file where `brew shellenv` belongs for the given $SHELL
+pub fn shell_config_file(shell: &str, home: &Path) -> PathBuf {
+    match Path::new(shell).file_name().and_then(|n| n.to_str()) {
+        Some("zsh") => home.join(".zshrc"),
+        Some("bash") => home.join(".bashrc"),
+        Some("fish") => home.join(".config/fish/config.fish"),
+        _ => home.join(".profile"),
+    }
+}
+
+/// Line that puts brew on PATH, in the syntax of the given shell
+pub fn shellenv_line(program: &Path, shell: &str) -> String {
+    if Path::new(shell).file_name().is_some_and(|n| n == "fish") {
+        format!("{} shellenv | source", program.display())
+    } else {
+        format!("eval \"$({} shellenv)\"", program.display())
+    }
+}
+
+/// Whether a shell config file already sets up brew
+pub fn has_shellenv(contents: &str) -> bool {
+    contents
+        .lines()
+        .map(str::trim)
+        .any(|line| !line.starts_with('#') && line.contains("brew shellenv"))
+}
+
+/// Shell files checked for an existing `brew shellenv` line
+pub const SHELL_CONFIG_FILES: &[&str] = &[
+    ".profile",
+    ".bash_profile",
+    ".bashrc",
+    ".zprofile",
+    ".zshrc",
+    ".config/fish/config.fish",
+];
+
+/// The first shell config file under `home` that already runs `brew shellenv`
+pub fn find_shellenv_config(home: &Path) -> Option<PathBuf> {
+    SHELL_CONFIG_FILES
+        .iter()
+        .map(|name| home.join(name))
+        .find(|path| std::fs::read_to_string(path).is_ok_and(|contents| has_shellenv(&contents)))
+}
+
+/// Append the `brew shellenv` line to `config`, creating the file if needed
+pub fn add_shellenv(config: &Path, line: &str) -> std::io::Result<()> {
+    use std::io::Write;
+
+    if let Some(parent) = config.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(config)?;
+    writeln!(file, "\n# Added by BrewHouse\n{}", line)
+}
+
+/// Run `cmd`, sending stdout and stderr lines to `tx` as they arrive. A failure is
+/// classified from the stderr lines, like the errors of captured commands.
+async fn stream_output(
+    mut cmd: tokio::process::Command,
+    tx: tokio::sync::mpsc::UnboundedSender<String>,
+) -> BrewResult<()> {
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+
+    /// Forward each line to `tx`, returning them too
+    async fn forward_lines(
+        reader: impl tokio::io::AsyncRead + Unpin,
+        tx: tokio::sync::mpsc::UnboundedSender<String>,
+    ) -> Vec<String> {
+        let mut lines = BufReader::new(reader).lines();
+        let mut forwarded = Vec::new();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let _ = tx.send(line.clone());
+            forwarded.push(line);
+        }
+        forwarded
+    }
+
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let (_, stderr) = tokio::join!(forward_lines(stdout, tx.clone()), forward_lines(stderr, tx));
+
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+    if !status.success() {
+        if stderr.is_empty() {
+            return Err(BrewError::CommandFailed(format!("exited with {}", status)));
+        }
+        return Err(BrewError::from_stderr(stderr.join("\n")));
+    }
+    Ok(())
+}
+
+/// Everything `brew info --json=v2 --installed` reports, shared by the Installed
+/// view and the Status panel so one brew call serves both
+#[derive(Debug)]
+pub struct InstalledSnapshot {
+    pub formulae: Vec<BrewInfoFormula>,
+    pub casks: Vec<BrewInfoCask>,
+}
+
+impl InstalledSnapshot {
+    pub fn outdated_count(&self) -> usize {
+        self.formulae.iter().filter(|f| f.outdated.unwrap_or(false)).count()
+    }
+
+    /// Formulae no other installed formula depends on, like `brew leaves`
+    pub fn leaves_count(&self) -> usize {
+        let dependencies: std::collections::HashSet<&str> = self
+            .formulae
+            .iter()
+            .flat_map(|f| f.installed.iter().flatten())
+            .flat_map(|keg| keg.runtime_dependencies.iter().flatten())
+            .filter_map(|dep| dep.get("full_name").and_then(|n| n.as_str()))
+            .collect();
+
+        self.formulae
+            .iter()
+            .filter(|f| {
+                let full_name = f.full_name.as_deref().unwrap_or(&f.name);
+                !dependencies.contains(full_name) && !dependencies.contains(f.name.as_str())
+            })
+            .count()
+    }
+}
+
+/// Deserialize a list item by item, dropping (and logging) entries that don't parse,
+/// so one formula with an unexpected shape doesn't fail the whole response
+fn lenient_vec<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::de::DeserializeOwned,
+{
+    let values = Vec::<serde_json::Value>::deserialize(deserializer)?;
+    Ok(values
+        .into_iter()
+        .filter_map(|value| match serde_json::from_value(value) {
+            Ok(item) => Some(item),
+            Err(e) => {
+                eprintln!("Skipping unparseable brew info entry: {}", e);
+                None
+            }
+        })
+        .collect())
+}
+
+/// `lenient_vec` for optional lists; null stays None
+fn lenient_vec_option<'de, D, T>(deserializer: D) -> Result<Option<Vec<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::de::DeserializeOwned,
+{
+    #[derive(Deserialize)]
+    struct Lenient<T: serde::de::DeserializeOwned>(#[serde(deserialize_with = "lenient_vec")] Vec<T>);
+
+    Ok(Option::<Lenient<T>>::deserialize(deserializer)?.map(|Lenient(items)| items))
+}
+
+fn parse_installed_snapshot(json: &str) -> BrewResult<InstalledSnapshot> {
+    #[derive(Deserialize)]
+    struct BrewInfoResponse {
+        #[serde(default, deserialize_with = "lenient_vec")]
+        formulae: Vec<BrewInfoFormula>,
+        #[serde(default, deserialize_with = "lenient_vec")]
+        casks: Vec<BrewInfoCask>,
+    }
+
+    let response: BrewInfoResponse =
+        serde_json::from_str(json).map_err(|e| BrewError::ParseError(e.to_string()))?;
+
+    Ok(InstalledSnapshot {
+        formulae: response.formulae,
+        casks: response.casks,
+    })
+}
+
+// How long a snapshot is reused before brew is asked again
+const SNAPSHOT_TTL: std::time::Duration = std::time::Duration::from_secs(30);
+
+// Held across the fetch so concurrent callers wait for one brew call instead of each spawning
+static INSTALLED_SNAPSHOT: tokio::sync::Mutex<Option<(std::time::Instant, std::sync::Arc<InstalledSnapshot>)>> =
+    tokio::sync::Mutex::const_new(None);
+
+/// The installed formulae and casks, reusing a recent snapshot when there is one
+pub async fn get_installed_snapshot() -> BrewResult<std::sync::Arc<InstalledSnapshot>> {
+    let mut cached = INSTALLED_SNAPSHOT.lock().await;
+    if let Some((fetched, snapshot)) = cached.as_ref() {
+        if fetched.elapsed() < SNAPSHOT_TTL {
+            return Ok(snapshot.clone());
+        }
+    }
+
+    let output = run_brew(["info", "--json=v2", "--installed"])
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(BrewError::from_stderr(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    let snapshot = std::sync::Arc::new(parse_installed_snapshot(&String::from_utf8_lossy(&output.stdout))?);
+    *cached = Some((std::time::Instant::now(), snapshot.clone()));
+    Ok(snapshot)
+}
+
+/// Make the next installed-package lookup ask brew instead of reusing the recent snapshot,
+/// e.g. when the user refreshes after changing things from a terminal
+pub fn forget_installed_snapshot() {
+    // A lookup holding the lock is already fetching a fresh snapshot
+    if let Ok(mut cached) = INSTALLED_SNAPSHOT.try_lock() {
+        *cached = None;
+    }
+}
+
+/// Drop cached data after `package` (or, for None, anything) was installed, removed or changed
+async fn invalidate_caches(package: Option<&str>) {
+    *INSTALLED_SNAPSHOT.lock().await = None;
+    match package {
+        Some(name) => PACKAGE_INFO_CACHE.remove(name),
+        None => PACKAGE_INFO_CACHE.clear(),
+    }
+    metadata_cache::remove_package_info(package);
+}
+
+/// Get list of all installed packages (single batch call)
+pub async fn get_installed_packages() -> BrewResult<Vec<Package>> {
+    let snapshot = get_installed_snapshot().await?;
+
+    let formulae = snapshot
+        .formulae
+        .iter()
+        .cloned()
+        .map(|info| Package {
+            version: info.display_version().map(str::to_string),
+            name: info.name,
+            desc: info.desc,
+            homepage: info.homepage,
+            installed: true,
+            linked_keg: info.linked_keg,
+            keg_only: info.keg_only.unwrap_or(false),
+            installed_time: info.installed.iter().flatten().filter_map(|keg| keg.time).max(),
+            tap: info.tap,
+            installed_versions: info
+                .installed
+                .unwrap_or_default()
+                .into_iter()
+                .map(|keg| keg.version)
+                .collect(),
+            cask: false,
+        });
+
+    let casks = snapshot.casks.iter().cloned().map(|info| Package {
+        name: info.token,
+        version: info.installed.clone().or(info.version),
+        desc: info.desc,
+        homepage: info.homepage,
+        installed: true,
+        linked_keg: None,
+        keg_only: false,
+        installed_versions: info.installed.into_iter().collect(),
+        cask: true,
+        installed_time: info.installed_time,
+        tap: info.tap,
+    });
+
+    Ok(formulae.chain(casks).collect())
+}
+
+/// Search for packages (returns all if query is empty)
+pub async fn search_packages(query: &str) -> BrewResult<Vec<String>> {
+    SEARCH_RATE_LIMITER.check_or_error()?;
+    validate_search_query(query)?;
+
+    // A fully qualified name is its own result, even from a tap that isn't tapped yet
+    if split_tap_name(query).is_some() {
+        validate_package_name(query)?;
+        return Ok(vec![query.to_string()]);
+    }
+
+    // The full listing from a previous run shows immediately; refresh it if it's old
+    if query.is_empty() {
+        if let Some(index) = metadata_cache::formula_index() {
+            if index.is_stale() {
+                refresh_in_background(async { fetch_search_results("").await.map(|_| ()) });
+            }
+            return Ok(index.value);
+        }
+    }
+
+    fetch_search_results(query).await
+}
+
+/// Search formula names and descriptions for every word of the query, e.g. "pdf merge"
+/// finds qpdf. Brew searches for the longest word and the rest are matched in its results.
+pub async fn search_descriptions(query: &str) -> BrewResult<Vec<String>> {
+    SEARCH_RATE_LIMITER.check_or_error()?;
+    validate_search_query(query)?;
+    let terms: Vec<&str> = query.split_whitespace().collect();
+    let Some(longest) = terms.iter().max_by_key(|term| term.len()) else {
+        return Ok(Vec::new());
+    };
+    let lines = run_search(&["search", "--formula", "--desc", longest]).await?;
+    Ok(names_from_desc_search(&lines, &terms))
+}
+
+/// The names in `brew search --desc` lines, which look like "qpdf: Tools for and transforming
+/// PDF files", whose name or description contains each of `terms`, ignoring case. Brew has
+/// matched a single term itself, which may be a /regex/, so those lines all count.
+fn names_from_desc_search(lines: &[String], terms: &[&str]) -> Vec<String> {
+    let terms: Vec<String> = terms.iter().map(|term| term.to_lowercase()).collect();
+    lines
+        .iter()
+        .filter(|line| {
+            let line = line.to_lowercase();
+            terms.len() < 2 || terms.iter().all(|term| line.contains(term.as_str()))
+        })
+        .map(|line| line.split_once(':').map_or(line.as_str(), |(name, _)| name).trim().to_string())
+        .filter(|name| !name.is_empty() && !name.contains(char::is_whitespace))
+        .collect()
+}
+
+/// Search casks by name, for when no formula matches. Not rate limited: it follows
+/// `search_packages` as part of the same search.
+pub async fn search_casks(query: &str) -> BrewResult<Vec<String>> {
+    validate_search_query(query)?;
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    run_search(&["search", "--cask", query]).await
+}
+
+async fn fetch_search_results(query: &str) -> BrewResult<Vec<String>> {
+    let mut args = vec!["search", "--formula"];
+
+    if !query.is_empty() {
+        args.push(query);
+    }
+
+    let packages = run_search(&args).await?;
+    if query.is_empty() {
+        metadata_cache::store_formula_index(&packages);
+    }
+
+    Ok(packages)
+}
+
+/// Names printed by a `brew search` command, without its headings
+async fn run_search(args: &[&str]) -> BrewResult<Vec<String>> {
+    let output = run_brew(args)
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(BrewError::from_stderr(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    let packages: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty() && !s.starts_with("==>"))
+        .collect();
+
+    Ok(packages)
+}
+
+/// Run `task` on its own thread and runtime, so it outlives the caller's runtime.
+/// Used to refresh stale disk cache entries without making the UI wait.
+fn refresh_in_background(task: impl std::future::Future<Output = BrewResult<()>> + Send + 'static) {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        if let Err(e) = rt.block_on(task) {
+            eprintln!("Background refresh failed: {}", e);
+        }
+    });
+}
+
+/// Values keyed by name that expire `ttl` after being stored
+pub struct TtlCache<V> {
+    entries: Mutex<Option<HashMap<String, (std::time::Instant, V)>>>,
+    ttl: std::time::Duration,
+}
+
+impl<V: Clone> TtlCache<V> {
+    pub const fn new(ttl: std::time::Duration) -> Self {
+        Self {
+            entries: Mutex::new(None),
+            ttl,
+        }
+    }
+
+    /// The stored value, if it hasn't expired yet
+    pub fn get(&self, key: &str) -> Option<V> {
+        let mut guard = self.entries.lock().unwrap();
+        let entries = guard.get_or_insert_with(HashMap::new);
+        match entries.get(key) {
+            Some((stored, value)) if stored.elapsed() < self.ttl => Some(value.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, key: &str, value: V) {
+        let mut guard = self.entries.lock().unwrap();
+        guard
+            .get_or_insert_with(HashMap::new)
+            .insert(key.to_string(), (std::time::Instant::now(), value));
+    }
+
+    pub fn remove(&self, key: &str) {
+        if let Some(entries) = self.entries.lock().unwrap().as_mut() {
+            entries.remove(key);
+        }
+    }
+
+    pub fn clear(&self) {
+        *self.entries.lock().unwrap() = None;
+    }
+}
+
+// Formula details rarely change between clicks; installs and upgrades invalidate them
+static PACKAGE_INFO_CACHE: TtlCache<PackageInfo> = TtlCache::new(std::time::Duration::from_secs(5 * 60));
+
+/// Get detailed info about a specific formula or cask
+pub async fn get_package_info(package_name: &str) -> BrewResult<PackageInfo> {
+    validate_package_name(package_name)?;
+    if let Some(info) = PACKAGE_INFO_CACHE.get(package_name) {
+        return Ok(info);
+    }
+
+    // Metadata saved by a previous run shows immediately, with the install state taken fresh
+    // from the installed snapshot; refresh the metadata if it's old
+    if let Some(entry) = metadata_cache::package_info(package_name) {
+        if let Ok(snapshot) = get_installed_snapshot().await {
+            if entry.is_stale() {
+                let name = package_name.to_string();
+                refresh_in_background(async move { fetch_package_info(&name).await.map(|_| ()) });
+            }
+            let info = entry.value.with_install_state(Some(&snapshot));
+            PACKAGE_INFO_CACHE.insert(package_name, info.clone());
+            return Ok(info);
+        }
+    }
+
+    INFO_RATE_LIMITER.check_or_error()?;
+    fetch_package_info(package_name).await
+}
+
+async fn fetch_package_info(package_name: &str) -> BrewResult<PackageInfo> {
+    let output = run_brew(["info", "--json=v2", package_name])
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+
+    if !output.status.success() {
+        let error = BrewError::from_stderr(String::from_utf8_lossy(&output.stderr).to_string());
+        // A formula that was renamed since brew last updated can still be found by its old name
+        return match find_renamed(package_name).await {
+            Some(new_name) if new_name != package_name => {
+                let info = Box::pin(fetch_package_info(&new_name)).await?;
+                PACKAGE_INFO_CACHE.insert(package_name, info.clone());
+                Ok(info)
+            }
+            _ => Err(error),
+        };
+    }
+
+    let info = parse_package_info(&String::from_utf8_lossy(&output.stdout))?;
+    PACKAGE_INFO_CACHE.insert(package_name, info.clone());
+    metadata_cache::store_package_info(package_name, &info.static_metadata());
+    Ok(info)
+}
+
+/// Current name of the formula that `name` is an old name or alias of, looked up in the
+/// formula list brew downloads from its API. None if brew has no such list or no such formula.
+async fn find_renamed(name: &str) -> Option<String> {
+    get_renames().await.get(name).cloned()
+}
+
+// Like the descriptions, the old-name and alias map is parsed once per session
+static RENAMES_CACHE: Mutex<Option<std::sync::Arc<HashMap<String, String>>>> = Mutex::new(None);
+
+/// The formula each old name or alias stands for, from the formula list brew keeps of its API.
+/// Empty if brew hasn't downloaded it.
+async fn get_renames() -> std::sync::Arc<HashMap<String, String>> {
+    if let Some(cached) = RENAMES_CACHE.lock().unwrap().as_ref() {
+        return cached.clone();
+    }
+    let Ok(cache) = get_brew_path("--cache").await else {
+        return Default::default();
+    };
+    let json = std::fs::read_to_string(cache.join("api").join("formula.jws.json")).unwrap_or_default();
+    let renames = std::sync::Arc::new(renames_in_api(&json).into_iter().collect::<HashMap<_, _>>());
+    if !renames.is_empty() {
+        *RENAMES_CACHE.lock().unwrap() = Some(renames.clone());
+    }
+    renames
+}
+
+/// (old name or alias, name) for every formula in brew's formula list: a JWS envelope whose
+/// payload is a JSON array of formulae, or the bare array
+fn renames_in_api(json: &str) -> Vec<(String, String)> {
+    let mut renames = Vec::new();
+    for formula in api_list(json).unwrap_or_default() {
+        let Some(name) = formula.get("name").and_then(|name| name.as_str()) else {
+            continue;
+        };
+        for field in ["oldnames", "oldname", "aliases"] {
+            let old_names = match formula.get(field) {
+                Some(serde_json::Value::String(old)) => vec![old.as_str()],
+                Some(serde_json::Value::Array(names)) => names.iter().filter_map(|n| n.as_str()).collect(),
+                _ => Vec::new(),
+            };
+            renames.extend(old_names.into_iter().map(|old| (old.to_string(), name.to_string())));
+        }
+    }
+    renames
+}
+
+/// The entries of a formula or cask list from brew's API cache: a JWS envelope whose payload
+/// is a JSON array, or the bare array
+fn api_list(json: &str) -> Option<Vec<serde_json::Value>> {
+    let value: serde_json::Value = serde_json::from_str(json).ok()?;
+    let list = match value.get("payload").and_then(|payload| payload.as_str()) {
+        Some(payload) => serde_json::from_str(payload).ok()?,
+        None => value,
+    };
+    match list {
+        serde_json::Value::Array(entries) => Some(entries),
+        _ => None,
+    }
+}
+
+// Parsing brew's formula and cask lists takes a moment, so it happens once per session
+static DESCRIPTIONS_CACHE: Mutex<Option<std::sync::Arc<HashMap<String, String>>>> = Mutex::new(None);
+
+/// One-line descriptions of every formula and cask, by name, from the lists brew keeps of its
+/// API. Empty if brew hasn't downloaded them (e.g. with HOMEBREW_NO_INSTALL_FROM_API).
+pub async fn get_descriptions() -> std::sync::Arc<HashMap<String, String>> {
+    if let Some(cached) = DESCRIPTIONS_CACHE.lock().unwrap().as_ref() {
+        return cached.clone();
+    }
+    let Ok(cache) = get_brew_path("--cache").await else {
+        return Default::default();
+    };
+    let mut descriptions = HashMap::new();
+    for (file, name_field) in [("formula.jws.json", "name"), ("cask.jws.json", "token")] {
+        if let Ok(json) = std::fs::read_to_string(cache.join("api").join(file)) {
+            descriptions.extend(descriptions_in_api(&json, name_field));
+        }
+    }
+    let descriptions = std::sync::Arc::new(descriptions);
+    if !descriptions.is_empty() {
+        *DESCRIPTIONS_CACHE.lock().unwrap() = Some(descriptions.clone());
+    }
+    descriptions
+}
+
+/// (name, description) for each entry of an API list, named by `name_field`
+fn descriptions_in_api(json: &str, name_field: &str) -> Vec<(String, String)> {
+    api_list(json)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|entry| {
+            let name = entry.get(name_field)?.as_str()?;
+            let desc = entry.get("desc")?.as_str()?;
+            Some((name.to_string(), desc.to_string()))
+        })
+        .collect()
+}
+
+/// Package names in pasted text or a file, one per line or separated by spaces or commas,
+/// in order and without repeats. Copied commands and shell arrays from dotfile scripts work
+/// too: "brew install --cask firefox" gives firefox, "packages=(wget jq)" gives wget and jq,
+/// and `#` starts a comment.
+pub fn parse_package_list(text: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        for word in line.split(|c: char| c.is_whitespace() || c == ',' || c == ';') {
+            // Only the value of an assignment, e.g. wget in "packages=(wget"
+            let word = word.rsplit_once('=').map_or(word, |(_, value)| value);
+            let word = word.trim_matches(|c: char| matches!(c, '"' | '\'' | '`' | '$' | ':' | '(' | ')'));
+            if word.is_empty() || word.starts_with('-') || matches!(word, "brew" | "install" | "cask" | "&&" | "\\") {
+                continue;
+            }
+            if !names.iter().any(|name| name == word) {
+                names.push(word.to_string());
+            }
+        }
+    }
+    names
+}
+
+/// A pasted or imported package list, sorted by what installing it would do
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PackageListCheck {
+    /// Formulae to install, including ones from taps, which can't be checked before tapping
+    pub formulae: Vec<String>,
+    /// Casks to install
+    pub casks: Vec<String>,
+    pub installed: Vec<String>,
+    /// Names that are neither a formula nor a cask
+    pub unknown: Vec<String>,
+}
+
+/// Sort `names` into a PackageListCheck, given every formula, cask and installed package name
+fn classify_package_list(
+    names: &[String],
+    formulae: &std::collections::HashSet<String>,
+    casks: &std::collections::HashSet<String>,
+    installed: &std::collections::HashSet<String>,
+) -> PackageListCheck {
+    let mut check = PackageListCheck::default();
+    for name in names {
+        let list = if installed.contains(name) {
+            &mut check.installed
+        } else if formulae.contains(name) || (split_tap_name(name).is_some() && validate_package_name(name).is_ok()) {
+            &mut check.formulae
+        } else if casks.contains(name) {
+            &mut check.casks
+        } else {
+            &mut check.unknown
+        };
+        list.push(name.clone());
+    }
+    check
+}
+
+/// Look up which of `names` are formulae, casks or already installed. An alias or old name
+/// counts as the formula it stands for.
+pub async fn check_package_list(names: &[String]) -> BrewResult<PackageListCheck> {
+    let formulae = match metadata_cache::formula_index() {
+        Some(index) => index.value,
+        None => fetch_search_results("").await?,
+    };
+    let casks = match get_brew_path("--cache").await {
+        Ok(cache) => std::fs::read_to_string(cache.join("api").join("cask.jws.json"))
+            .map(|json| descriptions_in_api(&json, "token").into_iter().map(|(token, _)| token).collect())
+            .unwrap_or_default(),
+        Err(_) => Vec::new(),
+    };
+    // Without brew's API cache, ask brew for the full cask list
+    let casks: std::collections::HashSet<String> = if casks.is_empty() {
+        run_search(&["search", "--cask"]).await.unwrap_or_default().into_iter().collect()
+    } else {
+        casks.into_iter().collect()
+    };
+    let snapshot = get_installed_snapshot().await?;
+    let installed: std::collections::HashSet<String> = snapshot
+        .formulae
+        .iter()
+        .map(|formula| formula.name.clone())
+        .chain(snapshot.casks.iter().map(|cask| cask.token.clone()))
+        .collect();
+    let formulae: std::collections::HashSet<String> = formulae.into_iter().collect();
+
+    let renames = get_renames().await;
+    let mut names = names.to_vec();
+    for name in names.iter_mut().filter(|name| !formulae.contains(*name) && !casks.contains(*name)) {
+        let canonical = if !renames.is_empty() {
+            renames.get(name.as_str()).cloned()
+        } else if validate_package_name(name).is_ok() {
+            // brew info for each name in turn; the rate limit is for lookups from the UI
+            fetch_package_info(name).await.ok().and_then(|info| renamed_formula(name, info))
+        } else {
+            None
+        };
+        if let Some(canonical) = canonical {
+            *name = canonical;
+        }
+    }
+    Ok(classify_package_list(&names, &formulae, &casks, &installed))
+}
+
+// The formula list is large and changes a few times a day, so it is compared once per session
+static FEED_UPDATED: AtomicBool = AtomicBool::new(false);
+
+/// formulae.brew.sh's list of every formula, under the API domain
+const FORMULA_LIST_PATH: &str = "formula.json";
+
+/// What changed in formulae.brew.sh's formula list since it was last looked at, e.g.
+/// formulae that are new this week. The API has no date a formula was added, so the list
+/// is compared with the last one seen; on the first look, brew's own older copy of the list
+/// stands in for that. Offline, brew's copy is compared instead.
+pub async fn get_formula_feed() -> metadata_cache::FormulaFeed {
+    if FEED_UPDATED.load(Ordering::SeqCst) {
+        return metadata_cache::formula_feed();
+    }
+    let brew_copy = match get_brew_path("--cache").await {
+        Ok(cache) => {
+            let path = cache.join("api").join("formula.jws.json");
+            let taken = std::fs::metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |age| age.as_secs());
+            std::fs::read_to_string(&path).ok().map(|json| (formula_versions_in_api(&json), taken))
+        }
+        Err(_) => None,
+    };
+    let brew_copy = brew_copy.filter(|(versions, _)| !versions.is_empty());
+
+    let url = api_url(FORMULA_LIST_PATH);
+    let mut json = fetch_api(&url).await;
+    let default_url = join_api_url(DEFAULT_API_DOMAIN, FORMULA_LIST_PATH);
+    if json.is_err() && url != default_url {
+        json = fetch_api(&default_url).await;
+    }
+    let versions = json.map(|json| formula_versions_in_api(&json)).unwrap_or_default();
+
+    let (versions, baseline) = if !versions.is_empty() {
+        (versions, brew_copy)
+    } else if let Some((versions, _)) = brew_copy {
+        // Offline, brew's copy is the newest list there is
+        (versions, None)
+    } else {
+        return metadata_cache::formula_feed();
+    };
+    FEED_UPDATED.store(true, Ordering::SeqCst);
+    metadata_cache::update_formula_feed(versions, baseline)
+}
+
+/// Stable version of every formula in brew's formula list, by name
+fn formula_versions_in_api(json: &str) -> HashMap<String, String> {
+    api_list(json)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|formula| {
+            let name = formula.get("name")?.as_str()?;
+            let version = formula.pointer("/versions/stable")?.as_str()?;
+            Some((name.to_string(), version.to_string()))
+        })
+        .collect()
+}
+
+/// The formula `query` stands for if it is an alias or old name rather than a formula's
+/// own name, e.g. python@3.13 for "python"
+pub async fn canonical_name(query: &str) -> Option<String> {
+    validate_package_name(query).ok()?;
+    let renames = get_renames().await;
+    if !renames.is_empty() {
+        return renames.get(query).cloned();
+    }
+    // Without brew's formula list, brew info resolves the name instead
+    renamed_formula(query, get_package_info(query).await.ok()?)
+}
+
+/// The formula's own name if `info`, looked up as `query`, is a formula under another name
+fn renamed_formula(query: &str, info: PackageInfo) -> Option<String> {
+    match info {
+        PackageInfo::Formula(formula) if formula.name != query && formula.full_name.as_deref() != Some(query) => {
+            Some(formula.name)
+        }
+        _ => None,
+    }
+}
+
+/// The formula in a `brew info --json=v2` response, or the cask if there is no formula
+fn parse_package_info(json: &str) -> BrewResult<PackageInfo> {
+    #[derive(Deserialize)]
+    struct BrewInfoResponse {
+        #[serde(default, deserialize_with = "lenient_vec")]
+        formulae: Vec<BrewInfoFormula>,
+        #[serde(default, deserialize_with = "lenient_vec")]
+        casks: Vec<BrewInfoCask>,
+    }
+
+    let response: BrewInfoResponse =
+        serde_json::from_str(json).map_err(|e| BrewError::ParseError(e.to_string()))?;
+
+    let formula = response.formulae.into_iter().next().map(|f| PackageInfo::Formula(Box::new(f)));
+    formula
+        .or_else(|| response.casks.into_iter().next().map(|c| PackageInfo::Cask(Box::new(c))))
+        .ok_or_else(|| BrewError::ParseError("No formula or cask found in response".to_string()))
+}
+
+/// Optional flags for `brew install`
+#[derive(Debug, Clone, Default)]
+pub struct InstallOptions {
+    /// Install the development version (`--HEAD`)
+    pub head: bool,
+    /// Compile locally instead of pouring a bottle (`--build-from-source`)
+    pub build_from_source: bool,
+    /// Formula-specific options chosen by the user, e.g. `--with-openssl`
+    pub formula_options: Vec<String>,
+    /// Install a cask rather than a formula (`--cask`)
+    pub cask: bool,
+    /// Skip the macOS Gatekeeper quarantine for a cask (`--no-quarantine`)
+    pub no_quarantine: bool,
+}
+
+impl InstallOptions {
+    /// Command-line flags for these options, placed before the package name
+    pub fn args(&self) -> Vec<&str> {
+        let mut args = Vec::new();
+        if self.cask {
+            args.push("--cask");
+            // Only meaningful for casks; brew rejects it for formulae
+            if self.no_quarantine {
+                args.push("--no-quarantine");
+            }
+        }
+        if self.head {
+            args.push("--HEAD");
+        }
+        if self.build_from_source {
+            args.push("--build-from-source");
+        }
+        args.extend(self.formula_options.iter().map(String::as_str));
+        args
+    }
+}
+
+// macOS bottle tags, oldest first. A bottle built for an older release also
+// installs on newer ones.
+const MACOS_BOTTLE_TAGS: &[(u32, &str)] = &[
+    (11, "big_sur"),
+    (12, "monterey"),
+    (13, "ventura"),
+    (14, "sonoma"),
+    (15, "sequoia"),
+    (26, "tahoe"),
+];
+
+/// Bottle tag for the machine we're running on, e.g. "x86_64_linux" or "arm64_sonoma"
+pub fn current_bottle_tag() -> Option<String> {
+    static TAG: OnceLock<Option<String>> = OnceLock::new();
+    TAG.get_or_init(detect_bottle_tag).clone()
+}
+
+fn detect_bottle_tag() -> Option<String> {
+    let arm = std::env::consts::ARCH == "aarch64";
+
+    match std::env::consts::OS {
+        "linux" => Some(if arm { "arm64_linux" } else { "x86_64_linux" }.to_string()),
+        "macos" => {
+            let output = Command::new("sw_vers").arg("-productVersion").output().ok()?;
+            let version = String::from_utf8_lossy(&output.stdout);
+            let major: u32 = version.trim().split('.').next()?.parse().ok()?;
+            let (_, name) = MACOS_BOTTLE_TAGS.iter().rev().find(|(v, _)| *v <= major)?;
+            Some(if arm { format!("arm64_{}", name) } else { name.to_string() })
+        }
+        _ => None,
+    }
+}
+
+/// Whether the `bottle` field of `brew info` has a stable bottle usable on `tag`
+pub fn has_bottle_for(bottle: &serde_json::Value, tag: &str) -> bool {
+    let files = match bottle.pointer("/stable/files").and_then(|f| f.as_object()) {
+        Some(files) => files,
+        None => return false,
+    };
+
+    if files.contains_key("all") || files.contains_key(tag) {
+        return true;
+    }
+
+    // On macOS, bottles for older releases of the same architecture also work
+    let (prefix, name) = match tag.strip_prefix("arm64_") {
+        Some(name) => ("arm64_", name),
+        None => ("", tag),
+    };
+    let current = match MACOS_BOTTLE_TAGS.iter().position(|(_, n)| *n == name) {
+        Some(index) => index,
+        None => return false,
+    };
+    MACOS_BOTTLE_TAGS[..current]
+        .iter()
+        .any(|(_, older)| files.contains_key(&format!("{}{}", prefix, older)))
+}
+
+/// Platforms the `bottle` field of `brew info` has stable bottles for, as bottle tags
+pub fn bottle_tags(bottle: &serde_json::Value) -> Vec<String> {
+    let mut tags: Vec<String> = bottle
+        .pointer("/stable/files")
+        .and_then(|files| files.as_object())
+        .map(|files| files.keys().cloned().collect())
+        .unwrap_or_default();
+    tags.sort();
+    tags
+}
+
+/// Readable name of a bottle tag, e.g. "macOS Sonoma (Apple Silicon)" for "arm64_sonoma"
+pub fn bottle_tag_label(tag: &str) -> String {
+    // Intel macOS tags have no architecture prefix
+    let (name, arch) = match (tag.strip_prefix("arm64_"), tag.strip_prefix("x86_64_")) {
+        (Some(name), _) => (name, "Apple Silicon"),
+        (_, Some(name)) => (name, "Intel"),
+        _ => (tag, "Intel"),
+    };
+    match name {
+        "all" => "any platform".to_string(),
+        "linux" => format!("Linux ({})", if arch == "Apple Silicon" { "ARM64" } else { "x86-64" }),
+        _ => {
+            let release: Vec<String> = name
+                .split('_')
+                .map(|word| {
+                    let mut chars = word.chars();
+                    chars.next().map_or_else(String::new, |first| first.to_uppercase().chain(chars).collect())
+                })
+                .collect();
+            format!("macOS {} ({})", release.join(" "), arch)
+        }
+    }
+}
+
+/// Rough idea of how long building `formula` from source takes, judged by how much it
+/// depends on, for warnings such as "which will likely take several minutes"
+pub fn source_build_estimate(formula: &BrewInfoFormula) -> &'static str {
+    let dependencies = formula.dependencies.as_ref().map_or(0, Vec::len)
+        + formula.build_dependencies.as_ref().map_or(0, Vec::len);
+    match dependencies {
+        0..=2 => "a few minutes",
+        3..=9 => "several minutes",
+        _ => "a long time, possibly over an hour",
+    }
+}
+
+/// Install a package with the given `brew install` flags
+pub async fn install_package(package_name: &str, options: &InstallOptions) -> BrewResult<String> {
+    install(package_name, options, None).await
+}
+
+/// Install a package, sending download progress to `progress`
+pub async fn install_package_with_progress(
+    package_name: &str,
+    options: &InstallOptions,
+    progress: ProgressSender,
+) -> BrewResult<String> {
+    install(package_name, options, Some(&progress)).await
+}
+
+async fn install(
+    package_name: &str,
+    options: &InstallOptions,
+    progress: Option<&ProgressSender>,
+) -> BrewResult<String> {
+    INSTALL_RATE_LIMITER.check_or_error()?;
+    validate_package_name(package_name)?;
+    for option in &options.formula_options {
+        validate_install_flag(option)?;
+    }
+
+    let args = install_args(package_name, options);
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    if let Some(simulated) = simulate(&args, true).await {
+        return Err(simulated);
+    }
+
+    let output = run_brew_with_progress(&args, progress)
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+    invalidate_caches(Some(package_name)).await;
+
+    if !output.status.success() {
+        return Err(BrewError::from_stderr(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// The brew arguments `install_package` runs for `package_name`, also for running it in a
+/// terminal or copying the command
+pub fn install_args(package_name: &str, options: &InstallOptions) -> Vec<String> {
+    let options = InstallOptions {
+        no_quarantine: options.no_quarantine || NO_QUARANTINE.load(Ordering::Relaxed),
+        ..options.clone()
+    };
+    let mut args = vec!["install"];
+    if VERBOSE.load(Ordering::Relaxed) {
+        args.push("--verbose");
+    }
+    args.extend(options.args());
+    args.push(package_name);
+    args.into_iter().map(str::to_string).collect()
+}
+
+/// The brew arguments uninstalling `name`: `uninstall_package` for a formula, or
+/// `uninstall_cask` for a cask
+pub fn uninstall_args(name: &str, cask: bool, zap: bool) -> Vec<String> {
+    let mut args = vec!["uninstall"];
+    if cask {
+        if zap {
+            args.push("--zap");
+        }
+        args.push("--cask");
+    }
+    args.push(name);
+    args.into_iter().map(str::to_string).collect()
+}
+
+/// The brew arguments `upgrade_packages` runs for `package_name`, or for every package
+pub fn upgrade_args(package_name: Option<&str>) -> Vec<String> {
+    let mut args = vec!["upgrade"];
+    if VERBOSE.load(Ordering::Relaxed) {
+        args.push("--verbose");
+    }
+    args.extend(package_name);
+    args.into_iter().map(str::to_string).collect()
+}
+
+/// Uninstall a package
+pub async fn uninstall_package(package_name: &str) -> BrewResult<String> {
+    INSTALL_RATE_LIMITER.check_or_error()?;
+    validate_package_name(package_name)?;
+
+    let args = uninstall_args(package_name, false, false);
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    if let Some(simulated) = simulate(&args, false).await {
+        return Err(simulated);
+    }
+
+    let output = run_brew(&args)
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+    invalidate_caches(Some(package_name)).await;
+
+    if !output.status.success() {
+        return Err(BrewError::from_stderr(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Uninstall a cask. With `zap`, also remove the files listed in its `zap`
+/// stanza (preferences, caches, support files).
+pub async fn uninstall_cask(token: &str, zap: bool) -> BrewResult<String> {
+    INSTALL_RATE_LIMITER.check_or_error()?;
+    validate_package_name(token)?;
+
+    let args = uninstall_args(token, true, zap);
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    if let Some(simulated) = simulate(&args, false).await {
+        return Err(simulated);
+    }
+
+    let output = run_brew(&args)
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+    invalidate_caches(Some(token)).await;
+
+    if !output.status.success() {
+        return Err(BrewError::from_stderr(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Paths a zap uninstall of `token` would remove, for previewing before confirming
+pub async fn get_cask_zap_paths(token: &str) -> BrewResult<Vec<String>> {
+    INFO_RATE_LIMITER.check_or_error()?;
+    validate_package_name(token)?;
+
+    let output = run_brew(["info", "--json=v2", "--cask", token])
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(BrewError::from_stderr(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    let response: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| BrewError::ParseError(e.to_string()))?;
+
+    let artifacts = response
+        .pointer("/casks/0/artifacts")
+        .and_then(|a| a.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+
+    Ok(zap_paths(artifacts))
+}
+
+/// Collect the paths from the `zap` stanzas of a cask's `artifacts` array.
+/// Each stanza maps directives (trash, delete, rmdir) to a path or list of paths.
+pub fn zap_paths(artifacts: &[serde_json::Value]) -> Vec<String> {
+    let mut paths = Vec::new();
+
+    for stanza in artifacts.iter().filter_map(|a| a.get("zap")).filter_map(|z| z.as_array()).flatten() {
+        if let Some(directives) = stanza.as_object() {
+            for (directive, value) in directives {
+                if !matches!(directive.as_str(), "trash" | "delete" | "rmdir") {
+                    continue;
+                }
+                match value {
+                    serde_json::Value::String(path) => paths.push(path.clone()),
+                    serde_json::Value::Array(list) => {
+                        paths.extend(list.iter().filter_map(|p| p.as_str()).map(str::to_string))
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    paths
+}
+
+/// Files a formula installed under its keg, with their combined size
+#[derive(Debug, Clone, Default)]
+pub struct PackageFiles {
+    pub paths: Vec<String>,
+    pub total_bytes: u64,
+}
+
+/// List every file installed by a formula (`brew list --verbose <pkg>`)
+pub async fn get_package_files(package_name: &str) -> BrewResult<PackageFiles> {
+    validate_package_name(package_name)?;
+
+    let output = run_brew(["list", "--verbose", package_name])
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(BrewError::from_stderr(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    let paths: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+    let total_bytes = total_file_size(&paths);
+
+    Ok(PackageFiles { paths, total_bytes })
+}
+
+/// A commit to a formula's tap, as listed by `brew log`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormulaCommit {
+    pub hash: String,
+    /// Author name without the email address
+    pub author: String,
+    /// Commit date as git prints it, e.g. "Mon Oct 2 12:00:00 2024 +0000"
+    pub date: String,
+    /// First line of the commit message
+    pub subject: String,
+}
+
+impl FormulaCommit {
+    /// The date without time of day or time zone, e.g. "Oct 2 2024"
+    pub fn short_date(&self) -> String {
+        match self.date.split_whitespace().collect::<Vec<_>>()[..] {
+            [_, month, day, _, year, ..] => format!("{} {} {}", month, day, year),
+            _ => self.date.clone(),
+        }
+    }
+}
+
+/// Recent commits to a formula in its tap (`brew log --max-count=20 <pkg>`).
+/// Needs the tap checked out locally; with API-only Homebrew this fails.
+pub async fn get_formula_log(package_name: &str) -> BrewResult<Vec<FormulaCommit>> {
+    validate_package_name(package_name)?;
+
+    let output = run_brew(["log", "--max-count=20", package_name])
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(BrewError::from_stderr(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    Ok(parse_brew_log(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Versions `package_name` had in its tap's history, newest first, each with the date of
+/// the commit that introduced it. Needs the tap checked out locally, like `get_formula_log`.
+pub async fn get_formula_versions(package_name: &str) -> BrewResult<Vec<(String, String)>> {
+    validate_package_name(package_name)?;
+
+    let output = run_brew(["log", "--max-count=500", package_name])
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(BrewError::from_stderr(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    let name = split_tap_name(package_name).map_or(package_name, |(_, name)| name);
+    Ok(versions_in_log(name, &parse_brew_log(&String::from_utf8_lossy(&output.stdout))))
+}
+
+/// (version, short date) for each version bump in `commits`: Homebrew titles those
+/// "<name> <version>", e.g. "wget 1.24.5" or "wget 1.24.5 (#12345)"
+pub fn versions_in_log(name: &str, commits: &[FormulaCommit]) -> Vec<(String, String)> {
+    let mut versions: Vec<(String, String)> = Vec::new();
+    for commit in commits {
+        let Some(version) = commit
+            .subject
+            .strip_prefix(name)
+            .and_then(|rest| rest.strip_prefix(' '))
+            .and_then(|rest| rest.split_whitespace().next())
+            .map(|version| version.trim_end_matches([':', ',']))
+        else {
+            continue;
+        };
+        if version.starts_with(|c: char| c.is_ascii_digit()) && !versions.iter().any(|(v, _)| v == version) {
+            versions.push((version.to_string(), commit.short_date()));
+        }
+    }
+    versions
+}
+
+/// Local tap that `extract_version` copies old versions into, created on first use
+pub const EXTRACT_TAP: &str = "brewhouse/versions";
+
+/// Copy `package_name` as it was at `version` into `EXTRACT_TAP` (`brew extract`), so it
+/// can be installed next to the current one. Returns the name to install,
+/// e.g. `brewhouse/versions/wget@1.21.3`.
+pub async fn extract_version(package_name: &str, version: &str) -> BrewResult<String> {
+    validate_package_name(package_name)?;
+    if version.contains('/') || validate_package_name(version).is_err() {
+        return Err(BrewError::ParseError(format!("Invalid version: {}", version)));
+    }
+
+    let version_arg = format!("--version={}", version);
+    if let Some(simulated) = simulate(&["extract", &version_arg, package_name, EXTRACT_TAP], false).await {
+        return Err(simulated);
+    }
+
+    ensure_local_tap(EXTRACT_TAP).await?;
+
+    let output = run_brew(["extract", &version_arg, package_name, EXTRACT_TAP])
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(BrewError::from_stderr(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    Ok(extracted_name(package_name, version))
+}
+
+/// Name of the formula `brew extract` writes for `package_name` at `version`
+fn extracted_name(package_name: &str, version: &str) -> String {
+    let name = split_tap_name(package_name).map_or(package_name, |(_, name)| name);
+    format!("{}/{}@{}", EXTRACT_TAP, name, version)
+}
+
+/// Parse the default `git log` format printed by `brew log`
+pub fn parse_brew_log(output: &str) -> Vec<FormulaCommit> {
+    let mut commits = Vec::new();
+    let mut current: Option<FormulaCommit> = None;
+    for line in output.lines() {
+        if let Some(hash) = line.strip_prefix("commit ") {
+            commits.extend(current.take());
+            current = Some(FormulaCommit {
+                hash: hash.split_whitespace().next().unwrap_or_default().to_string(),
+                author: String::new(),
+                date: String::new(),
+                subject: String::new(),
+            });
+            continue;
+        }
+        let Some(commit) = current.as_mut() else {
+            continue;
+        };
+        if let Some(author) = line.strip_prefix("Author:") {
+            let author = author.trim();
+            commit.author = author.split(" <").next().unwrap_or(author).to_string();
+        } else if let Some(date) = line.strip_prefix("Date:") {
+            commit.date = date.trim().to_string();
+        } else if commit.subject.is_empty() && line.starts_with(char::is_whitespace) && !line.trim().is_empty() {
+            commit.subject = line.trim().to_string();
+        }
+    }
+    commits.extend(current);
+    commits
+}
+
+/// Sum the sizes of `paths`, not following symlinks. Missing files count as zero.
+pub fn total_file_size(paths: &[String]) -> u64 {
+    paths
+        .iter()
+        .filter_map(|path| std::fs::symlink_metadata(path).ok())
+        .map(|meta| meta.len())
+        .sum()
+}
+
+/// Symlink an installed formula into the Homebrew prefix.
+/// `force` is required for keg-only formulae.
+pub async fn link_package(package_name: &str, force: bool) -> BrewResult<String> {
+    INSTALL_RATE_LIMITER.check_or_error()?;
+    validate_package_name(package_name)?;
+
+    let mut args = vec!["link"];
+    if force {
+        args.push("--force");
+    }
+    args.push(package_name);
+    if let Some(simulated) = simulate(&args, true).await {
+        return Err(simulated);
+    }
+
+    let output = run_brew(&args)
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+    invalidate_caches(Some(package_name)).await;
+
+    if !output.status.success() {
+        return Err(BrewError::from_stderr(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Pin an installed formula so `brew upgrade` leaves it at its current version
+pub async fn pin_package(package_name: &str) -> BrewResult<String> {
+    INSTALL_RATE_LIMITER.check_or_error()?;
+    validate_package_name(package_name)?;
+    if let Some(simulated) = simulate(&["pin", package_name], false).await {
+        return Err(simulated);
+    }
+
+    let output = run_brew(["pin", package_name])
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+    invalidate_caches(Some(package_name)).await;
+
+    if !output.status.success() {
+        return Err(BrewError::from_stderr(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Remove a formula's symlinks from the Homebrew prefix
+pub async fn unlink_package(package_name: &str) -> BrewResult<String> {
+    INSTALL_RATE_LIMITER.check_or_error()?;
+    validate_package_name(package_name)?;
+
+    if let Some(simulated) = simulate(&["unlink", package_name], true).await {
+        return Err(simulated);
+    }
+
+    let output = run_brew(["unlink", package_name])
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+    invalidate_caches(Some(package_name)).await;
+
+    if !output.status.success() {
+        return Err(BrewError::from_stderr(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Remove old installed versions of a formula, keeping the current one
+pub async fn cleanup_package(package_name: &str) -> BrewResult<String> {
+    INSTALL_RATE_LIMITER.check_or_error()?;
+    validate_package_name(package_name)?;
+
+    if let Some(simulated) = simulate(&["cleanup", package_name], true).await {
+        return Err(simulated);
+    }
+
+    let output = run_brew(["cleanup", package_name])
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+    invalidate_caches(Some(package_name)).await;
+
+    if !output.status.success() {
+        return Err(BrewError::from_stderr(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Update brew itself - returns (stdout, stderr) for display
+pub async fn update_brew() -> BrewResult<(String, String)> {
+    let output = run_brew(["update"])
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+    // brew update writes progress to stderr, so we return both
+    if !output.status.success() {
+        return Err(BrewError::from_stderr(format!("{}\n{}", stdout, stderr)));
+    }
+
+    Ok((stdout, stderr))
+}
+
+/// What a `brew upgrade` run did, parsed from its output
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UpgradeReport {
+    /// `(name, from, to)` for each package that was upgraded
+    pub upgraded: Vec<(String, String, String)>,
+    /// Packages brew left alone because they were already up to date
+    pub skipped: Vec<String>,
+    /// Outdated packages brew did not upgrade because they are pinned
+    pub pinned: Vec<String>,
+    /// The raw stdout, for history and logs
+    pub output: String,
+}
+
+impl UpgradeReport {
+    /// One line describing the run, e.g. "Upgraded wget 1.21.3 → 1.21.4; 1 pinned: node"
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if self.upgraded.is_empty() {
+            parts.push("Nothing was upgraded".to_string());
+        } else {
+            let upgraded: Vec<String> = self
+                .upgraded
+                .iter()
+                .map(|(name, from, to)| format!("{} {} → {}", name, from, to))
+                .collect();
+            parts.push(format!("Upgraded {}", upgraded.join(", ")));
+        }
+        if !self.pinned.is_empty() {
+            parts.push(format!("{} pinned: {}", self.pinned.len(), self.pinned.join(", ")));
+        }
+        if !self.skipped.is_empty() {
+            parts.push(format!("already up to date: {}", self.skipped.join(", ")));
+        }
+        parts.join("; ")
+    }
+}
+
+/// Parse the combined stdout and stderr of `brew upgrade`.
+/// Lines that don't match a known shape are ignored, so the report may be incomplete
+/// but never wrong about a package it does list.
+pub fn parse_upgrade_output(output: &str) -> UpgradeReport {
+    enum Section {
+        Upgrading,
+        Pinned,
+    }
+
+    let mut report = UpgradeReport::default();
+    let mut section = None;
+    for line in output.lines() {
+        let line = line.trim_end();
+
+        // "==> Upgrading 2 outdated packages:" heads the list; "==> Upgrading wget" does not
+        if line.starts_with("==> Upgrading ") && line.ends_with(':') {
+            section = Some(Section::Upgrading);
+            continue;
+        }
+        if let Some(warning) = line.strip_prefix("Warning: ").or_else(|| line.strip_prefix("Error: ")) {
+            // "Warning: Not upgrading 1 pinned package:", or an error when the package was named
+            section = if warning.starts_with("Not upgrading ") && warning.contains("pinned") {
+                Some(Section::Pinned)
+            } else {
+                None
+            };
+            // "Warning: wget 1.21.4 already installed"
+            if let Some(name) = warning
+                .strip_suffix(" already installed")
+                .and_then(|rest| rest.split_whitespace().next())
+            {
+                report.skipped.push(name.to_string());
+            }
+            continue;
+        }
+        if line.is_empty() || line.starts_with("==>") || line.starts_with(char::is_whitespace) {
+            section = None;
+            continue;
+        }
+
+        match section {
+            // "wget 1.21.3 -> 1.21.4"; several old kegs may be listed before the arrow
+            Some(Section::Upgrading) => {
+                if let Some((old, new)) = line.split_once(" -> ") {
+                    if let Some((name, from)) = old.split_once(' ') {
+                        report
+                            .upgraded
+                            .push((name.to_string(), from.trim().to_string(), new.trim().to_string()));
+                    }
+                }
+            }
+            // "node 20.1.0"
+            Some(Section::Pinned) => {
+                if let Some(name) = line.split_whitespace().next() {
+                    report.pinned.push(name.to_string());
+                }
+            }
+            None => {}
+        }
+    }
+    report
+}
+
+/// Upgrade all packages or a specific package
+pub async fn upgrade_packages(package_name: Option<&str>) -> BrewResult<UpgradeReport> {
+    upgrade(package_name, None).await
+}
+
+/// Upgrade all packages or a specific package, sending download progress to `progress`
+pub async fn upgrade_packages_with_progress(
+    package_name: Option<&str>,
+    progress: ProgressSender,
+) -> BrewResult<UpgradeReport> {
+    upgrade(package_name, Some(&progress)).await
+}
+
+async fn upgrade(package_name: Option<&str>, progress: Option<&ProgressSender>) -> BrewResult<UpgradeReport> {
+    INSTALL_RATE_LIMITER.check_or_error()?;
+    if let Some(name) = package_name {
+        validate_package_name(name)?;
+    }
+
+    let args = upgrade_args(package_name);
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    if let Some(simulated) = simulate(&args, true).await {
+        return Err(simulated);
+    }
+
+    let output = run_brew_with_progress(&args, progress)
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+    invalidate_caches(package_name).await;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let mut report = parse_upgrade_output(&format!("{}\n{}", stdout, stderr));
+    // Naming a pinned package makes brew exit 1, but it was skipped rather than failed
+    let pinned = package_name.is_some_and(|name| report.pinned.iter().any(|pinned| pinned == name));
+    if !output.status.success() && !pinned {
+        return Err(BrewError::from_stderr(stderr.to_string()));
+    }
+
+    report.output = stdout;
+    Ok(report)
+}
+
+/// Run `brew <flag>` for a path query such as `--cellar` or `--caskroom`
+async fn get_brew_path(flag: &str) -> BrewResult<PathBuf> {
+    let output = run_brew([flag])
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(BrewError::from_stderr(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    Ok(PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()))
+}
+
+/// Total size in bytes of everything under `path`, not following symlinks.
+/// Unreadable entries are skipped.
+pub fn dir_size(path: &Path) -> u64 {
+    let meta = match std::fs::symlink_metadata(path) {
+        Ok(meta) => meta,
+        Err(_) => return 0,
+    };
+    if !meta.is_dir() {
+        return meta.len();
+    }
+
+    std::fs::read_dir(path)
+        .map(|entries| entries.flatten().map(|entry| dir_size(&entry.path())).sum())
+        .unwrap_or(0)
+}
+
+/// Disk usage of each formula's Cellar directory (all installed versions)
+pub async fn get_keg_sizes(names: &[String]) -> BrewResult<HashMap<String, u64>> {
+    let cellar = get_brew_path("--cellar").await?;
+
+    Ok(names
+        .iter()
+        .map(|name| (name.clone(), dir_size(&cellar.join(name))))
+        .collect())
+}
+
+/// Disk space used by the main Homebrew directories
+#[derive(Debug, Clone, Default)]
+pub struct DiskUsage {
+    pub cellar: u64,
+    pub caskroom: u64,
+    pub cache: u64,
+}
+
+impl DiskUsage {
+    pub fn total(&self) -> u64 {
+        self.cellar + self.caskroom + self.cache
+    }
+}
+
+/// Measure the Cellar, Caskroom and download cache
+pub async fn get_disk_usage() -> BrewResult<DiskUsage> {
+    let cellar = get_brew_path("--cellar").await?;
+    let caskroom = get_brew_path("--caskroom").await?;
+    let cache = get_brew_path("--cache").await?;
+
+    Ok(DiskUsage {
+        cellar: dir_size(&cellar),
+        caskroom: dir_size(&caskroom),
+        cache: dir_size(&cache),
+    })
+}
+
+/// A downloaded bottle or source archive in `brew --cache`
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    pub path: PathBuf,
+    /// File name without the URL hash prefix, e.g. "wget--1.21.4.arm64_sonoma.bottle.tar.gz"
+    pub name: String,
+    pub size: u64,
+    /// Unix timestamp (seconds) of the last modification
+    pub modified: u64,
+}
+
+/// List the downloads in the Homebrew cache, newest first
+pub async fn get_cache_entries() -> BrewResult<Vec<CacheEntry>> {
+    let cache = get_brew_path("--cache").await?;
+    Ok(list_cache_dir(&cache))
+}
+
+/// Entries of `<cache>/downloads`, where brew keeps the actual files
+/// (the top level mostly holds symlinks to them).
+pub fn list_cache_dir(cache: &Path) -> Vec<CacheEntry> {
+    let entries = match std::fs::read_dir(cache.join("downloads")) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut result: Vec<CacheEntry> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let meta = std::fs::symlink_metadata(&path).ok()?;
+            let modified = meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            let file_name = entry.file_name().to_string_lossy().to_string();
+
+            Some(CacheEntry {
+                name: cache_display_name(&file_name).to_string(),
+                size: dir_size(&path),
+                modified,
+                path,
+            })
+        })
+        .collect();
+
+    result.sort_by(|a, b| b.modified.cmp(&a.modified).then_with(|| a.name.cmp(&b.name)));
+    result
+}
+
+/// Strip the "<sha256>--" prefix brew puts in front of downloaded file names
+pub fn cache_display_name(file_name: &str) -> &str {
+    match file_name.split_once("--") {
+        Some((hash, rest)) if hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()) => rest,
+        _ => file_name,
+    }
+}
+
+/// Entries last modified more than `days` days before `now` (Unix seconds)
+pub fn cache_entries_older_than(entries: &[CacheEntry], days: u64, now: u64) -> Vec<CacheEntry> {
+    let cutoff = now.saturating_sub(days * 24 * 60 * 60);
+    entries.iter().filter(|e| e.modified < cutoff).cloned().collect()
+}
+
+/// Delete cache entries and any symlinks left dangling in the cache root.
+/// Returns the number of bytes freed.
+pub async fn delete_cache_entries(entries: &[CacheEntry]) -> BrewResult<u64> {
+    if is_dry_run() {
+        let names: Vec<&str> = entries.iter().map(|entry| entry.name.as_str()).collect();
+        return Err(BrewError::DryRun(format!("Would delete {}", names.join(", "))));
+    }
+    let cache = get_brew_path("--cache").await?;
+    let mut freed = 0;
+
+    for entry in entries {
+        // Never touch anything outside the cache
+        if !entry.path.starts_with(&cache) {
+            continue;
+        }
+        let removed = if entry.path.is_dir() {
+            std::fs::remove_dir_all(&entry.path)
+        } else {
+            std::fs::remove_file(&entry.path)
+        };
+        removed.map_err(|e| BrewError::CommandFailed(format!("{}: {}", entry.name, e)))?;
+        freed += entry.size;
+    }
+
+    remove_dangling_links(&cache);
+    Ok(freed)
+}
+
+/// Remove symlinks in `dir` whose target no longer exists
+fn remove_dangling_links(dir: &Path) {
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let is_link = std::fs::symlink_metadata(&path).map(|m| m.file_type().is_symlink()).unwrap_or(false);
+            if is_link && !path.exists() {
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+    }
+}
+
+/// The keg a file on disk belongs to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileOwner {
+    pub package: String,
+    pub version: String,
+    pub cask: bool,
+}
+
+/// Work out which installed formula or cask owns `path`. Symlinks (e.g.
+/// `$(brew --prefix)/bin/wget`) are resolved into the Cellar first.
+/// Returns None if the file isn't managed by Homebrew.
+pub async fn find_file_owner(path: &str) -> BrewResult<Option<FileOwner>> {
+    let resolved = std::fs::canonicalize(path)
+        .map_err(|e| BrewError::CommandFailed(format!("{}: {}", path, e)))?;
+
+    let cellar = get_brew_path("--cellar").await?;
+    if let Some(owner) = owner_from_keg_path(&resolved, &cellar, false) {
+        return Ok(Some(owner));
+    }
+
+    let caskroom = get_brew_path("--caskroom").await?;
+    Ok(owner_from_keg_path(&resolved, &caskroom, true))
+}
+
+/// If `path` lies inside `<root>/<package>/<version>/`, return that keg
+pub fn owner_from_keg_path(path: &Path, root: &Path, cask: bool) -> Option<FileOwner> {
+    // The root itself may be a symlink (e.g. /usr/local/Cellar on some setups)
+    let root = std::fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+    let mut components = path.strip_prefix(&root).ok()?.components();
+
+    let package = components.next()?.as_os_str().to_string_lossy().to_string();
+    let version = components.next()?.as_os_str().to_string_lossy().to_string();
+
+    Some(FileOwner { package, version, cask })
+}
+
+/// Get `brew config` output as (key, value) pairs in the order brew prints them
+pub async fn get_brew_config() -> BrewResult<Vec<(String, String)>> {
+    let output = run_brew(["config"])
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(BrewError::from_stderr(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    Ok(parse_brew_config(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// The Homebrew version from `brew --version`, e.g. "4.4.1"
+pub async fn get_brew_version() -> BrewResult<String> {
+    let output = run_brew(["--version"])
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(BrewError::from_stderr(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    parse_brew_version(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| BrewError::ParseError("brew --version printed no version".to_string()))
+}
+
+/// The version from the "Homebrew 4.4.1" line that starts `brew --version` output
+pub fn parse_brew_version(output: &str) -> Option<String> {
+    let version = output.lines().next()?.trim().strip_prefix("Homebrew ")?;
+    Some(version.trim().to_string()).filter(|version| !version.is_empty())
+}
+
+/// Parse "KEY: value" lines from `brew config`, skipping anything else
+pub fn parse_brew_config(output: &str) -> Vec<(String, String)> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .filter(|(key, _)| !key.is_empty())
+        .collect()
+}
+
+/// Get brew statistics for status overview
+pub async fn get_brew_stats() -> BrewResult<BrewStats> {
+    // Installed, outdated, leaves and casks all come from the shared snapshot;
+    // the rest are independent, so run everything concurrently
+    let (snapshot, formulae, taps) = tokio::join!(
+        get_installed_snapshot(),
+        count_output_lines(&["formulae"]),
+        count_output_lines(&["tap"]),
+    );
+    let (installed, casks, outdated, leaves) = match snapshot {
+        Ok(snapshot) => (
+            snapshot.formulae.len(),
+            snapshot.casks.len(),
+            snapshot.outdated_count(),
+            snapshot.leaves_count(),
+        ),
+        Err(_) => (0, 0, 0, 0),
+    };
+
+    Ok(BrewStats {
+        installed,
+        casks,
+        outdated,
+        formulae,
+        leaves,
+        taps,
+    })
+}
+
+/// Number of non-empty lines brew prints for `args`; 0 if the command can't run
+async fn count_output_lines(args: &[&str]) -> usize {
+    run_brew(args)
+        .await
+        .map(|o| count_lines(&o.stdout))
+        .unwrap_or(0)
+}
+
+fn count_lines(stdout: &[u8]) -> usize {
+    String::from_utf8_lossy(stdout).lines().filter(|l| !l.is_empty()).count()
+}
+
+#[derive(Debug, Clone)]
+pub struct BrewStats {
+    pub installed: usize,
+    pub casks: usize,
+    pub outdated: usize,
+    pub formulae: usize,
+    pub leaves: usize,
+    pub taps: usize,
+}
+
+/// Get list of outdated packages
+pub async fn get_outdated_packages() -> BrewResult<Vec<String>> {
+    let output = run_brew(["outdated", "--formula"])
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(BrewError::from_stderr(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    let packages = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    Ok(packages)
+}
+
+/// A published GitHub release of a formula's upstream project
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Release {
+    pub tag: String,
+    pub name: Option<String>,
+    /// Release notes as written upstream, usually Markdown
+    pub body: String,
+    pub url: String,
+}
+
+/// Upstream releases between the installed and the available version of a formula
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseNotes {
+    /// `owner/repo` on GitHub
+    pub repo: String,
+    pub installed: Option<String>,
+    pub available: String,
+    /// Newest first, as GitHub lists them
+    pub releases: Vec<Release>,
+}
+
+impl ReleaseNotes {
+    pub fn releases_url(&self) -> String {
+        format!("https://github.com/{}/releases", self.repo)
+    }
+}
+
+// The GitHub API allows 60 unauthenticated requests an hour, so notes are kept for a while
+static RELEASE_NOTES_CACHE: TtlCache<Option<ReleaseNotes>> = TtlCache::new(std::time::Duration::from_secs(60 * 60));
+
+/// `owner/repo` of the GitHub project a formula is downloaded from, if it is hosted there
+pub fn github_repo(info: &BrewInfoFormula) -> Option<String> {
+    let urls = info.urls.as_ref();
+    [
+        urls.and_then(|u| u.stable.as_ref()).map(|u| u.url.as_str()),
+        urls.and_then(|u| u.head.as_ref()).map(|u| u.url.as_str()),
+        info.homepage.as_deref(),
+    ]
+    .into_iter()
+    .flatten()
+    .find_map(github_repo_from_url)
+}
+
+fn github_repo_from_url(url: &str) -> Option<String> {
+    let path = ["https://github.com/", "http://github.com/", "https://www.github.com/"]
+        .iter()
+        .find_map(|prefix| url.strip_prefix(prefix))?;
+    let mut parts = path.split('/');
+    let owner = parts.next().filter(|owner| !owner.is_empty())?;
+    let repo = parts.next()?.trim_end_matches(".git");
+    if repo.is_empty() {
+        return None;
+    }
+    Some(format!("{}/{}", owner, repo))
+}
+
+/// Order two version strings by their numeric and alphabetic parts,
+/// so "1.10" sorts after "1.9" and "8_5_0" equals "8.5.0"
+pub fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    fn parts(version: &str) -> Vec<&str> {
+        let mut parts = Vec::new();
+        for chunk in version.split(|c: char| !c.is_ascii_alphanumeric()) {
+            // "1rc2" -> "1", "rc", "2"
+            let mut rest = chunk;
+            while !rest.is_empty() {
+                let digits = rest.starts_with(|c: char| c.is_ascii_digit());
+                let end = rest.find(|c: char| c.is_ascii_digit() != digits).unwrap_or(rest.len());
+                parts.push(&rest[..end]);
+                rest = &rest[end..];
+            }
+        }
+        parts
+    }
+
+    let (a, b) = (parts(a), parts(b));
+    for (x, y) in a.iter().zip(&b) {
+        let ordering = match (x.parse::<u64>(), y.parse::<u64>()) {
+            (Ok(x), Ok(y)) => x.cmp(&y),
+            _ => x.cmp(y),
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+/// The version a release tag names: "v1.2.3", "wget-1.2.3" and "release-1.2.3" all give "1.2.3"
+fn tag_version(tag: &str) -> &str {
+    tag.find(|c: char| c.is_ascii_digit()).map_or(tag, |start| &tag[start..])
+}
+
+/// Drop Homebrew's rebuild suffix, e.g. "1.21.3_1" -> "1.21.3"
+fn strip_revision(version: &str) -> &str {
+    match version.rsplit_once('_') {
+        Some((base, revision)) if revision.chars().all(|c| c.is_ascii_digit()) => base,
+        _ => version,
+    }
+}
+
+/// Parse the response of the GitHub "list releases" API, skipping drafts and pre-releases
+pub fn parse_github_releases(json: &str) -> BrewResult<Vec<Release>> {
+    #[derive(Deserialize)]
+    struct GithubRelease {
+        tag_name: String,
+        name: Option<String>,
+        body: Option<String>,
+        html_url: String,
+        #[serde(default)]
+        draft: bool,
+        #[serde(default)]
+        prerelease: bool,
+    }
+
+    let releases: Vec<GithubRelease> = serde_json::from_str(json)
+        .map_err(|e| BrewError::ParseError(e.to_string()))?;
+
+    Ok(releases
+        .into_iter()
+        .filter(|release| !release.draft && !release.prerelease)
+        .map(|release| Release {
+            tag: release.tag_name,
+            name: release.name.filter(|name| !name.trim().is_empty()),
+            body: release.body.unwrap_or_default().trim().to_string(),
+            url: release.html_url,
+        })
+        .collect())
+}
+
+/// Releases newer than `installed` up to and including `available`.
+/// Without an installed version only the release for `available` is kept.
+pub fn releases_between(releases: Vec<Release>, installed: Option<&str>, available: &str) -> Vec<Release> {
+    use std::cmp::Ordering;
+
+    let available = strip_revision(available);
+    let installed = installed.map(strip_revision);
+    releases
+        .into_iter()
+        .filter(|release| {
+            let version = tag_version(&release.tag);
+            let not_newer = compare_versions(version, available) != Ordering::Greater;
+            match installed {
+                Some(installed) => not_newer && compare_versions(version, installed) == Ordering::Greater,
+                None => compare_versions(version, available) == Ordering::Equal,
+            }
+        })
+        .collect()
+}
+
+/// Fetch the upstream release notes covering an update of `package_name`.
+/// `Ok(None)` means the formula isn't hosted on GitHub, so there is nothing to fetch.
+pub async fn get_release_notes(package_name: &str) -> BrewResult<Option<ReleaseNotes>> {
+    if let Some(notes) = RELEASE_NOTES_CACHE.get(package_name) {
+        return Ok(notes);
+    }
+
+    let PackageInfo::Formula(info) = get_package_info(package_name).await? else {
+        return Ok(None);
+    };
+    let (Some(repo), Some(available)) = (github_repo(&info), info.versions.stable.clone()) else {
+        RELEASE_NOTES_CACHE.insert(package_name, None);
+        return Ok(None);
+    };
+    let installed = info
+        .linked_keg
+        .clone()
+        .or_else(|| info.installed.as_ref()?.last().map(|keg| keg.version.clone()));
+
+    let url = format!("https://api.github.com/repos/{}/releases?per_page=30", repo);
+    let output = tokio::process::Command::new("curl")
+        .args(["-fsSL", "--max-time", "30", "-H", "Accept: application/vnd.github+json", &url])
+        .output()
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(BrewError::from_stderr(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    let releases = parse_github_releases(&String::from_utf8_lossy(&output.stdout))?;
+    let notes = ReleaseNotes {
+        releases: releases_between(releases, installed.as_deref(), &available),
+        repo,
+        installed,
+        available,
+    };
+    RELEASE_NOTES_CACHE.insert(package_name, Some(notes.clone()));
+    Ok(Some(notes))
+}
+
+/// Where Homebrew's JSON API lives unless HOMEBREW_API_DOMAIN points at a mirror
+const DEFAULT_API_DOMAIN: &str = "https://formulae.brew.sh/api";
+
+/// Homebrew's public analytics index, under the API domain: install-on-request counts
+/// over the last 30 days
+const ANALYTICS_PATH: &str = "analytics/install-on-request/30d.json";
+
+/// `path` under the API domain brew itself would use: HOMEBREW_API_DOMAIN as set with
+/// `runner::set_env` or in BrewHouse's environment, otherwise Homebrew's own
+fn api_url(path: &str) -> String {
+    let domain = runner::env()
+        .into_iter()
+        .find(|(name, _)| name == "HOMEBREW_API_DOMAIN")
+        .map(|(_, value)| value)
+        .or_else(|| std::env::var("HOMEBREW_API_DOMAIN").ok())
+        .filter(|domain| !domain.trim().is_empty());
+    join_api_url(domain.as_deref().unwrap_or(DEFAULT_API_DOMAIN), path)
+}
+
+fn join_api_url(domain: &str, path: &str) -> String {
+    format!("{}/{}", domain.trim().trim_end_matches('/'), path)
+}
+
+// The analytics index is ~1MB, so it is fetched at most once per session
+static ANALYTICS_CACHE: Mutex<Option<HashMap<String, u64>>> = Mutex::new(None);
+
+/// Get 30-day install counts per formula (cached after the first successful fetch)
+pub async fn get_install_analytics() -> BrewResult<HashMap<String, u64>> {
+    if let Some(cached) = ANALYTICS_CACHE.lock().unwrap().as_ref() {
+        return Ok(cached.clone());
+    }
+
+    let url = api_url(ANALYTICS_PATH);
+    let mut analytics = fetch_analytics(&url).await;
+    // Mirrors don't all carry the analytics, so Homebrew's own API is the fallback
+    let default_url = join_api_url(DEFAULT_API_DOMAIN, ANALYTICS_PATH);
+    if analytics.is_err() && url != default_url {
+        analytics = fetch_analytics(&default_url).await;
+    }
+    let analytics = analytics?;
+    *ANALYTICS_CACHE.lock().unwrap() = Some(analytics.clone());
+    Ok(analytics)
+}
+
+async fn fetch_analytics(url: &str) -> BrewResult<HashMap<String, u64>> {
+    parse_analytics(&fetch_api(url).await?)
+}
+
+/// The body of `url`, e.g. a file from Homebrew's JSON API
+async fn fetch_api(url: &str) -> BrewResult<String> {
+    // curl ships with every Homebrew installation, so it is always available here
+    let output = tokio::process::Command::new("curl")
+        .args(["-fsSL", "--compressed", "--max-time", "60", url])
+        .output()
+        .await
+        .map_err(|e| BrewError::CommandFailed(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(BrewError::from_stderr(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Parse an analytics index into a map of formula name -> install count
+pub fn parse_analytics(json: &str) -> BrewResult<HashMap<String, u64>> {
+    #[derive(Deserialize)]
+    struct AnalyticsResponse {
+        items: Vec<AnalyticsItem>,
+    }
+
+    #[derive(Deserialize)]
+    struct AnalyticsItem {
+        formula: Option<String>,
+        cask: Option<String>,
+        count: String,
+    }
+
+    let response: AnalyticsResponse = serde_json::from_str(json)
+        .map_err(|e| BrewError::ParseError(e.to_string()))?;
+
+    let analytics = response
+        .items
+        .into_iter()
+        .filter_map(|item| {
+            let name = item.formula.or(item.cask)?;
+            // Counts are formatted with thousands separators, e.g. "1,234"
+            let count = item.count.replace(',', "").parse().ok()?;
+            Some((name, count))
+        })
+        .collect();
+
+    Ok(analytics)
+}
+
+/// Sort package names by install count, most popular first.
+/// Packages without analytics data keep alphabetical order at the end.
+pub fn sort_by_popularity(names: &mut [String], analytics: &HashMap<String, u64>) {
+    names.sort_by(|a, b| {
+        let count_a = analytics.get(a).copied().unwrap_or(0);
+        let count_b = analytics.get(b).copied().unwrap_or(0);
+        count_b.cmp(&count_a).then_with(|| a.cmp(b))
+    });
+}
+
+/// The `count` most installed formulae with their install counts, most popular first
+pub fn most_installed(analytics: &HashMap<String, u64>, count: usize) -> Vec<(String, u64)> {
+    let mut top: Vec<(String, u64)> = analytics.iter().map(|(name, installs)| (name.clone(), *installs)).collect();
+    top.sort_by(|(a, a_installs), (b, b_installs)| b_installs.cmp(a_installs).then_with(|| a.cmp(b)));
+    top.truncate(count);
+    top
+}
+
+/// An install count shortened for display, e.g. 512034 -> "512K" and 1250000 -> "1.2M"
+pub fn format_install_count(count: u64) -> String {
+    match count {
+        0..=999 => count.to_string(),
+        1_000..=999_999 => format!("{}K", count / 1_000),
+        _ => format!("{}.{}M", count / 1_000_000, count % 1_000_000 / 100_000),
+    }
+}
+
+/// Sort package names by how well they match `query`: an exact name first, then names
+/// starting with it, names with it as a whole `-`/`@` separated word, names containing it,
+/// and the rest; ties go to the closest spelling, then the shortest name.
+pub fn sort_by_relevance(names: &mut [String], query: &str) {
+    let query = query.trim().to_lowercase();
+    names.sort_by_cached_key(|name| {
+        let name = name.to_lowercase();
+        // A tap-qualified name is ranked on the package part
+        let package = split_tap_name(&name).map_or(name.as_str(), |(_, package)| package).to_string();
+        let rank = if package == query {
+            0
+        } else if package.starts_with(&query) {
+            1
+        } else if package.split(['-', '@', '_', '.']).any(|word| word == query) {
+            2
+        } else if package.contains(&query) {
+            3
+        } else {
+            4
+        };
+        (rank, edit_distance(&package, &query), package.len(), name)
+    });
+}
+
+/// Byte range of the first case-insensitive occurrence of `query` in `text`, for highlighting it
+pub fn match_range(text: &str, query: &str) -> Option<std::ops::Range<usize>> {
+    let query = query.trim();
+    if query.is_empty() {
+        return None;
+    }
+    // ASCII lowercasing keeps byte offsets the same in both strings
+    let start = text.to_ascii_lowercase().find(&query.to_ascii_lowercase())?;
+    Some(start..start + query.len())
+}
+
+/// Levenshtein distance: the number of single-character edits that turn `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Build the terminal command equivalent to a GUI action, e.g. `brew install wget`.
+/// Package names are validated before use, so they never need shell quoting.
+pub fn command_line(subcommand: &str, packages: &[&str]) -> String {
+    let mut parts = vec!["brew", subcommand];
+    parts.extend_from_slice(packages);
+    parts.join(" ")
+}
+
+/// Sanitize error messages to remove sensitive information like file paths.
+/// This prevents leaking system information to users.
+pub fn sanitize_error(error: &str) -> String {
+    let mut sanitized = error.to_string();
+
+    // Remove absolute paths (Unix-style)
+    // Matches paths starting with common system directories
+    let path_regex = regex_lite::Regex::new(r"/(?:home|Users|usr|var|tmp|opt|etc)/[^\s:]+").unwrap();
+    sanitized = path_regex.replace_all(&sanitized, "[path]").to_string();
+
+    // Remove home directory references (tilde paths)
+    let home_regex = regex_lite::Regex::new(r"~/[^\s:]*").unwrap();
+    sanitized = home_regex.replace_all(&sanitized, "[path]").to_string();
+
+    sanitized
+}
+
+/// Validate a package name to prevent injection attacks.
+/// Valid package names: alphanumeric, dashes, underscores, dots, slashes (for taps), @
+/// Examples: "git", "node@18", "homebrew/core/wget"
+pub fn validate_package_name(name: &str) -> Result<(), BrewError> {
+    if name.is_empty() {
+        return Err(BrewError::ParseError("Package name cannot be empty".to_string()));
+    }
+
+    if name.len() > 256 {
+        return Err(BrewError::ParseError("Package name too long".to_string()));
+    }
+
+    // Check for valid characters only
+    let valid = name.chars().all(|c| {
+        c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' || c == '/' || c == '@'
+    });
+
+    if !valid {
+        return Err(BrewError::ParseError(format!(
+            "Invalid characters in package name: {}",
+            name
+        )));
+    }
+
+    // Prevent path traversal attempts
+    if name.contains("..") {
+        return Err(BrewError::ParseError("Invalid package name: contains '..'".to_string()));
+    }
+
+    // Prevent absolute paths
+    if name.starts_with('/') {
+        return Err(BrewError::ParseError("Invalid package name: cannot start with '/'".to_string()));
+    }
+
+    Ok(())
+}
+
+/// Validate a tap name: `user/repo`, e.g. "hashicorp/tap"
+pub fn validate_tap_name(tap: &str) -> Result<(), BrewError> {
+    validate_package_name(tap)?;
+    let mut parts = tap.split('/');
+    let valid = parts.next().is_some_and(|user| !user.is_empty())
+        && parts.next().is_some_and(|repo| !repo.is_empty())
+        && parts.next().is_none();
+    if !valid {
+        return Err(BrewError::ParseError(format!("A tap is named user/repo, not {}", tap)));
+    }
+    Ok(())
+}
+
+/// Validate the source archive URL for `brew create`
+pub fn validate_formula_url(url: &str) -> Result<(), BrewError> {
+    let valid = (url.starts_with("https://") || url.starts_with("http://"))
+        && url.len() <= 2048
+        && !url.chars().any(|c| c.is_whitespace() || c.is_control());
+    if !valid {
+        return Err(BrewError::ParseError(format!("Not a download URL: {}", url)));
+    }
+    Ok(())
+}
+
+/// Validate a formula option flag such as `--with-openssl` before passing it to brew
+pub fn validate_install_flag(flag: &str) -> Result<(), BrewError> {
+    let valid = flag.len() <= 128
+        && flag.len() > 2
+        && flag.starts_with("--")
+        && flag[2..]
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '=' || c == '.' || c == '@');
+
+    if !valid {
+        return Err(BrewError::ParseError(format!("Invalid install option: {}", flag)));
+    }
+
+    Ok(())
+}
+
+/// Validate a search query
+pub fn validate_search_query(query: &str) -> Result<(), BrewError> {
+    if query.len() > 256 {
+        return Err(BrewError::ParseError("Search query too long".to_string()));
+    }
+
+    // Allow regex-like patterns (^, $, ., *, etc.) but block shell metacharacters
+    // Note: $ alone is safe for regex anchoring, dangerous patterns like $(...) are caught by blocking ()
+    let forbidden = ['`', '(', ')', '{', '}', '<', '>', '|', ';', '&', '\n', '\r', '\0'];
+    for c in forbidden {
+        if query.contains(c) {
+            return Err(BrewError::ParseError(format!(
+                "Invalid character in search query: {:?}",
+                c
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::{with_runner, CannedRunner};
+    use std::sync::Arc;
+
+    // ==========================================================================
+    // Input Validation Tests
+    // ==========================================================================
+
+    #[test]
+    fn test_validate_package_name_valid() {
+        assert!(validate_package_name("git").is_ok());
+        assert!(validate_package_name("node").is_ok());
+        assert!(validate_package_name("node@18").is_ok());
+        assert!(validate_package_name("llvm@15").is_ok());
+        assert!(validate_package_name("homebrew/core/wget").is_ok());
+        assert!(validate_package_name("user/tap/formula").is_ok());
+        assert!(validate_package_name("my-package").is_ok());
+        assert!(validate_package_name("my_package").is_ok());
+        assert!(validate_package_name("package.name").is_ok());
+    }
+
+    #[test]
+    fn test_validate_package_name_empty() {
+        assert!(validate_package_name("").is_err());
+    }
+
+    #[test]
+    fn test_validate_package_name_too_long() {
+        let long_name = "a".repeat(257);
+        assert!(validate_package_name(&long_name).is_err());
+    }
+
+    #[test]
+    fn test_validate_package_name_shell_injection() {
+        // These should all be rejected
+        assert!(validate_package_name("; rm -rf /").is_err());
+        assert!(validate_package_name("git; echo pwned").is_err());
+        assert!(validate_package_name("$(whoami)").is_err());
+        assert!(validate_package_name("`whoami`").is_err());
+        assert!(validate_package_name("git | cat /etc/passwd").is_err());
+        assert!(validate_package_name("git && rm -rf /").is_err());
+        assert!(validate_package_name("git\nrm -rf /").is_err());
+        assert!(validate_package_name("git\0rm").is_err());
+    }
+
+    #[test]
+    fn test_validate_package_name_path_traversal() {
+        assert!(validate_package_name("../../../etc/passwd").is_err());
+        assert!(validate_package_name("foo/../bar").is_err());
+        assert!(validate_package_name("/etc/passwd").is_err());
+    }
+
+    #[test]
+    fn test_validate_search_query_valid() {
+        assert!(validate_search_query("git").is_ok());
+        assert!(validate_search_query("node").is_ok());
+        assert!(validate_search_query("json parser").is_ok());
+        assert!(validate_search_query("^git").is_ok());  // regex allowed
+        assert!(validate_search_query("git$").is_ok());
+        assert!(validate_search_query("").is_ok());  // empty is ok for search
+    }
+
+    #[test]
+    fn test_validate_search_query_shell_injection() {
+        assert!(validate_search_query("; rm -rf /").is_err());
+        assert!(validate_search_query("$(whoami)").is_err());  // blocked by ()
+        assert!(validate_search_query("`whoami`").is_err());
+        assert!(validate_search_query("git | cat").is_err());
+        assert!(validate_search_query("git && rm").is_err());
+        assert!(validate_search_query("git\nrm").is_err());
+    }
+
+    #[test]
+    fn test_validate_search_query_too_long() {
+        let long_query = "a".repeat(257);
+        assert!(validate_search_query(&long_query).is_err());
+    }
+
+    #[test]
+    fn test_validate_install_flag() {
+        assert!(validate_install_flag("--with-openssl").is_ok());
+        assert!(validate_install_flag("--with-python@3.12").is_ok());
+        assert!(validate_install_flag("--").is_err());
+        assert!(validate_install_flag("with-openssl").is_err());
+        assert!(validate_install_flag("--with; rm -rf /").is_err());
+        assert!(validate_install_flag("--with $(whoami)").is_err());
+    }
+
+    // ==========================================================================
+    // Error Display Tests
+    // ==========================================================================
+
+    #[test]
+    fn test_brew_error_display() {
+        let err = BrewError::CommandFailed("test error".to_string());
+        assert_eq!(format!("{}", err), "Brew command failed: test error");
+
+        let err = BrewError::ParseError("parse error".to_string());
+        assert_eq!(format!("{}", err), "Failed to parse brew output: parse error");
+
+        let err = BrewError::NotInstalled;
+        assert_eq!(format!("{}", err), "Homebrew is not installed or not in PATH");
+    }
+
+    #[test]
+    fn test_brew_error_from_stderr() {
+        let classify = |stderr: &str| BrewError::from_stderr(stderr.to_string());
+
+        assert!(matches!(
+            classify("curl: (6) Could not resolve host: ghcr.io\nError: Failed to download resource \"wget\""),
+            BrewError::NetworkError(_)
+        ));
+        assert!(matches!(
+            classify("Error: Permission denied @ rb_sysopen - /home/linuxbrew/.linuxbrew/bin/wget"),
+            BrewError::PermissionDenied(_)
+        ));
+        assert!(matches!(
+            classify("Error: Another active Homebrew update process is already in progress."),
+            BrewError::Locked(_)
+        ));
+        assert!(matches!(
+            classify("Error: No available formula with the name \"wgett\"."),
+            BrewError::FormulaNotFound(_)
+        ));
+        // A checksum failure also mentions the download, but the checksum is the cause
+        assert!(matches!(
+            classify("Error: SHA256 mismatch\nExpected: abc\nFailed to download resource"),
+            BrewError::ChecksumMismatch(_)
+        ));
+        assert!(matches!(classify("Error: something else"), BrewError::CommandFailed(_)));
+    }
+
+    #[test]
+    fn test_brew_error_summary() {
+        let err = BrewError::from_stderr(
+            "==> Downloading https://ghcr.io/v2/wget\nError: No available formula with the name \"wgett\".\nDid you mean?".to_string(),
+        );
+        assert_eq!(err.summary(), "No available formula with the name \"wgett\".");
+
+        let err = BrewError::CommandFailed("\n  cannot open /home/me/file\nmore".to_string());
+        assert_eq!(err.summary(), "cannot open [path]");
+
+        assert_eq!(BrewError::CommandFailed(String::new()).summary(), "Unknown error");
+        assert_eq!(BrewError::NotInstalled.summary(), "Homebrew is not installed or not in PATH");
+    }
+
+    #[test]
+    fn test_brew_error_user_message() {
+        let err = BrewError::from_stderr("Error: No available formula with the name \"wgett\".".to_string());
+        let message = err.user_message();
+        assert!(message.starts_with("Formula not found: Error: No available formula"));
+        assert!(message.ends_with(err.suggestion().unwrap()));
+
+        let err = BrewError::CommandFailed("failed in /home/me/x".to_string());
+        assert_eq!(err.user_message(), "Brew command failed: failed in [path]");
+    }
+
+    // ==========================================================================
+    // JSON Parsing Tests
+    // ==========================================================================
+
+    #[test]
+    fn test_parse_brew_info_response() {
+        let json = r#"{
+            "formulae": [{
+                "name": "git",
+                "desc": "Distributed revision control system",
+                "homepage": "https://git-scm.com",
+                "versions": {
+                    "stable": "2.43.0",
+                    "head": null,
+                    "bottle": true
+                }
+            }]
+        }"#;
+
+        #[derive(Deserialize)]
+        struct BrewInfoResponse {
+            formulae: Vec<BrewInfoFormula>,
+        }
+
+        let response: BrewInfoResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(response.formulae.len(), 1);
+        assert_eq!(response.formulae[0].name, "git");
+        assert_eq!(response.formulae[0].versions.stable.as_deref(), Some("2.43.0"));
+        assert_eq!(response.formulae[0].desc, Some("Distributed revision control system".to_string()));
+    }
+
+    #[test]
+    fn test_parse_brew_info_minimal() {
+        // Test with minimal required fields
+        let json = r#"{
+            "formulae": [{
+                "name": "minimal",
+                "versions": {
+                    "stable": "1.0.0"
+                }
+            }]
+        }"#;
+
+        #[derive(Deserialize)]
+        struct BrewInfoResponse {
+            formulae: Vec<BrewInfoFormula>,
+        }
+
+        let response: BrewInfoResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(response.formulae[0].name, "minimal");
+        assert!(response.formulae[0].desc.is_none());
+        assert!(response.formulae[0].homepage.is_none());
+    }
+
+    #[test]
+    fn test_parse_brew_info_with_dependencies() {
+        let json = r#"{
+            "formulae": [{
+                "name": "git",
+                "versions": {
+                    "stable": "2.43.0"
+                },
+                "dependencies": ["curl", "expat", "openssl", "perl"],
+                "build_dependencies": ["gettext", "cmake"]
+            }]
+        }"#;
+
+        #[derive(Deserialize)]
+        struct BrewInfoResponse {
+            formulae: Vec<BrewInfoFormula>,
+        }
+
+        let response: BrewInfoResponse = serde_json::from_str(json).unwrap();
+        let formula = &response.formulae[0];
+
+        // Verify runtime dependencies
+        let deps = formula.dependencies.as_ref().unwrap();
+        assert_eq!(deps.len(), 4);
+        assert!(deps.contains(&"curl".to_string()));
+        assert!(deps.contains(&"openssl".to_string()));
+
+        // Verify build dependencies
+        let build_deps = formula.build_dependencies.as_ref().unwrap();
+        assert_eq!(build_deps.len(), 2);
+        assert!(build_deps.contains(&"gettext".to_string()));
+        assert!(build_deps.contains(&"cmake".to_string()));
+    }
+
+    #[test]
+    fn test_parse_brew_info_empty_dependencies() {
+        let json = r#"{
+            "formulae": [{
+                "name": "simple",
+                "versions": { "stable": "1.0.0" },
+                "dependencies": [],
+                "build_dependencies": []
+            }]
+        }"#;
+
+        #[derive(Deserialize)]
+        struct BrewInfoResponse {
+            formulae: Vec<BrewInfoFormula>,
+        }
+
+        let response: BrewInfoResponse = serde_json::from_str(json).unwrap();
+        let formula = &response.formulae[0];
+
+        assert!(formula.dependencies.as_ref().unwrap().is_empty());
+        assert!(formula.build_dependencies.as_ref().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_package_struct() {
+        let pkg = Package {
+            name: "test".to_string(),
+            version: Some("1.0.0".to_string()),
+            desc: Some("Test package".to_string()),
+            homepage: Some("https://example.com".to_string()),
+            installed: true,
+            linked_keg: Some("1.0.0".to_string()),
+            keg_only: false,
+            installed_versions: vec!["1.0.0".to_string()],
+            cask: false,
+            installed_time: Some(1_700_000_000),
+            tap: Some("homebrew/core".to_string()),
+        };
+
+        // Test serialization roundtrip
+        let json = serde_json::to_string(&pkg).unwrap();
+        let parsed: Package = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed.name, "test");
+        assert_eq!(parsed.version, Some("1.0.0".to_string()));
+        assert!(parsed.installed);
+        assert_eq!(parsed.linked_keg, Some("1.0.0".to_string()));
+    }
+
+    #[test]
+    fn test_package_link_fields_default() {
+        let json = r#"{"name": "old", "version": null, "desc": null, "homepage": null, "installed": true}"#;
+        let parsed: Package = serde_json::from_str(json).unwrap();
+        assert!(parsed.linked_keg.is_none());
+        assert!(!parsed.keg_only);
+        assert!(parsed.installed_versions.is_empty());
+    }
+
+    #[test]
+    fn test_package_old_versions() {
+        let mut pkg: Package = serde_json::from_str(
+            r#"{"name": "node", "version": "21.5.0", "desc": null, "homepage": null, "installed": true}"#,
+        )
+        .unwrap();
+        assert_eq!(pkg.current_version(), None);
+        assert!(pkg.old_versions().is_empty());
+
+        pkg.installed_versions = vec!["20.10.0".to_string(), "21.5.0".to_string()];
+        assert_eq!(pkg.current_version(), Some("21.5.0"));
+        assert_eq!(pkg.old_versions(), vec!["20.10.0"]);
+
+        // An explicitly linked older keg is the one in use
+        pkg.linked_keg = Some("20.10.0".to_string());
+        assert_eq!(pkg.current_version(), Some("20.10.0"));
+        assert_eq!(pkg.old_versions(), vec!["21.5.0"]);
+    }
+
+    #[test]
+    fn test_link_state_label() {
+        let mut pkg: Package = serde_json::from_str(
+            r#"{"name": "openssl@3", "version": "3.2.0", "desc": null, "homepage": null, "installed": true}"#,
+        )
+        .unwrap();
+        assert_eq!(pkg.link_state_label(), "Not linked");
+
+        pkg.keg_only = true;
+        assert_eq!(pkg.link_state_label(), "Not linked (keg-only)");
+
+        pkg.linked_keg = Some("3.2.0".to_string());
+        assert_eq!(pkg.link_state_label(), "Linked: 3.2.0");
+    }
+
+    #[test]
+    fn test_parse_analytics() {
+        let json = r#"{
+            "category": "install_on_request",
+            "total_items": 3,
+            "items": [
+                {"number": 1, "formula": "python@3.13", "count": "512,034", "percent": "4.1"},
+                {"number": 2, "formula": "git", "count": "98,765", "percent": "0.8"},
+                {"number": 3, "formula": "broken", "count": "n/a", "percent": "0"}
+            ]
+        }"#;
+
+        let analytics = parse_analytics(json).unwrap();
+        assert_eq!(analytics.get("python@3.13"), Some(&512034));
+        assert_eq!(analytics.get("git"), Some(&98765));
+        // Unparseable counts are skipped rather than failing the whole index
+        assert!(!analytics.contains_key("broken"));
+    }
+
+    #[test]
+    fn test_parse_analytics_invalid_json() {
+        assert!(matches!(parse_analytics("not json"), Err(BrewError::ParseError(_))));
+    }
+
+    #[test]
+    fn test_parse_package_list() {
+        let text = "# Dev tools\nwget jq,ripgrep\n  brew install --cask firefox && brew install `fd`\nwget\n";
+        assert_eq!(parse_package_list(text), vec!["wget", "jq", "ripgrep", "firefox", "fd"]);
+        assert!(parse_package_list(" \n# nothing\n").is_empty());
+        assert_eq!(parse_package_list("PACKAGES=(\n  git\n  \"gh\"\n)\n"), vec!["git", "gh"]);
+        assert_eq!(parse_package_list("packages=(wget jq)"), vec!["wget", "jq"]);
+    }
+
+    #[test]
+    fn test_classify_package_list() {
+        let set = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        let names = parse_package_list("wget jq firefox user/tap/tool nope");
+        let check = classify_package_list(&names, &set(&["wget", "jq"]), &set(&["firefox"]), &set(&["wget"]));
+        assert_eq!(check.installed, vec!["wget"]);
+        assert_eq!(check.formulae, vec!["jq", "user/tap/tool"]);
+        assert_eq!(check.casks, vec!["firefox"]);
+        assert_eq!(check.unknown, vec!["nope"]);
+    }
+
+    #[test]
+    fn test_names_from_desc_search() {
+        let lines = vec![
+            "qpdf: Tools for and transforming and inspecting PDF files".to_string(),
+            "pdfcpu: PDF processor written in Go".to_string(),
+            "pdftk-java".to_string(),
+        ];
+        assert_eq!(names_from_desc_search(&lines, &["pdf"]), vec!["qpdf", "pdfcpu", "pdftk-java"]);
+        assert_eq!(names_from_desc_search(&lines, &["/^pdf/"]), vec!["qpdf", "pdfcpu", "pdftk-java"]);
+        // Every word has to match, in the name or the description
+        assert_eq!(names_from_desc_search(&lines, &["PDF", "inspecting"]), vec!["qpdf"]);
+        assert_eq!(names_from_desc_search(&lines, &["pdf", "go"]), vec!["pdfcpu"]);
+        assert!(names_from_desc_search(&lines, &["pdf", "merge"]).is_empty());
+    }
+
+    #[test]
+    fn test_join_api_url() {
+        assert_eq!(
+            join_api_url(DEFAULT_API_DOMAIN, ANALYTICS_PATH),
+            "https://formulae.brew.sh/api/analytics/install-on-request/30d.json"
+        );
+        let mirror = "https://mirrors.tuna.tsinghua.edu.cn/homebrew-bottles/api/";
+        assert_eq!(
+            join_api_url(mirror, "formula.jws.json"),
+            "https://mirrors.tuna.tsinghua.edu.cn/homebrew-bottles/api/formula.jws.json"
+        );
+    }
+
+    #[test]
+    fn test_sort_by_relevance() {
+        let mut names: Vec<String> = ["bpython", "python-tk@3.12", "ptpython", "python@3.12", "python", "cython"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        sort_by_relevance(&mut names, "Python");
+        assert_eq!(names, vec!["python", "python@3.12", "python-tk@3.12", "bpython", "ptpython", "cython"]);
+
+        // A whole word beats a substring; a near miss sorts by spelling
+        let mut names: Vec<String> =
+            ["jqp", "gojq", "jq-lsp", "homebrew/core/jq"].iter().map(|name| name.to_string()).collect();
+        sort_by_relevance(&mut names, "jq");
+        assert_eq!(names, vec!["homebrew/core/jq", "jqp", "jq-lsp", "gojq"]);
+
+        assert_eq!(edit_distance("wget", "wegt"), 2);
+        assert_eq!(edit_distance("", "git"), 3);
+    }
+
+    #[test]
+    fn test_match_range() {
+        assert_eq!(match_range("python@3.12", " PYTHON "), Some(0..6));
+        assert_eq!(match_range("bpython", "thon"), Some(3..7));
+        assert_eq!(match_range("wget", ""), None);
+        assert_eq!(match_range("wget", "curl"), None);
+    }
+
+    #[test]
+    fn test_sort_by_popularity() {
+        let analytics = HashMap::from([
+            ("python@3.13".to_string(), 500),
+            ("pytest".to_string(), 20),
+        ]);
+        let mut names = vec![
+            "bpython".to_string(),
+            "pytest".to_string(),
+            "apython".to_string(),
+            "python@3.13".to_string(),
+        ];
+
+        sort_by_popularity(&mut names, &analytics);
+        assert_eq!(names, vec!["python@3.13", "pytest", "apython", "bpython"]);
+    }
+
+    #[test]
+    fn test_most_installed() {
+        let analytics = HashMap::from([
+            ("python@3.13".to_string(), 500),
+            ("git".to_string(), 20),
+            ("bash".to_string(), 20),
+        ]);
+        assert_eq!(
+            most_installed(&analytics, 2),
+            vec![("python@3.13".to_string(), 500), ("bash".to_string(), 20)]
+        );
+        assert_eq!(most_installed(&HashMap::new(), 2), vec![]);
+    }
+
+    #[test]
+    fn test_format_install_count() {
+        assert_eq!(format_install_count(950), "950");
+        assert_eq!(format_install_count(512_034), "512K");
+        assert_eq!(format_install_count(1_250_000), "1.2M");
+    }
+
+    // ==========================================================================
+    // Output Parsing and Helper Tests
+    // ==========================================================================
+
+    #[test]
+    fn test_installed_snapshot_counts() {
+        let json = r#"{
+            "formulae": [
+                {
+                    "name": "wget",
+                    "full_name": "wget",
+                    "versions": { "stable": "1.24.5" },
+                    "outdated": true,
+                    "installed": [{
+                        "version": "1.24.5",
+                        "used_options": [],
+                        "built_as_bottle": true,
+                        "poured_from_bottle": true,
+                        "runtime_dependencies": [{ "full_name": "openssl@3", "version": "3.3.0" }],
+                        "installed_as_dependency": false,
+                        "installed_on_request": true
+                    }]
+                },
+                {
+                    "name": "openssl@3",
+                    "full_name": "openssl@3",
+                    "versions": { "stable": "3.3.0" },
+                    "outdated": false,
+                    "installed": [{
+                        "version": "3.3.0",
+                        "used_options": [],
+                        "built_as_bottle": true,
+                        "poured_from_bottle": true,
+                        "runtime_dependencies": [],
+                        "installed_as_dependency": true,
+                        "installed_on_request": false
+                    }]
+                },
+                { "name": "jq", "versions": { "stable": "1.7.1" } }
+            ],
+            "casks": [{ "token": "firefox" }]
+        }"#;
+
+        let snapshot = parse_installed_snapshot(json).unwrap();
+        assert_eq!(snapshot.formulae.len(), 3);
+        assert_eq!(snapshot.casks.len(), 1);
+        assert_eq!(snapshot.casks[0].token, "firefox");
+        assert_eq!(snapshot.outdated_count(), 1);
+        // openssl@3 is a dependency of wget; wget and jq are leaves
+        assert_eq!(snapshot.leaves_count(), 2);
+
+        let snapshot = parse_installed_snapshot(r#"{ "formulae": [] }"#).unwrap();
+        assert!(snapshot.casks.is_empty());
+        assert!(parse_installed_snapshot("not json").is_err());
+    }
+
+    #[test]
+    fn test_parse_package_info_formula_or_cask() {
+        let both = r#"{
+            "formulae": [{ "name": "docker", "versions": { "stable": "27.0" } }],
+            "casks": [{ "token": "docker", "name": ["Docker Desktop"] }]
+        }"#;
+        assert!(matches!(parse_package_info(both).unwrap(), PackageInfo::Formula(f) if f.name == "docker"));
+
+        let cask = r#"{
+            "formulae": [],
+            "casks": [{
+                "token": "firefox",
+                "name": ["Mozilla Firefox"],
+                "version": "128.0",
+                "installed": "127.0",
+                "auto_updates": true,
+                "artifacts": [{ "app": ["Firefox.app"] }],
+                "caveats": null
+            }]
+        }"#;
+        match parse_package_info(cask).unwrap() {
+            PackageInfo::Cask(cask) => {
+                assert_eq!(cask.display_name(), "Mozilla Firefox");
+                assert_eq!(cask.installed.as_deref(), Some("127.0"));
+                assert_eq!(cask.auto_updates, Some(true));
+                assert_eq!(cask.artifacts.len(), 1);
+            }
+            other => panic!("expected a cask, got {:?}", other),
+        }
+
+        assert!(parse_package_info(r#"{ "formulae": [], "casks": [] }"#).is_err());
+    }
+
+    #[test]
+    fn test_install_state_comes_from_snapshot() {
+        let info = parse_package_info(
+            r#"{ "formulae": [{
+                "name": "wget",
+                "desc": "Internet file retriever",
+                "installed": [{ "version": "1.24.5" }],
+                "linked_keg": "1.24.5",
+                "pinned": true,
+                "outdated": true
+            }] }"#,
+        )
+        .unwrap();
+        let PackageInfo::Formula(cached) = info.static_metadata() else { panic!("expected a formula") };
+        assert_eq!(cached.desc.as_deref(), Some("Internet file retriever"));
+        assert!(cached.installed.is_none() && cached.linked_keg.is_none());
+        assert_eq!((cached.pinned, cached.outdated), (None, None));
+
+        // Since it was cached, wget was upgraded and unpinned
+        let snapshot = parse_installed_snapshot(
+            r#"{ "formulae": [{ "name": "wget", "installed": [{ "version": "1.25.0" }], "linked_keg": "1.25.0",
+                "pinned": false, "outdated": false }] }"#,
+        )
+        .unwrap();
+        let PackageInfo::Formula(current) = PackageInfo::Formula(cached).with_install_state(Some(&snapshot)) else {
+            panic!("expected a formula")
+        };
+        assert_eq!(current.installed.unwrap()[0].version, "1.25.0");
+        assert_eq!((current.pinned, current.outdated), (Some(false), Some(false)));
+    }
+
+    #[test]
+    fn test_other_names() {
+        let formula: BrewInfoFormula = serde_json::from_value(serde_json::json!({
+            "name": "python@3.13",
+            "aliases": ["python", "python3"],
+            "oldnames": ["python3"],
+            "oldname": "python@3.13"
+        }))
+        .unwrap();
+        assert_eq!(formula.other_names(), vec!["python", "python3"]);
+    }
+
+    #[test]
+    fn test_renames_in_api() {
+        let formulae = r#"[
+            {"name": "wget", "oldnames": [], "aliases": []},
+            {"name": "kubernetes-cli", "oldnames": ["kubectl-old"], "aliases": ["kubectl"]},
+            {"name": "etcd", "oldname": "etcd-old"}
+        ]"#;
+        let jws = serde_json::json!({ "payload": formulae, "signatures": [] }).to_string();
+        let renames: HashMap<String, String> = renames_in_api(&jws).into_iter().collect();
+        assert_eq!(renames.get("kubectl-old").map(String::as_str), Some("kubernetes-cli"));
+        assert_eq!(renames.get("kubectl").map(String::as_str), Some("kubernetes-cli"));
+        assert_eq!(renames.get("nothing"), None);
+        assert_eq!(renames.len(), 3);
+        let renames = renames_in_api(formulae);
+        assert!(renames.contains(&("etcd-old".to_string(), "etcd".to_string())));
+        assert!(renames_in_api("not json").is_empty());
+    }
+
+    #[test]
+    fn test_formula_versions_in_api() {
+        let formulae = r#"[
+            {"name": "wget", "versions": {"stable": "1.24.5", "head": "HEAD"}},
+            {"name": "head-only", "versions": {"stable": null}}
+        ]"#;
+        let versions = formula_versions_in_api(formulae);
+        assert_eq!(versions.get("wget").map(String::as_str), Some("1.24.5"));
+        assert_eq!(versions.len(), 1);
+    }
+
+    #[test]
+    fn test_descriptions_in_api() {
+        let casks = r#"[{"token": "firefox", "desc": "Web browser"}, {"token": "no-desc", "desc": null}]"#;
+        let jws = serde_json::json!({ "payload": casks }).to_string();
+        assert_eq!(descriptions_in_api(&jws, "token"), vec![("firefox".to_string(), "Web browser".to_string())]);
+        assert!(descriptions_in_api("{}", "name").is_empty());
+    }
+
+    #[test]
+    fn test_package_details() {
+        let formula = parse_package_info(r#"{
+            "formulae": [{
+                "name": "wget",
+                "desc": "Internet file retriever",
+                "versions": { "stable": "1.24.5" },
+                "dependencies": ["libidn2", "openssl@3"],
+                "build_dependencies": ["pkgconf"]
+            }]
+        }"#)
+        .unwrap()
+        .details();
+        assert_eq!(formula.kind, PackageKind::Formula);
+        assert_eq!(formula.display_name, "wget");
+        assert_eq!(formula.version.as_deref(), Some("1.24.5"));
+        assert_eq!(formula.dependencies, vec!["libidn2", "openssl@3"]);
+        assert_eq!(formula.build_dependencies, vec!["pkgconf"]);
+
+        let cask = parse_package_info(r#"{
+            "casks": [{
+                "token": "wireshark-app",
+                "name": ["Wireshark"],
+                "version": "4.2.5",
+                "caveats": "Install ChmodBPF to capture packets",
+                "depends_on": { "macos": { ">=": ["10.14"] }, "formula": ["lua"], "cask": ["wireshark-chmodbpf"] }
+            }]
+        }"#)
+        .unwrap()
+        .details();
+        assert_eq!(cask.kind, PackageKind::Cask);
+        assert_eq!(cask.name, "wireshark-app");
+        assert_eq!(cask.display_name, "Wireshark");
+        assert_eq!(cask.dependencies, vec!["lua", "wireshark-chmodbpf"]);
+        assert!(cask.build_dependencies.is_empty());
+        assert_eq!(cask.caveats.as_deref(), Some("Install ChmodBPF to capture packets"));
+    }
+
+    #[test]
+    fn test_split_tap_name() {
+        assert_eq!(split_tap_name("hashicorp/tap/terraform"), Some(("hashicorp/tap", "terraform")));
+        assert_eq!(split_tap_name("homebrew/core/wget"), Some(("homebrew/core", "wget")));
+        assert_eq!(split_tap_name("wget"), None);
+        assert_eq!(split_tap_name("a/b"), None);
+        assert_eq!(split_tap_name("a/b/c/d"), None);
+        assert_eq!(split_tap_name("hashicorp//terraform"), None);
+    }
+
+    #[test]
+    fn test_missing_tap() {
+        let tapped = vec!["hashicorp/tap".to_string()];
+        assert_eq!(missing_tap("hashicorp/tap/terraform", &tapped), None);
+        assert_eq!(missing_tap("Hashicorp/homebrew-tap/terraform", &tapped), None);
+        assert_eq!(missing_tap("homebrew/core/wget", &[]), None);
+        assert_eq!(missing_tap("wget", &[]), None);
+        assert_eq!(missing_tap("mongodb/brew/mongodb-community", &tapped).as_deref(), Some("mongodb/brew"));
+    }
+
+    #[test]
+    fn test_parse_bundle_check() {
+        let output = "\
+brew bundle can't satisfy your Brewfile's dependencies.
+→ Formula wget needs to be installed or updated.
+→ Cask firefox needs to be installed or updated.
+Satisfy missing dependencies with `brew bundle install`.
+";
+        let check = parse_bundle_check(output);
+        assert_eq!(
+            check.missing,
+            vec!["Formula wget needs to be installed or updated.", "Cask firefox needs to be installed or updated."]
+        );
+        assert_eq!(check.label(), "2 missing");
+
+        let check = parse_bundle_check("The Brewfile's dependencies are satisfied.\n");
+        assert!(check.is_satisfied());
+        assert_eq!(check.label(), "satisfied");
+    }
+
+    #[test]
+    fn test_parse_upgrade_output() {
+        let output = "\
+==> Upgrading 2 outdated packages:
+wget 1.21.3 -> 1.21.4
+git 2.42.0, 2.43.0 -> 2.44.0
+==> Fetching wget
+==> Upgrading wget
+  1.21.3 -> 1.21.4
+==> Pouring wget--1.21.4.arm64_sonoma.bottle.tar.gz
+
+Warning: Not upgrading 1 pinned package:
+node 20.1.0
+Warning: jq 1.7.1 already installed
+";
+        let report = parse_upgrade_output(output);
+        assert_eq!(
+            report.upgraded,
+            vec![
+                ("wget".to_string(), "1.21.3".to_string(), "1.21.4".to_string()),
+                ("git".to_string(), "2.42.0, 2.43.0".to_string(), "2.44.0".to_string()),
+            ]
+        );
+        assert_eq!(report.pinned, vec!["node"]);
+        assert_eq!(report.skipped, vec!["jq"]);
+        assert_eq!(
+            report.summary(),
+            "Upgraded wget 1.21.3 → 1.21.4, git 2.42.0, 2.43.0 → 2.44.0; 1 pinned: node; already up to date: jq"
+        );
+
+        // `brew upgrade node` for a pinned node
+        let report = parse_upgrade_output("Error: Not upgrading 1 pinned package:\nnode 20.1.0\n");
+        assert_eq!(report.pinned, vec!["node"]);
+        assert_eq!(report.summary(), "Nothing was upgraded; 1 pinned: node");
+    }
+
+    #[test]
+    fn test_parse_download_progress() {
+        assert_eq!(parse_download_progress("######                                    8.9%"), Some(8.9));
+        assert_eq!(
+            parse_download_progress("######################################################################## 100.0%"),
+            Some(100.0)
+        );
+        assert_eq!(parse_download_progress("  0.0%"), Some(0.0));
+        assert_eq!(parse_download_progress("==> Downloading https://ghcr.io/v2/homebrew/core/wget"), None);
+        assert_eq!(parse_download_progress("Already downloaded: 100%"), None);
+        assert_eq!(parse_download_progress("#=#=#"), None);
+    }
+
+    #[test]
+    fn test_open_pty_is_a_terminal() {
+        use std::io::Read;
+
+        let (mut ours, theirs) = open_pty().unwrap();
+        let status = Command::new("sh").args(["-c", "test -t 1 && echo terminal"]).stdout(theirs).status().unwrap();
+        assert!(status.success());
+        let mut output = [0u8; 64];
+        let n = ours.read(&mut output).unwrap();
+        assert_eq!(&output[..n], b"terminal\r\n");
+    }
+
+    #[test]
+    fn test_send_download_progress() {
+        // stderr of `curl --progress-bar --limit-rate 4M -o /dev/null` (curl 7.88) for a 20 MB file
+        let stderr = concat!(
+            "\r#######################                                                   32.3%",
+            "\r###############################################                           66.2%",
+            "\r######################################################################## 100.0%\n"
+        );
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        // Read in chunks that split lines, as a pipe would
+        let mut buffer = Vec::new();
+        let mut line_start = 0;
+        for chunk in stderr.as_bytes().chunks(50) {
+            buffer.extend_from_slice(chunk);
+            line_start = send_download_progress(&buffer, line_start, &tx);
+        }
+        assert_eq!(line_start, buffer.len());
+        let mut sent = Vec::new();
+        while let Ok(percent) = rx.try_recv() {
+            sent.push(percent);
+        }
+        assert_eq!(sent, vec![32.3, 66.2, 100.0]);
+    }
+
+    #[test]
+    fn test_parse_brew_log() {
+        let output = "\
+commit 5f2c1e0a9b8d7c6e5f4a3b2c1d0e9f8a7b6c5d4e
+Author: BrewTestBot <1589480+BrewTestBot@users.noreply.github.com>
+Date:   Mon Oct 2 12:00:00 2024 +0000
+
+    wget: update 1.24.5 bottle.
+
+commit 0a1b2c3d4e5f6a7b8c9d0e1f2a3b4c5d6e7f8a9b
+Merge: 1111111 2222222
+Author: Jane Doe <jane@example.com>
+Date:   Sun Sep 1 08:30:00 2024 +0200
+
+    wget 1.24.5
+
+    Closes #12345.
+";
+        let commits = parse_brew_log(output);
+        assert_eq!(commits.len(), 2);
+        assert_eq!(commits[0].hash, "5f2c1e0a9b8d7c6e5f4a3b2c1d0e9f8a7b6c5d4e");
+        assert_eq!(commits[0].author, "BrewTestBot");
+        assert_eq!(commits[0].subject, "wget: update 1.24.5 bottle.");
+        assert_eq!(commits[0].short_date(), "Oct 2 2024");
+        assert_eq!(commits[1].author, "Jane Doe");
+        assert_eq!(commits[1].subject, "wget 1.24.5");
+        assert!(parse_brew_log("").is_empty());
+    }
+
+    #[test]
+    fn test_versions_in_log() {
+        let commit = |subject: &str, date: &str| FormulaCommit {
+            hash: String::new(),
+            author: String::new(),
+            date: date.to_string(),
+            subject: subject.to_string(),
+        };
+        let commits = [
+            commit("wget: update 1.24.5 bottle.", "Mon Oct 2 12:00:00 2024 +0000"),
+            commit("wget 1.24.5", "Sun Sep 1 08:30:00 2024 +0200"),
+            commit("wget 1.21.4 (#140123)", "Tue Jun 6 10:00:00 2023 +0000"),
+            commit("wget2 2.1.0", "Tue Jun 6 10:00:00 2023 +0000"),
+            commit("wget 1.21.4: fix build", "Mon Jun 5 10:00:00 2023 +0000"),
+            commit("wget HEAD fixes", "Mon Jun 5 10:00:00 2023 +0000"),
+        ];
+        assert_eq!(
+            versions_in_log("wget", &commits),
+            vec![
+                ("1.24.5".to_string(), "Sep 1 2024".to_string()),
+                ("1.21.4".to_string(), "Jun 6 2023".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_created_formula_path() {
+        let output = "==> Downloading https://example.com/hello-2.12.tar.gz\n\
+            Formula name: hello\n\
+            Please audit and test formula before submitting:\n  \
+            HOMEBREW_NO_INSTALL_FROM_API=1 brew audit --new hello\n\
+            Editing /opt/homebrew/Library/Taps/me/homebrew-tap/Formula/hello.rb\n";
+        assert_eq!(
+            created_formula_path(output),
+            Some(PathBuf::from("/opt/homebrew/Library/Taps/me/homebrew-tap/Formula/hello.rb"))
+        );
+        assert_eq!(created_formula_path("Error: nope"), None);
+    }
+
+    #[test]
+    fn test_validate_tap_and_url() {
+        assert!(validate_tap_name("me/tap").is_ok());
+        assert!(validate_tap_name("me").is_err());
+        assert!(validate_tap_name("me/tap/extra").is_err());
+        assert!(validate_formula_url("https://example.com/hello-2.12.tar.gz").is_ok());
+        assert!(validate_formula_url("ftp://example.com/hello.tar.gz").is_err());
+        assert!(validate_formula_url("https://example.com/a b.tar.gz").is_err());
+    }
+
+    #[test]
+    fn test_extracted_name() {
+        assert_eq!(extracted_name("wget", "1.21.3"), "brewhouse/versions/wget@1.21.3");
+        assert_eq!(extracted_name("hashicorp/tap/terraform", "1.5.7"), "brewhouse/versions/terraform@1.5.7");
+    }
+
+    #[test]
+    fn test_github_repo() {
+        let json = r#"{
+            "name": "jq",
+            "homepage": "https://jqlang.github.io/jq/",
+            "urls": {"stable": {"url": "https://github.com/jqlang/jq/releases/download/jq-1.7.1/jq-1.7.1.tar.gz"}}
+        }"#;
+        let info: BrewInfoFormula = serde_json::from_str(json).unwrap();
+        assert_eq!(github_repo(&info).as_deref(), Some("jqlang/jq"));
+
+        let json = r#"{
+            "name": "ripgrep",
+            "homepage": "https://github.com/BurntSushi/ripgrep",
+            "urls": {"stable": {"url": "https://crates.io/api/v1/crates/ripgrep/14.1.0/download"}}
+        }"#;
+        let info: BrewInfoFormula = serde_json::from_str(json).unwrap();
+        assert_eq!(github_repo(&info).as_deref(), Some("BurntSushi/ripgrep"));
+
+        let json = r#"{"name": "wget", "homepage": "https://www.gnu.org/software/wget/"}"#;
+        let info: BrewInfoFormula = serde_json::from_str(json).unwrap();
+        assert_eq!(github_repo(&info), None);
+
+        assert_eq!(github_repo_from_url("https://github.com/git/git.git").as_deref(), Some("git/git"));
+        assert_eq!(github_repo_from_url("https://github.com/sponsors"), None);
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        use std::cmp::Ordering;
+
+        assert_eq!(compare_versions("1.10", "1.9"), Ordering::Greater);
+        assert_eq!(compare_versions("8_5_0", "8.5.0"), Ordering::Equal);
+        assert_eq!(compare_versions("1.2", "1.2.1"), Ordering::Less);
+        assert_eq!(compare_versions("2.0b", "2.0a"), Ordering::Greater);
+        assert_eq!(tag_version("jq-1.7.1"), "1.7.1");
+        assert_eq!(tag_version("v14.1.0"), "14.1.0");
+        assert_eq!(strip_revision("1.21.3_1"), "1.21.3");
+    }
+
+    #[test]
+    fn test_releases_between() {
+        let json = r#"[
+            {"tag_name": "v1.4.0-rc1", "name": "", "body": null, "html_url": "u5", "prerelease": true},
+            {"tag_name": "v1.3.0", "name": "Third", "body": " Notes 3 ", "html_url": "u4"},
+            {"tag_name": "v1.2.1", "name": "", "body": "Fix", "html_url": "u3"},
+            {"tag_name": "v1.2.0", "name": null, "body": "Notes 2", "html_url": "u2", "draft": true},
+            {"tag_name": "v1.1.0", "name": "First", "body": "Notes 1", "html_url": "u1"}
+        ]"#;
+        let releases = parse_github_releases(json).unwrap();
+        assert_eq!(releases.len(), 3);
+        assert_eq!(releases[0].name.as_deref(), Some("Third"));
+        assert_eq!(releases[0].body, "Notes 3");
+        assert_eq!(releases[1].name, None);
+
+        let tags = |releases: Vec<Release>| releases.into_iter().map(|r| r.tag).collect::<Vec<_>>();
+        assert_eq!(tags(releases_between(releases.clone(), Some("1.1.0_2"), "1.3.0")), vec!["v1.3.0", "v1.2.1"]);
+        assert_eq!(tags(releases_between(releases.clone(), Some("1.2.1"), "1.2.1")), Vec::<String>::new());
+        assert_eq!(tags(releases_between(releases, None, "1.3.0")), vec!["v1.3.0"]);
+    }
+
+    #[test]
+    fn test_parse_upgrade_output_nothing_to_do() {
+        let report = parse_upgrade_output("");
+        assert_eq!(report, UpgradeReport::default());
+        assert_eq!(report.summary(), "Nothing was upgraded");
+    }
+
+    #[test]
+    fn test_installed_snapshot_skips_unparseable_formulae() {
+        let json = r#"{
+            "formulae": [
+                { "name": "head-only", "versions": { "stable": null, "head": "HEAD" } },
+                { "versions": { "stable": "1.0" } },
+                { "name": "no-versions", "installed": [{ "version": "2.0" }, { "version": 3 }] },
+                { "name": "wget", "versions": { "stable": "1.24.5" }, "installed": null }
+            ]
+        }"#;
+
+        let snapshot = parse_installed_snapshot(json).unwrap();
+        let names: Vec<&str> = snapshot.formulae.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["head-only", "no-versions", "wget"]);
+
+        assert_eq!(snapshot.formulae[0].display_version(), Some("HEAD"));
+        // The malformed keg is dropped, the other one still counts
+        assert_eq!(snapshot.formulae[1].installed.as_ref().map(Vec::len), Some(1));
+        assert_eq!(snapshot.formulae[1].display_version(), Some("2.0"));
+        assert!(snapshot.formulae[2].installed.is_none());
+        assert_eq!(snapshot.formulae[2].display_version(), Some("1.24.5"));
+    }
+
+    #[test]
+    fn test_ttl_cache() {
+        let cache: TtlCache<u32> = TtlCache::new(std::time::Duration::from_secs(60));
+        assert_eq!(cache.get("wget"), None);
+
+        cache.insert("wget", 1);
+        cache.insert("git", 2);
+        assert_eq!(cache.get("wget"), Some(1));
+
+        cache.remove("wget");
+        assert_eq!(cache.get("wget"), None);
+        assert_eq!(cache.get("git"), Some(2));
+
+        cache.clear();
+        assert_eq!(cache.get("git"), None);
+
+        let expired: TtlCache<u32> = TtlCache::new(std::time::Duration::ZERO);
+        expired.insert("wget", 1);
+        assert_eq!(expired.get("wget"), None);
+    }
+
+    #[test]
+    fn test_count_lines() {
+        assert_eq!(count_lines(b""), 0);
+        assert_eq!(count_lines(b"wget\ngit\n"), 2);
+        assert_eq!(count_lines(b"wget\n\ngit"), 2);
+    }
+
+    #[test]
+    fn test_install_script_command() {
+        let cmd = install_script_command();
+        assert!(cmd.starts_with("/bin/bash -c \"$(curl -fsSL "));
+        assert!(cmd.contains(INSTALL_SCRIPT_URL));
+    }
+
+    #[test]
+    fn test_shell_config_file() {
+        let home = Path::new("/home/me");
+        assert_eq!(shell_config_file("/bin/zsh", home), home.join(".zshrc"));
+        assert_eq!(shell_config_file("/usr/bin/bash", home), home.join(".bashrc"));
+        assert_eq!(shell_config_file("fish", home), home.join(".config/fish/config.fish"));
+        assert_eq!(shell_config_file("/bin/dash", home), home.join(".profile"));
+        assert_eq!(shell_config_file("", home), home.join(".profile"));
+    }
+
+    #[test]
+    fn test_shellenv_line() {
+        let brew = Path::new("/home/linuxbrew/.linuxbrew/bin/brew");
+        assert_eq!(
+            shellenv_line(brew, "/bin/bash"),
+            "eval \"$(/home/linuxbrew/.linuxbrew/bin/brew shellenv)\""
+        );
+        assert_eq!(
+            shellenv_line(brew, "/usr/bin/fish"),
+            "/home/linuxbrew/.linuxbrew/bin/brew shellenv | source"
+        );
+    }
+
+    #[test]
+    fn test_has_shellenv() {
+        assert!(has_shellenv("export FOO=1\neval \"$(/opt/homebrew/bin/brew shellenv)\"\n"));
+        assert!(has_shellenv("  /home/linuxbrew/.linuxbrew/bin/brew shellenv | source"));
+        assert!(!has_shellenv("# eval \"$(brew shellenv)\""));
+        assert!(!has_shellenv("export PATH=$HOME/bin:$PATH"));
+    }
+
+    #[test]
+    fn test_add_shellenv() {
+        let home = std::env::temp_dir().join(format!("brewhouse-shellenv-{}", std::process::id()));
+        let config = shell_config_file("fish", &home);
+        assert_eq!(find_shellenv_config(&home), None);
+
+        add_shellenv(&config, "/opt/homebrew/bin/brew shellenv | source").unwrap();
+        assert_eq!(find_shellenv_config(&home), Some(config));
+
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+
+    #[test]
+    fn test_stream_output() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.arg("-c").arg("echo one; echo two >&2");
+        assert!(rt.block_on(stream_output(cmd, tx)).is_ok());
+
+        let mut lines = Vec::new();
+        while let Ok(line) = rx.try_recv() {
+            lines.push(line);
+        }
+        lines.sort();
+        assert_eq!(lines, vec!["one", "two"]);
+
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.arg("-c").arg("exit 3");
+        assert!(rt.block_on(stream_output(cmd, tx)).is_err());
+
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.arg("-c").arg("echo 'curl: (6) Could not resolve host: github.com' >&2; exit 1");
+        assert!(matches!(rt.block_on(stream_output(cmd, tx)), Err(BrewError::NetworkError(_))));
+    }
+
+    #[test]
+    fn test_command_line() {
+        assert_eq!(command_line("install", &["wget"]), "brew install wget");
+        assert_eq!(command_line("upgrade", &["git", "node@18"]), "brew upgrade git node@18");
+        assert_eq!(command_line("upgrade", &[]), "brew upgrade");
+    }
+
+    #[test]
+    fn test_operation_args() {
+        let options = InstallOptions { cask: true, no_quarantine: true, ..Default::default() };
+        assert_eq!(install_args("firefox", &options), ["install", "--cask", "--no-quarantine", "firefox"]);
+        assert_eq!(uninstall_args("wget", false, true), ["uninstall", "wget"]);
+        assert_eq!(uninstall_args("firefox", true, true), ["uninstall", "--zap", "--cask", "firefox"]);
+        assert_eq!(upgrade_args(None), ["upgrade"]);
+    }
+
+    #[test]
+    fn test_terminal_args() {
+        let install = install_args("wget", &InstallOptions::default());
+        assert_eq!(terminal_args(&install, false).unwrap(), install);
+        assert_eq!(terminal_args(&install, true).unwrap(), ["install", "--dry-run", "wget"]);
+        assert!(matches!(terminal_args(&uninstall_args("wget", false, false), true), Err(BrewError::DryRun(_))));
+    }
+
+    #[test]
+    fn test_dry_run_args() {
+        assert_eq!(dry_run_args(&["install", "--cask", "firefox"]), vec!["install", "--dry-run", "--cask", "firefox"]);
+        assert_eq!(dry_run_args(&["upgrade"]), vec!["upgrade", "--dry-run"]);
+
+        let simulated = BrewError::DryRun("==> Would upgrade 1 outdated package:".to_string());
+        assert_eq!(simulated.summary(), "Simulated; nothing was changed");
+        assert!(simulated.user_message().contains("Would upgrade 1 outdated package"));
+    }
+
+    #[test]
+    fn test_install_options_args() {
+        assert!(InstallOptions::default().args().is_empty());
+
+        let options = InstallOptions { head: true, ..Default::default() };
+        assert_eq!(options.args(), vec!["--HEAD"]);
+
+        let options = InstallOptions {
+            head: true,
+            build_from_source: true,
+            formula_options: vec!["--with-openssl".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(options.args(), vec!["--HEAD", "--build-from-source", "--with-openssl"]);
+    }
+
+    #[test]
+    fn test_total_file_size() {
+        let dir = std::env::temp_dir().join(format!("brewhouse-size-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let a = dir.join("a");
+        let b = dir.join("b");
+        std::fs::write(&a, vec![0u8; 100]).unwrap();
+        std::fs::write(&b, vec![0u8; 23]).unwrap();
+
+        let paths = vec![
+            a.to_string_lossy().to_string(),
+            b.to_string_lossy().to_string(),
+            dir.join("missing").to_string_lossy().to_string(),
+        ];
+        assert_eq!(total_file_size(&paths), 123);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_dir_size() {
+        let dir = std::env::temp_dir().join(format!("brewhouse-du-test-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("1.0/bin")).unwrap();
+        std::fs::write(dir.join("1.0/bin/tool"), vec![0u8; 1000]).unwrap();
+        std::fs::write(dir.join("1.0/README"), vec![0u8; 24]).unwrap();
+
+        assert_eq!(dir_size(&dir), 1024);
+        assert_eq!(dir_size(&dir.join("1.0/README")), 24);
+        assert_eq!(dir_size(&dir.join("missing")), 0);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cache_display_name() {
+        let hash = "a".repeat(64);
+        assert_eq!(
+            cache_display_name(&format!("{}--wget--1.21.4.bottle.tar.gz", hash)),
+            "wget--1.21.4.bottle.tar.gz"
+        );
+        // Names without a hash prefix are left alone
+        assert_eq!(cache_display_name("wget--1.21.4.bottle.tar.gz"), "wget--1.21.4.bottle.tar.gz");
+        assert_eq!(cache_display_name("api"), "api");
+    }
+
+    #[test]
+    fn test_list_cache_dir_and_age_filter() {
+        let cache = std::env::temp_dir().join(format!("brewhouse-cache-test-{}", std::process::id()));
+        let downloads = cache.join("downloads");
+        std::fs::create_dir_all(&downloads).unwrap();
+        std::fs::write(downloads.join(format!("{}--git--2.43.0.bottle.tar.gz", "0".repeat(64))), vec![0u8; 10]).unwrap();
+
+        let entries = list_cache_dir(&cache);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "git--2.43.0.bottle.tar.gz");
+        assert_eq!(entries[0].size, 10);
+
+        let now = entries[0].modified;
+        assert!(cache_entries_older_than(&entries, 30, now).is_empty());
+        assert_eq!(cache_entries_older_than(&entries, 30, now + 31 * 24 * 60 * 60).len(), 1);
+
+        assert!(list_cache_dir(&cache.join("missing")).is_empty());
+        std::fs::remove_dir_all(&cache).unwrap();
+    }
+
+    #[test]
+    fn test_parse_brew_config() {
+        let output = "HOMEBREW_VERSION: 4.2.5\n\
+ORIGIN: https://github.com/Homebrew/brew\n\
+Core tap HEAD: 5e4a1b8\n\
+HOMEBREW_PREFIX: /home/linuxbrew/.linuxbrew\n\
+CPU: 16-core 64-bit zen3\n\
+\n\
+Clang: N/A\n";
+
+        let config = parse_brew_config(output);
+        assert_eq!(config.len(), 6);
+        assert_eq!(config[0], ("HOMEBREW_VERSION".to_string(), "4.2.5".to_string()));
+        // Only the first colon separates key and value
+        assert_eq!(config[1].1, "https://github.com/Homebrew/brew");
+        assert_eq!(config[2].0, "Core tap HEAD");
+        assert_eq!(config[5], ("Clang".to_string(), "N/A".to_string()));
+    }
+
+    #[test]
+    fn test_parse_brew_version() {
+        let output = "Homebrew 4.4.1\nHomebrew/homebrew-core (git revision 5e4a1b8; last commit 2024-10-14)\n";
+        assert_eq!(parse_brew_version(output).as_deref(), Some("4.4.1"));
+        assert_eq!(parse_brew_version("Homebrew 4.4.1-52-g6f1d0a1\n").as_deref(), Some("4.4.1-52-g6f1d0a1"));
+        assert_eq!(parse_brew_version("command not found\n"), None);
+        assert_eq!(parse_brew_version(""), None);
+    }
+
+    #[test]
+    fn test_brew_prefix_of() {
+        assert_eq!(brew_prefix_of(Path::new("/opt/homebrew/bin/brew")), PathBuf::from("/opt/homebrew"));
+        assert_eq!(
+            brew_prefix_of(Path::new("/home/linuxbrew/.linuxbrew/bin/brew")),
+            PathBuf::from("/home/linuxbrew/.linuxbrew")
+        );
+        // Not inside a bin directory: the path itself is the best we have
+        assert_eq!(brew_prefix_of(Path::new("/tmp/brew")), PathBuf::from("/tmp/brew"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_validate_brew_path() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("brewhouse-brew-path-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let fake_brew = dir.join("brew");
+        std::fs::write(&fake_brew, "#!/bin/sh\necho 'Homebrew 4.2.5'\n").unwrap();
+        std::fs::set_permissions(&fake_brew, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(validate_brew_path(&fake_brew).unwrap(), "Homebrew 4.2.5");
+
+        let not_brew = dir.join("not-brew");
+        std::fs::write(&not_brew, "#!/bin/sh\necho 'something else'\n").unwrap();
+        std::fs::set_permissions(&not_brew, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(validate_brew_path(&not_brew).is_err());
+
+        assert!(validate_brew_path(&dir.join("missing")).is_err());
+        assert!(validate_brew_path(&dir).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_disk_usage_total() {
+        let usage = DiskUsage { cellar: 1000, caskroom: 200, cache: 34 };
+        assert_eq!(usage.total(), 1234);
+        assert_eq!(DiskUsage::default().total(), 0);
+    }
+
+    #[test]
+    fn test_owner_from_keg_path() {
+        let cellar = Path::new("/nonexistent-prefix/Cellar");
+
+        let owner = owner_from_keg_path(Path::new("/nonexistent-prefix/Cellar/wget/1.21.4/bin/wget"), cellar, false);
+        assert_eq!(
+            owner,
+            Some(FileOwner { package: "wget".to_string(), version: "1.21.4".to_string(), cask: false })
+        );
+
+        // The package directory alone doesn't identify a keg
+        assert_eq!(owner_from_keg_path(Path::new("/nonexistent-prefix/Cellar/wget"), cellar, false), None);
+        assert_eq!(owner_from_keg_path(Path::new("/usr/bin/wget"), cellar, false), None);
+    }
+
+    #[test]
+    fn test_zap_paths() {
+        let artifacts: Vec<serde_json::Value> = serde_json::from_str(
+            r#"[
+                {"app": ["Firefox.app"]},
+                {"zap": [{
+                    "trash": [
+                        "~/Library/Application Support/Firefox",
+                        "~/Library/Caches/Firefox"
+                    ],
+                    "rmdir": "~/Library/Application Support/Mozilla",
+                    "signal": {"TERM": "org.mozilla.firefox"}
+                }]}
+            ]"#,
+        )
+        .unwrap();
+
+        let mut paths = zap_paths(&artifacts);
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                "~/Library/Application Support/Firefox",
+                "~/Library/Application Support/Mozilla",
+                "~/Library/Caches/Firefox",
+            ]
+        );
+
+        assert!(zap_paths(&[serde_json::json!({"app": ["Foo.app"]})]).is_empty());
+    }
+
+    #[test]
+    fn test_install_options_no_quarantine_needs_cask() {
+        let formula = InstallOptions { no_quarantine: true, ..Default::default() };
+        assert!(formula.args().is_empty());
+
+        let cask = InstallOptions { cask: true, no_quarantine: true, ..Default::default() };
+        assert_eq!(cask.args(), vec!["--cask", "--no-quarantine"]);
+    }
+
+    #[test]
+    fn test_formula_install_options() {
+        let json = r#"{
+            "formulae": [{
+                "name": "ffmpeg",
+                "versions": {"stable": "6.1"},
+                "options": [
+                    {"option": "--with-fdk-aac", "description": "Enable the Fraunhofer FDK AAC library"},
+                    {"option": "--HEAD"},
+                    "not-an-object"
+                ]
+            }]
+        }"#;
+
+        #[derive(Deserialize)]
+        struct BrewInfoResponse {
+            formulae: Vec<BrewInfoFormula>,
+        }
+
+        let response: BrewInfoResponse = serde_json::from_str(json).unwrap();
+        let options = response.formulae[0].install_options();
+        assert_eq!(options.len(), 2);
+        assert_eq!(options[0].option, "--with-fdk-aac");
+        assert_eq!(options[0].description, "Enable the Fraunhofer FDK AAC library");
+        assert_eq!(options[1].description, "");
+    }
+
+    #[test]
+    fn test_has_bottle_for() {
+        let bottle: serde_json::Value = serde_json::from_str(
+            r#"{"stable": {"rebuild": 0, "files": {
+                "arm64_ventura": {"cellar": ":any"},
+                "monterey": {"cellar": ":any"},
+                "x86_64_linux": {"cellar": ":any"}
+            }}}"#,
+        )
+        .unwrap();
+
+        assert!(has_bottle_for(&bottle, "x86_64_linux"));
+        assert!(!has_bottle_for(&bottle, "arm64_linux"));
+        assert!(has_bottle_for(&bottle, "arm64_ventura"));
+        // Older macOS bottles of the same architecture are usable
+        assert!(has_bottle_for(&bottle, "arm64_sequoia"));
+        assert!(has_bottle_for(&bottle, "sonoma"));
+        assert!(!has_bottle_for(&bottle, "arm64_monterey"));
+        assert!(!has_bottle_for(&bottle, "big_sur"));
+    }
+
+    #[test]
+    fn test_bottle_tags() {
+        let bottle = serde_json::json!({"stable": {"files": {"x86_64_linux": {}, "arm64_sonoma": {}}}});
+        assert_eq!(bottle_tags(&bottle), vec!["arm64_sonoma", "x86_64_linux"]);
+        assert!(bottle_tags(&serde_json::json!({})).is_empty());
+
+        assert_eq!(bottle_tag_label("arm64_sonoma"), "macOS Sonoma (Apple Silicon)");
+        assert_eq!(bottle_tag_label("big_sur"), "macOS Big Sur (Intel)");
+        assert_eq!(bottle_tag_label("x86_64_linux"), "Linux (x86-64)");
+        assert_eq!(bottle_tag_label("arm64_linux"), "Linux (ARM64)");
+        assert_eq!(bottle_tag_label("all"), "any platform");
+    }
+
+    #[test]
+    fn test_has_bottle_for_all_and_missing() {
+        let all: serde_json::Value =
+            serde_json::from_str(r#"{"stable": {"files": {"all": {"cellar": ":any_skip_relocation"}}}}"#).unwrap();
+        assert!(has_bottle_for(&all, "arm64_linux"));
+
+        assert!(!has_bottle_for(&serde_json::json!({}), "x86_64_linux"));
+    }
+
+    // ==========================================================================
+    // Integration Tests (require brew to be installed)
+    // ==========================================================================
+
+    #[test]
+    fn test_is_brew_installed() {
+        // This test just verifies the function runs without panic
+        // Result depends on whether brew is actually installed
+        let _ = is_brew_installed();
+    }
+
+    #[tokio::test]
+    #[ignore] // Run with: cargo test -- --ignored
+    async fn test_get_installed_packages_integration() {
+        let result = get_installed_packages().await;
+        // Should succeed if brew is installed
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_search_packages_integration() {
+        let result = search_packages("git").await;
+        assert!(result.is_ok());
+        let packages = result.unwrap();
+        // "git" should return at least one result
+        assert!(!packages.is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_get_package_info_integration() {
+        let result = get_package_info("git").await;
+        assert!(result.is_ok());
+        assert!(matches!(result.unwrap(), PackageInfo::Formula(info) if info.name == "git"));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_get_brew_stats_integration() {
+        let result = get_brew_stats().await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_get_outdated_packages_integration() {
+        let result = get_outdated_packages().await;
+        assert!(result.is_ok());
+    }
+
+    // ==========================================================================
+    // Security Tests - Verify malicious input doesn't execute
+    // ==========================================================================
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_search_with_shell_metacharacters() {
+        // Even if we don't validate, the Command API should not execute shell commands
+        // This test verifies brew just treats it as a literal search term
+        let result = search_packages("; echo PWNED").await;
+        // Should either fail gracefully or return no results, but never execute "echo PWNED"
+        match result {
+            Ok(packages) => {
+                // Should not contain "PWNED" as a package name from shell execution
+                assert!(!packages.iter().any(|p| p.contains("PWNED")));
+            }
+            Err(_) => {
+                // Error is acceptable - brew may reject the query
+            }
+        }
+    }
+
+    // ==========================================================================
+    // Tests verifying validation is enforced by functions
+    // ==========================================================================
+
+    #[tokio::test]
+    async fn test_search_packages_validates_input() {
+        // Should fail - either rate limited or validation error
+        // Both are acceptable as security measures
+        let result = search_packages("; malicious").await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_package_info_validates_input() {
+        let result = get_package_info("$(whoami)").await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_install_package_validates_input() {
+        let result = install_package("; rm -rf /", &InstallOptions::default()).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_uninstall_package_validates_input() {
+        let result = uninstall_package("pkg && echo pwned").await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_packages_validates_input() {
+        let result = upgrade_packages(Some("`whoami`")).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_install_package_path_traversal() {
+        let result = install_package("../../../etc/passwd", &InstallOptions::default()).await;
+        assert!(result.is_err());
+    }
+
+    // Test that validation functions themselves work correctly (not affected by rate limiting)
+    #[test]
+    fn test_validation_rejects_malicious_install_input() {
+        assert!(validate_package_name("; rm -rf /").is_err());
+        assert!(validate_package_name("$(whoami)").is_err());
+        assert!(validate_package_name("pkg && echo pwned").is_err());
+        assert!(validate_package_name("`whoami`").is_err());
+        assert!(validate_package_name("../../../etc/passwd").is_err());
+    }
+
+    // ==========================================================================
+    // Error Sanitization Tests
+    // ==========================================================================
+
+    #[test]
+    fn test_sanitize_error_removes_home_paths() {
+        let error = "Error: /home/username/projects/file.txt not found";
+        let sanitized = sanitize_error(error);
+        assert!(!sanitized.contains("username"));
+        assert!(!sanitized.contains("/home/"));
+        assert!(sanitized.contains("[path]"));
+    }
+
+    #[test]
+    fn test_sanitize_error_removes_users_paths() {
+        let error = "Error: /Users/john/Library/something failed";
+        let sanitized = sanitize_error(error);
+        assert!(!sanitized.contains("john"));
+        assert!(!sanitized.contains("/Users/"));
+        assert!(sanitized.contains("[path]"));
+    }
+
+    #[test]
+    fn test_sanitize_error_removes_var_paths() {
+        let error = "Failed to write to /var/log/brew.log";
+        let sanitized = sanitize_error(error);
+        assert!(!sanitized.contains("/var/log"));
+        assert!(sanitized.contains("[path]"));
+    }
+
+    #[test]
+    fn test_sanitize_error_removes_tilde_paths() {
+        let error = "Cannot access ~/.config/brew/settings";
+        let sanitized = sanitize_error(error);
+        assert!(!sanitized.contains("~/.config"));
+        assert!(sanitized.contains("[path]"));
+    }
+
+    #[test]
+    fn test_sanitize_error_preserves_non_path_content() {
+        let error = "Package 'git' not found in repository";
+        let sanitized = sanitize_error(error);
+        assert_eq!(sanitized, error);
+    }
+
+    #[test]
+    fn test_sanitize_error_multiple_paths() {
+        let error = "Error copying /home/user/src to /tmp/dest";
+        let sanitized = sanitize_error(error);
+        assert!(!sanitized.contains("/home/user"));
+        assert!(!sanitized.contains("/tmp/dest"));
+        assert_eq!(sanitized.matches("[path]").count(), 2);
+    }
+
+    // ==========================================================================
+    // Rate Limiter Tests
+    // ==========================================================================
+
+    #[test]
+    fn test_rate_limiter_allows_first_request() {
+        let limiter = RateLimiter::new(1000);
+        assert!(limiter.check().is_ok());
+    }
+
+    #[test]
+    fn test_rate_limiter_blocks_rapid_requests() {
+        let limiter = RateLimiter::new(1000);  // 1 second interval
+        assert!(limiter.check().is_ok());
+        // Immediate second request should be blocked
+        let result = limiter.check();
+        assert!(result.is_err());
+        // Should return remaining wait time
+        let wait_ms = result.unwrap_err();
+        assert!(wait_ms > 0 && wait_ms <= 1000);
+    }
+
+    #[test]
+    fn test_rate_limiter_check_or_error() {
+        let limiter = RateLimiter::new(1000);
+        assert!(limiter.check_or_error().is_ok());
+        let result = limiter.check_or_error();
+        assert!(result.is_err());
+        assert!(matches!(result.unwrap_err(), BrewError::CommandFailed(_)));
+    }
+
+    #[test]
+    fn test_rate_limiter_remaining_ms_does_not_consume() {
+        let limiter = RateLimiter::new(1000);
+        assert_eq!(limiter.remaining_ms(), 0);
+        assert_eq!(limiter.remaining_ms(), 0);
+        assert!(limiter.check().is_ok());
+
+        let remaining = limiter.remaining_ms();
+        assert!(remaining > 0 && remaining <= 1000);
+    }
+
+    #[test]
+    fn test_rate_limiter_zero_interval_allows_all() {
+        let limiter = RateLimiter::new(0);
+        assert!(limiter.check().is_ok());
+        assert!(limiter.check().is_ok());
+        assert!(limiter.check().is_ok());
+    }
+
+    // ==========================================================================
+    // Commands against a canned runner
+    // ==========================================================================
+
+    #[tokio::test]
+    async fn test_get_taps_with_canned_output() {
+        let runner = Arc::new(CannedRunner::default().respond(0, "homebrew/core\n\n  homebrew/cask  \n", ""));
+        let taps = with_runner(runner.clone(), get_taps()).await.unwrap();
+        assert_eq!(taps, vec!["homebrew/core", "homebrew/cask"]);
+        assert_eq!(runner.calls(), vec![vec!["tap"]]);
+    }
+
+    #[tokio::test]
+    async fn test_add_tap_failure_is_classified() {
+        let stderr = "Error: Failure while executing; `git clone` exited with 128.\ncurl: (6) Could not resolve host: github.com";
+        let runner = Arc::new(CannedRunner::default().respond(1, "", stderr));
+        let result = with_runner(runner.clone(), add_tap("user/repo")).await;
+        assert!(matches!(result, Err(BrewError::NetworkError(_))));
+        assert_eq!(runner.calls(), vec![vec!["tap", "user/repo"]]);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_name_never_runs_brew() {
+        let runner = Arc::new(CannedRunner::default());
+        let result = with_runner(runner.clone(), add_tap("user/repo; rm -rf ~")).await;
+        assert!(matches!(result, Err(BrewError::ParseError(_))));
+        assert!(runner.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_brew_failing_to_start() {
+        let runner = Arc::new(CannedRunner::default());
+        let result = with_runner(runner, get_outdated_packages()).await;
+        assert!(matches!(result, Err(BrewError::CommandFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_bundle_dump() {
+        let runner = Arc::new(CannedRunner::default().respond(0, "", ""));
+        with_runner(runner.clone(), bundle_dump(Path::new("/tmp/Brewfile"))).await.unwrap();
+        assert_eq!(runner.calls(), vec![vec!["bundle", "dump", "--force", "--file", "/tmp/Brewfile"]]);
+    }
+
+    #[tokio::test]
+    async fn test_get_outdated_packages_with_canned_output() {
+        let runner = Arc::new(CannedRunner::default().respond(0, "git\nnode\n", ""));
+        let outdated = with_runner(runner.clone(), get_outdated_packages()).await.unwrap();
+        assert_eq!(outdated, vec!["git", "node"]);
+        assert_eq!(runner.calls(), vec![vec!["outdated", "--formula"]]);
+    }
+
+    #[tokio::test]
+    async fn test_get_package_files_missing_formula() {
+        let runner = Arc::new(CannedRunner::default().respond(1, "", "Error: No available formula with the name \"nope\"."));
+        let result = with_runner(runner, get_package_files("nope")).await;
+        assert!(matches!(result, Err(BrewError::FormulaNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_get_package_files_counts_missing_files_as_zero() {
+        let stdout = "/nonexistent/Cellar/jq/1.7/bin/jq\n/nonexistent/Cellar/jq/1.7/share/man/man1/jq.1\n";
+        let runner = Arc::new(CannedRunner::default().respond(0, stdout, ""));
+        let files = with_runner(runner.clone(), get_package_files("jq")).await.unwrap();
+        assert_eq!(files.paths.len(), 2);
+        assert_eq!(files.total_bytes, 0);
+        assert_eq!(runner.calls(), vec![vec!["list", "--verbose", "jq"]]);
+    }
+
+    #[tokio::test]
+    async fn test_update_brew_failure_keeps_both_streams() {
+        let runner = Arc::new(CannedRunner::default().respond(1, "Updated 2 taps", "Error: Another active Homebrew update process is already in progress."));
+        match with_runner(runner, update_brew()).await {
+            Err(BrewError::Locked(message)) => {
+                assert!(message.contains("Updated 2 taps"));
+                assert!(message.contains("already in progress"));
+            }
+            other => panic!("expected Locked, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_brew_path_trims_output() {
+        let runner = Arc::new(CannedRunner::default().respond(0, "/opt/homebrew/Cellar\n", ""));
+        let path = with_runner(runner.clone(), get_brew_path("--cellar")).await.unwrap();
+        assert_eq!(path, PathBuf::from("/opt/homebrew/Cellar"));
+        assert_eq!(runner.calls(), vec![vec!["--cellar"]]);
+    }
+}
@@ -0,0 +1,662 @@
+//! What is installed, written out for other tools: plain name lists, JSON and CSV exports
+//! for inventories and audits, comparisons against a Brewfile, and the bundle that carries
+//! a setup to another machine.
+
+use crate::brew::{self, BrewResult, Package};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// File formats the installed packages can be exported in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// One name per line, which Install from List reads back
+    Text,
+    Json,
+    Csv,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [ExportFormat::Text, ExportFormat::Json, ExportFormat::Csv];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportFormat::Text => "Plain Text (names only)",
+            ExportFormat::Json => "JSON",
+            ExportFormat::Csv => "CSV",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Text => "txt",
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+/// One exported package; `kind` is "formula" or "cask"
+#[derive(Serialize)]
+struct ExportedPackage<'a> {
+    name: &'a str,
+    version: Option<&'a str>,
+    tap: Option<&'a str>,
+    kind: &'static str,
+}
+
+impl<'a> ExportedPackage<'a> {
+    fn new(package: &'a Package) -> Self {
+        Self {
+            name: &package.name,
+            version: package.version.as_deref(),
+            tap: package.tap.as_deref(),
+            kind: if package.cask { "cask" } else { "formula" },
+        }
+    }
+}
+
+/// `packages` written out in `format`, sorted by name
+pub fn export(packages: &[Package], format: ExportFormat) -> String {
+    let mut packages: Vec<ExportedPackage> = packages.iter().map(ExportedPackage::new).collect();
+    packages.sort_by(|a, b| a.name.cmp(b.name));
+    match format {
+        ExportFormat::Text => packages.iter().map(|package| format!("{}\n", package.name)).collect(),
+        ExportFormat::Json => {
+            // Only plain strings go in, so serializing can't fail
+            let mut json = serde_json::to_string_pretty(&packages).unwrap_or_default();
+            json.push('\n');
+            json
+        }
+        ExportFormat::Csv => {
+            let mut csv = String::from("name,version,tap,kind\n");
+            for package in &packages {
+                let fields = [package.name, package.version.unwrap_or(""), package.tap.unwrap_or(""), package.kind];
+                let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+                csv.push_str(&fields.join(","));
+                csv.push('\n');
+            }
+            csv
+        }
+    }
+}
+
+/// `field` quoted for CSV if it has a comma, quote or line break in it
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// What a Brewfile line asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BrewfileKind {
+    Tap,
+    Formula,
+    Cask,
+}
+
+/// One `tap`, `brew` or `cask` line of a Brewfile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrewfileEntry {
+    pub kind: BrewfileKind,
+    pub name: String,
+}
+
+impl BrewfileEntry {
+    pub fn new(kind: BrewfileKind, name: impl Into<String>) -> Self {
+        Self { kind, name: name.into() }
+    }
+
+    /// The entry as a Brewfile line, e.g. `brew "wget"`
+    pub fn line(&self) -> String {
+        let keyword = match self.kind {
+            BrewfileKind::Tap => "tap",
+            BrewfileKind::Formula => "brew",
+            BrewfileKind::Cask => "cask",
+        };
+        format!("{} \"{}\"", keyword, self.name)
+    }
+
+    /// What two entries for the same package share: tap-qualified formulae and casks go by
+    /// their own name, and taps by `user/repo` without the `homebrew-` repository prefix
+    fn key(&self) -> (BrewfileKind, String) {
+        let name = self.name.to_lowercase();
+        let name = match self.kind {
+            BrewfileKind::Tap => match name.split_once('/') {
+                Some((user, repo)) => format!("{}/{}", user, repo.strip_prefix("homebrew-").unwrap_or(repo)),
+                None => name,
+            },
+            _ => name.rsplit('/').next().unwrap_or(&name).to_string(),
+        };
+        (self.kind, name)
+    }
+}
+
+/// The taps, formulae and casks a Brewfile lists, in order. Other lines (`mas`, `vscode`,
+/// Ruby conditionals) are skipped; only the first quoted string of a line is its name.
+pub fn parse_brewfile(text: &str) -> Vec<BrewfileEntry> {
+    text.lines().filter_map(parse_brewfile_line).collect()
+}
+
+/// Whether `path` is named like something that can be imported: a Brewfile (`Brewfile`,
+/// `work.Brewfile`), a JSON bundle or a text list of package names
+pub fn is_import_file(path: &std::path::Path) -> bool {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    name == "Brewfile"
+        || name.ends_with(".Brewfile")
+        || path.extension().is_some_and(|extension| extension == "json" || extension == "txt")
+}
+
+fn parse_brewfile_line(line: &str) -> Option<BrewfileEntry> {
+    let (keyword, rest) = line.trim().split_once(|c: char| c.is_whitespace() || c == '(')?;
+    let kind = match keyword {
+        "tap" => BrewfileKind::Tap,
+        "brew" => BrewfileKind::Formula,
+        "cask" => BrewfileKind::Cask,
+        _ => return None,
+    };
+    let rest = rest.trim_start();
+    let quote = rest.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+    let name = rest[1..].split(quote).next()?;
+    (!name.is_empty()).then(|| BrewfileEntry::new(kind, name))
+}
+
+/// How a Brewfile compares with what is installed
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct BrewfileDiff {
+    /// In the Brewfile but not installed
+    pub missing: Vec<BrewfileEntry>,
+    /// Installed on request but not in the Brewfile
+    pub extra: Vec<BrewfileEntry>,
+    /// In the Brewfile and installed
+    pub matching: Vec<BrewfileEntry>,
+}
+
+// Taps brew has without being asked, which `brew bundle dump` leaves out
+const DEFAULT_TAPS: &[&str] = &["homebrew/core", "homebrew/cask"];
+
+/// Compare `brewfile` with the `installed` taps, formulae and casks. `dependencies` are
+/// formulae installed only for other formulae: they satisfy a Brewfile line but aren't extras.
+pub fn compare(brewfile: &[BrewfileEntry], installed: &[BrewfileEntry], dependencies: &[String]) -> BrewfileDiff {
+    let installed_keys: HashSet<_> = installed
+        .iter()
+        .map(BrewfileEntry::key)
+        .chain(dependencies.iter().map(|name| BrewfileEntry::new(BrewfileKind::Formula, name.as_str()).key()))
+        .collect();
+
+    let mut diff = BrewfileDiff::default();
+    let mut listed = HashSet::new();
+    for entry in brewfile {
+        let key = entry.key();
+        if !listed.insert(key.clone()) {
+            continue;
+        }
+        if installed_keys.contains(&key) {
+            diff.matching.push(entry.clone());
+        } else {
+            diff.missing.push(entry.clone());
+        }
+    }
+    for entry in installed {
+        if entry.kind == BrewfileKind::Tap && DEFAULT_TAPS.contains(&entry.key().1.as_str()) {
+            continue;
+        }
+        if listed.insert(entry.key()) {
+            diff.extra.push(entry.clone());
+        }
+    }
+    diff
+}
+
+/// What is installed here, as `compare` takes it
+struct Installed {
+    /// Taps, formulae installed on request, and casks
+    entries: Vec<BrewfileEntry>,
+    /// Formulae installed only for other formulae
+    dependencies: Vec<String>,
+    pinned: HashSet<String>,
+}
+
+async fn installed() -> BrewResult<Installed> {
+    let snapshot = brew::get_installed_snapshot().await?;
+    let taps = brew::get_taps().await?;
+
+    // Old installs record neither flag, so only a keg marked as a dependency makes one
+    let (requested, dependencies): (Vec<_>, Vec<_>) = snapshot.formulae.iter().partition(|formula| {
+        formula
+            .installed
+            .iter()
+            .flatten()
+            .any(|keg| keg.installed_on_request || !keg.installed_as_dependency)
+    });
+    let entries = taps
+        .into_iter()
+        .map(|tap| BrewfileEntry::new(BrewfileKind::Tap, tap))
+        .chain(requested.iter().map(|formula| BrewfileEntry::new(BrewfileKind::Formula, formula.name.as_str())))
+        .chain(snapshot.casks.iter().map(|cask| BrewfileEntry::new(BrewfileKind::Cask, cask.token.as_str())))
+        .collect();
+    Ok(Installed {
+        entries,
+        dependencies: dependencies.iter().map(|formula| formula.name.clone()).collect(),
+        pinned: snapshot
+            .formulae
+            .iter()
+            .filter(|formula| formula.pinned.unwrap_or(false))
+            .map(|formula| formula.name.clone())
+            .collect(),
+    })
+}
+
+/// Compare the Brewfile `text` with the installed taps, formulae and casks
+pub async fn compare_with_installed(text: &str) -> BrewResult<BrewfileDiff> {
+    let installed = installed().await?;
+    Ok(compare(&parse_brewfile(text), &installed.entries, &installed.dependencies))
+}
+
+/// The Brewfile `text` with a line for each of `entries` added at the end
+pub fn append_to_brewfile(text: &str, entries: &[BrewfileEntry]) -> String {
+    let mut text = text.to_string();
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    for entry in entries {
+        text.push_str(&entry.line());
+        text.push('\n');
+    }
+    text
+}
+
+// Written into bundles; a bundle from a newer BrewHouse may hold things this one can't set up
+const BUNDLE_VERSION: u32 = 1;
+
+// Brewfile comment that marks a formula to pin, as `to_brewfile` writes it
+const PINNED_COMMENT: &str = "pinned";
+
+/// A setup to carry to another machine: the taps, the formulae installed on request (and
+/// which of them are pinned) and the casks. Saved as JSON, or as a Brewfile that `brew bundle`
+/// reads too, with pinned formulae marked by a `# pinned` comment.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MachineBundle {
+    #[serde(default = "bundle_version")]
+    pub version: u32,
+    #[serde(default)]
+    pub taps: Vec<String>,
+    #[serde(default)]
+    pub formulae: Vec<BundledFormula>,
+    #[serde(default)]
+    pub casks: Vec<String>,
+}
+
+fn bundle_version() -> u32 {
+    BUNDLE_VERSION
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundledFormula {
+    pub name: String,
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+impl MachineBundle {
+    pub fn to_json(&self) -> String {
+        // Only plain strings and numbers go in, so serializing can't fail
+        let mut json = serde_json::to_string_pretty(self).unwrap_or_default();
+        json.push('\n');
+        json
+    }
+
+    pub fn to_brewfile(&self) -> String {
+        let mut brewfile = String::new();
+        for tap in &self.taps {
+            brewfile.push_str(&BrewfileEntry::new(BrewfileKind::Tap, tap.as_str()).line());
+            brewfile.push('\n');
+        }
+        for formula in &self.formulae {
+            brewfile.push_str(&BrewfileEntry::new(BrewfileKind::Formula, formula.name.as_str()).line());
+            if formula.pinned {
+                brewfile.push_str(" # ");
+                brewfile.push_str(PINNED_COMMENT);
+            }
+            brewfile.push('\n');
+        }
+        for cask in &self.casks {
+            brewfile.push_str(&BrewfileEntry::new(BrewfileKind::Cask, cask.as_str()).line());
+            brewfile.push('\n');
+        }
+        brewfile
+    }
+
+    /// Read a bundle saved as JSON, or a Brewfile (from `to_brewfile` or `brew bundle dump`),
+    /// telling them apart by the leading brace
+    pub fn parse(text: &str) -> Result<MachineBundle, String> {
+        if !text.trim_start().starts_with('{') {
+            return Ok(Self::from_brewfile(text));
+        }
+        let bundle: MachineBundle = serde_json::from_str(text).map_err(|e| format!("Not a valid bundle: {}", e))?;
+        if bundle.version > BUNDLE_VERSION {
+            return Err("This bundle was saved by a newer version of BrewHouse".to_string());
+        }
+        Ok(bundle)
+    }
+
+    fn from_brewfile(text: &str) -> MachineBundle {
+        let mut bundle = MachineBundle { version: BUNDLE_VERSION, ..Default::default() };
+        for line in text.lines() {
+            let Some(entry) = parse_brewfile_line(line) else {
+                continue;
+            };
+            match entry.kind {
+                BrewfileKind::Tap => bundle.taps.push(entry.name),
+                BrewfileKind::Cask => bundle.casks.push(entry.name),
+                BrewfileKind::Formula => {
+                    let pinned = line.rsplit_once('#').is_some_and(|(_, comment)| comment.trim() == PINNED_COMMENT);
+                    bundle.formulae.push(BundledFormula { name: entry.name, pinned });
+                }
+            }
+        }
+        bundle
+    }
+
+    fn entries(&self) -> Vec<BrewfileEntry> {
+        let taps = self.taps.iter().map(|tap| BrewfileEntry::new(BrewfileKind::Tap, tap.as_str()));
+        let formulae = self.formulae.iter().map(|f| BrewfileEntry::new(BrewfileKind::Formula, f.name.as_str()));
+        let casks = self.casks.iter().map(|cask| BrewfileEntry::new(BrewfileKind::Cask, cask.as_str()));
+        taps.chain(formulae).chain(casks).collect()
+    }
+
+    fn from_installed(installed: &Installed) -> MachineBundle {
+        let mut bundle = MachineBundle { version: BUNDLE_VERSION, ..Default::default() };
+        for entry in &installed.entries {
+            match entry.kind {
+                BrewfileKind::Tap if DEFAULT_TAPS.contains(&entry.key().1.as_str()) => {}
+                BrewfileKind::Tap => bundle.taps.push(entry.name.clone()),
+                BrewfileKind::Cask => bundle.casks.push(entry.name.clone()),
+                BrewfileKind::Formula => bundle.formulae.push(BundledFormula {
+                    name: entry.name.clone(),
+                    pinned: installed.pinned.contains(&entry.name),
+                }),
+            }
+        }
+        // A dependency comes back by itself, but its pin has to be carried over
+        let pinned_dependencies = installed.dependencies.iter().filter(|name| installed.pinned.contains(*name));
+        bundle.formulae.extend(pinned_dependencies.map(|name| BundledFormula { name: name.clone(), pinned: true }));
+        bundle
+    }
+}
+
+/// A bundle of everything set up on this machine
+pub async fn machine_bundle() -> BrewResult<MachineBundle> {
+    Ok(MachineBundle::from_installed(&installed().await?))
+}
+
+/// What importing a bundle will do here
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MigrationPlan {
+    pub taps: Vec<String>,
+    pub formulae: Vec<String>,
+    pub casks: Vec<String>,
+    /// Formulae to pin, once installed if they aren't yet
+    pub pins: Vec<String>,
+    /// Taps and packages in the bundle that are here already
+    pub present: usize,
+}
+
+impl MigrationPlan {
+    pub fn is_empty(&self) -> bool {
+        self.taps.is_empty() && self.formulae.is_empty() && self.casks.is_empty() && self.pins.is_empty()
+    }
+
+    /// e.g. "Add 1 tap, install 3 formulae and 2 casks, and pin 1 formula; 12 are here already"
+    pub fn summary(&self) -> String {
+        fn count(count: usize, one: &str, many: &str) -> String {
+            format!("{} {}", count, if count == 1 { one } else { many })
+        }
+        let mut steps = Vec::new();
+        if !self.taps.is_empty() {
+            steps.push(format!("add {}", count(self.taps.len(), "tap", "taps")));
+        }
+        let mut installs = Vec::new();
+        if !self.formulae.is_empty() {
+            installs.push(count(self.formulae.len(), "formula", "formulae"));
+        }
+        if !self.casks.is_empty() {
+            installs.push(count(self.casks.len(), "cask", "casks"));
+        }
+        if !installs.is_empty() {
+            steps.push(format!("install {}", installs.join(" and ")));
+        }
+        if !self.pins.is_empty() {
+            steps.push(format!("pin {}", count(self.pins.len(), "formula", "formulae")));
+        }
+        let present = match self.present {
+            0 => String::new(),
+            1 => "; 1 is here already".to_string(),
+            present => format!("; {} are here already", present),
+        };
+        let steps = match steps.as_slice() {
+            [] => return "Everything in the bundle is set up already".to_string(),
+            [only] => only.clone(),
+            [rest @ .., last] => format!("{}, and {}", rest.join(", "), last),
+        };
+        let mut summary = steps + &present;
+        summary[..1].make_ascii_uppercase();
+        summary
+    }
+}
+
+fn plan_migration(bundle: &MachineBundle, installed: &Installed) -> MigrationPlan {
+    let diff = compare(&bundle.entries(), &installed.entries, &installed.dependencies);
+    let mut plan = MigrationPlan { present: diff.matching.len(), ..Default::default() };
+    for entry in diff.missing {
+        match entry.kind {
+            BrewfileKind::Tap => plan.taps.push(entry.name),
+            BrewfileKind::Formula => plan.formulae.push(entry.name),
+            BrewfileKind::Cask => plan.casks.push(entry.name),
+        }
+    }
+    let pinned: HashSet<(BrewfileKind, String)> = installed
+        .pinned
+        .iter()
+        .map(|name| BrewfileEntry::new(BrewfileKind::Formula, name.as_str()).key())
+        .collect();
+    plan.pins = bundle
+        .formulae
+        .iter()
+        .filter(|formula| formula.pinned)
+        .filter(|formula| !pinned.contains(&BrewfileEntry::new(BrewfileKind::Formula, formula.name.as_str()).key()))
+        .map(|formula| formula.name.clone())
+        .collect();
+    plan
+}
+
+/// What importing `bundle` will add to this machine
+pub async fn plan_import(bundle: &MachineBundle) -> BrewResult<MigrationPlan> {
+    Ok(plan_migration(bundle, &installed().await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, version: &str, tap: &str, cask: bool) -> Package {
+        Package {
+            name: name.to_string(),
+            version: Some(version.to_string()),
+            tap: Some(tap.to_string()),
+            cask,
+            installed: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_export_text_and_csv() {
+        let packages = [
+            package("wget", "1.24.5", "homebrew/core", false),
+            package("firefox", "131.0", "homebrew/cask", true),
+        ];
+        assert_eq!(export(&packages, ExportFormat::Text), "firefox\nwget\n");
+        assert_eq!(
+            export(&packages, ExportFormat::Csv),
+            "name,version,tap,kind\nfirefox,131.0,homebrew/cask,cask\nwget,1.24.5,homebrew/core,formula\n"
+        );
+    }
+
+    #[test]
+    fn test_export_json() {
+        let json = export(&[package("wget", "1.24.5", "homebrew/core", false)], ExportFormat::Json);
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            parsed,
+            serde_json::json!([{ "name": "wget", "version": "1.24.5", "tap": "homebrew/core", "kind": "formula" }])
+        );
+    }
+
+    #[test]
+    fn test_is_import_file() {
+        use std::path::Path;
+        for name in ["Brewfile", "/home/me/dotfiles/Brewfile", "work.Brewfile", "bundle.json", "packages.txt"] {
+            assert!(is_import_file(Path::new(name)), "{}", name);
+        }
+        for name in ["Brewfile.lock.json.bak", "photo.png", "brewfile", "notes", "/tmp/"] {
+            assert!(!is_import_file(Path::new(name)), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_parse_brewfile() {
+        let brewfile = "# Dumped by brew bundle\n\
+                        tap \"homebrew/bundle\"\n\
+                        brew \"wget\"\n\
+                        brew 'postgresql@16', restart_service: :changed\n\
+                        cask \"firefox\", args: { appdir: \"~/Apps\" }\n\
+                        mas \"Xcode\", id: 497799835\n\
+                        brew \"\"\n";
+        assert_eq!(
+            parse_brewfile(brewfile),
+            vec![
+                BrewfileEntry::new(BrewfileKind::Tap, "homebrew/bundle"),
+                BrewfileEntry::new(BrewfileKind::Formula, "wget"),
+                BrewfileEntry::new(BrewfileKind::Formula, "postgresql@16"),
+                BrewfileEntry::new(BrewfileKind::Cask, "firefox"),
+            ]
+        );
+        assert_eq!(BrewfileEntry::new(BrewfileKind::Cask, "firefox").line(), "cask \"firefox\"");
+    }
+
+    #[test]
+    fn test_compare() {
+        let brewfile = parse_brewfile(
+            "tap \"user/homebrew-tools\"\nbrew \"user/tools/mytool\"\nbrew \"wget\"\n\
+             brew \"openssl@3\"\ncask \"firefox\"\n",
+        );
+        let installed = [
+            BrewfileEntry::new(BrewfileKind::Tap, "homebrew/core"),
+            BrewfileEntry::new(BrewfileKind::Tap, "user/tools"),
+            BrewfileEntry::new(BrewfileKind::Formula, "mytool"),
+            BrewfileEntry::new(BrewfileKind::Formula, "jq"),
+            BrewfileEntry::new(BrewfileKind::Cask, "iterm2"),
+        ];
+        let diff = compare(&brewfile, &installed, &["openssl@3".to_string(), "oniguruma".to_string()]);
+        assert_eq!(
+            diff.missing,
+            vec![BrewfileEntry::new(BrewfileKind::Formula, "wget"), BrewfileEntry::new(BrewfileKind::Cask, "firefox")]
+        );
+        assert_eq!(
+            diff.extra,
+            vec![BrewfileEntry::new(BrewfileKind::Formula, "jq"), BrewfileEntry::new(BrewfileKind::Cask, "iterm2")]
+        );
+        let matching: Vec<&str> = diff.matching.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(matching, ["user/homebrew-tools", "user/tools/mytool", "openssl@3"]);
+    }
+
+    #[test]
+    fn test_append_to_brewfile() {
+        let extras = [BrewfileEntry::new(BrewfileKind::Formula, "jq")];
+        assert_eq!(append_to_brewfile("brew \"wget\"", &extras), "brew \"wget\"\nbrew \"jq\"\n");
+        assert_eq!(append_to_brewfile("", &extras), "brew \"jq\"\n");
+    }
+
+    fn bundle() -> MachineBundle {
+        MachineBundle {
+            version: BUNDLE_VERSION,
+            taps: vec!["user/tools".to_string()],
+            formulae: vec![
+                BundledFormula { name: "wget".to_string(), pinned: false },
+                BundledFormula { name: "node@20".to_string(), pinned: true },
+            ],
+            casks: vec!["firefox".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let bundle = bundle();
+        assert_eq!(
+            bundle.to_brewfile(),
+            "tap \"user/tools\"\nbrew \"wget\"\nbrew \"node@20\" # pinned\ncask \"firefox\"\n"
+        );
+        assert_eq!(MachineBundle::parse(&bundle.to_brewfile()), Ok(bundle.clone()));
+        assert_eq!(MachineBundle::parse(&bundle.to_json()), Ok(bundle));
+
+        // A plain `brew bundle dump` has nothing pinned
+        let dumped = MachineBundle::parse("brew \"jq\", link: false\n").unwrap();
+        assert_eq!(dumped.formulae, vec![BundledFormula { name: "jq".to_string(), pinned: false }]);
+        assert!(MachineBundle::parse("{\"version\": 2}").is_err());
+        assert!(MachineBundle::parse("{\"taps\": 1}").is_err());
+    }
+
+    #[test]
+    fn test_plan_migration() {
+        let installed = Installed {
+            entries: vec![
+                BrewfileEntry::new(BrewfileKind::Tap, "homebrew/core"),
+                BrewfileEntry::new(BrewfileKind::Formula, "wget"),
+            ],
+            dependencies: vec!["node@20".to_string()],
+            pinned: HashSet::new(),
+        };
+        let plan = plan_migration(&bundle(), &installed);
+        assert_eq!(
+            plan,
+            MigrationPlan {
+                taps: vec!["user/tools".to_string()],
+                formulae: Vec::new(),
+                casks: vec!["firefox".to_string()],
+                pins: vec!["node@20".to_string()],
+                present: 2,
+            }
+        );
+        assert_eq!(plan.summary(), "Add 1 tap, install 1 cask, and pin 1 formula; 2 are here already");
+
+        let bundled = MachineBundle::from_installed(&Installed {
+            entries: installed.entries.clone(),
+            dependencies: installed.dependencies.clone(),
+            pinned: ["wget".to_string()].into(),
+        });
+        assert_eq!(bundled.taps, Vec::<String>::new());
+        assert_eq!(bundled.formulae, vec![BundledFormula { name: "wget".to_string(), pinned: true }]);
+
+        // Only pinned dependencies are bundled
+        let bundled = MachineBundle::from_installed(&Installed { pinned: ["node@20".to_string()].into(), ..installed });
+        assert_eq!(
+            bundled.formulae,
+            vec![
+                BundledFormula { name: "wget".to_string(), pinned: false },
+                BundledFormula { name: "node@20".to_string(), pinned: true },
+            ]
+        );
+        assert_eq!(MigrationPlan::default().summary(), "Everything in the bundle is set up already");
+    }
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("1.0"), "1.0");
+        assert_eq!(csv_field("a,\"b\""), "\"a,\"\"b\"\"\"");
+    }
+}
@@ -0,0 +1,32 @@
+//! Homebrew operations used by BrewHouse, independent of any UI.
+//!
+//! Everything that runs `brew` lives in [`brew`]. The functions are async and
+//! expect a tokio runtime; each returns a [`brew::BrewResult`] whose error can be
+//! shown to users with [`brew::BrewError::user_message`]. Lookups are cached in
+//! memory and in [`metadata_cache`], and operations that change what is installed
+//! drop the affected entries.
+//!
+//! ```no_run
+//! use brewhouse_core::brew;
+//!
+//! # async fn example() -> brew::BrewResult<()> {
+//! for package in brew::get_installed_packages().await? {
+//!     println!("{} {}", package.name, package.version.as_deref().unwrap_or("?"));
+//! }
+//! brew::install_package("wget", &brew::InstallOptions::default()).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`runner`] builds the processes, routing them through `flatpak-spawn` or
+//! Rosetta when needed, and [`storage`] reads and writes the versioned JSON files
+//! kept under the XDG directories. [`ansi`] cleans up the terminal escapes in
+//! brew's output for display, and [`inventory`] writes the installed packages out for
+//! other tools and compares them with a Brewfile.
+
+pub mod ansi;
+pub mod brew;
+pub mod inventory;
+pub mod metadata_cache;
+pub mod runner;
+pub mod storage;
@@ -0,0 +1,376 @@
+use crate::brew::PackageInfo;
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Entries older than this are still used, but refreshed in the background
+const STALE_AFTER_SECS: u64 = 24 * 60 * 60;
+
+/// Package info entries kept on disk; the least recently fetched are dropped first
+const MAX_INFO_ENTRIES: usize = 500;
+
+/// How long changes to the formula list stay in the feed
+const FEED_KEEP_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// A cached value and when it was fetched from brew (Unix seconds)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry<T> {
+    pub fetched: u64,
+    pub value: T,
+}
+
+impl<T> Entry<T> {
+    pub fn is_stale(&self) -> bool {
+        now().saturating_sub(self.fetched) > STALE_AFTER_SECS
+    }
+}
+
+/// Formula metadata from previous runs, in $XDG_CACHE_HOME/brewhouse/metadata.json,
+/// so Browse works before brew has answered
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct MetadataCache {
+    /// Every formula name, as listed by `brew search --formula` with no query
+    formula_index: Option<Entry<Vec<String>>>,
+    /// `brew info` results by formula or cask name, without their install state
+    package_info: HashMap<String, Entry<PackageInfo>>,
+    formula_feed: FormulaFeed,
+    /// Formulae each saved search with notifications on matched at the last background check
+    saved_search_matches: HashMap<String, Vec<String>>,
+}
+
+/// Changes to the formula list noticed between snapshots of it. Brew's API has no date a
+/// formula was added, so a formula is new from when it first shows up in the list.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FormulaFeed {
+    /// Stable version of every formula at the last snapshot; empty before the first
+    versions: HashMap<String, String>,
+    /// Formulae that weren't in the previous snapshot, with when they were first seen
+    pub added: Vec<(String, u64)>,
+    /// New versions of formulae, newest first
+    pub updated: Vec<VersionChange>,
+}
+
+/// A formula whose stable version changed between two snapshots
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionChange {
+    pub name: String,
+    pub old: String,
+    pub new: String,
+    /// When the new version was first seen (Unix seconds)
+    pub seen: u64,
+}
+
+impl VersionChange {
+    /// Whether the first number of the version changed, e.g. 16.4 -> 17.0
+    pub fn is_major(&self) -> bool {
+        let major = |version: &str| version.split(['.', '_', '-']).next().map(str::to_string);
+        major(&self.old) != major(&self.new)
+    }
+}
+
+impl FormulaFeed {
+    /// Compare `versions`, the formula list as it is now, with the last snapshot and keep it
+    /// as the new one. The first snapshot only sets the baseline.
+    fn observe(&mut self, versions: HashMap<String, String>, now: u64) {
+        if !self.versions.is_empty() {
+            let added = versions.keys().filter(|name| !self.versions.contains_key(*name));
+            self.added.extend(added.map(|name| (name.clone(), now)));
+
+            for (name, new) in &versions {
+                let Some(old) = self.versions.get(name).filter(|old| *old != new) else {
+                    continue;
+                };
+                // Several bumps between looks show as one change from the oldest version
+                let old = match self.updated.iter().position(|change| change.name == *name) {
+                    Some(index) => self.updated.remove(index).old,
+                    None => old.clone(),
+                };
+                self.updated.push(VersionChange { name: name.clone(), old, new: new.clone(), seen: now });
+            }
+        }
+        self.updated.retain(|change| now.saturating_sub(change.seen) <= FEED_KEEP_SECS);
+        self.updated.sort_by(|a, b| b.seen.cmp(&a.seen).then_with(|| a.name.cmp(&b.name)));
+        // A formula that was removed again is no longer new
+        self.added.retain(|(name, seen)| now.saturating_sub(*seen) <= FEED_KEEP_SECS && versions.contains_key(name));
+        self.added.sort_by(|(a, a_seen), (b, b_seen)| b_seen.cmp(a_seen).then_with(|| a.cmp(b)));
+        self.versions = versions;
+    }
+
+    /// `observe`, with `baseline` taken as the previous snapshot if there is none yet
+    fn observe_from(
+        &mut self,
+        baseline: Option<(HashMap<String, String>, u64)>,
+        versions: HashMap<String, String>,
+        now: u64,
+    ) {
+        if let Some((old_versions, taken)) = baseline.filter(|_| self.versions.is_empty()) {
+            self.observe(old_versions, taken);
+        }
+        self.observe(versions, now);
+    }
+
+    /// Version changes seen in the last `secs` seconds: major ones first, then newest first
+    pub fn updated_within(&self, secs: u64) -> Vec<VersionChange> {
+        let now = now();
+        let mut updated: Vec<VersionChange> =
+            self.updated.iter().filter(|change| now.saturating_sub(change.seen) <= secs).cloned().collect();
+        updated.sort_by_key(|change| !change.is_major());
+        updated
+    }
+
+    /// Formulae first seen in the last `secs` seconds, newest first
+    pub fn added_within(&self, secs: u64) -> Vec<String> {
+        let now = now();
+        self.added
+            .iter()
+            .filter(|(_, seen)| now.saturating_sub(*seen) <= secs)
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+}
+
+// Schema migrations for metadata.json, oldest first (see storage::Migration)
+const MIGRATIONS: &[storage::Migration] = &[tag_formula_info];
+
+/// v1 -> v2: package info could only be a formula; it is now tagged formula or cask
+fn tag_formula_info(mut data: serde_json::Value) -> serde_json::Value {
+    if let Some(entries) = data.get_mut("package_info").and_then(|v| v.as_object_mut()) {
+        for entry in entries.values_mut() {
+            if let Some(value) = entry.get_mut("value") {
+                *value = serde_json::json!({ "formula": value.take() });
+            }
+        }
+    }
+    data
+}
+
+static CACHE: Mutex<Option<MetadataCache>> = Mutex::new(None);
+
+fn cache_path() -> PathBuf {
+    storage::cache_dir().join("metadata.json")
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Run `f` on the cache, reading it from disk on first use
+fn with_cache<R>(f: impl FnOnce(&mut MetadataCache) -> R) -> R {
+    let mut guard = CACHE.lock().unwrap();
+    let cache = guard.get_or_insert_with(|| match storage::load(&cache_path(), MIGRATIONS) {
+        Ok(cache) => cache.unwrap_or_default(),
+        Err(e) => {
+            eprintln!("Failed to load metadata cache: {}", e);
+            MetadataCache::default()
+        }
+    });
+    f(cache)
+}
+
+fn save(cache: &MetadataCache) {
+    if let Err(e) = storage::save(&cache_path(), cache, MIGRATIONS) {
+        eprintln!("Failed to save metadata cache: {}", e);
+    }
+}
+
+pub fn formula_index() -> Option<Entry<Vec<String>>> {
+    with_cache(|cache| cache.formula_index.clone())
+}
+
+pub fn store_formula_index(names: &[String]) {
+    with_cache(|cache| {
+        cache.formula_index = Some(Entry {
+            fetched: now(),
+            value: names.to_vec(),
+        });
+        save(cache);
+    });
+}
+
+/// What `query` matched at the last saved search check; None before the first
+pub fn saved_search_matches(query: &str) -> Option<Vec<String>> {
+    with_cache(|cache| cache.saved_search_matches.get(query).cloned())
+}
+
+/// Record what each checked saved search matches, forgetting searches that weren't checked
+pub fn store_saved_search_matches(matches: HashMap<String, Vec<String>>) {
+    with_cache(|cache| {
+        cache.saved_search_matches = matches;
+        save(cache);
+    });
+}
+
+pub fn formula_feed() -> FormulaFeed {
+    with_cache(|cache| cache.formula_feed.clone())
+}
+
+/// Record the formula list as it is now, given as name -> stable version, and return the
+/// feed of what changed since the previous snapshot. Without one yet, `baseline` (an older
+/// copy of the list and when it was taken) stands in for it, so the first look already
+/// finds what changed since then.
+pub fn update_formula_feed(
+    versions: HashMap<String, String>,
+    baseline: Option<(HashMap<String, String>, u64)>,
+) -> FormulaFeed {
+    with_cache(|cache| {
+        cache.formula_feed.observe_from(baseline, versions, now());
+        save(cache);
+        cache.formula_feed.clone()
+    })
+}
+
+pub fn package_info(name: &str) -> Option<Entry<PackageInfo>> {
+    with_cache(|cache| cache.package_info.get(name).cloned())
+}
+
+pub fn store_package_info(name: &str, info: &PackageInfo) {
+    with_cache(|cache| {
+        cache.package_info.insert(
+            name.to_string(),
+            Entry {
+                fetched: now(),
+                value: info.clone(),
+            },
+        );
+        prune(&mut cache.package_info, MAX_INFO_ENTRIES);
+        save(cache);
+    });
+}
+
+/// Forget cached info for one package, or for all of them with None
+pub fn remove_package_info(name: Option<&str>) {
+    with_cache(|cache| {
+        let changed = match name {
+            Some(name) => cache.package_info.remove(name).is_some(),
+            None => {
+                let had_entries = !cache.package_info.is_empty();
+                cache.package_info.clear();
+                had_entries
+            }
+        };
+        if changed {
+            save(cache);
+        }
+    });
+}
+
+/// Drop the oldest entries until at most `max` remain
+fn prune<T>(entries: &mut HashMap<String, Entry<T>>, max: usize) {
+    if entries.len() <= max {
+        return;
+    }
+    let mut by_age: Vec<(u64, String)> = entries.iter().map(|(k, e)| (e.fetched, k.clone())).collect();
+    by_age.sort();
+    for (_, key) in by_age.into_iter().take(entries.len() - max) {
+        entries.remove(&key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_is_stale() {
+        let fresh = Entry { fetched: now(), value: () };
+        assert!(!fresh.is_stale());
+
+        let old = Entry {
+            fetched: now() - STALE_AFTER_SECS - 1,
+            value: (),
+        };
+        assert!(old.is_stale());
+    }
+
+    #[test]
+    fn test_prune_drops_oldest() {
+        let mut entries: HashMap<String, Entry<u32>> = HashMap::new();
+        for (name, fetched) in [("a", 30), ("b", 10), ("c", 20)] {
+            entries.insert(name.to_string(), Entry { fetched, value: 0 });
+        }
+
+        prune(&mut entries, 3);
+        assert_eq!(entries.len(), 3);
+
+        prune(&mut entries, 2);
+        assert_eq!(entries.len(), 2);
+        assert!(!entries.contains_key("b"));
+
+        prune(&mut entries, 0);
+        assert!(entries.is_empty());
+    }
+
+    fn versions(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries.iter().map(|(name, version)| (name.to_string(), version.to_string())).collect()
+    }
+
+    #[test]
+    fn test_formula_feed_added() {
+        let mut feed = FormulaFeed::default();
+        feed.observe(versions(&[("wget", "1.24.5")]), 1_000);
+        assert!(feed.added.is_empty());
+
+        feed.observe(versions(&[("wget", "1.24.5"), ("uv", "0.4.0")]), 2_000);
+        feed.observe(versions(&[("wget", "1.24.5"), ("uv", "0.4.0"), ("ruff", "0.6.0")]), 3_000);
+        assert_eq!(feed.added, vec![("ruff".to_string(), 3_000), ("uv".to_string(), 2_000)]);
+
+        // Removed formulae and ones seen too long ago drop out
+        feed.observe(versions(&[("wget", "1.24.5"), ("uv", "0.4.0")]), 2_000 + FEED_KEEP_SECS + 1);
+        assert!(feed.added.is_empty());
+    }
+
+    #[test]
+    fn test_formula_feed_baseline() {
+        // The first look compares with the baseline
+        let mut feed = FormulaFeed::default();
+        let baseline = Some((versions(&[("wget", "1.24.5")]), 1_000));
+        feed.observe_from(baseline.clone(), versions(&[("wget", "1.25.0"), ("uv", "0.4.0")]), 2_000);
+        assert_eq!(feed.added, vec![("uv".to_string(), 2_000)]);
+        assert_eq!(feed.updated.len(), 1);
+
+        // Later looks compare with the last snapshot only
+        feed.observe_from(baseline, versions(&[("wget", "1.25.0"), ("uv", "0.4.0")]), 3_000);
+        assert_eq!(feed.added, vec![("uv".to_string(), 2_000)]);
+        assert_eq!(feed.updated.len(), 1);
+    }
+
+    #[test]
+    fn test_formula_feed_updated() {
+        let mut feed = FormulaFeed::default();
+        feed.observe(versions(&[("node", "22.9.0"), ("postgresql@17", "17.0")]), 1_000);
+        feed.observe(versions(&[("node", "22.10.0"), ("postgresql@17", "17.0")]), 2_000);
+        feed.observe(versions(&[("node", "23.0.0"), ("postgresql@17", "17.0")]), 3_000);
+
+        let change = VersionChange {
+            name: "node".to_string(),
+            old: "22.9.0".to_string(),
+            new: "23.0.0".to_string(),
+            seen: 3_000,
+        };
+        assert_eq!(feed.updated, vec![change.clone()]);
+        assert!(change.is_major());
+        assert!(!VersionChange { old: "1.2".to_string(), new: "1.3_1".to_string(), ..change }.is_major());
+    }
+
+    #[test]
+    fn test_formula_info_migration() {
+        let old = serde_json::json!({
+            "package_info": {
+                "wget": { "fetched": 1, "value": { "name": "wget", "versions": { "stable": "1.24.5" } } }
+            }
+        });
+        let cache: MetadataCache = serde_json::from_value(tag_formula_info(old)).unwrap();
+        match &cache.package_info["wget"].value {
+            PackageInfo::Formula(info) => assert_eq!(info.name, "wget"),
+            other => panic!("expected a formula, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use std::ffi::OsString;
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::process::Output;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// How a brew invocation has to be wrapped to reach the real executable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Wrapping {
+    /// Running inside a Flatpak sandbox: brew lives on the host
+    pub flatpak: bool,
+    /// Intel Homebrew on an Apple Silicon Mac: run under Rosetta
+    pub rosetta: bool,
+}
+
+/// Whether BrewHouse is running inside a Flatpak sandbox
+pub fn is_flatpak() -> bool {
+    static FLATPAK: OnceLock<bool> = OnceLock::new();
+    *FLATPAK.get_or_init(|| Path::new("/.flatpak-info").exists() || std::env::var_os("FLATPAK_ID").is_some())
+}
+
+/// The Intel prefix on an Apple Silicon Mac has to run under Rosetta
+pub fn needs_rosetta(prefix: &Path, os: &str, arch: &str) -> bool {
+    os == "macos" && arch == "aarch64" && prefix == Path::new("/usr/local")
+}
+
+// Extra environment for every command built here; see set_env
+static ENV: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+/// Set `vars` (e.g. HOMEBREW_NO_ANALYTICS=1) for every command built from now on,
+/// replacing the ones set before
+pub fn set_env(vars: Vec<(String, String)>) {
+    *ENV.lock().unwrap() = vars;
+}
+
+/// The variables set with `set_env`
+pub fn env() -> Vec<(String, String)> {
+    ENV.lock().unwrap().clone()
+}
+
+/// Full argv (program first) for running `program` with the given wrapping. The host
+/// doesn't inherit the sandbox's environment, so under Flatpak `env` is passed along with
+/// `--env`; otherwise it has to be set on the process.
+pub fn command_argv(program: &Path, wrapping: Wrapping, env: &[(String, String)]) -> Vec<OsString> {
+    let mut argv: Vec<OsString> = Vec::new();
+    if wrapping.flatpak {
+        // --watch-bus ends the host process if BrewHouse goes away
+        argv.extend(["flatpak-spawn", "--host", "--watch-bus"].map(OsString::from));
+        argv.extend(env.iter().map(|(name, value)| OsString::from(format!("--env={}={}", name, value))));
+    }
+    if wrapping.rosetta {
+        argv.extend(["arch", "-x86_64"].map(OsString::from));
+    }
+    argv.push(program.as_os_str().to_owned());
+    argv
+}
+
+/// Wrapping needed for a brew in `prefix` in the current environment
+pub fn current_wrapping(prefix: &Path) -> Wrapping {
+    Wrapping {
+        flatpak: is_flatpak(),
+        rosetta: needs_rosetta(prefix, std::env::consts::OS, std::env::consts::ARCH),
+    }
+}
+
+/// Async command for `program` with the `set_env` variables, routed through the host
+/// or Rosetta when needed
+pub fn tokio_command(program: &Path, prefix: &Path) -> tokio::process::Command {
+    tokio_command_with_env(program, prefix, &[])
+}
+
+/// `tokio_command` with `extra` variables on top of the `set_env` ones
+pub fn tokio_command_with_env(program: &Path, prefix: &Path, extra: &[(&str, &str)]) -> tokio::process::Command {
+    let mut env = env();
+    env.extend(extra.iter().map(|(name, value)| (name.to_string(), value.to_string())));
+    let argv = command_argv(program, current_wrapping(prefix), &env);
+    let mut cmd = tokio::process::Command::new(&argv[0]);
+    cmd.args(&argv[1..]).envs(env);
+    cmd
+}
+
+/// Blocking variant of `tokio_command`
+pub fn std_command(program: &Path, prefix: &Path) -> std::process::Command {
+    let env = env();
+    let argv = command_argv(program, current_wrapping(prefix), &env);
+    let mut cmd = std::process::Command::new(&argv[0]);
+    cmd.args(&argv[1..]).envs(env);
+    cmd
+}
+
+/// Future returned by `BrewRunner::output`
+pub type OutputFuture<'a> = Pin<Box<dyn Future<Output = io::Result<Output>> + Send + 'a>>;
+
+/// Runs brew to completion and collects its output. brew.rs goes through this
+/// instead of spawning processes itself, so tests can answer with canned output.
+pub trait BrewRunner: Send + Sync {
+    /// Run brew with `args` (not including the program itself)
+    fn output(&self, args: Vec<OsString>) -> OutputFuture<'_>;
+}
+
+/// Runs the real brew executable with tokio::process
+pub struct ProcessRunner {
+    program: PathBuf,
+    prefix: PathBuf,
+}
+
+impl ProcessRunner {
+    pub fn new(program: PathBuf, prefix: PathBuf) -> Self {
+        Self { program, prefix }
+    }
+}
+
+impl BrewRunner for ProcessRunner {
+    fn output(&self, args: Vec<OsString>) -> OutputFuture<'_> {
+        let mut cmd = tokio_command(&self.program, &self.prefix);
+        cmd.args(args);
+        Box::pin(async move { cmd.output().await })
+    }
+}
+
+tokio::task_local! {
+    static RUNNER: Arc<dyn BrewRunner>;
+}
+
+/// Run `future` with every brew invocation it makes on the current task going to `runner`.
+/// Work handed to other threads (e.g. background cache refreshes) still runs the real brew.
+pub async fn with_runner<F: Future>(runner: Arc<dyn BrewRunner>, future: F) -> F::Output {
+    RUNNER.scope(runner, future).await
+}
+
+/// The runner installed by `with_runner` for the current task, if any
+pub fn scoped_runner() -> Option<Arc<dyn BrewRunner>> {
+    RUNNER.try_with(Arc::clone).ok()
+}
+
+/// Test runner that answers each invocation with the next canned response, in order,
+/// and records the arguments it was called with
+#[cfg(test)]
+#[derive(Default)]
+pub struct CannedRunner {
+    responses: std::sync::Mutex<std::collections::VecDeque<(i32, String, String)>>,
+    calls: std::sync::Mutex<Vec<Vec<String>>>,
+}
+
+#[cfg(test)]
+impl CannedRunner {
+    /// Queue a response with the given exit code, stdout and stderr
+    pub fn respond(self, code: i32, stdout: &str, stderr: &str) -> Self {
+        self.responses.lock().unwrap().push_back((code, stdout.to_string(), stderr.to_string()));
+        self
+    }
+
+    /// Arguments of every invocation so far
+    pub fn calls(&self) -> Vec<Vec<String>> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+impl BrewRunner for CannedRunner {
+    fn output(&self, args: Vec<OsString>) -> OutputFuture<'_> {
+        use std::os::unix::process::ExitStatusExt;
+
+        self.calls
+            .lock()
+            .unwrap()
+            .push(args.iter().map(|arg| arg.to_string_lossy().to_string()).collect());
+        // Running out of responses looks like brew failing to start
+        let result = match self.responses.lock().unwrap().pop_front() {
+            Some((code, stdout, stderr)) => Ok(Output {
+                status: std::process::ExitStatus::from_raw(code << 8),
+                stdout: stdout.into_bytes(),
+                stderr: stderr.into_bytes(),
+            }),
+            None => Err(io::Error::new(io::ErrorKind::NotFound, "no canned response left")),
+        };
+        Box::pin(async move { result })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(program: &str, flatpak: bool, rosetta: bool) -> Vec<String> {
+        command_argv(Path::new(program), Wrapping { flatpak, rosetta }, &[])
+            .into_iter()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_command_argv() {
+        assert_eq!(argv("brew", false, false), vec!["brew"]);
+        assert_eq!(
+            argv("brew", true, false),
+            vec!["flatpak-spawn", "--host", "--watch-bus", "brew"]
+        );
+        assert_eq!(
+            argv("/usr/local/bin/brew", false, true),
+            vec!["arch", "-x86_64", "/usr/local/bin/brew"]
+        );
+    }
+
+    #[test]
+    fn test_command_argv_passes_env_to_host() {
+        let env = vec![("HOMEBREW_NO_ANALYTICS".to_string(), "1".to_string())];
+        let host = command_argv(Path::new("brew"), Wrapping { flatpak: true, rosetta: false }, &env);
+        assert_eq!(host[3], "--env=HOMEBREW_NO_ANALYTICS=1");
+        assert_eq!(host[4], "brew");
+        // Outside Flatpak the variables are set on the process instead
+        let local = command_argv(Path::new("brew"), Wrapping { flatpak: false, rosetta: false }, &env);
+        assert_eq!(local, vec![OsString::from("brew")]);
+    }
+
+    #[test]
+    fn test_needs_rosetta() {
+        let intel = Path::new("/usr/local");
+        let arm = Path::new("/opt/homebrew");
+        assert!(needs_rosetta(intel, "macos", "aarch64"));
+        assert!(!needs_rosetta(intel, "macos", "x86_64"));
+        assert!(!needs_rosetta(arm, "macos", "aarch64"));
+        assert!(!needs_rosetta(intel, "linux", "aarch64"));
+    }
+
+    #[tokio::test]
+    async fn test_canned_runner() {
+        let runner = Arc::new(CannedRunner::default().respond(0, "ok\n", "").respond(1, "", "Error: nope"));
+        let output = with_runner(runner.clone(), async {
+            let runner = scoped_runner().expect("runner is scoped");
+            let first = runner.output(vec!["tap".into()]).await.unwrap();
+            let second = runner.output(vec!["tap".into(), "x/y".into()]).await.unwrap();
+            let third = runner.output(vec![]).await;
+            (first, second, third)
+        })
+        .await;
+
+        assert!(output.0.status.success());
+        assert_eq!(output.0.stdout, b"ok\n");
+        assert!(!output.1.status.success());
+        assert_eq!(output.1.stderr, b"Error: nope");
+        assert!(output.2.is_err());
+        assert_eq!(runner.calls(), vec![vec!["tap"], vec!["tap", "x/y"], vec![]]);
+        assert!(scoped_runner().is_none());
+    }
+}
@@ -0,0 +1,283 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Upgrades a file's data from one schema version to the next.
+/// A file's migration list is append-only: entry `i` turns version `i + 1` into `i + 2`.
+pub type Migration = fn(Value) -> Value;
+
+/// Directory for user configuration ($XDG_CONFIG_HOME/brewhouse)
+pub fn config_dir() -> PathBuf {
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// Directory for persistent application data ($XDG_DATA_HOME/brewhouse)
+pub fn data_dir() -> PathBuf {
+    xdg_dir("XDG_DATA_HOME", ".local/share")
+}
+
+/// Directory for data that can be rebuilt at any time ($XDG_CACHE_HOME/brewhouse)
+pub fn cache_dir() -> PathBuf {
+    xdg_dir("XDG_CACHE_HOME", ".cache")
+}
+
+/// Directory desktop sessions start programs from at login ($XDG_CONFIG_HOME/autostart)
+pub fn autostart_dir() -> PathBuf {
+    xdg_base("XDG_CONFIG_HOME", ".config").join("autostart")
+}
+
+fn xdg_dir(var: &str, fallback: &str) -> PathBuf {
+    xdg_base(var, fallback).join("brewhouse")
+}
+
+fn xdg_base(var: &str, fallback: &str) -> PathBuf {
+    // The XDG spec says relative values must be ignored
+    std::env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .unwrap_or_else(|| home_dir().join(fallback))
+}
+
+fn home_dir() -> PathBuf {
+    std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+}
+
+/// Schema version of a file with the given migrations.
+/// Version 1 is the first format; every migration adds one.
+pub fn current_version(migrations: &[Migration]) -> u32 {
+    migrations.len() as u32 + 1
+}
+
+/// Load a versioned JSON file. Returns Ok(None) if the file doesn't exist.
+///
+/// Files written by an older release are migrated to the current schema and
+/// rewritten, keeping the previous contents as `<file>.v<N>.bak`. Files from a
+/// newer release are read as they are and left alone; `save` won't overwrite them.
+/// Files that can't be parsed are preserved as `<file>.corrupt.bak` and reported
+/// as errors.
+pub fn load<T: DeserializeOwned>(path: &Path, migrations: &[Migration]) -> io::Result<Option<T>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    let current = current_version(migrations);
+    let parsed = serde_json::from_str::<Value>(&contents)
+        .map_err(|e| e.to_string())
+        .map(split_envelope)
+        .and_then(|(version, data)| {
+            let old_version = version.unwrap_or(1);
+            let data = if old_version < current { migrate(old_version, data, migrations) } else { data };
+            serde_json::from_value::<T>(data.clone())
+                .map(|value| (version, data, value))
+                .map_err(|e| e.to_string())
+        });
+
+    let (version, data, value) = match parsed {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            fs::copy(path, backup_path(path, "corrupt"))?;
+            return Err(io::Error::new(io::ErrorKind::InvalidData, e));
+        }
+    };
+
+    // Unversioned files get an envelope even when there is nothing to migrate
+    let old_version = version.unwrap_or(1);
+    if version.is_none() || old_version < current {
+        fs::copy(path, backup_path(path, &format!("v{}", old_version)))?;
+        write_envelope(path, current, data)?;
+    }
+
+    Ok(Some(value))
+}
+
+/// Save data to a versioned JSON file, creating parent directories as needed.
+/// Refuses to replace a file written by a newer release, which this one can't fully read.
+pub fn save<T: Serialize>(path: &Path, data: &T, migrations: &[Migration]) -> io::Result<()> {
+    let current = current_version(migrations);
+    if let Some(version) = file_version(path).filter(|version| *version > current) {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} was written by a newer BrewHouse (schema version {})", path.display(), version),
+        ));
+    }
+    let data = serde_json::to_value(data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    write_envelope(path, current, data)
+}
+
+/// Schema version of the file at `path`, if it exists and has one
+fn file_version(path: &Path) -> Option<u32> {
+    let contents = fs::read_to_string(path).ok()?;
+    split_envelope(serde_json::from_str(&contents).ok()?).0
+}
+
+/// Split a file into (schema version, data).
+/// Files written before versioning was introduced have no envelope and no version;
+/// their data is treated as version 1.
+fn split_envelope(value: Value) -> (Option<u32>, Value) {
+    if let Value::Object(map) = &value {
+        if let (Some(version), Some(data)) = (map.get("schema_version").and_then(Value::as_u64), map.get("data")) {
+            return (Some(version as u32), data.clone());
+        }
+    }
+    (None, value)
+}
+
+/// Apply the migrations needed to bring `data` from `version` to the current schema
+fn migrate(version: u32, mut data: Value, migrations: &[Migration]) -> Value {
+    let start = version.max(1) as usize - 1;
+    for migration in migrations.iter().skip(start) {
+        data = migration(data);
+    }
+    data
+}
+
+fn write_envelope(path: &Path, version: u32, data: Value) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let envelope = serde_json::json!({ "schema_version": version, "data": data });
+    let json = serde_json::to_string_pretty(&envelope)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    // Write to a temporary file first so a crash never leaves a half-written file
+    let tmp_path = sibling_path(path, "tmp");
+    fs::write(&tmp_path, json)?;
+    fs::rename(&tmp_path, path)
+}
+
+/// Path of a backup next to `path`, e.g. `settings.json.v1.bak`
+fn backup_path(path: &Path, tag: &str) -> PathBuf {
+    sibling_path(path, &format!("{}.bak", tag))
+}
+
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Notes {
+        entries: Vec<String>,
+    }
+
+    fn temp_file(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("brewhouse-storage-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("notes.json")
+    }
+
+    // v1 stored a bare list; v2 wraps it in an object
+    fn wrap_entries(data: Value) -> Value {
+        serde_json::json!({ "entries": data })
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let path = temp_file("missing");
+        let loaded: Option<Notes> = load(&path, &[]).unwrap();
+        assert!(loaded.is_none());
+    }
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let path = temp_file("roundtrip");
+        let notes = Notes { entries: vec!["git".to_string()] };
+
+        save(&path, &notes, &[]).unwrap();
+        let loaded: Option<Notes> = load(&path, &[]).unwrap();
+        assert_eq!(loaded, Some(notes));
+
+        let raw: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(raw["schema_version"], 1);
+    }
+
+    #[test]
+    fn test_load_unversioned_file_as_version_1() {
+        let path = temp_file("unversioned");
+        fs::write(&path, r#"{"entries": ["wget"]}"#).unwrap();
+
+        let loaded: Option<Notes> = load(&path, &[]).unwrap();
+        assert_eq!(loaded.unwrap().entries, vec!["wget"]);
+
+        // Rewritten with an envelope, original kept as a backup
+        let raw: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(raw["schema_version"], 1);
+        assert!(backup_path(&path, "v1").exists());
+    }
+
+    #[test]
+    fn test_load_runs_migrations_and_keeps_backup() {
+        let path = temp_file("migrate");
+        fs::write(&path, r#"{"schema_version": 1, "data": ["jq", "fd"]}"#).unwrap();
+
+        let migrations: &[Migration] = &[wrap_entries];
+        let loaded: Option<Notes> = load(&path, migrations).unwrap();
+        assert_eq!(loaded.unwrap().entries, vec!["jq", "fd"]);
+
+        let raw: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(raw["schema_version"], 2);
+        assert_eq!(raw["data"]["entries"][0], "jq");
+
+        let backup = fs::read_to_string(backup_path(&path, "v1")).unwrap();
+        assert!(backup.contains(r#"["jq", "fd"]"#));
+    }
+
+    #[test]
+    fn test_load_current_version_does_not_rewrite() {
+        let path = temp_file("current");
+        let migrations: &[Migration] = &[wrap_entries];
+        save(&path, &Notes { entries: vec![] }, migrations).unwrap();
+
+        let _: Option<Notes> = load(&path, migrations).unwrap();
+        assert!(!backup_path(&path, "v2").exists());
+    }
+
+    #[test]
+    fn test_load_newer_version_leaves_file_alone() {
+        let path = temp_file("newer");
+        let newer = r#"{"schema_version": 3, "data": {"entries": ["gh"], "tags": {}}}"#;
+        fs::write(&path, newer).unwrap();
+
+        let migrations: &[Migration] = &[wrap_entries];
+        let loaded: Option<Notes> = load(&path, migrations).unwrap();
+        assert_eq!(loaded.unwrap().entries, vec!["gh"]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), newer);
+        assert!(!backup_path(&path, "v3").exists());
+
+        // Saving would lose what the newer release added
+        assert!(save(&path, &Notes { entries: vec![] }, migrations).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), newer);
+    }
+
+    #[test]
+    fn test_load_corrupt_file_is_preserved() {
+        let path = temp_file("corrupt");
+        fs::write(&path, "{ not json").unwrap();
+
+        let result: io::Result<Option<Notes>> = load(&path, &[]);
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(backup_path(&path, "corrupt")).unwrap(), "{ not json");
+    }
+
+    #[test]
+    fn test_current_version() {
+        assert_eq!(current_version(&[]), 1);
+        assert_eq!(current_version(&[wrap_entries, wrap_entries]), 3);
+    }
+}
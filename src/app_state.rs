@@ -0,0 +1,113 @@
+use crate::brew;
+use crate::history;
+use crate::package_object::PackageObject;
+use gtk4::gio;
+use gtk4::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Data shared by the views of one main window. Views own their widgets and go through
+/// this for package data, so a new page only needs a clone of the state.
+/// Cloning is cheap; all clones share the same stores.
+#[derive(Clone)]
+pub struct AppState {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    /// Installed packages as `PackageObject`s
+    installed: gio::ListStore,
+    /// Set once the installed packages have been loaded
+    installed_loaded: Cell<bool>,
+    /// Output of the last failed upgrade, by package name
+    upgrade_errors: RefCell<HashMap<String, String>>,
+}
+
+impl AppState {
+    pub fn new() -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                installed: gio::ListStore::new::<PackageObject>(),
+                installed_loaded: Cell::new(false),
+                upgrade_errors: RefCell::new(HashMap::new()),
+            }),
+        }
+    }
+
+    // ========================================================================
+    // Installed packages
+    // ========================================================================
+
+    /// The installed packages, for binding to a list
+    pub fn installed_packages(&self) -> &gio::ListStore {
+        &self.inner.installed
+    }
+
+    pub fn installed_count(&self) -> u32 {
+        self.inner.installed.n_items()
+    }
+
+    /// Whether the installed packages have been loaded, so an empty list means nothing is installed
+    pub fn installed_loaded(&self) -> bool {
+        self.inner.installed_loaded.get()
+    }
+
+    pub fn find_installed(&self, name: &str) -> Option<PackageObject> {
+        self.inner.installed.iter::<PackageObject>().flatten().find(|package| package.name() == name)
+    }
+
+    /// Replace the installed packages with a freshly loaded list
+    pub fn set_installed_packages(&self, packages: Vec<brew::Package>) {
+        let changes = history::last_changes();
+        let objects: Vec<PackageObject> = packages.into_iter().map(|package| new_object(package, &changes)).collect();
+        // Set first, so handlers of the change see the list as loaded
+        self.inner.installed_loaded.set(true);
+        self.inner.installed.splice(0, self.inner.installed.n_items(), &objects);
+    }
+
+    /// Fill in keg sizes; packages missing from `sizes` are set to 0
+    pub fn set_keg_sizes(&self, sizes: &HashMap<String, u64>) {
+        for package in self.inner.installed.iter::<PackageObject>().flatten() {
+            package.set_size(sizes.get(&package.name()).copied().unwrap_or(0));
+        }
+    }
+
+    pub fn add_installed(&self, package: brew::Package) {
+        self.inner.installed.append(&new_object(package, &history::last_changes()));
+    }
+
+    pub fn remove_installed(&self, package: &PackageObject) {
+        if let Some(position) = self.inner.installed.find(package) {
+            self.inner.installed.remove(position);
+        }
+    }
+
+    // ========================================================================
+    // Upgrade failures
+    // ========================================================================
+
+    /// Output of the last failed upgrade of `name`, if it failed
+    pub fn upgrade_error(&self, name: &str) -> Option<String> {
+        self.inner.upgrade_errors.borrow().get(name).cloned()
+    }
+
+    /// Remember the outcome of an upgrade run: successes clear earlier failures
+    pub fn record_upgrade_results(&self, succeeded: &[String], failed: &[(String, String)]) {
+        let mut errors = self.inner.upgrade_errors.borrow_mut();
+        for name in succeeded {
+            errors.remove(name);
+        }
+        errors.extend(failed.iter().cloned());
+    }
+}
+
+/// A package object whose change time also counts installs and upgrades in the history,
+/// for casks and older brew versions that don't report an install time
+fn new_object(package: brew::Package, changes: &HashMap<String, u64>) -> PackageObject {
+    let object = PackageObject::new(package);
+    if let Some(&changed) = changes.get(&object.name()) {
+        object.set_changed(object.changed().max(changed as i64));
+    }
+    object
+}
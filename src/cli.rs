@@ -0,0 +1,179 @@
+use crate::brew;
+use crate::history::{self, Operation};
+use gtk4::prelude::*;
+use gtk4::{glib, Application};
+use serde_json::json;
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// An operation requested on the command line, run without a window
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Command {
+    CheckUpdates,
+    UpgradeAll,
+    ExportBrewfile(PathBuf),
+}
+
+/// Where the main window should open, from `--page`, `--package` or the Shell search provider
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// A page of the main window by name, e.g. "updates"
+    Page(String),
+    /// A package: selected in Installed if it is installed, otherwise found through Browse
+    Package(String),
+    /// A search in Browse
+    Search(String),
+}
+
+thread_local! {
+    // Consumed by the next main window that opens
+    static TARGET: RefCell<Option<Target>> = const { RefCell::new(None) };
+}
+
+/// Open the next main window at `target`
+pub fn set_target(target: Target) {
+    TARGET.with(|current| *current.borrow_mut() = Some(target));
+}
+
+/// Where the main window being built should open, if anywhere in particular
+pub fn take_target() -> Option<Target> {
+    TARGET.with(|current| current.borrow_mut().take())
+}
+
+/// Add the command-line options to `app`. Each scripting option runs its operation, prints
+/// the result to stdout as JSON and exits with 0 on success or 1 on failure, without opening
+/// a window. `--page` and `--package` choose where the window opens instead.
+pub fn add_options(app: &Application) {
+    app.add_main_option(
+        "check-updates",
+        glib::Char::from(0),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::None,
+        "Print the outdated packages and exit",
+        None,
+    );
+    app.add_main_option(
+        "upgrade-all",
+        glib::Char::from(0),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::None,
+        "Upgrade every outdated package and exit",
+        None,
+    );
+    app.add_main_option(
+        "export-brewfile",
+        glib::Char::from(0),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::Filename,
+        "Write a Brewfile of everything installed to PATH and exit",
+        Some("PATH"),
+    );
+    app.add_main_option(
+        "page",
+        glib::Char::from(0),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::String,
+        "Open on a page: installed, browse, updates, history or cache",
+        Some("NAME"),
+    );
+    app.add_main_option(
+        "package",
+        glib::Char::from(0),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::String,
+        "Open on a package, in Installed or else in Browse",
+        Some("NAME"),
+    );
+
+    app.connect_handle_local_options(|_, options| {
+        let command = if options.contains("check-updates") {
+            Command::CheckUpdates
+        } else if options.contains("upgrade-all") {
+            Command::UpgradeAll
+        } else if let Ok(Some(path)) = options.lookup::<PathBuf>("export-brewfile") {
+            Command::ExportBrewfile(path)
+        } else {
+            return -1;
+        };
+        run(command)
+    });
+}
+
+/// The target named by `--package` or `--page` in `options`; the package wins if both are given.
+/// Read in the primary instance, from the options of whichever invocation it is handling.
+pub fn target(options: &glib::VariantDict) -> Option<Target> {
+    if let Ok(Some(name)) = options.lookup::<String>("package") {
+        Some(Target::Package(name))
+    } else if let Ok(Some(page)) = options.lookup::<String>("page") {
+        Some(Target::Page(page))
+    } else {
+        None
+    }
+}
+
+/// Run `command` to completion and print its JSON result; returns the exit code
+fn run(command: Command) -> i32 {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let result = match command {
+        Command::CheckUpdates => rt
+            .block_on(brew::get_outdated_packages())
+            .map(|outdated| json!({ "outdated": outdated })),
+        Command::UpgradeAll => {
+            let started = Instant::now();
+            let result = rt.block_on(brew::upgrade_packages(None));
+            history::record(
+                Operation::Upgrade,
+                None,
+                result.as_ref().map(|r| r.output.as_str()),
+                started.elapsed(),
+            );
+            result.map(|report| upgrade_json(&report))
+        }
+        Command::ExportBrewfile(path) => rt
+            .block_on(brew::bundle_dump(&path))
+            .map(|_| json!({ "brewfile": path.display().to_string() })),
+    };
+
+    match result {
+        Ok(value) => {
+            println!("{}", value);
+            0
+        }
+        Err(e) => {
+            println!("{}", json!({ "error": e.user_message() }));
+            1
+        }
+    }
+}
+
+fn upgrade_json(report: &brew::UpgradeReport) -> serde_json::Value {
+    let upgraded: Vec<_> = report
+        .upgraded
+        .iter()
+        .map(|(name, from, to)| json!({ "name": name, "from": from, "to": to }))
+        .collect();
+    json!({
+        "upgraded": upgraded,
+        "pinned": report.pinned,
+        "skipped": report.skipped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upgrade_json() {
+        let report = brew::UpgradeReport {
+            upgraded: vec![("wget".to_string(), "1.21.3".to_string(), "1.21.4".to_string())],
+            pinned: vec!["node".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            upgrade_json(&report).to_string(),
+            r#"{"pinned":["node"],"skipped":[],"upgraded":[{"from":"1.21.3","name":"wget","to":"1.21.4"}]}"#
+        );
+    }
+}
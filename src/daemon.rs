@@ -0,0 +1,330 @@
+use crate::{brew, jobs, settings, storage};
+use brewhouse_core::metadata_cache;
+use gtk4::prelude::*;
+use gtk4::{gio, glib, Application};
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+
+/// How often background mode and the tray icon run `brew outdated`
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+// Packages listed by name in a notification; the rest are counted
+const NAMES_IN_NOTIFICATION: usize = 3;
+
+thread_local! {
+    // Set by --daemon until the first activation has started background mode
+    static REQUESTED: Cell<bool> = const { Cell::new(false) };
+    // Keeps the application running while no window is open
+    static HOLD: RefCell<Option<gio::ApplicationHoldGuard>> = const { RefCell::new(None) };
+}
+
+/// Add the `--daemon` option to `app`. With it, BrewHouse starts without a window and
+/// only checks for updates; a second `--daemon` exits if one is already running.
+pub fn add_option(app: &Application) {
+    app.add_main_option(
+        "daemon",
+        glib::Char::from(0),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::None,
+        "Run in the background and notify when updates are available",
+        None,
+    );
+    app.connect_handle_local_options(|app, options| {
+        if !options.contains("daemon") {
+            return -1;
+        }
+        if app.register(gio::Cancellable::NONE).is_ok() && app.is_remote() {
+            eprintln!("BrewHouse is already running");
+            return 0;
+        }
+        REQUESTED.with(|requested| requested.set(true));
+        -1
+    });
+}
+
+/// Whether this invocation should start background mode rather than open a window.
+/// True only once, so launching BrewHouse again while the daemon runs opens the GUI.
+pub fn take_request() -> bool {
+    REQUESTED.with(|requested| requested.replace(false))
+}
+
+/// Keep running without a window, checking for updates now and every `CHECK_INTERVAL`.
+/// Clicking a notification or its "View Updates" button calls `show_updates`; its
+/// "Upgrade All" button upgrades without opening a window. Saved searches that ask for it
+/// are checked for new formulae at the same time; clicking that notification calls `show_search`.
+pub fn start(
+    app: &Application,
+    show_updates: impl Fn(&Application) + 'static,
+    show_search: impl Fn(&Application, &str) + 'static,
+) {
+    HOLD.with(|hold| *hold.borrow_mut() = Some(app.hold()));
+
+    let action = gio::SimpleAction::new("show-updates", None);
+    let app_for_action = app.clone();
+    action.connect_activate(move |_, _| show_updates(&app_for_action));
+    app.add_action(&action);
+
+    let upgrade_action = gio::SimpleAction::new("upgrade-all", None);
+    let app_for_upgrade = app.clone();
+    upgrade_action.connect_activate(move |_, _| upgrade_all(&app_for_upgrade));
+    app.add_action(&upgrade_action);
+
+    let search_action = gio::SimpleAction::new("show-search", Some(glib::VariantTy::STRING));
+    let app_for_search = app.clone();
+    search_action.connect_activate(move |_, query| {
+        if let Some(query) = query.and_then(|query| query.get::<String>()) {
+            show_search(&app_for_search, &query);
+        }
+    });
+    app.add_action(&search_action);
+
+    // Only new updates are announced, so a package left outdated doesn't notify every check
+    let announced = Rc::new(RefCell::new(HashSet::new()));
+    check_for_updates(app, &announced);
+    check_saved_searches(app);
+    let app = app.clone();
+    glib::timeout_add_local(CHECK_INTERVAL, move || {
+        check_for_updates(&app, &announced);
+        check_saved_searches(&app);
+        glib::ControlFlow::Continue
+    });
+}
+
+/// Add the `app.autostart` toggle for Preferences, which adds or removes a login entry
+/// that starts `brewhouse --daemon`. The entry itself is the setting, so deleting it by hand
+/// also turns the toggle off.
+pub fn add_autostart_action(app: &Application) {
+    let action = gio::SimpleAction::new_stateful("autostart", None, &autostart_path().exists().to_variant());
+    action.connect_change_state(|action, state| {
+        let Some(enabled) = state.and_then(|state| state.get::<bool>()) else {
+            return;
+        };
+        match set_autostart(enabled) {
+            Ok(()) => action.set_state(&enabled.to_variant()),
+            Err(e) => eprintln!("Could not update the autostart entry: {}", e),
+        }
+    });
+    app.add_action(&action);
+}
+
+fn autostart_path() -> PathBuf {
+    storage::autostart_dir().join("io.github.brewhouse.app.desktop")
+}
+
+fn set_autostart(enabled: bool) -> io::Result<()> {
+    let path = autostart_path();
+    if !enabled {
+        return match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        };
+    }
+    let exe = std::env::current_exe()?;
+    std::fs::create_dir_all(storage::autostart_dir())?;
+    std::fs::write(&path, autostart_entry(&exe))
+}
+
+/// Desktop entry that runs `exe --daemon` at login
+fn autostart_entry(exe: &Path) -> String {
+    format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=BrewHouse\n\
+         Comment=Check for Homebrew updates in the background\n\
+         Exec={} --daemon\n\
+         Icon=system-software-install\n\
+         NoDisplay=true\n\
+         X-GNOME-Autostart-enabled=true\n",
+        quote_exec_arg(&exe.to_string_lossy())
+    )
+}
+
+/// Quote an argument for the Exec key as the Desktop Entry spec asks
+fn quote_exec_arg(arg: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in arg.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn check_for_updates(app: &Application, announced: &Rc<RefCell<HashSet<String>>>) {
+    let app = app.clone();
+    let announced = announced.clone();
+    glib::spawn_future_local(async move {
+        let result = gtk4::gio::spawn_blocking(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(brew::get_outdated_packages())
+        })
+        .await
+        .expect("Background task failed");
+
+        let outdated = match result {
+            Ok(outdated) => outdated,
+            Err(e) => {
+                eprintln!("Background update check failed: {}", e.user_message());
+                return;
+            }
+        };
+
+        let new = unannounced(&outdated, &announced.borrow());
+        // Upgraded packages can be announced again when their next version comes out
+        *announced.borrow_mut() = outdated.into_iter().collect();
+        if new.is_empty() {
+            return;
+        }
+
+        let title = match new.len() {
+            1 => "Update available".to_string(),
+            count => format!("{} updates available", count),
+        };
+        let notification = gio::Notification::new(&title);
+        notification.set_body(Some(&notification_body(&new)));
+        notification.set_default_action("app.show-updates");
+        notification.add_button("Upgrade All", "app.upgrade-all");
+        notification.add_button("View Updates", "app.show-updates");
+        app.send_notification(Some("updates"), &notification);
+    });
+}
+
+/// Notify about formulae that newly match a saved search with notifications on
+fn check_saved_searches(app: &Application) {
+    if !settings::get().saved_searches.iter().any(|search| search.notify) {
+        return;
+    }
+    let app = app.clone();
+    glib::spawn_future_local(async move {
+        // The full formula list; a stale one is refreshed in the background for the next check
+        let result = gtk4::gio::spawn_blocking(|| {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(brew::search_packages(""))
+        })
+        .await
+        .expect("Background task failed");
+        let index = match result {
+            Ok(index) => index,
+            Err(e) => {
+                eprintln!("Saved search check failed: {}", e.user_message());
+                return;
+            }
+        };
+
+        let mut found = Vec::new();
+        let mut checked = HashMap::new();
+        for search in settings::get().saved_searches.iter().filter(|search| search.notify) {
+            let known = metadata_cache::saved_search_matches(&search.query);
+            let new = new_matches(&search.query, known.as_deref(), &index);
+            if !new.is_empty() {
+                found.push((search.query.clone(), new));
+            }
+            checked.insert(search.query.clone(), matches(&search.query, &index));
+        }
+        metadata_cache::store_saved_search_matches(checked);
+        for (query, new) in found {
+            let notification = gio::Notification::new(&format!("New formulae for “{}”", query));
+            notification.set_body(Some(&notification_body(&new)));
+            notification.set_default_action_and_target_value("app.show-search", Some(&query.to_variant()));
+            app.send_notification(Some(&format!("saved-search-{}", query)), &notification);
+        }
+    });
+}
+
+/// Formulae in `index` whose names contain `query`
+fn matches(query: &str, index: &[String]) -> Vec<String> {
+    index.iter().filter(|name| brew::match_range(name, query).is_some()).cloned().collect()
+}
+
+/// Formulae matching `query` that weren't `known` to at its last check. The first check only
+/// notes what matches, so saving a search doesn't announce every formula it already finds.
+fn new_matches(query: &str, known: Option<&[String]>, index: &[String]) -> Vec<String> {
+    let Some(known) = known else {
+        return Vec::new();
+    };
+    matches(query, index).into_iter().filter(|name| !known.contains(name)).collect()
+}
+
+/// Upgrade everything from a notification, replacing it with the outcome
+fn upgrade_all(app: &Application) {
+    app.withdraw_notification("updates");
+    let app_for_finished = app.clone();
+    let on_finished = move |result: &brew::BrewResult<brew::UpgradeReport>| {
+        let notification = match result {
+            Ok(report) => {
+                let notification = gio::Notification::new("Upgrade finished");
+                notification.set_body(Some(&report.summary()));
+                notification
+            }
+            Err(e) => {
+                let notification = gio::Notification::new("Upgrade failed");
+                notification.set_body(Some(&e.user_message()));
+                notification.add_button("View Updates", "app.show-updates");
+                notification
+            }
+        };
+        notification.set_default_action("app.show-updates");
+        app_for_finished.send_notification(Some("updates"), &notification);
+    };
+    jobs::enqueue_upgrade(None, on_finished, || {});
+}
+
+/// Outdated packages that no earlier notification mentioned
+fn unannounced(outdated: &[String], announced: &HashSet<String>) -> Vec<String> {
+    outdated.iter().filter(|name| !announced.contains(*name)).cloned().collect()
+}
+
+/// e.g. "wget, git and node", or "wget, git, node and 2 more"
+fn notification_body(packages: &[String]) -> String {
+    let (named, rest) = packages.split_at(packages.len().min(NAMES_IN_NOTIFICATION));
+    match (named, rest.len()) {
+        ([], _) => String::new(),
+        ([only], 0) => only.clone(),
+        (named, 0) => format!("{} and {}", named[..named.len() - 1].join(", "), named[named.len() - 1]),
+        (named, more) => format!("{} and {} more", named.join(", "), more),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_unannounced() {
+        let announced: HashSet<String> = names(&["wget"]).into_iter().collect();
+        assert_eq!(unannounced(&names(&["wget", "git"]), &announced), names(&["git"]));
+        assert!(unannounced(&names(&["wget"]), &announced).is_empty());
+    }
+
+    #[test]
+    fn test_new_matches() {
+        let index = names(&["rust", "rustup", "rust-analyzer", "wget"]);
+        assert!(new_matches("Rust", None, &index).is_empty());
+        assert_eq!(new_matches("Rust", Some(&names(&["rust", "rustup"])), &index), names(&["rust-analyzer"]));
+    }
+
+    #[test]
+    fn test_autostart_entry() {
+        let entry = autostart_entry(Path::new("/home/me/My Apps/brewhouse"));
+        assert!(entry.starts_with("[Desktop Entry]\nType=Application\n"));
+        assert!(entry.contains("\nExec=\"/home/me/My Apps/brewhouse\" --daemon\n"));
+        assert_eq!(quote_exec_arg("/opt/$dir/\"x\""), "\"/opt/\\$dir/\\\"x\\\"\"");
+    }
+
+    #[test]
+    fn test_notification_body() {
+        assert_eq!(notification_body(&names(&["wget"])), "wget");
+        assert_eq!(notification_body(&names(&["wget", "git", "node"])), "wget, git and node");
+        assert_eq!(notification_body(&names(&["wget", "git", "node", "jq", "go"])), "wget, git, node and 2 more");
+    }
+}
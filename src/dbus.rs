@@ -0,0 +1,110 @@
+use crate::brew;
+use crate::events;
+use crate::jobs;
+use gtk4::gio::{self, DBusConnection, DBusMethodInvocation};
+use gtk4::glib;
+use gtk4::prelude::*;
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// Interface exported on the application's object path, e.g.
+/// `gdbus call --session --dest io.github.brewhouse.app --object-path /io/github/brewhouse/app
+/// --method io.github.brewhouse.GetOutdated`
+const INTERFACE: &str = "io.github.brewhouse";
+
+const INTROSPECTION_XML: &str = r#"
+<node>
+  <interface name="io.github.brewhouse">
+    <method name="GetOutdated">
+      <arg type="as" name="packages" direction="out"/>
+    </method>
+    <method name="Upgrade">
+      <arg type="s" name="name" direction="in"/>
+      <arg type="t" name="job" direction="out"/>
+    </method>
+    <signal name="PackagesChanged"/>
+    <signal name="UpgradeFinished">
+      <arg type="t" name="job"/>
+      <arg type="s" name="name"/>
+      <arg type="b" name="success"/>
+      <arg type="s" name="summary"/>
+    </signal>
+  </interface>
+</node>
+"#;
+
+const ERROR_FAILED: &str = "io.github.brewhouse.Error.Failed";
+
+/// Export the BrewHouse interface on `connection` at `object_path`, so shell extensions,
+/// status bars and scripts can query outdated packages, start upgrades and hear about changes
+pub fn export(connection: &DBusConnection, object_path: &str) {
+    let node = gio::DBusNodeInfo::for_xml(INTROSPECTION_XML).expect("Invalid D-Bus introspection XML");
+    let interface = node.lookup_interface(INTERFACE).expect("Introspection XML declares the interface");
+
+    let registration = connection
+        .register_object(object_path, &interface)
+        .method_call(|connection, _, object_path, _, method, parameters, invocation| match method {
+            "GetOutdated" => get_outdated(invocation),
+            "Upgrade" => match parameters.get::<(String,)>() {
+                Some((name,)) => upgrade(&connection, object_path, name, invocation),
+                None => invocation.return_dbus_error(ERROR_FAILED, "Expected a package name"),
+            },
+            _ => invocation.return_dbus_error("org.freedesktop.DBus.Error.UnknownMethod", method),
+        })
+        .build();
+    if let Err(e) = registration {
+        eprintln!("Could not export the D-Bus interface: {}", e);
+        return;
+    }
+
+    let connection = connection.clone();
+    let object_path = object_path.to_string();
+    events::subscribe_app(move |_| {
+        if let Err(e) = connection.emit_signal(None, &object_path, INTERFACE, "PackagesChanged", None) {
+            eprintln!("Could not emit PackagesChanged: {}", e);
+        }
+    });
+}
+
+fn get_outdated(invocation: DBusMethodInvocation) {
+    glib::spawn_future_local(async move {
+        let result = gtk4::gio::spawn_blocking(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(brew::get_outdated_packages())
+        })
+        .await
+        .expect("Background task failed");
+
+        match result {
+            Ok(outdated) => invocation.return_value(Some(&(outdated,).to_variant())),
+            Err(e) => invocation.return_dbus_error(ERROR_FAILED, &e.user_message()),
+        }
+    });
+}
+
+/// Queue an upgrade of `name` like one started from the window. The call returns the job's id
+/// straight away; UpgradeFinished carries that id once the job has run or been removed.
+fn upgrade(connection: &DBusConnection, object_path: &str, name: String, invocation: DBusMethodInvocation) {
+    // The job id is only known once it is queued, and neither callback runs before that
+    let job: Rc<Cell<jobs::JobId>> = Rc::default();
+    let finished = {
+        let (connection, object_path, job, name) =
+            (connection.clone(), object_path.to_string(), job.clone(), name.clone());
+        move |success: bool, summary: String| {
+            let args = (job.get(), name.as_str(), success, summary.as_str()).to_variant();
+            if let Err(e) = connection.emit_signal(None, &object_path, INTERFACE, "UpgradeFinished", Some(&args)) {
+                eprintln!("Could not emit UpgradeFinished: {}", e);
+            }
+        }
+    };
+    let finished = Rc::new(finished);
+
+    let finished_for_removed = finished.clone();
+    let on_removed = move || finished_for_removed(false, "The upgrade was removed from the queue".to_string());
+    let on_finished = move |result: &brew::BrewResult<brew::UpgradeReport>| match result {
+        Ok(report) => finished(true, report.summary()),
+        Err(e) => finished(false, e.user_message()),
+    };
+    job.set(jobs::enqueue_upgrade(Some(name), on_finished, on_removed));
+    invocation.return_value(Some(&(job.get(),).to_variant()));
+}
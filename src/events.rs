@@ -0,0 +1,78 @@
+use gtk4::glib;
+use gtk4::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+/// Something changed that other views may be showing.
+/// Views emit these after a successful operation and subscribe to refresh their data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppEvent {
+    PackageInstalled(String),
+    PackageUninstalled(String),
+    /// One or more packages were upgraded
+    PackagesUpgraded,
+    /// A package was linked, unlinked or cleaned up
+    PackageChanged(String),
+}
+
+struct Subscriber {
+    owner: glib::WeakRef<gtk4::Widget>,
+    /// Whether the owner has been part of a window; once it leaves, the subscription ends
+    seen_rooted: Cell<bool>,
+    handler: Box<dyn Fn(&AppEvent)>,
+}
+
+type AppHandler = Rc<dyn Fn(&AppEvent)>;
+
+thread_local! {
+    // GTK widgets live on the main thread, so subscribers do too
+    static SUBSCRIBERS: RefCell<Vec<Subscriber>> = const { RefCell::new(Vec::new()) };
+    static APP_SUBSCRIBERS: RefCell<Vec<AppHandler>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Call `handler` for every event while `owner` is shown in a window.
+/// The subscription ends when the owner's window goes away, e.g. after switching Homebrew prefixes.
+pub fn subscribe(owner: &impl IsA<gtk4::Widget>, handler: impl Fn(&AppEvent) + 'static) {
+    let subscriber = Subscriber {
+        owner: owner.upcast_ref::<gtk4::Widget>().downgrade(),
+        seen_rooted: Cell::new(false),
+        handler: Box::new(handler),
+    };
+    SUBSCRIBERS.with(|subscribers| subscribers.borrow_mut().push(subscriber));
+}
+
+/// Call `handler` for every event for as long as the application runs, with or without a window
+pub fn subscribe_app(handler: impl Fn(&AppEvent) + 'static) {
+    APP_SUBSCRIBERS.with(|subscribers| subscribers.borrow_mut().push(Rc::new(handler)));
+}
+
+/// Notify all live subscribers of `event`
+pub fn emit(event: AppEvent) {
+    let app_subscribers = APP_SUBSCRIBERS.with(|subscribers| subscribers.borrow().clone());
+    for handler in app_subscribers {
+        handler(&event);
+    }
+
+    // Take the list out so handlers can subscribe or emit without a double borrow
+    let subscribers = SUBSCRIBERS.with(|subscribers| std::mem::take(&mut *subscribers.borrow_mut()));
+
+    let mut alive = Vec::with_capacity(subscribers.len());
+    for subscriber in subscribers {
+        let Some(owner) = subscriber.owner.upgrade() else {
+            continue;
+        };
+        if owner.root().is_some() {
+            subscriber.seen_rooted.set(true);
+            (subscriber.handler)(&event);
+        } else if subscriber.seen_rooted.get() {
+            continue;
+        }
+        alive.push(subscriber);
+    }
+
+    SUBSCRIBERS.with(|subscribers| {
+        let mut subscribers = subscribers.borrow_mut();
+        alive.append(&mut subscribers);
+        *subscribers = alive;
+    });
+}
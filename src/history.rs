@@ -0,0 +1,478 @@
+use crate::{brew, settings, storage};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Captured output beyond this is truncated so a verbose build can't bloat history.json;
+// the full output goes to a file in logs_dir() instead
+const MAX_OUTPUT_BYTES: usize = 64 * 1024;
+
+// Schema migrations for history.json, oldest first (see storage::Migration)
+const MIGRATIONS: &[storage::Migration] = &[];
+
+/// Kind of brew operation performed through the app
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Operation {
+    Install,
+    Uninstall,
+    Upgrade,
+    Update,
+    Link,
+    Unlink,
+    Cleanup,
+    Tap,
+    Bundle,
+    Fetch,
+    Test,
+    Pin,
+}
+
+impl Operation {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Operation::Install => "Install",
+            Operation::Uninstall => "Uninstall",
+            Operation::Upgrade => "Upgrade",
+            Operation::Update => "Update",
+            Operation::Link => "Link",
+            Operation::Unlink => "Unlink",
+            Operation::Cleanup => "Cleanup",
+            Operation::Tap => "Tap",
+            Operation::Bundle => "Bundle",
+            Operation::Fetch => "Download",
+            Operation::Test => "Test",
+            Operation::Pin => "Pin",
+        }
+    }
+}
+
+/// Human-readable duration, e.g. "850 ms", "12.3 s" or "2 min 05 s"
+pub fn format_duration(duration: Duration) -> String {
+    let ms = duration.as_millis();
+    if ms < 1000 {
+        format!("{} ms", ms)
+    } else if ms < 60_000 {
+        format!("{:.1} s", ms as f64 / 1000.0)
+    } else {
+        let secs = ms / 1000;
+        format!("{} min {:02} s", secs / 60, secs % 60)
+    }
+}
+
+/// One recorded operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Unix timestamp (seconds) of when the operation finished
+    pub timestamp: u64,
+    pub operation: Operation,
+    /// Package the operation applied to; None for whole-system operations like "Upgrade All"
+    pub package: Option<String>,
+    pub success: bool,
+    pub duration_ms: u64,
+    /// Captured stdout on success, or the error message on failure
+    pub output: String,
+    /// File in `logs_dir()` with the whole output, kept when `output` had to be truncated
+    #[serde(default)]
+    pub log_file: Option<String>,
+}
+
+impl HistoryEntry {
+    /// Short title such as "Install wget" or "Upgrade (all packages)"
+    pub fn title(&self) -> String {
+        match &self.package {
+            Some(name) => format!("{} {}", self.operation.label(), name),
+            None if self.operation == Operation::Upgrade => "Upgrade (all packages)".to_string(),
+            None => self.operation.label().to_string(),
+        }
+    }
+
+    /// Human-readable duration, e.g. "850 ms", "12.3 s" or "2 min 05 s"
+    pub fn duration_label(&self) -> String {
+        format_duration(Duration::from_millis(self.duration_ms))
+    }
+
+    /// The whole captured output, read from the log file when `output` is truncated
+    pub fn full_output(&self) -> String {
+        self.log_file
+            .as_ref()
+            .and_then(|name| std::fs::read_to_string(logs_dir().join(name)).ok())
+            .unwrap_or_else(|| self.output.clone())
+    }
+
+    /// Suggested name when saving the output, e.g. "brewhouse-install-wget-1700000000.log"
+    pub fn export_file_name(&self) -> String {
+        let title: String = self
+            .title()
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '@' || c == '.' { c } else { '-' })
+            .collect();
+        let title: Vec<&str> = title.split('-').filter(|part| !part.is_empty()).collect();
+        format!("brewhouse-{}-{}.log", title.join("-"), self.timestamp)
+    }
+
+    fn remove_log_file(&self) {
+        if let Some(name) = &self.log_file {
+            let _ = std::fs::remove_file(logs_dir().join(name));
+        }
+    }
+
+    /// Case-insensitive match against the title and captured output
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+        query.is_empty()
+            || self.title().to_lowercase().contains(&query)
+            || self.output.to_lowercase().contains(&query)
+    }
+}
+
+/// Ordered log of operations, oldest first
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct History {
+    entries: Vec<HistoryEntry>,
+}
+
+impl History {
+    pub const fn new() -> Self {
+        Self { entries: Vec::new() }
+    }
+
+    pub fn push(&mut self, entry: HistoryEntry) {
+        self.entries.push(entry);
+    }
+
+    /// Add the entries of `other` that this history doesn't have, e.g. ones another process
+    /// wrote to disk meanwhile, keeping the oldest first
+    pub fn merge(&mut self, other: History) {
+        let key = |entry: &HistoryEntry| (entry.timestamp, entry.operation, entry.package.clone(), entry.duration_ms);
+        let same = |a: &HistoryEntry, b: &HistoryEntry| key(a) == key(b);
+        for entry in other.entries {
+            if !self.entries.iter().any(|known| same(known, &entry)) {
+                self.entries.push(entry);
+            }
+        }
+        self.entries.sort_by_key(|entry| entry.timestamp);
+    }
+
+    /// Drop the oldest entries so at most `max_entries` remain, with their log files
+    pub fn trim(&mut self, max_entries: usize) {
+        let excess = self.entries.len().saturating_sub(max_entries);
+        for entry in self.entries.drain(..excess) {
+            entry.remove_log_file();
+        }
+    }
+
+    /// Entries matching `query`, newest first
+    pub fn search(&self, query: &str) -> Vec<HistoryEntry> {
+        self.entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.matches(query))
+            .cloned()
+            .collect()
+    }
+
+    /// When each package was last installed or upgraded successfully
+    pub fn last_changes(&self) -> HashMap<String, u64> {
+        let mut changes = HashMap::new();
+        for entry in &self.entries {
+            if entry.success && matches!(entry.operation, Operation::Install | Operation::Upgrade) {
+                if let Some(package) = &entry.package {
+                    changes.insert(package.clone(), entry.timestamp);
+                }
+            }
+        }
+        changes
+    }
+}
+
+static HISTORY: Mutex<History> = Mutex::new(History::new());
+
+fn history_path() -> PathBuf {
+    storage::data_dir().join("history.json")
+}
+
+/// Directory with the full output of operations too long for history.json
+fn logs_dir() -> PathBuf {
+    storage::data_dir().join("logs")
+}
+
+/// Write `output` to a new file in `logs_dir()` and return its name
+fn write_log_file(operation: Operation, output: &str) -> Option<String> {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+    let name = format!("{}-{:?}.log", nanos, operation).to_lowercase();
+    let written = std::fs::create_dir_all(logs_dir()).and_then(|_| std::fs::write(logs_dir().join(&name), output));
+    match written {
+        Ok(()) => Some(name),
+        Err(e) => {
+            eprintln!("Failed to write the operation log: {}", e);
+            None
+        }
+    }
+}
+
+/// Load the history written by previous runs. Called on startup, and again when the
+/// History page is refreshed to pick up operations run from the command line.
+pub fn init() {
+    match storage::load::<History>(&history_path(), MIGRATIONS) {
+        Ok(Some(mut history)) => {
+            history.trim(settings::get().history_max_entries);
+            *HISTORY.lock().unwrap() = history;
+        }
+        Ok(None) => {}
+        Err(e) => eprintln!("Failed to load history: {}", e),
+    }
+}
+
+/// Apply the retention limit from settings and write the history to disk
+pub fn apply_retention() {
+    let mut history = HISTORY.lock().unwrap();
+    history.trim(settings::get().history_max_entries);
+    save(&mut history);
+}
+
+/// Write `history` to disk, along with what the scripting options or another instance
+/// recorded there since it was loaded
+fn save(history: &mut History) {
+    match storage::load::<History>(&history_path(), MIGRATIONS) {
+        Ok(Some(on_disk)) => {
+            history.merge(on_disk);
+            history.trim(settings::get().history_max_entries);
+        }
+        Ok(None) => {}
+        Err(e) => eprintln!("Failed to reload history before saving: {}", e),
+    }
+    if let Err(e) = storage::save(&history_path(), history, MIGRATIONS) {
+        eprintln!("Failed to save history: {}", e);
+    }
+}
+
+/// Cut `output` down to `MAX_OUTPUT_BYTES`, keeping the end where errors usually are
+fn truncate_output(mut output: String) -> String {
+    if output.len() <= MAX_OUTPUT_BYTES {
+        return output;
+    }
+    let mut start = output.len() - MAX_OUTPUT_BYTES;
+    while !output.is_char_boundary(start) {
+        start += 1;
+    }
+    output.replace_range(..start, "[earlier output truncated]\n");
+    output
+}
+
+/// Record the outcome of an operation in the global history.
+/// `result` holds the captured output on success, e.g. `result.as_deref()`.
+pub fn record<T: AsRef<str>, E: Display>(
+    operation: Operation,
+    package: Option<&str>,
+    result: Result<T, E>,
+    duration: Duration,
+) {
+    // A simulated operation changed nothing, so there is nothing to record
+    if brew::is_dry_run() {
+        return;
+    }
+
+    let (success, output) = match result {
+        Ok(output) => (true, output.as_ref().to_string()),
+        Err(e) => (false, e.to_string()),
+    };
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    // Tests exercise record() but must not write to the user's data directory
+    let log_file = if output.len() > MAX_OUTPUT_BYTES && !cfg!(test) {
+        write_log_file(operation, &output)
+    } else {
+        None
+    };
+
+    let mut history = HISTORY.lock().unwrap();
+    history.push(HistoryEntry {
+        timestamp,
+        operation,
+        package: package.map(str::to_string),
+        success,
+        duration_ms: duration.as_millis() as u64,
+        output: truncate_output(output),
+        log_file,
+    });
+    history.trim(settings::get().history_max_entries);
+
+    if !cfg!(test) {
+        save(&mut history);
+    }
+}
+
+/// Search the global history, newest first
+pub fn search(query: &str) -> Vec<HistoryEntry> {
+    HISTORY.lock().unwrap().search(query)
+}
+
+/// When each package was last installed or upgraded through BrewHouse
+pub fn last_changes() -> HashMap<String, u64> {
+    HISTORY.lock().unwrap().last_changes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(operation: Operation, package: Option<&str>, output: &str) -> HistoryEntry {
+        HistoryEntry {
+            timestamp: 0,
+            operation,
+            package: package.map(str::to_string),
+            success: true,
+            duration_ms: 0,
+            output: output.to_string(),
+            log_file: None,
+        }
+    }
+
+    #[test]
+    fn test_export_file_name() {
+        let mut install = entry(Operation::Install, Some("python@3.12"), "");
+        install.timestamp = 1_700_000_000;
+        assert_eq!(install.export_file_name(), "brewhouse-install-python@3.12-1700000000.log");
+        assert_eq!(
+            entry(Operation::Upgrade, None, "").export_file_name(),
+            "brewhouse-upgrade-all-packages-0.log"
+        );
+    }
+
+    #[test]
+    fn test_entry_title() {
+        assert_eq!(entry(Operation::Install, Some("wget"), "").title(), "Install wget");
+        assert_eq!(entry(Operation::Upgrade, None, "").title(), "Upgrade (all packages)");
+        assert_eq!(entry(Operation::Update, None, "").title(), "Update");
+    }
+
+    #[test]
+    fn test_duration_label() {
+        let mut e = entry(Operation::Install, Some("git"), "");
+        e.duration_ms = 850;
+        assert_eq!(e.duration_label(), "850 ms");
+        e.duration_ms = 12_340;
+        assert_eq!(e.duration_label(), "12.3 s");
+        e.duration_ms = 125_000;
+        assert_eq!(e.duration_label(), "2 min 05 s");
+    }
+
+    #[test]
+    fn test_entry_matches() {
+        let e = entry(Operation::Uninstall, Some("node@18"), "Uninstalling /opt/node@18... (2,345 files)");
+        assert!(e.matches(""));
+        assert!(e.matches("NODE"));
+        assert!(e.matches("uninstall"));
+        assert!(e.matches("2,345 files"));
+        assert!(!e.matches("python"));
+    }
+
+    #[test]
+    fn test_search_newest_first() {
+        let mut history = History::new();
+        history.push(entry(Operation::Install, Some("git"), ""));
+        history.push(entry(Operation::Install, Some("wget"), ""));
+        history.push(entry(Operation::Uninstall, Some("git"), ""));
+
+        let titles: Vec<String> = history.search("").iter().map(|e| e.title()).collect();
+        assert_eq!(titles, vec!["Uninstall git", "Install wget", "Install git"]);
+
+        let git: Vec<String> = history.search("git").iter().map(|e| e.title()).collect();
+        assert_eq!(git, vec!["Uninstall git", "Install git"]);
+    }
+
+    #[test]
+    fn test_last_changes() {
+        let mut history = History::new();
+        let mut install = entry(Operation::Install, Some("wget"), "");
+        install.timestamp = 100;
+        history.push(install);
+        let mut upgrade = entry(Operation::Upgrade, Some("wget"), "");
+        upgrade.timestamp = 200;
+        history.push(upgrade);
+        let mut failed = entry(Operation::Install, Some("git"), "");
+        failed.success = false;
+        history.push(failed);
+        history.push(entry(Operation::Uninstall, Some("node"), ""));
+
+        let changes = history.last_changes();
+        assert_eq!(changes.get("wget"), Some(&200));
+        assert_eq!(changes.len(), 1);
+    }
+
+    #[test]
+    fn test_trim_keeps_newest() {
+        let mut history = History::new();
+        for name in ["a", "b", "c", "d"] {
+            history.push(entry(Operation::Install, Some(name), ""));
+        }
+
+        history.trim(2);
+        let titles: Vec<String> = history.search("").iter().map(|e| e.title()).collect();
+        assert_eq!(titles, vec!["Install d", "Install c"]);
+
+        history.trim(10);
+        assert_eq!(history.search("").len(), 2);
+    }
+
+    #[test]
+    fn test_merge_adds_missing_entries() {
+        let mut history = History::new();
+        history.push(HistoryEntry { timestamp: 10, ..entry(Operation::Install, Some("wget"), "") });
+        history.push(HistoryEntry { timestamp: 30, ..entry(Operation::Install, Some("git"), "") });
+
+        let mut on_disk = History::new();
+        on_disk.push(HistoryEntry { timestamp: 10, ..entry(Operation::Install, Some("wget"), "") });
+        on_disk.push(HistoryEntry { timestamp: 20, ..entry(Operation::Upgrade, None, "") });
+        history.merge(on_disk);
+
+        let titles: Vec<String> = history.search("").iter().map(|e| e.title()).collect();
+        assert_eq!(titles, vec!["Install git", "Upgrade (all packages)", "Install wget"]);
+    }
+
+    #[test]
+    fn test_truncate_output_keeps_tail() {
+        let short = "Pouring git--2.43.0.bottle.tar.gz".to_string();
+        assert_eq!(truncate_output(short.clone()), short);
+
+        let long = format!("{}Error: build failed", "x".repeat(MAX_OUTPUT_BYTES));
+        let truncated = truncate_output(long);
+        assert!(truncated.starts_with("[earlier output truncated]"));
+        assert!(truncated.ends_with("Error: build failed"));
+        assert!(truncated.len() < MAX_OUTPUT_BYTES + 64);
+    }
+
+    #[test]
+    fn test_history_serialization_roundtrip() {
+        let mut history = History::new();
+        history.push(entry(Operation::Upgrade, None, "==> Upgrading 3 outdated packages"));
+
+        let json = serde_json::to_string(&history).unwrap();
+        let parsed: History = serde_json::from_str(&json).unwrap();
+        let entries = parsed.search("");
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].operation, Operation::Upgrade);
+        assert!(entries[0].package.is_none());
+    }
+
+    #[test]
+    fn test_record_captures_errors() {
+        let result: crate::brew::BrewResult<String> =
+            Err(crate::brew::BrewError::CommandFailed("No available formula".to_string()));
+        record(Operation::Install, Some("not-a-real-formula-xyz"), result.as_deref(), Duration::from_millis(1500));
+
+        let found = search("not-a-real-formula-xyz");
+        assert_eq!(found.len(), 1);
+        assert!(!found[0].success);
+        assert_eq!(found[0].duration_ms, 1500);
+        assert!(found[0].output.contains("No available formula"));
+    }
+}
@@ -0,0 +1,520 @@
+use crate::brew;
+use crate::events::{self, AppEvent};
+use crate::history::{self, Operation};
+use gtk4::glib;
+use gtk4::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+// Finished jobs kept for the panel; older ones are forgotten
+const MAX_FINISHED: usize = 50;
+
+pub type JobId = u64;
+
+/// What a job resolves to: its output, or the error message
+pub type JobResult = Result<String, String>;
+
+/// Where a job is in the queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobState {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl JobState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            JobState::Queued => "Queued",
+            JobState::Running => "Running",
+            JobState::Succeeded => "Done",
+            JobState::Failed => "Failed",
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self, JobState::Succeeded | JobState::Failed)
+    }
+}
+
+/// One operation in the queue, as shown in the jobs panel
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub id: JobId,
+    /// e.g. "Install wget"
+    pub title: String,
+    pub state: JobState,
+    /// Output on success or the error message on failure; empty until finished
+    pub output: String,
+}
+
+struct Pending {
+    id: JobId,
+    future: Pin<Box<dyn Future<Output = JobResult>>>,
+    on_removed: Box<dyn FnOnce()>,
+}
+
+struct Listener {
+    owner: glib::WeakRef<gtk4::Widget>,
+    handler: Box<dyn Fn()>,
+}
+
+thread_local! {
+    // Jobs drive widgets, so the queue lives on the main thread with them
+    static JOBS: RefCell<Vec<Job>> = const { RefCell::new(Vec::new()) };
+    static PENDING: RefCell<VecDeque<Pending>> = const { RefCell::new(VecDeque::new()) };
+    static RUNNING: Cell<bool> = const { Cell::new(false) };
+    static NEXT_ID: Cell<JobId> = const { Cell::new(1) };
+    static LISTENERS: RefCell<Vec<Listener>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Queue `job` to run once every job queued before it has finished. Brew holds a lock
+/// while it changes the prefix, so running operations one at a time avoids lock errors.
+/// `on_removed` is called instead if the job is removed from the queue before it starts.
+pub fn enqueue(
+    title: impl Into<String>,
+    job: impl Future<Output = JobResult> + 'static,
+    on_removed: impl FnOnce() + 'static,
+) -> JobId {
+    let id = NEXT_ID.with(|next| next.replace(next.get() + 1));
+    JOBS.with(|jobs| {
+        jobs.borrow_mut().push(Job {
+            id,
+            title: title.into(),
+            state: JobState::Queued,
+            output: String::new(),
+        })
+    });
+    PENDING.with(|pending| {
+        pending.borrow_mut().push_back(Pending {
+            id,
+            future: Box::pin(job),
+            on_removed: Box::new(on_removed),
+        })
+    });
+    notify();
+    start_next();
+    id
+}
+
+/// Queue an upgrade of `name`, or of every outdated package, for callers without a page of
+/// their own (D-Bus, the tray icon). Records it in the history and announces upgraded packages;
+/// `on_finished` gets the result once it has run.
+pub fn enqueue_upgrade(
+    name: Option<String>,
+    on_finished: impl FnOnce(&brew::BrewResult<brew::UpgradeReport>) + 'static,
+    on_removed: impl FnOnce() + 'static,
+) -> JobId {
+    let title = match &name {
+        Some(name) => format!("Upgrade {}", name),
+        None => "Upgrade all packages".to_string(),
+    };
+    let job = async move {
+        let started = Instant::now();
+        let name_for_task = name.clone();
+        let result = gtk4::gio::spawn_blocking(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(brew::upgrade_packages(name_for_task.as_deref()))
+        })
+        .await
+        .expect("Background task failed");
+        history::record(
+            Operation::Upgrade,
+            name.as_deref(),
+            result.as_ref().map(|r| r.output.as_str()),
+            started.elapsed(),
+        );
+        if result.as_ref().is_ok_and(|report| !report.upgraded.is_empty()) {
+            events::emit(AppEvent::PackagesUpgraded);
+        }
+        let outcome = outcome(&result.as_ref().map(|r| r.output.as_str()));
+        on_finished(&result);
+        outcome
+    };
+    enqueue(title, job, on_removed)
+}
+
+/// One step of a batch job
+#[derive(Debug, Clone)]
+pub enum BatchStep {
+    Tap(String),
+    Install(String, brew::InstallOptions),
+    /// Pin an installed formula so upgrades leave it alone
+    Pin(String),
+}
+
+impl BatchStep {
+    /// The tap or package the step is for
+    pub fn name(&self) -> &str {
+        match self {
+            BatchStep::Tap(name) | BatchStep::Install(name, _) | BatchStep::Pin(name) => name,
+        }
+    }
+
+    fn action(&self) -> &'static str {
+        match self {
+            BatchStep::Tap(_) => "Tapping",
+            BatchStep::Install(..) => "Installing",
+            BatchStep::Pin(_) => "Pinning",
+        }
+    }
+
+    /// Run the step, sending the download percentage of an install to `progress_tx`,
+    /// and record it in the history
+    async fn run(self, progress_tx: tokio::sync::mpsc::UnboundedSender<f64>) -> brew::BrewResult<String> {
+        let started = Instant::now();
+        let (operation, name, result) = match self {
+            BatchStep::Tap(tap) => {
+                let tap_for_task = tap.clone();
+                let result = gtk4::gio::spawn_blocking(move || {
+                    let rt = tokio::runtime::Runtime::new().unwrap();
+                    rt.block_on(brew::add_tap(&tap_for_task))
+                })
+                .await
+                .expect("Background task failed");
+                (Operation::Tap, tap, result)
+            }
+            BatchStep::Install(name, options) => {
+                let name_for_task = name.clone();
+                let result = gtk4::gio::spawn_blocking(move || {
+                    let rt = tokio::runtime::Runtime::new().unwrap();
+                    rt.block_on(brew::install_package_with_progress(&name_for_task, &options, progress_tx))
+                })
+                .await
+                .expect("Background task failed");
+                if result.is_ok() {
+                    events::emit(AppEvent::PackageInstalled(name.clone()));
+                }
+                (Operation::Install, name, result)
+            }
+            BatchStep::Pin(name) => {
+                let name_for_task = name.clone();
+                let result = gtk4::gio::spawn_blocking(move || {
+                    let rt = tokio::runtime::Runtime::new().unwrap();
+                    rt.block_on(brew::pin_package(&name_for_task))
+                })
+                .await
+                .expect("Background task failed");
+                if result.is_ok() {
+                    events::emit(AppEvent::PackageChanged(name.clone()));
+                }
+                (Operation::Pin, name, result)
+            }
+        };
+        history::record(operation, Some(&name), result.as_deref(), started.elapsed());
+        result
+    }
+}
+
+/// How far a batch job has got
+#[derive(Debug, Clone)]
+pub struct BatchProgress {
+    /// What the current step does, e.g. "Installing"
+    pub action: &'static str,
+    /// Tap or package of the current step, the `index`th (from 0) of `total`
+    pub name: String,
+    pub index: usize,
+    pub total: usize,
+    /// Share of the batch done, counting the current package's download
+    pub fraction: f64,
+}
+
+impl BatchProgress {
+    /// e.g. "Installing wget (1/3)"
+    pub fn text(&self) -> String {
+        format!("{} {} ({}/{})", self.action, self.name, self.index + 1, self.total)
+    }
+}
+
+/// What a batch job did
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    pub installed: Vec<String>,
+    pub tapped: Vec<String>,
+    pub pinned: Vec<String>,
+    /// Taps and packages that failed, with brew's error
+    pub failed: Vec<(String, String)>,
+}
+
+impl BatchReport {
+    /// One line describing the run, e.g. "Installed 3 packages; pinned node; 1 failed: foo"
+    pub fn summary(&self) -> String {
+        let mut parts = vec![match self.installed.as_slice() {
+            [] => "Nothing was installed".to_string(),
+            [only] => format!("Installed {}", only),
+            installed => format!("Installed {} packages", installed.len()),
+        }];
+        if !self.tapped.is_empty() {
+            parts.push(format!("tapped {}", self.tapped.join(", ")));
+        }
+        if !self.pinned.is_empty() {
+            parts.push(format!("pinned {}", self.pinned.join(", ")));
+        }
+        if !self.failed.is_empty() {
+            let failed: Vec<&str> = self.failed.iter().map(|(name, _)| name.as_str()).collect();
+            parts.push(format!("{} failed: {}", failed.len(), failed.join(", ")));
+        }
+        parts.join("; ")
+    }
+}
+
+/// Queue one job installing `packages` one after another, each with its own options. Each
+/// install is recorded in the history and announced; a failure doesn't stop the rest.
+/// `on_progress` follows the batch as it runs and `on_finished` gets what it did.
+pub fn enqueue_install_batch(
+    packages: Vec<(String, brew::InstallOptions)>,
+    on_progress: impl Fn(&BatchProgress) + 'static,
+    on_finished: impl FnOnce(&BatchReport) + 'static,
+    on_removed: impl FnOnce() + 'static,
+) -> JobId {
+    let title = match packages.as_slice() {
+        [(name, _)] => format!("Install {}", name),
+        packages => format!("Install {} packages", packages.len()),
+    };
+    let steps = packages.into_iter().map(|(name, options)| BatchStep::Install(name, options)).collect();
+    enqueue_batch(title, steps, on_progress, on_finished, on_removed)
+}
+
+/// Queue one job running `steps` in order, like `enqueue_install_batch` but with taps and pins
+/// too, e.g. to tap a repository before installing from it
+pub fn enqueue_batch(
+    title: impl Into<String>,
+    steps: Vec<BatchStep>,
+    on_progress: impl Fn(&BatchProgress) + 'static,
+    on_finished: impl FnOnce(&BatchReport) + 'static,
+    on_removed: impl FnOnce() + 'static,
+) -> JobId {
+    let on_progress = Rc::new(on_progress);
+    let job = async move {
+        let total = steps.len();
+        let mut report = BatchReport::default();
+        let mut output = String::new();
+        for (index, step) in steps.into_iter().enumerate() {
+            let (action, name) = (step.action(), step.name().to_string());
+            let fraction = index as f64 / total as f64;
+            on_progress(&BatchProgress { action, name: name.clone(), index, total, fraction });
+
+            let (progress_tx, mut progress_rx) = tokio::sync::mpsc::unbounded_channel::<f64>();
+            let on_download = on_progress.clone();
+            let name_for_download = name.clone();
+            glib::spawn_future_local(async move {
+                while let Some(percent) = progress_rx.recv().await {
+                    let fraction = (index as f64 + percent / 100.0) / total as f64;
+                    on_download(&BatchProgress { action, name: name_for_download.clone(), index, total, fraction });
+                }
+            });
+
+            // Steps that finish quickly, like pins, failed installs and dry runs, would
+            // otherwise run into the install rate limit the step before them used
+            let wait_ms = brew::INSTALL_RATE_LIMITER.remaining_ms();
+            if wait_ms > 0 {
+                glib::timeout_future(Duration::from_millis(wait_ms)).await;
+            }
+
+            let done = match &step {
+                BatchStep::Tap(_) => &mut report.tapped,
+                BatchStep::Install(..) => &mut report.installed,
+                BatchStep::Pin(_) => &mut report.pinned,
+            };
+            match step.run(progress_tx).await {
+                Ok(step_output) => {
+                    output.push_str(&step_output);
+                    done.push(name);
+                }
+                Err(e) => {
+                    output.push_str(&format!("{}: {}\n", name, e));
+                    report.failed.push((name, e.summary()));
+                }
+            }
+        }
+        on_finished(&report);
+        if report.failed.is_empty() {
+            Ok(output)
+        } else {
+            Err(format!("{}\n\n{}", report.summary(), output))
+        }
+    };
+    enqueue(title, job, on_removed)
+}
+
+/// Whether a job is running, so a new one would have to wait
+pub fn is_busy() -> bool {
+    RUNNING.with(Cell::get)
+}
+
+/// Whether no job is running or waiting to run
+pub fn is_idle() -> bool {
+    !is_busy() && PENDING.with(|pending| pending.borrow().is_empty())
+}
+
+/// All known jobs, oldest first
+pub fn jobs() -> Vec<Job> {
+    JOBS.with(|jobs| jobs.borrow().clone())
+}
+
+/// Take a queued job out of the queue. Returns false if it already started.
+pub fn remove(id: JobId) -> bool {
+    let removed = PENDING.with(|pending| {
+        let mut pending = pending.borrow_mut();
+        let position = pending.iter().position(|p| p.id == id)?;
+        pending.remove(position)
+    });
+    let Some(removed) = removed else {
+        return false;
+    };
+    JOBS.with(|jobs| jobs.borrow_mut().retain(|job| job.id != id));
+    (removed.on_removed)();
+    notify();
+    true
+}
+
+/// Forget every finished job
+pub fn clear_finished() {
+    JOBS.with(|jobs| jobs.borrow_mut().retain(|job| !job.state.is_finished()));
+    notify();
+}
+
+/// The job result for an operation's result, in the shape `history::record` takes
+pub fn outcome<T: AsRef<str>, E: Display>(result: &Result<T, E>) -> JobResult {
+    match result {
+        Ok(output) => Ok(output.as_ref().to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Call `handler` whenever a job is added, starts, finishes or is removed,
+/// for as long as `owner` is alive
+pub fn connect_changed(owner: &impl IsA<gtk4::Widget>, handler: impl Fn() + 'static) {
+    let listener = Listener {
+        owner: owner.upcast_ref::<gtk4::Widget>().downgrade(),
+        handler: Box::new(handler),
+    };
+    LISTENERS.with(|listeners| listeners.borrow_mut().push(listener));
+}
+
+fn notify() {
+    // Take the list out so handlers can call back into the queue without a double borrow
+    let mut listeners = LISTENERS.with(|listeners| std::mem::take(&mut *listeners.borrow_mut()));
+    listeners.retain(|listener| listener.owner.upgrade().is_some());
+    for listener in &listeners {
+        (listener.handler)();
+    }
+    LISTENERS.with(|current| {
+        let mut current = current.borrow_mut();
+        listeners.append(&mut current);
+        *current = listeners;
+    });
+}
+
+fn set_state(id: JobId, state: JobState, output: Option<String>) {
+    JOBS.with(|jobs| {
+        let mut jobs = jobs.borrow_mut();
+        if let Some(job) = jobs.iter_mut().find(|job| job.id == id) {
+            job.state = state;
+            if let Some(output) = output {
+                job.output = output;
+            }
+        }
+
+        let finished = jobs.iter().filter(|job| job.state.is_finished()).count();
+        let mut excess = finished.saturating_sub(MAX_FINISHED);
+        jobs.retain(|job| {
+            let forget = excess > 0 && job.state.is_finished();
+            if forget {
+                excess -= 1;
+            }
+            !forget
+        });
+    });
+}
+
+fn start_next() {
+    if RUNNING.with(Cell::get) {
+        return;
+    }
+    let Some(next) = PENDING.with(|pending| pending.borrow_mut().pop_front()) else {
+        return;
+    };
+
+    RUNNING.with(|running| running.set(true));
+    set_state(next.id, JobState::Running, None);
+    notify();
+
+    glib::spawn_future_local(async move {
+        let result = next.future.await;
+        match result {
+            Ok(output) => set_state(next.id, JobState::Succeeded, Some(output)),
+            Err(error) => set_state(next.id, JobState::Failed, Some(error)),
+        }
+        RUNNING.with(|running| running.set(false));
+        notify();
+        start_next();
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_report_summary() {
+        let mut report = BatchReport::default();
+        assert_eq!(report.summary(), "Nothing was installed");
+        report.installed.push("wget".to_string());
+        assert_eq!(report.summary(), "Installed wget");
+        report.installed.push("jq".to_string());
+        report.failed.push(("foo".to_string(), "No available formula".to_string()));
+        assert_eq!(report.summary(), "Installed 2 packages; 1 failed: foo");
+
+        report.tapped.push("user/tools".to_string());
+        report.pinned.push("node".to_string());
+        assert_eq!(report.summary(), "Installed 2 packages; tapped user/tools; pinned node; 1 failed: foo");
+    }
+
+    #[test]
+    fn test_batch_progress_text() {
+        let step = BatchStep::Pin("node".to_string());
+        let name = step.name().to_string();
+        let progress = BatchProgress { action: step.action(), name, index: 1, total: 3, fraction: 0.5 };
+        assert_eq!(progress.text(), "Pinning node (2/3)");
+    }
+
+    #[test]
+    fn test_remove_queued_job() {
+        // With a job already running, new jobs wait in the queue
+        RUNNING.with(|running| running.set(true));
+        let removed = Rc::new(Cell::new(false));
+        let removed_for_job = removed.clone();
+        let id = enqueue("Install wget", std::future::pending(), move || removed_for_job.set(true));
+        assert_eq!(jobs().iter().find(|job| job.id == id).map(|job| job.state), Some(JobState::Queued));
+
+        assert!(remove(id));
+        assert!(removed.get());
+        assert!(jobs().iter().all(|job| job.id != id));
+        assert!(!remove(id));
+        RUNNING.with(|running| running.set(false));
+    }
+
+    #[test]
+    fn test_finished_jobs_are_trimmed() {
+        let job = |id, state| Job { id, title: format!("Job {}", id), state, output: String::new() };
+        JOBS.with(|jobs| {
+            let mut jobs = jobs.borrow_mut();
+            jobs.push(job(1, JobState::Running));
+            jobs.extend((2..=MAX_FINISHED as JobId + 1).map(|id| job(id, JobState::Succeeded)));
+        });
+
+        set_state(1, JobState::Failed, Some("error".to_string()));
+        let jobs = jobs();
+        assert_eq!(jobs.len(), MAX_FINISHED);
+        // The oldest finished job is forgotten first
+        assert_eq!(jobs[0].id, 2);
+        assert!(jobs.iter().all(|job| job.id != 1));
+    }
+}
@@ -1,43 +1,300 @@
-mod brew;
+mod app_state;
+mod cli;
+mod daemon;
+mod dbus;
+mod events;
+mod history;
+mod jobs;
+mod notes;
+mod package_object;
+mod search_provider;
+mod settings;
+mod tray;
+mod views;
+use brewhouse_core::{ansi, brew, inventory, runner, storage};
 use gtk4::glib;
 use gtk4::prelude::*;
 use gtk4::{
-    Application, ApplicationWindow, Box, Button, CheckButton, Label, Orientation,
-    ScrolledWindow, ListBox, ListBoxRow, Stack, StackSidebar, SearchEntry,
-    Paned, Spinner, TextView, Window,
+    Application, ApplicationWindow, Box, Button, Label, Orientation,
+    ScrolledWindow, ListBox, SearchEntry, Spinner, TextView, Window,
 };
 use libadwaita as adw;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::rc::Rc;
+use std::time::Instant;
+
+use app_state::AppState;
+use history::Operation;
 
 const APP_ID: &str = "io.github.brewhouse.app";
 
+// Window width (sp) below which the header bar has no room for the page switcher
+const NARROW_WINDOW_WIDTH: u32 = 800;
+
 fn main() {
     // Set program name before GTK init to control WM_CLASS
     glib::set_prgname(Some("brewhouse"));
     glib::set_application_name("BrewHouse");
 
-    adw::init().expect("Failed to initialize libadwaita");
-
-    if !brew::is_brew_installed() {
-        eprintln!("Homebrew is not installed!");
-    }
-
+    // Load (and migrate, if needed) data written by previous runs
+    settings::init();
+    history::init();
+    notes::init();
+    brew::set_brew_path(settings::get().brew_path.map(std::path::PathBuf::from));
+    brew::set_verbose(settings::get().verbose_operations);
+    brew::set_no_quarantine(settings::get().no_quarantine);
+    views::preferences::apply_homebrew_env();
+
+    // One instance per session: running brewhouse again hands its command line to the
+    // running instance, so two processes never drive brew at once
     let app = Application::builder()
         .application_id(APP_ID)
+        .flags(gtk4::gio::ApplicationFlags::HANDLES_COMMAND_LINE)
         .build();
+    cli::add_options(&app);
+    daemon::add_option(&app);
 
-    app.connect_startup(|_| {
+    // GTK starts with the application, so the scripting options work without a display
+    app.connect_startup(|app| {
+        adw::init().expect("Failed to initialize libadwaita");
+        apply_color_scheme(settings::get().color_scheme);
         load_css();
+        add_shortcuts(app);
+        // Startup only runs in the primary instance, which owns the bus name
+        if let (Some(connection), Some(path)) = (app.dbus_connection(), app.dbus_object_path()) {
+            dbus::export(&connection, &path);
+            search_provider::export(app, &connection, |app, target| open_target(app, Some(target)));
+            tray::init(app, &connection, |app| open_target(app, None));
+        }
+        daemon::add_autostart_action(app);
+        add_verbose_action(app);
+        add_no_quarantine_action(app);
+        add_dry_run_action(app);
     });
 
-    app.connect_activate(|app| {
-        build_ui(app);
+    app.connect_command_line(|app, command_line| {
+        if daemon::take_request() {
+            daemon::start(app, show_updates_page, |app, query| {
+                open_target(app, Some(cli::Target::Search(query.to_string())))
+            });
+        } else {
+            open_target(app, cli::target(&command_line.options_dict()));
+        }
+        0
     });
+    app.connect_activate(|app| open_target(app, None));
 
     app.run();
 }
 
+/// Add the `app.verbose` toggle for Preferences, which runs installs and upgrades with --verbose
+fn add_verbose_action(app: &Application) {
+    let verbose = settings::get().verbose_operations;
+    let action = gtk4::gio::SimpleAction::new_stateful("verbose", None, &verbose.to_variant());
+    action.connect_change_state(|action, state| {
+        let Some(verbose) = state.and_then(|state| state.get::<bool>()) else {
+            return;
+        };
+        brew::set_verbose(verbose);
+        settings::update(|s| s.verbose_operations = verbose);
+        action.set_state(&verbose.to_variant());
+    });
+    app.add_action(&action);
+}
+
+/// Add the `app.no-quarantine` toggle for Preferences (see `brew::set_no_quarantine`)
+fn add_no_quarantine_action(app: &Application) {
+    let no_quarantine = settings::get().no_quarantine;
+    let action = gtk4::gio::SimpleAction::new_stateful("no-quarantine", None, &no_quarantine.to_variant());
+    action.connect_change_state(|action, state| {
+        let Some(no_quarantine) = state.and_then(|state| state.get::<bool>()) else {
+            return;
+        };
+        brew::set_no_quarantine(no_quarantine);
+        settings::update(|s| s.no_quarantine = no_quarantine);
+        action.set_state(&no_quarantine.to_variant());
+    });
+    app.add_action(&action);
+}
+
+/// Add the `app.dry-run` toggle for Preferences, which makes changes preview only (see
+/// `brew::set_dry_run`). Read in startup rather than main, so the scripting options always run.
+fn add_dry_run_action(app: &Application) {
+    let dry_run = settings::get().simulate_only;
+    brew::set_dry_run(dry_run);
+    let action = gtk4::gio::SimpleAction::new_stateful("dry-run", None, &dry_run.to_variant());
+    action.connect_change_state(|action, state| {
+        let Some(dry_run) = state.and_then(|state| state.get::<bool>()) else {
+            return;
+        };
+        brew::set_dry_run(dry_run);
+        settings::update(|s| s.simulate_only = dry_run);
+        action.set_state(&dry_run.to_variant());
+    });
+    app.add_action(&action);
+}
+
+/// Keep `title`'s subtitle saying whether Simulate Only is on, for as long as it exists
+fn show_dry_run_state(app: &Application, title: &adw::WindowTitle) {
+    let Some(action) = app.lookup_action("dry-run") else {
+        return;
+    };
+    let update = |title: &adw::WindowTitle, action: &gtk4::gio::Action| {
+        let dry_run = action.state().and_then(|state| state.get::<bool>()).unwrap_or(false);
+        title.set_subtitle(if dry_run { "Simulate only: nothing is changed" } else { "" });
+    };
+    update(title, &action);
+    let title = title.downgrade();
+    action.connect_state_notify(move |action| {
+        if let Some(title) = title.upgrade() {
+            update(&title, action.upcast_ref());
+        }
+    });
+}
+
+// Pages reachable with Ctrl+1 to Ctrl+5, in view switcher order
+const NUMBERED_PAGES: &[&str] = &["installed", "browse", "updates", "history", "cache"];
+
+fn add_shortcuts(app: &Application) {
+    let quit_action = gtk4::gio::SimpleAction::new("quit", None);
+    let app_for_quit = app.clone();
+    quit_action.connect_activate(move |_, _| app_for_quit.quit());
+    app.add_action(&quit_action);
+
+    app.set_accels_for_action("app.quit", &["<Ctrl>q"]);
+    app.set_accels_for_action("win.preferences", &["<Ctrl>comma"]);
+    app.set_accels_for_action("win.focus-search", &["<Ctrl>f"]);
+    app.set_accels_for_action("win.refresh", &["F5", "<Ctrl>r"]);
+    for (index, page) in NUMBERED_PAGES.iter().enumerate() {
+        app.set_accels_for_action(&format!("win.show-page('{}')", page), &[&format!("<Ctrl>{}", index + 1)]);
+    }
+}
+
+const SHORTCUTS_UI: &str = r#"
+<interface>
+  <object class="GtkShortcutsWindow" id="shortcuts">
+    <property name="modal">true</property>
+    <child>
+      <object class="GtkShortcutsSection">
+        <property name="section-name">shortcuts</property>
+        <child>
+          <object class="GtkShortcutsGroup">
+            <property name="title">General</property>
+            <child>
+              <object class="GtkShortcutsShortcut">
+                <property name="title">Search</property>
+                <property name="accelerator">&lt;Ctrl&gt;f</property>
+              </object>
+            </child>
+            <child>
+              <object class="GtkShortcutsShortcut">
+                <property name="title">Refresh the current page</property>
+                <property name="accelerator">F5 &lt;Ctrl&gt;r</property>
+              </object>
+            </child>
+            <child>
+              <object class="GtkShortcutsShortcut">
+                <property name="title">Keyboard shortcuts</property>
+                <property name="accelerator">&lt;Ctrl&gt;question</property>
+              </object>
+            </child>
+            <child>
+              <object class="GtkShortcutsShortcut">
+                <property name="title">Preferences</property>
+                <property name="accelerator">&lt;Ctrl&gt;comma</property>
+              </object>
+            </child>
+            <child>
+              <object class="GtkShortcutsShortcut">
+                <property name="title">Quit</property>
+                <property name="accelerator">&lt;Ctrl&gt;q</property>
+              </object>
+            </child>
+          </object>
+        </child>
+        <child>
+          <object class="GtkShortcutsGroup">
+            <property name="title">Pages</property>
+            <child>
+              <object class="GtkShortcutsShortcut">
+                <property name="title">Installed</property>
+                <property name="accelerator">&lt;Ctrl&gt;1</property>
+              </object>
+            </child>
+            <child>
+              <object class="GtkShortcutsShortcut">
+                <property name="title">Browse</property>
+                <property name="accelerator">&lt;Ctrl&gt;2</property>
+              </object>
+            </child>
+            <child>
+              <object class="GtkShortcutsShortcut">
+                <property name="title">Updates</property>
+                <property name="accelerator">&lt;Ctrl&gt;3</property>
+              </object>
+            </child>
+            <child>
+              <object class="GtkShortcutsShortcut">
+                <property name="title">History</property>
+                <property name="accelerator">&lt;Ctrl&gt;4</property>
+              </object>
+            </child>
+            <child>
+              <object class="GtkShortcutsShortcut">
+                <property name="title">Downloads</property>
+                <property name="accelerator">&lt;Ctrl&gt;5</property>
+              </object>
+            </child>
+          </object>
+        </child>
+        <child>
+          <object class="GtkShortcutsGroup">
+            <property name="title">Installed</property>
+            <child>
+              <object class="GtkShortcutsShortcut">
+                <property name="title">Uninstall the selected package</property>
+                <property name="accelerator">Delete</property>
+              </object>
+            </child>
+          </object>
+        </child>
+      </object>
+    </child>
+  </object>
+</interface>
+"#;
+
+/// The first search entry in `widget`, depth first
+fn find_search_entry(widget: &gtk4::Widget) -> Option<SearchEntry> {
+    if let Some(entry) = widget.downcast_ref::<SearchEntry>() {
+        return Some(entry.clone());
+    }
+    let mut child = widget.first_child();
+    while let Some(current) = child {
+        if let Some(entry) = find_search_entry(&current) {
+            return Some(entry);
+        }
+        child = current.next_sibling();
+    }
+    None
+}
+
+// Choices for the Appearance preference, in the order shown
+const COLOR_SCHEMES: [(settings::ColorScheme, &str); 3] = [
+    (settings::ColorScheme::System, "Follow System"),
+    (settings::ColorScheme::Light, "Light"),
+    (settings::ColorScheme::Dark, "Dark"),
+];
+
+fn apply_color_scheme(scheme: settings::ColorScheme) {
+    adw::StyleManager::default().set_color_scheme(match scheme {
+        settings::ColorScheme::System => adw::ColorScheme::Default,
+        settings::ColorScheme::Light => adw::ColorScheme::ForceLight,
+        settings::ColorScheme::Dark => adw::ColorScheme::ForceDark,
+    });
+}
+
 fn load_css() {
     let provider = gtk4::CssProvider::new();
     provider.load_from_data(
@@ -75,14 +332,38 @@ fn load_css() {
         "#,
     );
 
-    gtk4::style_context_add_provider_for_display(
-        &gtk4::gdk::Display::default().expect("Could not get default display"),
-        &provider,
-        gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
-    );
+    let display = gtk4::gdk::Display::default().expect("Could not get default display");
+    gtk4::style_context_add_provider_for_display(&display, &provider, gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION);
+
+    // Optional user overrides, e.g. fonts or row padding, applied on top of the built-in style
+    let user_css = storage::config_dir().join("style.css");
+    if user_css.exists() {
+        let user_provider = gtk4::CssProvider::new();
+        user_provider.connect_parsing_error(|_, section, error| {
+            eprintln!("style.css {}: {}", section, error);
+        });
+        user_provider.load_from_path(&user_css);
+        gtk4::style_context_add_provider_for_display(
+            &display,
+            &user_provider,
+            gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION + 1,
+        );
+    }
 }
 
 fn build_ui(app: &Application) {
+    if !brew::is_brew_installed() {
+        let app_clone = app.clone();
+        views::setup::show_install_brew_dialog(app, move || build_ui(&app_clone));
+        return;
+    }
+
+    if !settings::get().onboarding_complete {
+        let app_clone = app.clone();
+        views::setup::show_setup_wizard(app, move || build_ui(&app_clone));
+        return;
+    }
+
     let app_clone = app.clone();
 
     // Show update dialog first
@@ -91,6 +372,260 @@ fn build_ui(app: &Application) {
     });
 }
 
+fn show_updates_page(app: &Application) {
+    open_target(app, Some(cli::Target::Page("updates".to_string())));
+}
+
+/// Bring up BrewHouse at `target`: in the open main window if there is one, otherwise in
+/// a new one. Without a target an open window is just raised.
+fn open_target(app: &Application, target: Option<cli::Target>) {
+    // Dialogs are plain windows; only the main window is an ApplicationWindow
+    let main_window = app.windows().into_iter().find(|window| window.is::<ApplicationWindow>());
+    let Some(window) = main_window else {
+        if let Some(target) = target {
+            cli::set_target(target);
+        }
+        build_ui(app);
+        return;
+    };
+
+    let result = match &target {
+        Some(cli::Target::Page(page)) => window.activate_action("win.show-page", Some(&page.to_variant())),
+        Some(cli::Target::Package(name)) => window.activate_action("win.show-package", Some(&name.to_variant())),
+        Some(cli::Target::Search(query)) => window.activate_action("win.search", Some(&query.to_variant())),
+        None => Ok(()),
+    };
+    if let Err(e) = result {
+        eprintln!("Could not open {:?}: {}", target, e);
+    }
+    window.present();
+}
+
+/// Switch `stack` to `page`, warning about names that aren't pages
+fn show_page(stack: &adw::ViewStack, page: &str) {
+    if stack.child_by_name(page).is_some() {
+        stack.set_visible_child_name(page);
+    } else {
+        eprintln!("No page called '{}'", page);
+    }
+}
+
+/// Show the package `name`: selected in Installed if it is installed, otherwise searched for
+/// in Browse. Waits for the installed list first if it is still loading.
+fn show_package(stack: &adw::ViewStack, state: &AppState, name: &str) {
+    if !state.installed_loaded() {
+        let handler = Rc::new(RefCell::new(None));
+        let handler_for_load = handler.clone();
+        let stack = stack.clone();
+        let state_for_load = state.clone();
+        let name = name.to_string();
+        let id = state.installed_packages().connect_items_changed(move |store, _, _, _| {
+            if let Some(id) = handler_for_load.borrow_mut().take() {
+                store.disconnect(id);
+            }
+            show_package(&stack, &state_for_load, &name);
+        });
+        *handler.borrow_mut() = Some(id);
+        return;
+    }
+
+    if state.find_installed(name).is_none() {
+        show_search(stack, name);
+        return;
+    }
+    stack.set_visible_child_name("installed");
+    if let Some(view) = stack.child_by_name("installed") {
+        let _ = view.activate_action("installed.select", Some(&name.to_variant()));
+    }
+}
+
+/// Search Browse for `query`, selecting the package of that name if it is found
+fn show_search(stack: &adw::ViewStack, query: &str) {
+    stack.set_visible_child_name("browse");
+    if let Some(view) = stack.child_by_name("browse") {
+        let _ = view.activate_action("browse.show", Some(&query.to_variant()));
+    }
+}
+
+/// Read-only operation output with a search bar. Ctrl+F, or the `log.find` action from inside
+/// the view, opens it; Enter and Ctrl+G select the next match and Shift+Ctrl+G the previous.
+fn create_log_view() -> (Box, TextView) {
+    let log_box = Box::new(Orientation::Vertical, 6);
+    log_box.set_vexpand(true);
+
+    let search_bar = gtk4::SearchBar::new();
+    search_bar.set_show_close_button(true);
+    let search_entry = SearchEntry::new();
+    search_entry.set_placeholder_text(Some("Find in output..."));
+    search_entry.set_hexpand(true);
+    search_bar.set_child(Some(&search_entry));
+    search_bar.connect_entry(&search_entry);
+    log_box.append(&search_bar);
+
+    let scroll = ScrolledWindow::new();
+    scroll.set_vexpand(true);
+    let text_view = TextView::new();
+    text_view.set_editable(false);
+    text_view.set_wrap_mode(gtk4::WrapMode::Word);
+    text_view.set_monospace(true);
+    scroll.set_child(Some(&text_view));
+    log_box.append(&scroll);
+
+    // Typing refines the current match; the other signals move on from it
+    let find = move |entry: &SearchEntry, text_view: &TextView, direction: Option<bool>| {
+        let found = find_in_log(text_view, &entry.text(), direction);
+        if found || entry.text().is_empty() {
+            entry.remove_css_class("error");
+        } else {
+            entry.add_css_class("error");
+        }
+    };
+    let text_view_for_search = text_view.clone();
+    search_entry.connect_search_changed(move |entry| find(entry, &text_view_for_search, None));
+    let text_view_for_activate = text_view.clone();
+    search_entry.connect_activate(move |entry| find(entry, &text_view_for_activate, Some(true)));
+    let text_view_for_next = text_view.clone();
+    search_entry.connect_next_match(move |entry| find(entry, &text_view_for_next, Some(true)));
+    let text_view_for_previous = text_view.clone();
+    search_entry.connect_previous_match(move |entry| find(entry, &text_view_for_previous, Some(false)));
+
+    let find_action = gtk4::gio::SimpleAction::new("find", None);
+    find_action.connect_activate(move |_, _| {
+        search_bar.set_search_mode(true);
+        search_entry.grab_focus();
+    });
+    let actions = gtk4::gio::SimpleActionGroup::new();
+    actions.add_action(&find_action);
+    log_box.insert_action_group("log", Some(&actions));
+
+    (log_box, text_view)
+}
+
+/// Select the match of `query` in `text_view` at the selection, or after it when `forward` is
+/// Some(true) or before it when Some(false), wrapping around. Returns whether there was one.
+fn find_in_log(text_view: &TextView, query: &str, forward: Option<bool>) -> bool {
+    if query.is_empty() {
+        return false;
+    }
+    let buffer = text_view.buffer();
+    let (selection_start, selection_end) = buffer
+        .selection_bounds()
+        .unwrap_or_else(|| (buffer.start_iter(), buffer.start_iter()));
+    let flags = gtk4::TextSearchFlags::CASE_INSENSITIVE;
+    let found = match forward {
+        Some(false) => selection_start
+            .backward_search(query, flags, None)
+            .or_else(|| buffer.end_iter().backward_search(query, flags, None)),
+        Some(true) => selection_end
+            .forward_search(query, flags, None)
+            .or_else(|| buffer.start_iter().forward_search(query, flags, None)),
+        None => selection_start
+            .forward_search(query, flags, None)
+            .or_else(|| buffer.start_iter().forward_search(query, flags, None)),
+    };
+    let Some((mut match_start, match_end)) = found else {
+        return false;
+    };
+    buffer.select_range(&match_start, &match_end);
+    text_view.scroll_to_iter(&mut match_start, 0.1, false, 0.0, 0.0);
+    true
+}
+
+/// Show brew output in `text_view`, in place of what it showed before
+fn set_output_text(text_view: &TextView, text: &str) {
+    let buffer = text_view.buffer();
+    buffer.set_text("");
+    insert_ansi(&buffer, text);
+}
+
+/// Append brew output to `buffer`, rendering its terminal colors, bold and underline with
+/// text tags and dropping every other escape sequence
+fn insert_ansi(buffer: &gtk4::TextBuffer, text: &str) {
+    let mut end = buffer.end_iter();
+    for span in ansi::parse(text) {
+        let tags = ansi_tags(buffer, span.style);
+        let tags: Vec<&gtk4::TextTag> = tags.iter().collect();
+        buffer.insert_with_tags(&mut end, &span.text, &tags);
+    }
+}
+
+/// Tags for `style`, created in the buffer's tag table on first use
+fn ansi_tags(buffer: &gtk4::TextBuffer, style: ansi::Style) -> Vec<gtk4::TextTag> {
+    let table = buffer.tag_table();
+    let mut tags = Vec::new();
+    let mut add = |name: &str, configure: &dyn Fn(&gtk4::TextTag)| {
+        let tag = table.lookup(name).unwrap_or_else(|| {
+            let tag = gtk4::TextTag::new(Some(name));
+            configure(&tag);
+            table.add(&tag);
+            tag
+        });
+        tags.push(tag);
+    };
+    if style.bold {
+        add("ansi-bold", &|tag| tag.set_weight(700));
+    }
+    if style.underline {
+        add("ansi-underline", &|tag| tag.set_underline(gtk4::pango::Underline::Single));
+    }
+    if let Some(color) = style.color.and_then(ansi_color) {
+        add(&format!("ansi-{}", color), &|tag| tag.set_foreground(Some(color)));
+    }
+    tags
+}
+
+/// Foreground for a terminal color, from the GNOME palette so it reads on light and dark
+/// backgrounds. Black and white would vanish on one of them and keep the text color instead.
+fn ansi_color(color: ansi::Color) -> Option<&'static str> {
+    match color {
+        ansi::Color::Red => Some("#e01b24"),
+        ansi::Color::Green => Some("#2ec27e"),
+        ansi::Color::Yellow => Some("#e5a50a"),
+        ansi::Color::Blue => Some("#3584e4"),
+        ansi::Color::Magenta => Some("#c061cb"),
+        ansi::Color::Cyan => Some("#33b2a4"),
+        ansi::Color::Black | ansi::Color::White => None,
+    }
+}
+
+/// Append lines from `rx` to the end of `text_view` as they arrive, keeping the newest visible
+fn stream_into_text_view(text_view: &TextView, mut rx: tokio::sync::mpsc::UnboundedReceiver<String>) {
+    let text_view = text_view.clone();
+    glib::spawn_future_local(async move {
+        while let Some(line) = rx.recv().await {
+            let buffer = text_view.buffer();
+            insert_ansi(&buffer, &format!("{}\n", line));
+            let mark = buffer.create_mark(None, &buffer.end_iter(), false);
+            text_view.scroll_mark_onscreen(&mark);
+            buffer.delete_mark(&mark);
+        }
+    });
+}
+
+/// Progress bar for downloads during an install or upgrade, hidden until one starts
+fn create_download_progress() -> gtk4::ProgressBar {
+    let bar = gtk4::ProgressBar::new();
+    bar.set_show_text(true);
+    bar.set_visible(false);
+    bar
+}
+
+/// Drive `bar` from the returned sender. The bar shows while progress arrives
+/// and hides again once the sender is dropped, i.e. when the operation ends.
+fn track_download_progress(bar: &gtk4::ProgressBar) -> brew::ProgressSender {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<f64>();
+    let bar = bar.clone();
+    glib::spawn_future_local(async move {
+        while let Some(percent) = rx.recv().await {
+            bar.set_visible(true);
+            bar.set_fraction(percent / 100.0);
+            bar.set_text(Some(&format!("Downloading… {:.0}%", percent)));
+        }
+        bar.set_visible(false);
+    });
+    tx
+}
+
 fn show_update_dialog<F: Fn() + 'static>(app: &Application, on_complete: F) {
     let dialog = Window::builder()
         .application(app)
@@ -112,9 +647,10 @@ fn show_update_dialog<F: Fn() + 'static>(app: &Application, on_complete: F) {
     let header_box = Box::new(Orientation::Horizontal, 10);
     let spinner = Spinner::new();
     spinner.set_spinning(true);
+    set_accessible_label(&spinner, "Updating Homebrew");
     header_box.append(&spinner);
 
-    let status_label = Label::new(Some("Updating Homebrew..."));
+    let status_label = create_status_label(Some("Updating Homebrew..."));
     status_label.add_css_class("title-3");
     header_box.append(&status_label);
     vbox.append(&header_box);
@@ -147,14 +683,26 @@ fn show_update_dialog<F: Fn() + 'static>(app: &Application, on_complete: F) {
     let text_view_clone = text_view.clone();
     let continue_btn_clone = continue_btn.clone();
     let _dialog_clone = dialog.clone();
+    let on_removed = {
+        let spinner = spinner.clone();
+        let continue_btn = continue_btn.clone();
+        move || {
+            spinner.set_visible(false);
+            continue_btn.set_visible(true);
+        }
+    };
 
-    glib::spawn_future_local(async move {
+    let job = async move {
+        let started = Instant::now();
         let result = gtk4::gio::spawn_blocking(move || {
             let rt = tokio::runtime::Runtime::new().unwrap();
             rt.block_on(brew::update_brew())
         })
         .await
         .expect("Background task failed");
+        let output = result.as_ref().map(|(stdout, stderr)| format!("{}{}", stderr, stdout));
+        history::record(Operation::Update, None, output.as_deref(), started.elapsed());
+        let outcome = jobs::outcome(&output);
 
         spinner_clone.set_spinning(false);
         spinner_clone.set_visible(false);
@@ -171,23 +719,25 @@ fn show_update_dialog<F: Fn() + 'static>(app: &Application, on_complete: F) {
                 }
                 if !stdout.is_empty() {
                     if !output.is_empty() {
-                        output.push_str("\n");
+                        output.push('\n');
                     }
                     output.push_str(&stdout);
                 }
                 if output.trim().is_empty() {
                     output = "Already up-to-date.".to_string();
                 }
-                buffer.set_text(&output);
+                set_output_text(&text_view_clone, &output);
             }
             Err(e) => {
                 status_label_clone.set_text("Update failed (continuing anyway)");
-                buffer.set_text(&format!("Error: {}", brew::sanitize_error(&e.to_string())));
+                buffer.set_text(&format!("Error: {}", e.user_message()));
             }
         }
 
         continue_btn_clone.set_visible(true);
-    });
+        outcome
+    };
+    jobs::enqueue("Update Homebrew", job, on_removed);
 
     // Continue button handler
     let dialog_for_btn = dialog.clone();
@@ -198,33 +748,242 @@ fn show_update_dialog<F: Fn() + 'static>(app: &Application, on_complete: F) {
 }
 
 fn build_main_window(app: &Application) {
-    let window = ApplicationWindow::builder()
+    use adw::prelude::AdwApplicationWindowExt;
+
+    let layout = settings::get();
+    let window = adw::ApplicationWindow::builder()
         .application(app)
         .title("BrewHouse")
-        .default_width(1200)
-        .default_height(700)
+        .default_width(layout.window_width)
+        .default_height(layout.window_height)
+        .maximized(layout.window_maximized)
         .build();
 
+    // Remember the size for the next launch; the default size is the unmaximized one
+    window.connect_close_request(|window| {
+        let (width, height) = (window.default_width(), window.default_height());
+        let maximized = window.is_maximized();
+        settings::update(|s| {
+            s.window_width = width;
+            s.window_height = height;
+            s.window_maximized = maximized;
+        });
+        glib::Propagation::Proceed
+    });
+
     // Clear any cached icon
     window.set_icon_name(None);
 
     let main_box = Box::new(Orientation::Horizontal, 0);
 
-    let stack = Stack::new();
+    let stack = adw::ViewStack::new();
     stack.set_hexpand(true);
 
-    stack.add_titled(&create_installed_view(), Some("installed"), "Installed");
-    stack.add_titled(&create_browse_view(), Some("browse"), "Browse");
-    stack.add_titled(&create_updates_view(), Some("updates"), "Updates");
+    // Each window gets its own state, so switching prefixes starts from empty stores
+    let state = AppState::new();
+    let pages: [(gtk4::Widget, &str, &str, &str); 5] = [
+        (views::installed::create_installed_view(&state).upcast(), "installed", "Installed", "view-list-symbolic"),
+        (views::browse::create_browse_view().upcast(), "browse", "Browse", "system-search-symbolic"),
+        (
+            views::updates::create_updates_view(&state).upcast(),
+            "updates",
+            "Updates",
+            "software-update-available-symbolic",
+        ),
+        (views::history::create_history_view().upcast(), "history", "History", "document-open-recent-symbolic"),
+        (views::cache::create_cache_view().upcast(), "cache", "Downloads", "folder-download-symbolic"),
+    ];
+    for (view, name, title, icon_name) in &pages {
+        stack.add_titled(view, Some(name), title).set_icon_name(Some(icon_name));
+    }
+
+    // Reopen the last page; a page given on the command line below takes precedence
+    if let Some(page) = layout.last_page.filter(|page| stack.child_by_name(page).is_some()) {
+        stack.set_visible_child_name(&page);
+    }
+    stack.connect_visible_child_name_notify(|stack| {
+        let page = stack.visible_child_name().map(|name| name.to_string());
+        if page.is_some() && page != settings::get().last_page {
+            settings::update(|s| s.last_page = page);
+        }
+    });
+
+    let show_page_action = gtk4::gio::SimpleAction::new("show-page", Some(glib::VariantTy::STRING));
+    let stack_for_page = stack.clone();
+    show_page_action.connect_activate(move |_, page| {
+        if let Some(page) = page.and_then(|page| page.str()) {
+            show_page(&stack_for_page, page);
+        }
+    });
+    window.add_action(&show_page_action);
+
+    let show_package_action = gtk4::gio::SimpleAction::new("show-package", Some(glib::VariantTy::STRING));
+    let stack_for_package = stack.clone();
+    let state_for_package = state.clone();
+    show_package_action.connect_activate(move |_, name| {
+        if let Some(name) = name.and_then(|name| name.str()) {
+            show_package(&stack_for_package, &state_for_package, name);
+        }
+    });
+    window.add_action(&show_package_action);
 
-    // Left panel: sidebar + stats
+    let search_action = gtk4::gio::SimpleAction::new("search", Some(glib::VariantTy::STRING));
+    let stack_for_search = stack.clone();
+    search_action.connect_activate(move |_, query| {
+        if let Some(query) = query.and_then(|query| query.str()) {
+            show_search(&stack_for_search, query);
+        }
+    });
+    window.add_action(&search_action);
+
+    // Ctrl+F: the search of the output being read, the current page's search, or Browse's on
+    // pages without one
+    let focus_search_action = gtk4::gio::SimpleAction::new("focus-search", None);
+    let stack_for_focus = stack.clone();
+    focus_search_action.connect_activate(move |_, _| {
+        let focus = stack_for_focus.root().and_then(|root| root.focus());
+        if focus.is_some_and(|focus| focus.activate_action("log.find", None).is_ok()) {
+            return;
+        }
+        let entry = stack_for_focus.visible_child().and_then(|page| find_search_entry(&page)).or_else(|| {
+            stack_for_focus.set_visible_child_name("browse");
+            stack_for_focus.child_by_name("browse").and_then(|page| find_search_entry(&page))
+        });
+        if let Some(entry) = entry {
+            entry.grab_focus();
+        }
+    });
+    window.add_action(&focus_search_action);
+
+    // F5 and Ctrl+R: every page offers a <page>.refresh action
+    let refresh_action = gtk4::gio::SimpleAction::new("refresh", None);
+    let stack_for_refresh = stack.clone();
+    refresh_action.connect_activate(move |_, _| {
+        if let (Some(page), Some(name)) = (stack_for_refresh.visible_child(), stack_for_refresh.visible_child_name()) {
+            let _ = page.activate_action(&format!("{}.refresh", name), None);
+        }
+    });
+    window.add_action(&refresh_action);
+
+    let shortcuts_window: gtk4::ShortcutsWindow = gtk4::Builder::from_string(SHORTCUTS_UI)
+        .object("shortcuts")
+        .expect("Shortcuts UI defines the window");
+    window.set_help_overlay(Some(&shortcuts_window));
+
+    match cli::take_target() {
+        Some(cli::Target::Page(page)) => show_page(&stack, &page),
+        Some(cli::Target::Package(name)) => show_package(&stack, &state, &name),
+        Some(cli::Target::Search(query)) => show_search(&stack, &query),
+        None => {}
+    }
+
+    // Header bar: page switcher in the middle, window-wide controls at the sides. A narrow
+    // window shows the title there instead and the switcher at the bottom.
+    let switcher = adw::ViewSwitcher::new();
+    switcher.set_policy(adw::ViewSwitcherPolicy::Wide);
+    switcher.set_stack(Some(&stack));
+    let window_title = adw::WindowTitle::new("BrewHouse", "");
+    show_dry_run_state(app, &window_title);
+    let header_bar = adw::HeaderBar::new();
+    header_bar.set_title_widget(Some(&switcher));
+
+    // Left panel: stats
     let left_panel = Box::new(Orientation::Vertical, 0);
     left_panel.set_width_request(200);
 
-    let stack_sidebar = StackSidebar::new();
-    stack_sidebar.set_stack(&stack);
-    stack_sidebar.set_vexpand(true);
-    left_panel.append(&stack_sidebar);
+    // Prefix switcher, for machines with more than one Homebrew (e.g. ARM and Intel on a Mac)
+    let installations = brew::detect_brew_installations();
+    if installations.len() > 1 {
+        let prefixes: Vec<String> = installations
+            .iter()
+            .map(|path| brew::brew_prefix_of(path).display().to_string())
+            .collect();
+        let prefix_names: Vec<&str> = prefixes.iter().map(String::as_str).collect();
+        let prefix_dropdown = gtk4::DropDown::from_strings(&prefix_names);
+        prefix_dropdown.set_tooltip_text(Some("Homebrew installation to manage"));
+
+        let current = brew::resolved_brew_program();
+        if let Some(index) = installations.iter().position(|path| *path == current) {
+            prefix_dropdown.set_selected(index as u32);
+        }
+
+        let window_for_prefix = window.clone();
+        prefix_dropdown.connect_selected_notify(move |dropdown| {
+            if let Some(path) = installations.get(dropdown.selected() as usize) {
+                if *path == brew::resolved_brew_program() {
+                    return;
+                }
+                brew::set_brew_path(Some(path.clone()));
+                settings::update(|s| s.brew_path = Some(path.display().to_string()));
+
+                // Every view and the stats belong to one prefix, so rebuild the window
+                if let Some(app) = window_for_prefix.application() {
+                    build_main_window(&app);
+                    window_for_prefix.close();
+                }
+            }
+        });
+        header_bar.pack_start(&prefix_dropdown);
+    }
+
+    // Tools menu
+    let find_owner_action = gtk4::gio::SimpleAction::new("find-owner", None);
+    let window_for_owner = window.clone();
+    find_owner_action.connect_activate(move |_, _| {
+        views::tools::show_file_owner_dialog(&window_for_owner);
+    });
+    window.add_action(&find_owner_action);
+
+    let create_formula_action = gtk4::gio::SimpleAction::new("create-formula", None);
+    let window_for_create = window.clone();
+    create_formula_action.connect_activate(move |_, _| {
+        views::tools::show_create_formula_dialog(&window_for_create);
+    });
+    window.add_action(&create_formula_action);
+
+    let install_list_action = gtk4::gio::SimpleAction::new("install-list", None);
+    let window_for_list = window.clone();
+    install_list_action.connect_activate(move |_, _| {
+        views::tools::show_install_list_dialog(&window_for_list, "");
+    });
+    window.add_action(&install_list_action);
+
+    let compare_brewfile_action = gtk4::gio::SimpleAction::new("compare-brewfile", None);
+    let window_for_compare = window.clone();
+    compare_brewfile_action.connect_activate(move |_, _| {
+        views::tools::choose_brewfile_to_compare(&window_for_compare);
+    });
+    window.add_action(&compare_brewfile_action);
+
+    let migrate_action = gtk4::gio::SimpleAction::new("migrate", None);
+    let window_for_migrate = window.clone();
+    migrate_action.connect_activate(move |_, _| {
+        views::migrate::show_migrate_dialog(&window_for_migrate, None);
+    });
+    window.add_action(&migrate_action);
+
+    let tools_menu = gtk4::gio::Menu::new();
+    tools_menu.append(Some("Install from List…"), Some("win.install-list"));
+    tools_menu.append(Some("Compare with Brewfile…"), Some("win.compare-brewfile"));
+    tools_menu.append(Some("Migrate Machine…"), Some("win.migrate"));
+    tools_menu.append(Some("Which Package Owns a File?"), Some("win.find-owner"));
+    tools_menu.append(Some("Create a Formula…"), Some("win.create-formula"));
+    tools_menu.append(Some("About Homebrew"), Some("win.brew-config"));
+
+    let brew_config_action = gtk4::gio::SimpleAction::new("brew-config", None);
+    let window_for_config = window.clone();
+    brew_config_action.connect_activate(move |_, _| {
+        views::tools::show_brew_config_dialog(&window_for_config);
+    });
+    window.add_action(&brew_config_action);
+
+    let tools_btn = gtk4::MenuButton::new();
+    tools_btn.set_label("Tools");
+    tools_btn.set_menu_model(Some(&tools_menu));
+    header_bar.pack_start(&tools_btn);
+
+    let (jobs_btn, jobs_panel) = views::jobs::create_jobs_panel();
+    header_bar.pack_start(&jobs_btn);
 
     // Stats panel
     let stats_frame = Box::new(Orientation::Vertical, 4);
@@ -250,36 +1009,244 @@ fn build_main_window(app: &Application) {
     let stat_formulae = create_stat_row(&stats_grid, 3, "Formulae:", "...");
     let stat_leaves = create_stat_row(&stats_grid, 4, "Leaves:", "...");
     let stat_taps = create_stat_row(&stats_grid, 5, "Taps:", "...");
+    let stat_disk = create_stat_row(&stats_grid, 6, "Disk:", "...");
+
+    // Only shown while Preferences names a Brewfile; clicking it lists what is missing
+    let brewfile_name = Label::new(Some("Brewfile:"));
+    brewfile_name.set_halign(gtk4::Align::Start);
+    brewfile_name.add_css_class("dim-label");
+    let brewfile_btn = Button::with_label("...");
+    brewfile_btn.add_css_class("flat");
+    brewfile_btn.set_halign(gtk4::Align::End);
+    brewfile_btn.set_hexpand(true);
+    brewfile_btn.set_tooltip_text(Some("Show what brew bundle check found"));
+    stats_grid.attach(&brewfile_name, 0, 7, 1, 1);
+    stats_grid.attach(&brewfile_btn, 1, 7, 1, 1);
 
     stats_frame.append(&stats_grid);
     left_panel.append(&stats_frame);
 
-    main_box.append(&left_panel);
-    main_box.append(&stack);
+    // Pages on top, with the jobs panel sliding up beneath them and, in a narrow window, the
+    // page switcher at the bottom
+    let content_box = Box::new(Orientation::Vertical, 0);
+    stack.set_vexpand(true);
+    content_box.append(&stack);
+    content_box.append(&jobs_panel);
+
+    let switcher_bar = adw::ViewSwitcherBar::new();
+    switcher_bar.set_stack(Some(&stack));
+    content_box.append(&switcher_bar);
 
-    window.set_child(Some(&main_box));
+    main_box.append(&left_panel);
+    main_box.append(&content_box);
+
+    let toast_overlay = adw::ToastOverlay::new();
+    toast_overlay.set_child(Some(&main_box));
+    toast_overlay.set_vexpand(true);
+
+    // Primary menu
+    let preferences_action = gtk4::gio::SimpleAction::new("preferences", None);
+    let window_for_preferences = window.clone();
+    preferences_action
+        .connect_activate(move |_, _| views::preferences::show_preferences_window(&window_for_preferences));
+    window.add_action(&preferences_action);
+
+    let about_action = gtk4::gio::SimpleAction::new("about", None);
+    let window_for_about = window.clone();
+    about_action.connect_activate(move |_, _| show_about_window(&window_for_about));
+    window.add_action(&about_action);
+
+    let primary_menu = gtk4::gio::Menu::new();
+    let window_section = gtk4::gio::Menu::new();
+    window_section.append(Some("Preferences"), Some("win.preferences"));
+    window_section.append(Some("Keyboard Shortcuts"), Some("win.show-help-overlay"));
+    window_section.append(Some("About BrewHouse"), Some("win.about"));
+    primary_menu.append_section(None, &window_section);
+    let quit_section = gtk4::gio::Menu::new();
+    quit_section.append(Some("Quit"), Some("app.quit"));
+    primary_menu.append_section(None, &quit_section);
+
+    let menu_btn = gtk4::MenuButton::new();
+    menu_btn.set_icon_name("open-menu-symbolic");
+    menu_btn.set_tooltip_text(Some("Main Menu"));
+    set_accessible_label(&menu_btn, "Main Menu");
+    menu_btn.set_menu_model(Some(&primary_menu));
+
+    header_bar.pack_end(&menu_btn);
+
+    let window_box = Box::new(Orientation::Vertical, 0);
+    window_box.append(&header_bar);
+    window_box.append(&toast_overlay);
+
+    window.set_content(Some(&window_box));
+
+    // Below this width the stats panel goes and the page switcher moves to the bottom
+    window.set_size_request(600, 400);
+    let narrow = adw::Breakpoint::new(
+        adw::BreakpointCondition::parse(&format!("max-width: {}sp", NARROW_WINDOW_WIDTH)).unwrap(),
+    );
+    narrow.add_setter(&header_bar, "title-widget", Some(&window_title.to_value()));
+    narrow.add_setter(&switcher_bar, "reveal", Some(&true.to_value()));
+    narrow.add_setter(&left_panel, "visible", Some(&false.to_value()));
+    window.add_breakpoint(narrow);
+
+    // A Brewfile, bundle or package list dropped anywhere on the window opens its import preview
+    let drop_target = gtk4::DropTarget::new(gtk4::gio::File::static_type(), gtk4::gdk::DragAction::COPY);
+    let (window_for_drop, toasts_for_drop) = (window.clone(), toast_overlay.clone());
+    drop_target.connect_drop(move |_, value, _, _| {
+        match value.get::<gtk4::gio::File>().ok().and_then(|file| file.path()) {
+            Some(path) => open_dropped_file(&window_for_drop, &toasts_for_drop, path),
+            None => false,
+        }
+    });
+    window.add_controller(drop_target);
     window.present();
 
-    // Load stats asynchronously
+    // Load stats asynchronously, and again whenever packages change
+    let stats_owner = stat_installed.clone();
+    let load_stats = move || {
+        let stat_installed = stat_installed.clone();
+        let stat_casks = stat_casks.clone();
+        let stat_outdated = stat_outdated.clone();
+        let stat_formulae = stat_formulae.clone();
+        let stat_leaves = stat_leaves.clone();
+        let stat_taps = stat_taps.clone();
+        glib::spawn_future_local(async move {
+            let result = gtk4::gio::spawn_blocking(move || {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                rt.block_on(brew::get_brew_stats())
+            })
+            .await
+            .expect("Background task failed");
+
+            if let Ok(stats) = result {
+                stat_installed.set_text(&stats.installed.to_string());
+                stat_casks.set_text(&stats.casks.to_string());
+                stat_outdated.set_text(&stats.outdated.to_string());
+                stat_formulae.set_text(&stats.formulae.to_string());
+                stat_leaves.set_text(&stats.leaves.to_string());
+                stat_taps.set_text(&stats.taps.to_string());
+            }
+        });
+    };
+    load_stats();
+    events::subscribe(&stats_owner, move |_| load_stats());
+
+    // The last `brew bundle check` of the Brewfile chosen in Preferences. Package changes make
+    // a check due; it runs once the job queue is idle, and after the check already running.
+    let brewfile_check: Rc<RefCell<Option<brew::BundleCheck>>> = Rc::default();
+    let (check_due, check_running) = (Rc::new(Cell::new(true)), Rc::new(Cell::new(false)));
+    let brewfile_check_for_check = brewfile_check.clone();
+    let (check_due_for_check, check_running_for_check) = (check_due.clone(), check_running.clone());
+    let (brewfile_name_for_check, brewfile_btn_for_check) = (brewfile_name.clone(), brewfile_btn.clone());
+    let check_brewfile_when_idle: Rc<dyn Fn()> = Rc::new(move || {
+        if !check_due_for_check.get() || check_running_for_check.get() || !jobs::is_idle() {
+            return;
+        }
+        check_due_for_check.set(false);
+        let path = settings::get().brewfile_path.map(std::path::PathBuf::from);
+        brewfile_name_for_check.set_visible(path.is_some());
+        brewfile_btn_for_check.set_visible(path.is_some());
+        let Some(path) = path else {
+            return;
+        };
+        check_running_for_check.set(true);
+        let brewfile_check = brewfile_check_for_check.clone();
+        let brewfile_btn = brewfile_btn_for_check.clone();
+        let (check_due, check_running) = (check_due_for_check.clone(), check_running_for_check.clone());
+        glib::spawn_future_local(async move {
+            let path_for_task = path.clone();
+            let result = gtk4::gio::spawn_blocking(move || {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                rt.block_on(brew::bundle_check(&path_for_task))
+            })
+            .await
+            .expect("Background task failed");
+            check_running.set(false);
+            // A check that came due meanwhile, e.g. for another Brewfile, replaces this one
+            let chosen = settings::get().brewfile_path.map(std::path::PathBuf::from);
+            if check_due.get() || chosen.as_ref() != Some(&path) {
+                check_due.set(true);
+                let _ = brewfile_btn.activate_action("win.check-brewfile", None);
+                return;
+            }
+            match result {
+                Ok(check) => {
+                    brewfile_btn.set_label(&check.label());
+                    brewfile_btn.set_tooltip_text(Some("Show what brew bundle check found"));
+                    *brewfile_check.borrow_mut() = Some(check);
+                }
+                Err(e) => {
+                    brewfile_btn.set_label("unknown");
+                    brewfile_btn.set_tooltip_text(Some(&format!("Could not check the Brewfile: {}", e.user_message())));
+                    brewfile_check.take();
+                }
+            }
+        });
+    });
+    let check_brewfile: Rc<dyn Fn()> = {
+        let (check_due, check_brewfile_when_idle) = (check_due.clone(), check_brewfile_when_idle.clone());
+        Rc::new(move || {
+            check_due.set(true);
+            check_brewfile_when_idle();
+        })
+    };
+    check_brewfile_when_idle();
+    let check_brewfile_for_events = check_brewfile.clone();
+    events::subscribe(&brewfile_btn, move |_| check_brewfile_for_events());
+    jobs::connect_changed(&brewfile_btn, move || check_brewfile_when_idle());
+    let brewfile_btn_for_timer = brewfile_btn.downgrade();
+    let check_brewfile_for_timer = check_brewfile.clone();
+    glib::timeout_add_local(BREWFILE_CHECK_INTERVAL, move || {
+        if brewfile_btn_for_timer.upgrade().is_none() {
+            return glib::ControlFlow::Break;
+        }
+        check_brewfile_for_timer();
+        glib::ControlFlow::Continue
+    });
+    // win.check-brewfile: check again, e.g. after Preferences chose another Brewfile
+    let check_brewfile_action = gtk4::gio::SimpleAction::new("check-brewfile", None);
+    check_brewfile_action.connect_activate(move |_, _| check_brewfile());
+    window.add_action(&check_brewfile_action);
+
+    let window_for_brewfile = window.clone();
+    brewfile_btn.connect_clicked(move |_| {
+        let Some(path) = settings::get().brewfile_path.map(std::path::PathBuf::from) else {
+            return;
+        };
+        views::tools::show_brewfile_check_dialog(&window_for_brewfile, path, brewfile_check.borrow().as_ref());
+    });
+
+    // Disk usage walks every keg, so it gets its own background task
     glib::spawn_future_local(async move {
         let result = gtk4::gio::spawn_blocking(move || {
             let rt = tokio::runtime::Runtime::new().unwrap();
-            rt.block_on(brew::get_brew_stats())
+            rt.block_on(brew::get_disk_usage())
         })
         .await
         .expect("Background task failed");
 
-        if let Ok(stats) = result {
-            stat_installed.set_text(&stats.installed.to_string());
-            stat_casks.set_text(&stats.casks.to_string());
-            stat_outdated.set_text(&stats.outdated.to_string());
-            stat_formulae.set_text(&stats.formulae.to_string());
-            stat_leaves.set_text(&stats.leaves.to_string());
-            stat_taps.set_text(&stats.taps.to_string());
+        match result {
+            Ok(usage) => {
+                stat_disk.set_text(&glib::format_size(usage.total()));
+                stat_disk.set_tooltip_text(Some(&format!(
+                    "Cellar: {}\nCaskroom: {}\nDownload cache: {}",
+                    glib::format_size(usage.cellar),
+                    glib::format_size(usage.caskroom),
+                    glib::format_size(usage.cache)
+                )));
+            }
+            Err(e) => {
+                stat_disk.set_text("?");
+                stat_disk.set_tooltip_text(Some(&e.user_message()));
+            }
         }
     });
 }
 
+// How often the Status panel runs `brew bundle check`, besides after every package change
+const BREWFILE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30 * 60);
+
 fn create_stat_row(grid: &gtk4::Grid, row: i32, label: &str, value: &str) -> Label {
     let name_label = Label::new(Some(label));
     name_label.set_halign(gtk4::Align::Start);
@@ -295,943 +1262,534 @@ fn create_stat_row(grid: &gtk4::Grid, row: i32, label: &str, value: &str) -> Lab
     value_label
 }
 
-// ============================================================================
-// Installed View
-// ============================================================================
-
-fn create_installed_view() -> Box {
-    let view = Box::new(Orientation::Vertical, 10);
-    view.set_margin_start(10);
-    view.set_margin_end(10);
-    view.set_margin_top(10);
-    view.set_margin_bottom(10);
-
-    // Header with status
-    let header_box = Box::new(Orientation::Horizontal, 10);
-    let header = Label::new(Some("Installed Packages"));
-    header.add_css_class("title-2");
-    header_box.append(&header);
-
-    let spinner = Spinner::new();
-    spinner.set_spinning(true);
-    header_box.append(&spinner);
-
-    let status_label = Label::new(Some("Loading..."));
-    status_label.set_hexpand(true);
-    status_label.set_halign(gtk4::Align::Start);
-    header_box.append(&status_label);
-
-    view.append(&header_box);
-
-    // Split pane: list | details
-    let paned = Paned::new(Orientation::Horizontal);
-    paned.set_vexpand(true);
-    paned.set_position(400);
-
-    // Left: package list
-    let list_scroll = ScrolledWindow::new();
-    list_scroll.set_vexpand(true);
-    let list_box = ListBox::new();
-    list_box.set_selection_mode(gtk4::SelectionMode::Single);
-    list_box.add_css_class("boxed-list");
-    list_scroll.set_child(Some(&list_box));
-
-    // Right: details panel
-    let details_box = Box::new(Orientation::Vertical, 10);
-    details_box.set_margin_start(20);
-    details_box.set_margin_end(20);
-    details_box.set_margin_top(20);
-    details_box.set_margin_bottom(20);
-    details_box.set_hexpand(true);
-
-    let details_name = Label::new(Some("Select a package"));
-    details_name.add_css_class("title-1");
-    details_name.set_halign(gtk4::Align::Start);
-    details_box.append(&details_name);
-
-    let details_version = Label::new(None);
-    details_version.set_halign(gtk4::Align::Start);
-    details_version.add_css_class("dim-label");
-    details_box.append(&details_version);
-
-    let details_desc = Label::new(None);
-    details_desc.set_halign(gtk4::Align::Start);
-    details_desc.set_wrap(true);
-    details_desc.set_max_width_chars(50);
-    details_box.append(&details_desc);
-
-    let details_homepage = Label::new(None);
-    details_homepage.set_halign(gtk4::Align::Start);
-    details_homepage.set_selectable(true);
-    details_homepage.add_css_class("dim-label");
-    details_box.append(&details_homepage);
-
-    // Uninstall button (hidden until package selected)
-    let uninstall_btn = Button::with_label("Uninstall");
-    uninstall_btn.add_css_class("destructive-action");
-    uninstall_btn.set_halign(gtk4::Align::Start);
-    uninstall_btn.set_margin_top(20);
-    uninstall_btn.set_visible(false);
-    details_box.append(&uninstall_btn);
-
-    let uninstall_status = Label::new(None);
-    uninstall_status.set_halign(gtk4::Align::Start);
-    details_box.append(&uninstall_status);
-
-    // Spacer
-    let spacer = Box::new(Orientation::Vertical, 0);
-    spacer.set_vexpand(true);
-    details_box.append(&spacer);
-
-    paned.set_start_child(Some(&list_scroll));
-    paned.set_end_child(Some(&details_box));
-    view.append(&paned);
-
-    // Store packages for lookup
-    let packages_store: Rc<RefCell<Vec<brew::Package>>> = Rc::new(RefCell::new(Vec::new()));
-
-    // Row selection handler
-    let packages_for_selection = packages_store.clone();
-    let details_name_clone = details_name.clone();
-    let details_version_clone = details_version.clone();
-    let details_desc_clone = details_desc.clone();
-    let details_homepage_clone = details_homepage.clone();
-    let uninstall_btn_clone = uninstall_btn.clone();
+thread_local! {
+    // Running jobs per stack page, so overlapping jobs keep the indicator lit
+    static BUSY_VIEWS: RefCell<HashMap<String, u32>> = RefCell::new(HashMap::new());
+}
 
-    list_box.connect_row_selected(move |_, row| {
-        if let Some(row) = row {
-            let idx = row.index() as usize;
-            let packages = packages_for_selection.borrow();
-            if let Some(pkg) = packages.get(idx) {
-                details_name_clone.set_text(&pkg.name);
-                details_version_clone.set_text(&format!("Version: {}", pkg.version.as_deref().unwrap_or("unknown")));
-                details_desc_clone.set_text(pkg.desc.as_deref().unwrap_or("No description available"));
-                if let Some(hp) = &pkg.homepage {
-                    details_homepage_clone.set_text(hp);
-                    details_homepage_clone.set_visible(true);
+/// Mark the stack page that contains `widget` as busy or idle.
+/// Busy pages are flagged as needing attention, which the view switcher marks with a dot.
+fn set_view_busy(widget: &impl IsA<gtk4::Widget>, busy: bool) {
+    let mut child = widget.clone().upcast::<gtk4::Widget>();
+    while let Some(parent) = child.parent() {
+        if let Ok(stack) = parent.clone().downcast::<adw::ViewStack>() {
+            let page = stack.page(&child);
+            let name = page.name().map(|n| n.to_string()).unwrap_or_default();
+            let running = BUSY_VIEWS.with(|views| {
+                let mut views = views.borrow_mut();
+                let count = views.entry(name).or_insert(0);
+                if busy {
+                    *count += 1;
                 } else {
-                    details_homepage_clone.set_visible(false);
+                    *count = count.saturating_sub(1);
                 }
-                uninstall_btn_clone.set_visible(true);
-            }
+                *count
+            });
+            page.set_needs_attention(running > 0);
+            return;
         }
-    });
-
-    // Uninstall button handler
-    let packages_for_uninstall = packages_store.clone();
-    let list_box_for_uninstall = list_box.clone();
-    let uninstall_status_clone = uninstall_status.clone();
-    let details_name_for_uninstall = details_name.clone();
-    let uninstall_btn_for_handler = uninstall_btn.clone();
-
-    uninstall_btn.connect_clicked(move |btn| {
-        let selected_row = list_box_for_uninstall.selected_row();
-        if let Some(row) = selected_row {
-            let idx = row.index() as usize;
-            let packages = packages_for_uninstall.borrow();
-            if let Some(pkg) = packages.get(idx) {
-                let pkg_name = pkg.name.clone();
-                let status_label = uninstall_status_clone.clone();
-                let btn_clone = btn.clone();
-                let row_clone = row.clone();
-                let list_box_clone = list_box_for_uninstall.clone();
-                let details_name_clone = details_name_for_uninstall.clone();
-                let uninstall_btn_clone = uninstall_btn_for_handler.clone();
-
-                // Show confirmation dialog
-                let parent_window = btn.root()
-                    .and_then(|r| r.downcast::<gtk4::Window>().ok());
-
-                let dialog = Window::builder()
-                    .title("Confirm Uninstall")
-                    .default_width(400)
-                    .default_height(150)
-                    .modal(true)
-                    .build();
-
-                if let Some(ref parent) = parent_window {
-                    dialog.set_transient_for(Some(parent));
-                }
-
-                let dialog_box = Box::new(Orientation::Vertical, 10);
-                dialog_box.set_margin_start(20);
-                dialog_box.set_margin_end(20);
-                dialog_box.set_margin_top(20);
-                dialog_box.set_margin_bottom(20);
+        child = parent;
+    }
+}
 
-                let title_label = Label::new(Some(&format!("Uninstall {}?", pkg_name)));
-                title_label.add_css_class("title-3");
-                dialog_box.append(&title_label);
+/// Preview importing a file dropped on the window: a Brewfile or bundle in Migrate Machine,
+/// a list of package names in Install from List. Returns false for other kinds of file; those,
+/// and files that can't be read, are reported in `toasts`.
+fn open_dropped_file(parent: &adw::ApplicationWindow, toasts: &adw::ToastOverlay, path: std::path::PathBuf) -> bool {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    if !inventory::is_import_file(&path) {
+        let message = format!("Can't import {}: drop a Brewfile, a bundle (.json) or a package list (.txt)", file_name);
+        toasts.add_toast(adw::Toast::new(&glib::markup_escape_text(&message)));
+        return false;
+    }
 
-                let detail_label = Label::new(Some(&format!(
-                    "Are you sure you want to uninstall {}?\nThis action cannot be undone.",
-                    pkg_name
-                )));
-                detail_label.set_wrap(true);
-                dialog_box.append(&detail_label);
-
-                let button_box = Box::new(Orientation::Horizontal, 10);
-                button_box.set_halign(gtk4::Align::End);
-                button_box.set_margin_top(20);
-
-                let cancel_btn = Button::with_label("Cancel");
-                let confirm_btn = Button::with_label("Uninstall");
-                confirm_btn.add_css_class("destructive-action");
-
-                button_box.append(&cancel_btn);
-                button_box.append(&confirm_btn);
-                dialog_box.append(&button_box);
-
-                dialog.set_child(Some(&dialog_box));
-
-                // Cancel button closes dialog
-                let dialog_for_cancel = dialog.clone();
-                cancel_btn.connect_clicked(move |_| {
-                    dialog_for_cancel.close();
-                });
-
-                // Confirm button triggers uninstall
-                let dialog_for_confirm = dialog.clone();
-                let pkg_name_for_dialog = pkg_name.clone();
-                confirm_btn.connect_clicked(move |_| {
-                    dialog_for_confirm.close();
-
-                    let pkg_name = pkg_name_for_dialog.clone();
-                    let status_label = status_label.clone();
-                    let btn_clone = btn_clone.clone();
-                    let row_clone = row_clone.clone();
-                    let list_box_clone = list_box_clone.clone();
-                    let details_name_clone = details_name_clone.clone();
-                    let uninstall_btn_clone = uninstall_btn_clone.clone();
-
-                    btn_clone.set_sensitive(false);
-                    status_label.set_text("Uninstalling...");
-
-                    glib::spawn_future_local(async move {
-                        let result = gtk4::gio::spawn_blocking(move || {
-                            let rt = tokio::runtime::Runtime::new().unwrap();
-                            rt.block_on(brew::uninstall_package(&pkg_name))
-                        })
-                        .await
-                        .expect("Background task failed");
-
-                        match result {
-                            Ok(_) => {
-                                status_label.set_text("Uninstalled successfully!");
-                                list_box_clone.remove(&row_clone);
-                                details_name_clone.set_text("Package uninstalled");
-                                uninstall_btn_clone.set_visible(false);
-                            }
-                            Err(e) => {
-                                status_label.set_text(&format!("Error: {}", brew::sanitize_error(&e.to_string())));
-                                btn_clone.set_sensitive(true);
-                            }
-                        }
-                    });
-                });
-
-                dialog.present();
+    let (parent, toasts) = (parent.clone(), toasts.clone());
+    glib::spawn_future_local(async move {
+        let path_for_task = path.clone();
+        let text = gtk4::gio::spawn_blocking(move || std::fs::read_to_string(path_for_task))
+            .await
+            .expect("Background task failed");
+        match text {
+            Ok(text) if text.trim_start().starts_with('{') || !inventory::parse_brewfile(&text).is_empty() => {
+                views::migrate::show_migrate_dialog(&parent, Some((&path, text)));
+            }
+            Ok(text) => views::tools::show_install_list_dialog(&parent, &text),
+            Err(e) => {
+                let message = format!("Could not read {}: {}", file_name, e);
+                toasts.add_toast(adw::Toast::new(&glib::markup_escape_text(&message)));
             }
         }
     });
+    true
+}
 
-    // Load packages async
-    let list_box_clone = list_box.clone();
-    let spinner_clone = spinner.clone();
-    let status_label_clone = status_label.clone();
-    let packages_store_clone = packages_store.clone();
+const REPOSITORY_URL: &str = "https://github.com/dlbradford/brewhouse";
 
+/// About BrewHouse, with the Homebrew version and prefix filled in once `brew --version` answers
+fn show_about_window(parent: &impl IsA<Window>) {
+    let about = adw::AboutWindow::builder()
+        .transient_for(parent)
+        .modal(true)
+        .application_name("BrewHouse")
+        .application_icon("system-software-install")
+        .version(env!("CARGO_PKG_VERSION"))
+        .comments("A graphical interface for Homebrew")
+        .website(REPOSITORY_URL)
+        .issue_url(format!("{}/issues", REPOSITORY_URL))
+        .build();
+    about.add_acknowledgement_section(
+        Some("Built on"),
+        &[
+            "Homebrew https://brew.sh",
+            "GTK https://gtk.org",
+            "libadwaita https://gnome.pages.gitlab.gnome.org/libadwaita/",
+        ],
+    );
+    about.present();
+
+    let about_clone = about.clone();
     glib::spawn_future_local(async move {
         let result = gtk4::gio::spawn_blocking(move || {
             let rt = tokio::runtime::Runtime::new().unwrap();
-            rt.block_on(brew::get_installed_packages())
+            rt.block_on(brew::get_brew_version())
         })
         .await
         .expect("Background task failed");
 
-        match result {
-            Ok(packages) => {
-                spinner_clone.set_spinning(false);
-                spinner_clone.set_visible(false);
-                status_label_clone.set_text(&format!("{} packages", packages.len()));
-
-                for package in &packages {
-                    let row = create_package_row(&package.name, package.version.as_deref(), package.desc.as_deref());
-                    list_box_clone.append(&row);
-                }
-                *packages_store_clone.borrow_mut() = packages;
-            }
-            Err(e) => {
-                spinner_clone.set_spinning(false);
-                spinner_clone.set_visible(false);
-                status_label_clone.set_text(&format!("Error: {}", brew::sanitize_error(&e.to_string())));
-            }
-        }
+        let prefix = brew::brew_prefix_of(&brew::resolved_brew_program());
+        let homebrew = match result {
+            Ok(version) => format!("Homebrew {} at {}", version, prefix.display()),
+            Err(e) => format!("Homebrew at {}: {}", prefix.display(), e.user_message()),
+        };
+        about_clone.set_comments(&format!("A graphical interface for Homebrew\n\n{}", homebrew));
+        about_clone.set_debug_info(&format!("BrewHouse {}\n{}\n", env!("CARGO_PKG_VERSION"), homebrew));
     });
-
-    view
 }
 
 // ============================================================================
-// Browse View
+// Embedded Terminal
 // ============================================================================
 
-fn create_browse_view() -> Box {
-    let view = Box::new(Orientation::Vertical, 10);
-    view.set_margin_start(10);
-    view.set_margin_end(10);
-    view.set_margin_top(10);
-    view.set_margin_bottom(10);
+/// Run a brew command in an embedded terminal, for operations that need user
+/// input (sudo prompts for casks, license acceptances) which the captured-output
+/// runner can't provide. `args` are what the captured runner would pass brew, e.g. from
+/// `brew::install_args`. The command is queued like any other job, and under Simulate Only
+/// only previews what it would do. `on_exit` receives whether the command succeeded, unless
+/// it was only a preview.
+#[cfg(feature = "terminal")]
+fn show_terminal_dialog<F: Fn(bool) + 'static>(
+    parent: &impl IsA<gtk4::Widget>,
+    operation: Operation,
+    package: Option<&str>,
+    args: Vec<String>,
+    on_exit: F,
+) {
+    let title = match package {
+        Some(package) => format!("{} {} in Terminal", operation.label(), package),
+        None => format!("{} in Terminal", operation.label()),
+    };
+    let parent = parent.clone().upcast::<gtk4::Widget>();
+    let package = package.map(str::to_string);
+    let job = async move { run_in_terminal(&parent, operation, package.as_deref(), &args, on_exit).await };
+    jobs::enqueue(title, job, || {});
+}
 
-    // Search bar
-    let search_box = Box::new(Orientation::Horizontal, 10);
-    let search_entry = SearchEntry::new();
-    search_entry.set_placeholder_text(Some("Search packages..."));
-    search_entry.set_hexpand(true);
-    search_box.append(&search_entry);
-
-    let search_spinner = Spinner::new();
-    search_box.append(&search_spinner);
-
-    let search_status = Label::new(Some("Enter a search term"));
-    search_status.add_css_class("dim-label");
-    search_box.append(&search_status);
-
-    view.append(&search_box);
-
-    // Split pane
-    let paned = Paned::new(Orientation::Horizontal);
-    paned.set_vexpand(true);
-    paned.set_position(400);
-
-    // Left: results list
-    let list_scroll = ScrolledWindow::new();
-    list_scroll.set_vexpand(true);
-    let list_box = ListBox::new();
-    list_box.set_selection_mode(gtk4::SelectionMode::Single);
-    list_box.add_css_class("boxed-list");
-    list_scroll.set_child(Some(&list_box));
-
-    // Right: details
-    let details_box = Box::new(Orientation::Vertical, 10);
-    details_box.set_margin_start(20);
-    details_box.set_margin_end(20);
-    details_box.set_margin_top(20);
-
-    let details_name = Label::new(Some("Select a package"));
-    details_name.add_css_class("title-1");
-    details_name.set_halign(gtk4::Align::Start);
-    details_box.append(&details_name);
-
-    let details_version = Label::new(None);
-    details_version.set_halign(gtk4::Align::Start);
-    details_version.add_css_class("dim-label");
-    details_box.append(&details_version);
-
-    let details_desc = Label::new(None);
-    details_desc.set_halign(gtk4::Align::Start);
-    details_desc.set_wrap(true);
-    details_desc.set_max_width_chars(50);
-    details_box.append(&details_desc);
-
-    let details_homepage = Label::new(None);
-    details_homepage.set_halign(gtk4::Align::Start);
-    details_homepage.set_selectable(true);
-    details_homepage.add_css_class("dim-label");
-    details_box.append(&details_homepage);
-
-    // Dependencies section
-    let deps_header = Label::new(Some("Dependencies:"));
-    deps_header.set_halign(gtk4::Align::Start);
-    deps_header.add_css_class("heading");
-    deps_header.set_margin_top(15);
-    deps_header.set_visible(false);
-    details_box.append(&deps_header);
-
-    let deps_list = Label::new(None);
-    deps_list.set_halign(gtk4::Align::Start);
-    deps_list.set_wrap(true);
-    deps_list.set_max_width_chars(50);
-    deps_list.add_css_class("dim-label");
-    deps_list.add_css_class("caption");
-    deps_list.set_visible(false);
-    details_box.append(&deps_list);
-
-    // Build dependencies section
-    let build_deps_header = Label::new(Some("Build Dependencies:"));
-    build_deps_header.set_halign(gtk4::Align::Start);
-    build_deps_header.add_css_class("heading");
-    build_deps_header.set_margin_top(10);
-    build_deps_header.set_visible(false);
-    details_box.append(&build_deps_header);
-
-    let build_deps_list = Label::new(None);
-    build_deps_list.set_halign(gtk4::Align::Start);
-    build_deps_list.set_wrap(true);
-    build_deps_list.set_max_width_chars(50);
-    build_deps_list.add_css_class("dim-label");
-    build_deps_list.add_css_class("caption");
-    build_deps_list.set_visible(false);
-    details_box.append(&build_deps_list);
-
-    // Install button
-    let install_btn = Button::with_label("Install");
-    install_btn.add_css_class("suggested-action");
-    install_btn.set_halign(gtk4::Align::Start);
-    install_btn.set_margin_top(20);
-    install_btn.set_visible(false);
-    details_box.append(&install_btn);
-
-    let install_status = Label::new(None);
-    install_status.set_halign(gtk4::Align::Start);
-    details_box.append(&install_status);
-
-    paned.set_start_child(Some(&list_scroll));
-    paned.set_end_child(Some(&details_box));
-    view.append(&paned);
-
-    // Store search results
-    let results_store: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(Vec::new()));
-
-    // Search handler
-    let list_box_for_search = list_box.clone();
-    let search_spinner_clone = search_spinner.clone();
-    let search_status_clone = search_status.clone();
-    let results_store_clone = results_store.clone();
-    let details_name_reset = details_name.clone();
-    let details_version_reset = details_version.clone();
-    let details_desc_reset = details_desc.clone();
-    let install_btn_reset = install_btn.clone();
-
-    search_entry.connect_activate(move |entry| {
-        let query = entry.text().to_string();
-        eprintln!("Search activated with query: '{}'", query);
-        if query.is_empty() {
-            eprintln!("Empty query, returning");
-            return;
+/// The job `show_terminal_dialog` queues: open the terminal and wait for brew to exit
+#[cfg(feature = "terminal")]
+async fn run_in_terminal(
+    parent: &gtk4::Widget,
+    operation: Operation,
+    package: Option<&str>,
+    args: &[String],
+    on_exit: impl Fn(bool),
+) -> jobs::JobResult {
+    use vte4::prelude::*;
+
+    // Nothing is recorded in History for a preview, as for captured operations
+    let simulated = brew::is_dry_run();
+    let args = match brew::terminal_args(args, simulated) {
+        Ok(args) => args,
+        Err(e) => {
+            show_error_dialog(parent, &format!("Could not run {}", operation.label().to_lowercase()), &e);
+            return Err(e.to_string());
         }
+    };
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let command = brew::command_line(args[0], &args[1..]);
 
-        // Clear previous results
-        while let Some(child) = list_box_for_search.first_child() {
-            list_box_for_search.remove(&child);
-        }
-        details_name_reset.set_text("Searching...");
-        details_version_reset.set_text("");
-        details_desc_reset.set_text("");
-        install_btn_reset.set_visible(false);
+    let dialog = Window::builder()
+        .title("BrewHouse - Terminal")
+        .default_width(800)
+        .default_height(500)
+        .modal(true)
+        .build();
 
-        search_spinner_clone.set_spinning(true);
-        search_status_clone.set_text("Searching...");
+    if let Some(parent_window) = parent.root().and_then(|r| r.downcast::<gtk4::Window>().ok()) {
+        dialog.set_transient_for(Some(&parent_window));
+    }
 
-        let list_box_clone = list_box_for_search.clone();
-        let spinner_clone = search_spinner_clone.clone();
-        let status_clone = search_status_clone.clone();
-        let results_clone = results_store_clone.clone();
-        let details_name_clone = details_name_reset.clone();
+    let vbox = Box::new(Orientation::Vertical, 10);
+    vbox.set_margin_start(20);
+    vbox.set_margin_end(20);
+    vbox.set_margin_top(20);
+    vbox.set_margin_bottom(20);
 
-        eprintln!("Spawning search task...");
-        glib::spawn_future_local(async move {
-            eprintln!("Search task started for query");
-            let result = gtk4::gio::spawn_blocking(move || {
-                eprintln!("Running brew search...");
-                let rt = tokio::runtime::Runtime::new().unwrap();
-                rt.block_on(brew::search_packages(&query))
-            })
-            .await
-            .expect("Background task failed");
+    let status_label = Label::new(Some(&format!("Running: {}", command)));
+    status_label.add_css_class("title-3");
+    status_label.set_halign(gtk4::Align::Start);
+    vbox.append(&status_label);
 
-            eprintln!("Search completed: {:?}", result.as_ref().map(|v| v.len()));
-            spinner_clone.set_spinning(false);
+    let terminal = vte4::Terminal::new();
+    terminal.set_vexpand(true);
+    terminal.set_hexpand(true);
+    vbox.append(&terminal);
 
-            match result {
-                Ok(packages) => {
-                    eprintln!("Found {} packages", packages.len());
-                    status_clone.set_text(&format!("{} results", packages.len()));
-                    details_name_clone.set_text("Select a package");
-
-                    for pkg_name in &packages {
-                        let row = create_simple_row(pkg_name);
-                        list_box_clone.append(&row);
-                    }
-                    *results_clone.borrow_mut() = packages;
-                }
-                Err(e) => {
-                    status_clone.set_text(&format!("Error: {}", brew::sanitize_error(&e.to_string())));
-                }
-            }
-        });
-    });
+    let close_btn = Button::with_label("Close");
+    close_btn.set_halign(gtk4::Align::End);
+    vbox.append(&close_btn);
 
-    // Row selection - fetch package info
-    let results_for_selection = results_store.clone();
-    let details_name_clone = details_name.clone();
-    let details_version_clone = details_version.clone();
-    let details_desc_clone = details_desc.clone();
-    let details_homepage_clone = details_homepage.clone();
-    let deps_header_clone = deps_header.clone();
-    let deps_list_clone = deps_list.clone();
-    let build_deps_header_clone = build_deps_header.clone();
-    let build_deps_list_clone = build_deps_list.clone();
-    let install_btn_clone = install_btn.clone();
-    let install_status_clone = install_status.clone();
+    dialog.set_child(Some(&vbox));
 
-    list_box.connect_row_selected(move |_, row| {
-        if let Some(row) = row {
-            let idx = row.index() as usize;
-            let results = results_for_selection.borrow();
-            if let Some(pkg_name) = results.get(idx) {
-                let pkg_name = pkg_name.clone();
-                let name_label = details_name_clone.clone();
-                let version_label = details_version_clone.clone();
-                let desc_label = details_desc_clone.clone();
-                let homepage_label = details_homepage_clone.clone();
-                let deps_header = deps_header_clone.clone();
-                let deps_list = deps_list_clone.clone();
-                let build_deps_header = build_deps_header_clone.clone();
-                let build_deps_list = build_deps_list_clone.clone();
-                let btn = install_btn_clone.clone();
-                let status = install_status_clone.clone();
-
-                name_label.set_text("Loading...");
-                version_label.set_text("");
-                desc_label.set_text("");
-                homepage_label.set_text("");
-                deps_header.set_visible(false);
-                deps_list.set_visible(false);
-                build_deps_header.set_visible(false);
-                build_deps_list.set_visible(false);
-                btn.set_visible(false);
-                status.set_text("");
-
-                glib::spawn_future_local(async move {
-                    let result = gtk4::gio::spawn_blocking(move || {
-                        let rt = tokio::runtime::Runtime::new().unwrap();
-                        rt.block_on(brew::get_package_info(&pkg_name))
-                    })
-                    .await
-                    .expect("Background task failed");
-
-                    match result {
-                        Ok(info) => {
-                            name_label.set_text(&info.name);
-                            version_label.set_text(&format!("Version: {}", info.versions.stable));
-                            desc_label.set_text(info.desc.as_deref().unwrap_or("No description"));
-                            if let Some(hp) = &info.homepage {
-                                homepage_label.set_text(hp);
-                                homepage_label.set_visible(true);
-                            } else {
-                                homepage_label.set_visible(false);
-                            }
-
-                            // Show runtime dependencies
-                            if let Some(deps) = &info.dependencies {
-                                if !deps.is_empty() {
-                                    deps_list.set_text(&deps.join(", "));
-                                    deps_header.set_visible(true);
-                                    deps_list.set_visible(true);
-                                }
-                            }
-
-                            // Show build dependencies
-                            if let Some(deps) = &info.build_dependencies {
-                                if !deps.is_empty() {
-                                    build_deps_list.set_text(&deps.join(", "));
-                                    build_deps_header.set_visible(true);
-                                    build_deps_list.set_visible(true);
-                                }
-                            }
-
-                            btn.set_visible(true);
-                        }
-                        Err(e) => {
-                            name_label.set_text("Error loading package");
-                            desc_label.set_text(&brew::sanitize_error(&e.to_string()));
-                        }
-                    }
-                });
-            }
-        }
+    let dialog_for_close = dialog.clone();
+    close_btn.connect_clicked(move |_| {
+        dialog_for_close.close();
     });
 
-    // Install button handler
-    let results_for_install = results_store.clone();
-    let list_box_for_install = list_box.clone();
-    let install_status_for_handler = install_status.clone();
-
-    install_btn.connect_clicked(move |btn| {
-        let selected_row = list_box_for_install.selected_row();
-        if let Some(row) = selected_row {
-            let idx = row.index() as usize;
-            let results = results_for_install.borrow();
-            if let Some(pkg_name) = results.get(idx) {
-                let pkg_name = pkg_name.clone();
-                let status = install_status_for_handler.clone();
-                let btn_clone = btn.clone();
-
-                btn.set_sensitive(false);
-                status.set_text("Installing...");
-
-                glib::spawn_future_local(async move {
-                    let result = gtk4::gio::spawn_blocking(move || {
-                        let rt = tokio::runtime::Runtime::new().unwrap();
-                        rt.block_on(brew::install_package(&pkg_name))
-                    })
-                    .await
-                    .expect("Background task failed");
-
-                    match result {
-                        Ok(_) => {
-                            status.set_text("Installed successfully!");
-                        }
-                        Err(e) => {
-                            status.set_text(&format!("Error: {}", brew::sanitize_error(&e.to_string())));
-                            btn_clone.set_sensitive(true);
-                        }
-                    }
-                });
-            }
+    // brew's exit status; closing the window before it exits ends the job as failed
+    let (exit_tx, exit_rx) = tokio::sync::oneshot::channel::<i32>();
+    let exit_tx = Rc::new(RefCell::new(Some(exit_tx)));
+    let send_exit = move |status: i32| {
+        if let Some(tx) = exit_tx.borrow_mut().take() {
+            let _ = tx.send(status);
         }
+    };
+    let send_exit_for_child = send_exit.clone();
+    terminal.connect_child_exited(move |_, status| send_exit_for_child(status));
+    let send_exit_for_close = send_exit.clone();
+    dialog.connect_close_request(move |_| {
+        send_exit_for_close(-1);
+        glib::Propagation::Proceed
     });
 
-    view
+    let started = Instant::now();
+    let brew_argv = brew::brew_argv();
+    let mut argv: Vec<&str> = brew_argv.iter().map(String::as_str).collect();
+    argv.extend_from_slice(&args);
+    let env: Vec<String> = runner::env().iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+    let env: Vec<&str> = env.iter().map(String::as_str).collect();
+
+    let status_for_spawn = status_label.clone();
+    terminal.spawn_async(
+        vte4::PtyFlags::DEFAULT,
+        None,
+        &argv,
+        &env,
+        glib::SpawnFlags::SEARCH_PATH,
+        || {},
+        -1,
+        None::<&gtk4::gio::Cancellable>,
+        move |result| {
+            if let Err(e) = result {
+                status_for_spawn.set_text(&format!("Error: {}", brew::sanitize_error(&e.to_string())));
+                send_exit(-1);
+            }
+        },
+    );
+
+    dialog.present();
+    terminal.grab_focus();
+
+    let success = exit_rx.await == Ok(0);
+    let result = match (success, simulated) {
+        (true, true) => {
+            status_label.set_text("Preview finished; Simulate Only is on, so nothing was changed");
+            Ok("Previewed in the embedded terminal")
+        }
+        (true, false) => {
+            status_label.set_text("Finished successfully");
+            Ok("Ran interactively in the embedded terminal")
+        }
+        (false, _) => {
+            status_label.set_text("Command failed (see output above)");
+            Err("Command failed in the embedded terminal")
+        }
+    };
+    if !simulated {
+        history::record(operation, package, result, started.elapsed());
+        on_exit(success);
+    }
+    jobs::outcome(&result)
 }
 
 // ============================================================================
-// Updates View
+// Helper functions
 // ============================================================================
 
-fn create_updates_view() -> Box {
-    let view = Box::new(Orientation::Vertical, 10);
-    view.set_margin_start(10);
-    view.set_margin_end(10);
-    view.set_margin_top(10);
-    view.set_margin_bottom(10);
-
-    // Header
-    let header_box = Box::new(Orientation::Horizontal, 10);
-    let header = Label::new(Some("Available Updates"));
-    header.add_css_class("title-2");
-    header_box.append(&header);
-
-    let spinner = Spinner::new();
-    spinner.set_spinning(true);
-    header_box.append(&spinner);
-
-    let status_label = Label::new(Some("Checking for updates..."));
-    status_label.set_hexpand(true);
-    status_label.set_halign(gtk4::Align::Start);
-    header_box.append(&status_label);
-
-    // Upgrade Selected button
-    let upgrade_selected_btn = Button::with_label("Upgrade Selected");
-    upgrade_selected_btn.add_css_class("suggested-action");
-    upgrade_selected_btn.set_visible(false);
-    header_box.append(&upgrade_selected_btn);
-
-    // Upgrade All button
-    let upgrade_all_btn = Button::with_label("Upgrade All");
-    upgrade_all_btn.set_visible(false);
-    header_box.append(&upgrade_all_btn);
-
-    view.append(&header_box);
-
-    // List of outdated packages
-    let scroll = ScrolledWindow::new();
-    scroll.set_vexpand(true);
-    let list_box = ListBox::new();
-    list_box.set_selection_mode(gtk4::SelectionMode::None);
-    list_box.add_css_class("boxed-list");
-    scroll.set_child(Some(&list_box));
-    view.append(&scroll);
-
-    // Upgrade status
-    let upgrade_status = Label::new(None);
-    upgrade_status.set_halign(gtk4::Align::Start);
-    view.append(&upgrade_status);
-
-    // Store checkboxes for access
-    let checkboxes: Rc<RefCell<Vec<(String, CheckButton)>>> = Rc::new(RefCell::new(Vec::new()));
-
-    // Load outdated packages
-    let list_box_clone = list_box.clone();
-    let spinner_clone = spinner.clone();
-    let status_label_clone = status_label.clone();
-    let upgrade_all_btn_clone = upgrade_all_btn.clone();
-    let upgrade_selected_btn_clone = upgrade_selected_btn.clone();
-    let checkboxes_clone = checkboxes.clone();
-
-    glib::spawn_future_local(async move {
-        let result = gtk4::gio::spawn_blocking(move || {
-            let rt = tokio::runtime::Runtime::new().unwrap();
-            rt.block_on(brew::get_outdated_packages())
-        })
-        .await
-        .expect("Background task failed");
+// Narrowest the list of a split view gets, in pixels, and how far from its edge a drag resizes it
+const MIN_LIST_WIDTH: f64 = 280.0;
+const SPLIT_HANDLE_WIDTH: f64 = 6.0;
 
-        spinner_clone.set_spinning(false);
-        spinner_clone.set_visible(false);
+/// Refresh button for a page header, running the page's `action` (e.g. "installed.refresh")
+fn create_refresh_button(action: &str) -> Button {
+    let btn = Button::with_label("Refresh");
+    btn.set_tooltip_text(Some("Ask brew again (F5)"));
+    btn.set_action_name(Some(action));
+    btn
+}
 
-        match result {
-            Ok(packages) => {
-                if packages.is_empty() {
-                    status_label_clone.set_text("All packages are up to date!");
-                } else {
-                    status_label_clone.set_text(&format!("{} updates available", packages.len()));
-                    upgrade_all_btn_clone.set_visible(true);
-                    upgrade_selected_btn_clone.set_visible(true);
-
-                    let mut cbs = checkboxes_clone.borrow_mut();
-                    for pkg_name in packages {
-                        let (row, checkbox) = create_update_row_with_checkbox(&pkg_name);
-                        cbs.push((pkg_name, checkbox));
-                        list_box_clone.append(&row);
-                    }
-                }
-            }
-            Err(e) => {
-                status_label_clone.set_text(&format!("Error: {}", brew::sanitize_error(&e.to_string())));
-            }
+/// The list and details of a page side by side; dragging the edge of the list resizes it,
+/// and the width is saved under `key`. When there isn't room for both, one shows at a time:
+/// selecting a row in `list_box` opens the details and a back button returns.
+fn create_split_view(
+    key: &str,
+    list: &impl IsA<gtk4::Widget>,
+    list_box: &ListBox,
+    details: &impl IsA<gtk4::Widget>,
+) -> adw::BreakpointBin {
+    use adw::prelude::BreakpointBinExt;
+
+    let split_view = adw::NavigationSplitView::new();
+    split_view.set_min_sidebar_width(MIN_LIST_WIDTH);
+    split_view.set_max_sidebar_width(f64::MAX);
+    let fraction = settings::get().split_positions.get(key).copied().unwrap_or(0.4);
+    split_view.set_sidebar_width_fraction(fraction.clamp(0.1, 0.9));
+    let list_page = adw::NavigationPage::new(list, "List");
+    split_view.set_sidebar(Some(&list_page));
+
+    // The list's edge can be dragged, keeping both sides at least MIN_LIST_WIDTH wide
+    let near_edge = {
+        let split_view = split_view.clone();
+        let list_page = list_page.clone();
+        move |x: f64| !split_view.is_collapsed() && (x - list_page.width() as f64).abs() <= SPLIT_HANDLE_WIDTH
+    };
+    let motion = gtk4::EventControllerMotion::new();
+    let split_view_for_motion = split_view.clone();
+    let near_edge_for_motion = near_edge.clone();
+    motion.connect_motion(move |_, x, _| {
+        split_view_for_motion.set_cursor_from_name(near_edge_for_motion(x).then_some("col-resize"));
+    });
+    split_view.add_controller(motion);
+
+    let drag = gtk4::GestureDrag::new();
+    drag.set_propagation_phase(gtk4::PropagationPhase::Capture);
+    let drag_start_width = Rc::new(Cell::new(0.0));
+    let list_page_for_drag = list_page.clone();
+    let start_width = drag_start_width.clone();
+    drag.connect_drag_begin(move |gesture, x, _| {
+        if near_edge(x) {
+            start_width.set(list_page_for_drag.width() as f64);
+            gesture.set_state(gtk4::EventSequenceState::Claimed);
+        } else {
+            gesture.set_state(gtk4::EventSequenceState::Denied);
         }
     });
-
-    // Upgrade Selected handler
-    let checkboxes_for_selected = checkboxes.clone();
-    let upgrade_status_selected = upgrade_status.clone();
-    let list_box_for_selected = list_box.clone();
-    let status_for_selected = status_label.clone();
-    let upgrade_all_for_selected = upgrade_all_btn.clone();
-    let upgrade_selected_for_handler = upgrade_selected_btn.clone();
-
-    upgrade_selected_btn.connect_clicked(move |btn| {
-        // Debug: show all checkbox states
-        {
-            let cbs = checkboxes_for_selected.borrow();
-            eprintln!("Checkbox states ({} total):", cbs.len());
-            for (name, cb) in cbs.iter() {
-                eprintln!("  {} = {}", name, cb.is_active());
-            }
+    let split_view_for_drag = split_view.clone();
+    drag.connect_drag_update(move |_, offset_x, _| {
+        let total = split_view_for_drag.width() as f64;
+        if total < 2.0 * MIN_LIST_WIDTH {
+            return;
         }
-
-        let selected: Vec<String> = checkboxes_for_selected
-            .borrow()
-            .iter()
-            .filter(|(_, cb)| cb.is_active())
-            .map(|(name, _)| name.clone())
-            .collect();
-
-        eprintln!("Selected for upgrade: {:?}", selected);
-
-        if selected.is_empty() {
-            upgrade_status_selected.set_text("No packages selected");
+        let width = (drag_start_width.get() + offset_x).clamp(MIN_LIST_WIDTH, total - MIN_LIST_WIDTH);
+        split_view_for_drag.set_sidebar_width_fraction(width / total);
+    });
+    let split_view_for_end = split_view.clone();
+    let key = key.to_string();
+    drag.connect_drag_end(move |_, _, _| {
+        // Also ends clicks that weren't on the edge, which leave the width as it was
+        let fraction = split_view_for_end.sidebar_width_fraction();
+        if settings::get().split_positions.get(&key) == Some(&fraction) {
             return;
         }
-
-        btn.set_sensitive(false);
-        let total = selected.len();
-
-        let status = upgrade_status_selected.clone();
-        let list_box = list_box_for_selected.clone();
-        let header_status = status_for_selected.clone();
-        let btn_clone = btn.clone();
-        let checkboxes_clone = checkboxes_for_selected.clone();
-        let upgrade_all_clone = upgrade_all_for_selected.clone();
-        let upgrade_selected_clone = upgrade_selected_for_handler.clone();
-
-        // Upgrade packages one by one with progress updates
-        glib::spawn_future_local(async move {
-            let mut succeeded = Vec::new();
-            let mut failed: Vec<(String, String)> = Vec::new();
-
-            for (i, pkg) in selected.iter().enumerate() {
-                status.set_text(&format!("Upgrading {} ({}/{})...", pkg, i + 1, total));
-
-                let pkg_clone = pkg.clone();
-                let result = gtk4::gio::spawn_blocking(move || {
-                    let rt = tokio::runtime::Runtime::new().unwrap();
-                    rt.block_on(brew::upgrade_packages(Some(&pkg_clone)))
-                })
-                .await
-                .expect("Background task failed");
-
-                match result {
-                    Ok(_) => succeeded.push(pkg.clone()),
-                    Err(e) => failed.push((pkg.clone(), e.to_string())),
-                }
-            }
-
-            // Clear the list UI
-            while let Some(child) = list_box.first_child() {
-                list_box.remove(&child);
-            }
-
-            // Get remaining packages (failed ones + ones not attempted)
-            let remaining: Vec<String> = {
-                let cbs = checkboxes_clone.borrow();
-                cbs.iter()
-                    .filter(|(name, _)| !succeeded.contains(name))
-                    .map(|(name, _)| name.clone())
-                    .collect()
-            };
-
-            // Show results
-            if failed.is_empty() {
-                status.set_text(&format!("{} packages upgraded successfully!", succeeded.len()));
-            } else {
-                let failed_names: Vec<&str> = failed.iter().map(|(n, _)| n.as_str()).collect();
-                let error_msg = failed.iter().map(|(n, e)| format!("{}: {}", n, e)).collect::<Vec<_>>().join("\n");
-                status.set_text(&format!(
-                    "{} upgraded, {} failed: {}",
-                    succeeded.len(),
-                    failed.len(),
-                    failed_names.join(", ")
-                ));
-                eprintln!("Upgrade errors:\n{}", error_msg);
-            }
-
-            // Rebuild checkboxes store and UI
-            {
-                let mut cbs = checkboxes_clone.borrow_mut();
-                cbs.clear();
-
-                if remaining.is_empty() {
-                    header_status.set_text("All packages are up to date!");
-                    upgrade_all_clone.set_visible(false);
-                    upgrade_selected_clone.set_visible(false);
-                } else {
-                    header_status.set_text(&format!("{} updates available", remaining.len()));
-                    for name in remaining {
-                        let (row, new_cb) = create_update_row_with_checkbox(&name);
-                        list_box.append(&row);
-                        cbs.push((name, new_cb));
-                    }
-                }
-            }
-            btn_clone.set_sensitive(true);
+        settings::update(|s| {
+            s.split_positions.insert(key.clone(), fraction);
         });
     });
+    split_view.add_controller(drag);
+
+    let details_page = Box::new(Orientation::Vertical, 0);
+    details_page.set_hexpand(true);
+    let back_btn = Button::from_icon_name("go-previous-symbolic");
+    back_btn.add_css_class("flat");
+    back_btn.set_tooltip_text(Some("Back to the List"));
+    set_accessible_label(&back_btn, "Back to the List");
+    back_btn.set_halign(gtk4::Align::Start);
+    back_btn.set_margin_start(10);
+    back_btn.set_margin_top(10);
+    split_view.bind_property("collapsed", &back_btn, "visible").sync_create().build();
+    let split_view_for_back = split_view.clone();
+    back_btn.connect_clicked(move |_| {
+        split_view_for_back.set_show_content(false);
+    });
+    details_page.append(&back_btn);
+    details_page.append(details);
+    split_view.set_content(Some(&adw::NavigationPage::new(&details_page, "Details")));
 
-    // Upgrade All handler
-    let upgrade_status_clone = upgrade_status.clone();
-    let list_box_for_upgrade = list_box.clone();
-    let status_for_upgrade = status_label.clone();
-    let upgrade_selected_for_all = upgrade_selected_btn.clone();
+    let split_view_for_selection = split_view.clone();
+    list_box.connect_row_selected(move |_, row| {
+        if row.is_some() && split_view_for_selection.is_collapsed() {
+            split_view_for_selection.set_show_content(true);
+        }
+    });
 
-    upgrade_all_btn.connect_clicked(move |btn| {
-        btn.set_sensitive(false);
-        upgrade_status_clone.set_text("Upgrading all packages...");
+    // Collapse once the page is too narrow for the list and details side by side
+    let bin = adw::BreakpointBin::new();
+    bin.set_size_request(MIN_LIST_WIDTH as i32, 200);
+    bin.set_vexpand(true);
+    let collapse = adw::Breakpoint::new(
+        adw::BreakpointCondition::parse(&format!("max-width: {}px", 2.0 * MIN_LIST_WIDTH + 120.0)).unwrap(),
+    );
+    collapse.add_setter(&split_view, "collapsed", Some(&true.to_value()));
+    bin.add_breakpoint(collapse);
+    bin.set_child(Some(&split_view));
+    bin
+}
 
-        let status = upgrade_status_clone.clone();
-        let list_box = list_box_for_upgrade.clone();
-        let header_status = status_for_upgrade.clone();
-        let btn_clone = btn.clone();
-        let upgrade_selected_clone = upgrade_selected_for_all.clone();
+/// Button that copies the equivalent terminal command to the clipboard
+fn create_copy_command_button() -> Button {
+    let btn = Button::with_label("Copy Command");
+    btn.set_tooltip_text(Some("Copy the equivalent brew command to the clipboard"));
+    btn
+}
 
-        glib::spawn_future_local(async move {
-            let result = gtk4::gio::spawn_blocking(move || {
-                let rt = tokio::runtime::Runtime::new().unwrap();
-                rt.block_on(brew::upgrade_packages(None))
-            })
-            .await
-            .expect("Background task failed");
+/// Copy text to the clipboard and briefly confirm it on the button
+fn copy_to_clipboard(btn: &Button, text: &str) {
+    btn.clipboard().set_text(text);
+    let original_label = btn.label().map(|l| l.to_string()).unwrap_or_default();
+    if original_label == "Copied!" {
+        return;
+    }
+    btn.set_label("Copied!");
 
-            match result {
-                Ok(_) => {
-                    status.set_text("All packages upgraded successfully!");
-                    while let Some(child) = list_box.first_child() {
-                        list_box.remove(&child);
-                    }
-                    header_status.set_text("All packages are up to date!");
-                    btn_clone.set_visible(false);
-                    upgrade_selected_clone.set_visible(false);
-                }
-                Err(e) => {
-                    status.set_text(&format!("Error: {}", brew::sanitize_error(&e.to_string())));
-                    btn_clone.set_sensitive(true);
-                }
-            }
-        });
+    let btn_clone = btn.clone();
+    glib::timeout_add_seconds_local_once(2, move || {
+        btn_clone.set_label(&original_label);
     });
-
-    view
 }
 
-// ============================================================================
-// Helper functions
-// ============================================================================
+// Where "Report Issue" in the error dialog opens a new issue
+const ISSUES_URL: &str = "https://github.com/dlbradford/brewhouse/issues/new";
+
+/// Show a failed operation in a dialog: summary and suggestion up front, the full
+/// error output in an expander with buttons to copy it or report an issue
+fn show_error_dialog(widget: &impl IsA<gtk4::Widget>, heading: &str, error: &brew::BrewError) {
+    // A simulated operation didn't fail, so show what it would have done instead
+    if let brew::BrewError::DryRun(preview) = error {
+        let body = "Simulate Only is on, so nothing was changed. Turn it off in Preferences to run this for real.";
+        show_error_details(widget, "Simulated", body, &brew::sanitize_error(preview));
+        return;
+    }
+    let body = match error.suggestion() {
+        Some(suggestion) => format!("{}\n\n{}", error.summary(), suggestion),
+        None => error.summary(),
+    };
+    show_error_details(widget, heading, &body, &brew::sanitize_error(&error.to_string()));
+}
 
-fn create_package_row(name: &str, version: Option<&str>, description: Option<&str>) -> ListBoxRow {
-    let row = ListBoxRow::new();
+fn show_error_details(widget: &impl IsA<gtk4::Widget>, heading: &str, body: &str, details: &str) {
+    use adw::prelude::MessageDialogExt;
 
-    let hbox = Box::new(Orientation::Horizontal, 12);
-    hbox.set_margin_start(12);
-    hbox.set_margin_end(12);
-    hbox.set_margin_top(8);
-    hbox.set_margin_bottom(8);
+    let parent = widget.root().and_then(|root| root.downcast::<gtk4::Window>().ok());
+    let dialog = adw::MessageDialog::new(parent.as_ref(), Some(heading), Some(body));
+    dialog.add_response("report", "Report Issue");
+    dialog.add_response("close", "Close");
+    dialog.set_default_response(Some("close"));
+    dialog.set_close_response("close");
 
-    let info_box = Box::new(Orientation::Vertical, 2);
-    info_box.set_hexpand(true);
+    let expander = gtk4::Expander::new(Some("Details"));
+    let details_box = Box::new(Orientation::Vertical, 6);
 
-    let name_label = Label::new(Some(name));
-    name_label.set_halign(gtk4::Align::Start);
-    name_label.add_css_class("heading");
-    info_box.append(&name_label);
-
-    if let Some(ver) = version {
-        let version_label = Label::new(Some(ver));
-        version_label.set_halign(gtk4::Align::Start);
-        version_label.add_css_class("dim-label");
-        version_label.add_css_class("caption");
-        info_box.append(&version_label);
-    }
-
-    if let Some(desc) = description {
-        let desc_label = Label::new(Some(desc));
-        desc_label.set_halign(gtk4::Align::Start);
-        desc_label.set_wrap(true);
-        desc_label.set_max_width_chars(50);
-        desc_label.add_css_class("caption");
-        info_box.append(&desc_label);
-    }
+    let scroll = ScrolledWindow::new();
+    scroll.set_min_content_height(160);
+    scroll.set_max_content_height(320);
+    scroll.set_propagate_natural_height(true);
+    let text_view = TextView::new();
+    text_view.set_editable(false);
+    text_view.set_wrap_mode(gtk4::WrapMode::WordChar);
+    text_view.set_monospace(true);
+    set_output_text(&text_view, details);
+    scroll.set_child(Some(&text_view));
+    details_box.append(&scroll);
+
+    // Copies and reports get the text without terminal escapes
+    let details = ansi::strip(details);
+
+    // Copying lives here rather than in the responses so the dialog stays open
+    let copy_btn = Button::with_label("Copy to Clipboard");
+    copy_btn.set_halign(gtk4::Align::End);
+    let details_for_copy = details.clone();
+    copy_btn.connect_clicked(move |btn| copy_to_clipboard(btn, &details_for_copy));
+    details_box.append(&copy_btn);
+
+    expander.set_child(Some(&details_box));
+    dialog.set_extra_child(Some(&expander));
+
+    let title = heading.to_string();
+    let issue_body = format!(
+        "BrewHouse {}\n\n```\n{}\n```\n",
+        env!("CARGO_PKG_VERSION"),
+        details
+    );
+    dialog.connect_response(None, move |dialog, response| {
+        if response == "report" {
+            let url = format!(
+                "{}?title={}&body={}",
+                ISSUES_URL,
+                glib::Uri::escape_string(&title, None, false),
+                glib::Uri::escape_string(&issue_body, None, false)
+            );
+            gtk4::show_uri(dialog.transient_for().as_ref(), &url, gtk4::gdk::CURRENT_TIME);
+        }
+    });
 
-    hbox.append(&info_box);
-    row.set_child(Some(&hbox));
-    row
+    dialog.present();
 }
 
-fn create_simple_row(name: &str) -> ListBoxRow {
-    let row = ListBoxRow::new();
-    let label = Label::new(Some(name));
-    label.set_halign(gtk4::Align::Start);
-    label.set_margin_start(12);
-    label.set_margin_end(12);
-    label.set_margin_top(8);
-    label.set_margin_bottom(8);
-    row.set_child(Some(&label));
-    row
+/// Link button for a package homepage; opens in the default browser when clicked
+fn create_homepage_link() -> gtk4::LinkButton {
+    let link = gtk4::LinkButton::new("");
+    link.set_halign(gtk4::Align::Start);
+    link.set_visible(false);
+    link
 }
 
-fn create_update_row_with_checkbox(name: &str) -> (ListBoxRow, CheckButton) {
-    let row = ListBoxRow::new();
-
-    let hbox = Box::new(Orientation::Horizontal, 12);
-    hbox.set_margin_start(12);
-    hbox.set_margin_end(12);
-    hbox.set_margin_top(8);
-    hbox.set_margin_bottom(8);
+fn set_homepage_link(link: &gtk4::LinkButton, homepage: Option<&str>) {
+    match homepage {
+        Some(url) => {
+            link.set_uri(url);
+            link.set_label(url);
+            link.set_tooltip_text(Some("Open homepage in browser"));
+            link.set_visible(true);
+        }
+        None => link.set_visible(false),
+    }
+}
 
-    let checkbox = CheckButton::new();
-    hbox.append(&checkbox);
+/// Label reporting how a page's operations went. It is a status region, so screen readers
+/// announce its new text when an operation finishes or fails.
+fn create_status_label(text: Option<&str>) -> Label {
+    Label::builder()
+        .label(text.unwrap_or_default())
+        .accessible_role(gtk4::AccessibleRole::Status)
+        .build()
+}
 
-    let label = Label::new(Some(name));
-    label.set_halign(gtk4::Align::Start);
-    label.set_hexpand(true);
-    label.add_css_class("heading");
-    hbox.append(&label);
+/// Name screen readers use for a widget without text of its own, e.g. an icon button or spinner
+fn set_accessible_label(widget: &impl IsA<gtk4::Accessible>, label: &str) {
+    widget.update_property(&[gtk4::accessible::Property::Label(label)]);
+}
 
-    let update_icon = Label::new(Some("⬆"));
-    update_icon.add_css_class("dim-label");
-    hbox.append(&update_icon);
+/// Have screen readers name `widget` by the text of `label`, e.g. a list row by its title
+fn set_labelled_by(widget: &impl IsA<gtk4::Accessible>, label: &impl IsA<gtk4::Accessible>) {
+    widget.update_relation(&[gtk4::accessible::Relation::LabelledBy(&[label.upcast_ref()])]);
+}
 
-    row.set_child(Some(&hbox));
-    (row, checkbox)
+/// Show a toast in the window containing `widget`
+fn show_toast(widget: &impl IsA<gtk4::Widget>, toast: adw::Toast) {
+    if let Some(overlay) = widget
+        .ancestor(adw::ToastOverlay::static_type())
+        .and_then(|w| w.downcast::<adw::ToastOverlay>().ok())
+    {
+        overlay.add_toast(toast);
+    }
 }
+
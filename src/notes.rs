@@ -0,0 +1,105 @@
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+// Schema migrations for notes.json, oldest first (see storage::Migration)
+const MIGRATIONS: &[storage::Migration] = &[];
+
+/// Free-text notes the user attached to packages, e.g. "installed for client X's project"
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Notes {
+    notes: BTreeMap<String, String>,
+}
+
+impl Notes {
+    pub const fn new() -> Self {
+        Self { notes: BTreeMap::new() }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.notes.get(name).map(String::as_str)
+    }
+
+    /// Attach `note` to `name`, or remove its note if `note` is blank.
+    /// Returns whether anything changed.
+    pub fn set(&mut self, name: &str, note: &str) -> bool {
+        let note = note.trim();
+        if note.is_empty() {
+            return self.notes.remove(name).is_some();
+        }
+        if self.get(name) == Some(note) {
+            return false;
+        }
+        self.notes.insert(name.to_string(), note.to_string());
+        true
+    }
+
+    /// Whether the note on `name` contains `query`, ignoring case
+    pub fn matches(&self, name: &str, query: &str) -> bool {
+        self.get(name).is_some_and(|note| note.to_lowercase().contains(&query.to_lowercase()))
+    }
+}
+
+static NOTES: Mutex<Notes> = Mutex::new(Notes::new());
+
+fn notes_path() -> PathBuf {
+    storage::data_dir().join("notes.json")
+}
+
+/// Load the notes written by previous runs. Called once on startup.
+pub fn init() {
+    match storage::load::<Notes>(&notes_path(), MIGRATIONS) {
+        Ok(Some(notes)) => *NOTES.lock().unwrap() = notes,
+        Ok(None) => {}
+        Err(e) => eprintln!("Failed to load notes: {}", e),
+    }
+}
+
+/// The note on package `name`, if it has one
+pub fn get(name: &str) -> Option<String> {
+    NOTES.lock().unwrap().get(name).map(str::to_string)
+}
+
+/// Set or, with a blank `note`, remove the note on `name` and write the notes to disk
+pub fn set(name: &str, note: &str) {
+    let mut notes = NOTES.lock().unwrap();
+    if !notes.set(name, note) {
+        return;
+    }
+    if let Err(e) = storage::save(&notes_path(), &*notes, MIGRATIONS) {
+        eprintln!("Failed to save notes: {}", e);
+    }
+}
+
+/// Whether the note on `name` contains `query`, ignoring case
+pub fn matches(name: &str, query: &str) -> bool {
+    NOTES.lock().unwrap().matches(name, query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_and_remove() {
+        let mut notes = Notes::new();
+        assert!(notes.set("postgresql@16", "  For the client project  "));
+        assert_eq!(notes.get("postgresql@16"), Some("For the client project"));
+        assert!(!notes.set("postgresql@16", "For the client project"));
+
+        assert!(notes.set("postgresql@16", " "));
+        assert_eq!(notes.get("postgresql@16"), None);
+        assert!(!notes.set("wget", ""));
+    }
+
+    #[test]
+    fn test_matches() {
+        let mut notes = Notes::new();
+        notes.set("node", "Needed by the Client X website");
+        assert!(notes.matches("node", "client x"));
+        assert!(!notes.matches("node", "backend"));
+        assert!(!notes.matches("wget", "client"));
+    }
+}
@@ -0,0 +1,76 @@
+use crate::brew;
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4::subclass::prelude::*;
+
+mod imp {
+    use super::*;
+    use std::cell::{Cell, RefCell};
+
+    #[derive(Default, glib::Properties)]
+    #[properties(wrapper_type = super::PackageObject)]
+    pub struct PackageObject {
+        #[property(get)]
+        pub name: RefCell<String>,
+        #[property(get, nullable)]
+        pub version: RefCell<Option<String>>,
+        #[property(get, nullable)]
+        pub desc: RefCell<Option<String>>,
+        /// Disk space used by all installed kegs; 0 until measured
+        #[property(get, set)]
+        pub size: Cell<u64>,
+        /// Unix time the package was last installed or upgraded; 0 if unknown
+        #[property(get, set)]
+        pub changed: Cell<i64>,
+        pub package: RefCell<brew::Package>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for PackageObject {
+        const NAME: &'static str = "BrewhousePackageObject";
+        type Type = super::PackageObject;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for PackageObject {}
+}
+
+glib::wrapper! {
+    /// A `brew::Package` as a GObject, so it can live in a `gio::ListStore`
+    /// and row widgets can bind to its properties
+    pub struct PackageObject(ObjectSubclass<imp::PackageObject>);
+}
+
+impl PackageObject {
+    pub fn new(package: brew::Package) -> Self {
+        let obj: Self = glib::Object::new();
+        let imp = obj.imp();
+        imp.name.replace(package.name.clone());
+        imp.version.replace(package.version.clone());
+        imp.desc.replace(package.desc.clone());
+        imp.changed.set(package.installed_time.unwrap_or(0));
+        imp.package.replace(package);
+        obj
+    }
+
+    /// A copy of the wrapped package
+    pub fn package(&self) -> brew::Package {
+        self.imp().package.borrow().clone()
+    }
+
+    /// Change the wrapped package in place, notifying bound widgets of any property that changed
+    pub fn update(&self, f: impl FnOnce(&mut brew::Package)) {
+        let imp = self.imp();
+        f(&mut imp.package.borrow_mut());
+
+        let package = imp.package.borrow();
+        if *imp.version.borrow() != package.version {
+            imp.version.replace(package.version.clone());
+            self.notify_version();
+        }
+        if *imp.desc.borrow() != package.desc {
+            imp.desc.replace(package.desc.clone());
+            self.notify_desc();
+        }
+    }
+}
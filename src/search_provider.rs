@@ -0,0 +1,194 @@
+use crate::cli::Target;
+use brewhouse_core::{brew, metadata_cache};
+use gtk4::gio::{self, DBusConnection};
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4::Application;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// Object path the search provider is exported at; must match `ObjectPath` in
+/// data/io.github.brewhouse.app.search-provider.ini
+pub const OBJECT_PATH: &str = "/io/github/brewhouse/app/SearchProvider";
+
+const INTERFACE: &str = "org.gnome.Shell.SearchProvider2";
+
+// The overview shows a handful of results per provider, so more would only cost time
+const MAX_RESULTS: usize = 10;
+
+type IndexWaiter = Box<dyn FnOnce(&[String])>;
+
+thread_local! {
+    // Callers waiting on the formula listing in flight, if one is running
+    static PENDING_INDEX: RefCell<Option<Vec<IndexWaiter>>> = const { RefCell::new(None) };
+}
+
+const INTROSPECTION_XML: &str = r#"
+<node>
+  <interface name="org.gnome.Shell.SearchProvider2">
+    <method name="GetInitialResultSet">
+      <arg type="as" name="terms" direction="in"/>
+      <arg type="as" name="results" direction="out"/>
+    </method>
+    <method name="GetSubsearchResultSet">
+      <arg type="as" name="previous_results" direction="in"/>
+      <arg type="as" name="terms" direction="in"/>
+      <arg type="as" name="results" direction="out"/>
+    </method>
+    <method name="GetResultMetas">
+      <arg type="as" name="identifiers" direction="in"/>
+      <arg type="aa{sv}" name="metas" direction="out"/>
+    </method>
+    <method name="ActivateResult">
+      <arg type="s" name="identifier" direction="in"/>
+      <arg type="as" name="terms" direction="in"/>
+      <arg type="u" name="timestamp" direction="in"/>
+    </method>
+    <method name="LaunchSearch">
+      <arg type="as" name="terms" direction="in"/>
+      <arg type="u" name="timestamp" direction="in"/>
+    </method>
+  </interface>
+</node>
+"#;
+
+/// Export the GNOME Shell search provider on `connection`. Results come from the formula
+/// index kept by Browse, so searching the overview never runs brew; `open` shows a result.
+pub fn export(app: &Application, connection: &DBusConnection, open: impl Fn(&Application, Target) + 'static) {
+    let node = gio::DBusNodeInfo::for_xml(INTROSPECTION_XML).expect("Invalid D-Bus introspection XML");
+    let interface = node.lookup_interface(INTERFACE).expect("Introspection XML declares the interface");
+
+    let app = app.clone();
+    let registration = connection
+        .register_object(OBJECT_PATH, &interface)
+        .method_call(move |_, _, _, _, method, parameters, invocation| match method {
+            "GetInitialResultSet" => {
+                let (terms,) = parameters.get::<(Vec<String>,)>().unwrap_or_default();
+                with_formula_index(move |index| {
+                    let results = matching_packages(index, &terms);
+                    invocation.return_value(Some(&(results,).to_variant()));
+                });
+            }
+            "GetSubsearchResultSet" => {
+                let (previous, terms) = parameters.get::<(Vec<String>, Vec<String>)>().unwrap_or_default();
+                let results = matching_packages(&previous, &terms);
+                invocation.return_value(Some(&(results,).to_variant()));
+            }
+            "GetResultMetas" => {
+                let (names,) = parameters.get::<(Vec<String>,)>().unwrap_or_default();
+                let metas: Vec<HashMap<String, glib::Variant>> = names.iter().map(|name| result_meta(name)).collect();
+                invocation.return_value(Some(&(metas,).to_variant()));
+            }
+            "ActivateResult" => {
+                // Results offer to install, so open the details in Browse even if it is installed
+                if let Some((name, _, _)) = parameters.get::<(String, Vec<String>, u32)>() {
+                    open(&app, Target::Search(name));
+                }
+                invocation.return_value(None);
+            }
+            "LaunchSearch" => {
+                if let Some((terms, _)) = parameters.get::<(Vec<String>, u32)>() {
+                    open(&app, Target::Search(terms.join(" ")));
+                }
+                invocation.return_value(None);
+            }
+            _ => invocation.return_dbus_error("org.freedesktop.DBus.Error.UnknownMethod", method),
+        })
+        .build();
+    if let Err(e) = registration {
+        eprintln!("Could not export the search provider: {}", e);
+    }
+}
+
+/// Call `on_ready` with the formula names from the last full listing. Until Browse has listed
+/// everything once, list them in the background first; calls made meanwhile wait for the same
+/// listing rather than starting another.
+fn with_formula_index(on_ready: impl FnOnce(&[String]) + 'static) {
+    if let Some(index) = metadata_cache::formula_index() {
+        on_ready(&index.value);
+        return;
+    }
+    let already_listing = PENDING_INDEX.with(|pending| {
+        let mut pending = pending.borrow_mut();
+        let waiters = pending.get_or_insert_with(Vec::new);
+        waiters.push(Box::new(on_ready));
+        waiters.len() > 1
+    });
+    if already_listing {
+        return;
+    }
+    glib::spawn_future_local(async {
+        let _ = gtk4::gio::spawn_blocking(|| {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(brew::search_packages(""))
+        })
+        .await;
+        let index = metadata_cache::formula_index().map(|index| index.value).unwrap_or_default();
+        let waiters = PENDING_INDEX.with(|pending| pending.borrow_mut().take()).unwrap_or_default();
+        for waiter in waiters {
+            waiter(&index);
+        }
+    });
+}
+
+fn result_meta(name: &str) -> HashMap<String, glib::Variant> {
+    let mut meta = HashMap::from([
+        ("id".to_string(), name.to_variant()),
+        ("name".to_string(), name.to_variant()),
+        ("description".to_string(), "Install with BrewHouse".to_variant()),
+    ]);
+    if let Some(icon) = gio::ThemedIcon::new("system-software-install").serialize() {
+        meta.insert("icon".to_string(), icon);
+    }
+    meta
+}
+
+/// Names containing every term (case-insensitive), best first: exact matches, then names
+/// starting with the first term, then the rest, shorter names first within each group
+fn matching_packages(names: &[String], terms: &[String]) -> Vec<String> {
+    let terms: Vec<String> = terms.iter().map(|term| term.to_lowercase()).filter(|t| !t.is_empty()).collect();
+    let Some(first) = terms.first() else {
+        return Vec::new();
+    };
+
+    let mut matches: Vec<&String> = names
+        .iter()
+        .filter(|name| {
+            let name = name.to_lowercase();
+            terms.iter().all(|term| name.contains(term.as_str()))
+        })
+        .collect();
+    matches.sort_by_key(|name| {
+        let lower = name.to_lowercase();
+        let rank = if lower == *first {
+            0
+        } else if lower.starts_with(first.as_str()) {
+            1
+        } else {
+            2
+        };
+        (rank, name.len(), name.to_string())
+    });
+    matches.into_iter().take(MAX_RESULTS).cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_matching_packages() {
+        let index = names(&["ffmpegthumbnailer", "ffmpeg", "ffmpeg@6", "chromaprint", "libav-ffmpeg"]);
+        assert_eq!(
+            matching_packages(&index, &names(&["FFmpeg"])),
+            names(&["ffmpeg", "ffmpeg@6", "ffmpegthumbnailer", "libav-ffmpeg"])
+        );
+        assert_eq!(matching_packages(&index, &names(&["ffmpeg", "thumb"])), names(&["ffmpegthumbnailer"]));
+        assert!(matching_packages(&index, &names(&[""])).is_empty());
+        assert!(matching_packages(&index, &[]).is_empty());
+    }
+}
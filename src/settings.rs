@@ -0,0 +1,209 @@
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// User preferences, stored in $XDG_CONFIG_HOME/brewhouse/settings.json.
+/// New fields must have a default so older files keep loading without a migration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Order of Browse search results
+    pub browse_sort: BrowseSort,
+    /// Match Browse searches against package descriptions as well as names
+    pub browse_search_descriptions: bool,
+    /// Show Browse search results as a grid of cards instead of a list
+    pub browse_grid: bool,
+    /// Queries saved in Browse to run again with one click
+    pub saved_searches: Vec<SavedSearch>,
+    /// Number of operations kept in the history log
+    pub history_max_entries: usize,
+    /// Path to the brew executable; None looks it up on PATH
+    pub brew_path: Option<String>,
+    /// The first-run setup wizard has been finished or skipped
+    pub onboarding_complete: bool,
+    /// Show a tray icon with the number of outdated packages
+    pub show_tray_icon: bool,
+    /// Light or dark style, or whichever the desktop prefers
+    pub color_scheme: ColorScheme,
+    /// Run installs and upgrades with --verbose
+    pub verbose_operations: bool,
+    /// Install casks with --no-quarantine
+    pub no_quarantine: bool,
+    /// Only preview changes, with --dry-run where brew supports it
+    pub simulate_only: bool,
+    /// Environment variables set for every brew command, e.g. HOMEBREW_NO_ANALYTICS=1
+    pub homebrew_env: BTreeMap<String, String>,
+    /// Brewfile the Status panel checks with `brew bundle check`
+    pub brewfile_path: Option<String>,
+    /// Page the main window was last showing, reopened on the next launch
+    pub last_page: Option<String>,
+    /// Size of the main window when it was last closed
+    pub window_width: i32,
+    pub window_height: i32,
+    pub window_maximized: bool,
+    /// Width of the list beside the details on each page, as a fraction of the page, by page
+    pub split_positions: BTreeMap<String, f64>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            browse_sort: BrowseSort::Relevance,
+            browse_search_descriptions: false,
+            browse_grid: false,
+            saved_searches: Vec::new(),
+            history_max_entries: 500,
+            brew_path: None,
+            onboarding_complete: false,
+            show_tray_icon: false,
+            color_scheme: ColorScheme::System,
+            verbose_operations: false,
+            no_quarantine: false,
+            simulate_only: false,
+            homebrew_env: BTreeMap::new(),
+            brewfile_path: None,
+            last_page: None,
+            window_width: 1200,
+            window_height: 700,
+            window_maximized: false,
+            split_positions: BTreeMap::new(),
+        }
+    }
+}
+
+/// Style of the windows, as chosen in Preferences
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorScheme {
+    /// Follow the desktop's light or dark preference
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+/// A Browse query saved for later
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedSearch {
+    pub query: String,
+    /// Notify in background mode when new formulae match the query
+    pub notify: bool,
+}
+
+/// Order of Browse search results, as chosen next to the search entry
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BrowseSort {
+    /// Best match for the query first
+    #[default]
+    Relevance,
+    /// Alphabetical, as brew lists them
+    Name,
+    /// Most installed in the last 30 days first
+    Popularity,
+}
+
+// Schema migrations for settings.json, oldest first (see storage::Migration)
+const MIGRATIONS: &[storage::Migration] = &[skip_onboarding_for_existing_users, sort_order_from_popularity_flag];
+
+/// v1 -> v2: settings written before the setup wizard existed belong to people
+/// who already use BrewHouse, so don't show them the wizard
+fn skip_onboarding_for_existing_users(mut data: serde_json::Value) -> serde_json::Value {
+    if let Some(map) = data.as_object_mut() {
+        map.insert("onboarding_complete".to_string(), serde_json::Value::Bool(true));
+    }
+    data
+}
+
+/// v2 -> v3: the popularity checkbox became a choice of sort orders
+fn sort_order_from_popularity_flag(mut data: serde_json::Value) -> serde_json::Value {
+    let popular = data
+        .as_object_mut()
+        .and_then(|map| map.remove("browse_sort_by_popularity"))
+        .and_then(|flag| flag.as_bool())
+        .unwrap_or(false);
+    if popular {
+        data["browse_sort"] = serde_json::json!("popularity");
+    }
+    data
+}
+
+static SETTINGS: Mutex<Option<Settings>> = Mutex::new(None);
+
+fn settings_path() -> PathBuf {
+    storage::config_dir().join("settings.json")
+}
+
+/// Load settings from disk, migrating files written by older releases.
+/// Called once on startup; falls back to defaults if the file is unreadable.
+pub fn init() {
+    let settings = match storage::load(&settings_path(), MIGRATIONS) {
+        Ok(settings) => settings.unwrap_or_default(),
+        Err(e) => {
+            eprintln!("Failed to load settings: {}", e);
+            Settings::default()
+        }
+    };
+    *SETTINGS.lock().unwrap() = Some(settings);
+}
+
+/// Get a snapshot of the current settings
+pub fn get() -> Settings {
+    SETTINGS.lock().unwrap().clone().unwrap_or_default()
+}
+
+/// Modify the settings and write them to disk
+pub fn update(f: impl FnOnce(&mut Settings)) {
+    let mut guard = SETTINGS.lock().unwrap();
+    let settings = guard.get_or_insert_with(Settings::default);
+    f(settings);
+
+    if let Err(e) = storage::save(&settings_path(), settings, MIGRATIONS) {
+        eprintln!("Failed to save settings: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_existing_settings_skip_onboarding() {
+        let old = serde_json::json!({ "browse_grid": true });
+        let migrated: Settings = serde_json::from_value(skip_onboarding_for_existing_users(old)).unwrap();
+        assert!(migrated.onboarding_complete);
+        assert!(migrated.browse_grid);
+
+        assert!(!Settings::default().onboarding_complete);
+    }
+
+    #[test]
+    fn test_sort_order_from_popularity_flag() {
+        let by_popularity = serde_json::json!({ "browse_sort_by_popularity": true });
+        let migrated: Settings = serde_json::from_value(sort_order_from_popularity_flag(by_popularity)).unwrap();
+        assert_eq!(migrated.browse_sort, BrowseSort::Popularity);
+
+        let by_name = serde_json::json!({ "browse_sort_by_popularity": false });
+        let migrated: Settings = serde_json::from_value(sort_order_from_popularity_flag(by_name)).unwrap();
+        assert_eq!(migrated.browse_sort, BrowseSort::Relevance);
+    }
+
+    #[test]
+    fn test_missing_layout_uses_defaults() {
+        let old: Settings = serde_json::from_value(serde_json::json!({ "history_max_entries": 100 })).unwrap();
+        assert_eq!(old.last_page, None);
+        assert_eq!((old.window_width, old.window_height), (1200, 700));
+        assert!(!old.window_maximized);
+        assert_eq!(old.color_scheme, ColorScheme::System);
+    }
+
+    #[test]
+    fn test_color_scheme_names() {
+        assert_eq!(serde_json::to_value(ColorScheme::Dark).unwrap(), serde_json::json!("dark"));
+        let light: ColorScheme = serde_json::from_value(serde_json::json!("light")).unwrap();
+        assert_eq!(light, ColorScheme::Light);
+    }
+}
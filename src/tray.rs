@@ -0,0 +1,425 @@
+use crate::brew;
+use crate::daemon::CHECK_INTERVAL;
+use crate::events;
+use crate::jobs;
+use crate::settings;
+use gtk4::gio::{self, DBusConnection};
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4::Application;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// The icon is a StatusNotifierItem (the freedesktop tray protocol used by KDE, most panels
+// and the GNOME AppIndicator extension) with its menu exported over com.canonical.dbusmenu
+const ITEM_PATH: &str = "/StatusNotifierItem";
+const MENU_PATH: &str = "/MenuBar";
+
+const ITEM_XML: &str = r#"
+<node>
+  <interface name="org.kde.StatusNotifierItem">
+    <property name="Category" type="s" access="read"/>
+    <property name="Id" type="s" access="read"/>
+    <property name="Title" type="s" access="read"/>
+    <property name="Status" type="s" access="read"/>
+    <property name="IconName" type="s" access="read"/>
+    <property name="ToolTip" type="(sa(iiay)ss)" access="read"/>
+    <property name="Menu" type="o" access="read"/>
+    <property name="ItemIsMenu" type="b" access="read"/>
+    <method name="Activate">
+      <arg type="i" name="x" direction="in"/>
+      <arg type="i" name="y" direction="in"/>
+    </method>
+    <method name="SecondaryActivate">
+      <arg type="i" name="x" direction="in"/>
+      <arg type="i" name="y" direction="in"/>
+    </method>
+    <method name="ContextMenu">
+      <arg type="i" name="x" direction="in"/>
+      <arg type="i" name="y" direction="in"/>
+    </method>
+    <method name="Scroll">
+      <arg type="i" name="delta" direction="in"/>
+      <arg type="s" name="orientation" direction="in"/>
+    </method>
+    <signal name="NewIcon"/>
+    <signal name="NewToolTip"/>
+  </interface>
+</node>
+"#;
+
+const MENU_XML: &str = r#"
+<node>
+  <interface name="com.canonical.dbusmenu">
+    <property name="Version" type="u" access="read"/>
+    <property name="TextDirection" type="s" access="read"/>
+    <property name="Status" type="s" access="read"/>
+    <method name="GetLayout">
+      <arg type="i" name="parentId" direction="in"/>
+      <arg type="i" name="recursionDepth" direction="in"/>
+      <arg type="as" name="propertyNames" direction="in"/>
+      <arg type="u" name="revision" direction="out"/>
+      <arg type="(ia{sv}av)" name="layout" direction="out"/>
+    </method>
+    <method name="GetGroupProperties">
+      <arg type="ai" name="ids" direction="in"/>
+      <arg type="as" name="propertyNames" direction="in"/>
+      <arg type="a(ia{sv})" name="properties" direction="out"/>
+    </method>
+    <method name="GetProperty">
+      <arg type="i" name="id" direction="in"/>
+      <arg type="s" name="name" direction="in"/>
+      <arg type="v" name="value" direction="out"/>
+    </method>
+    <method name="Event">
+      <arg type="i" name="id" direction="in"/>
+      <arg type="s" name="eventId" direction="in"/>
+      <arg type="v" name="data" direction="in"/>
+      <arg type="u" name="timestamp" direction="in"/>
+    </method>
+    <method name="AboutToShow">
+      <arg type="i" name="id" direction="in"/>
+      <arg type="b" name="needUpdate" direction="out"/>
+    </method>
+    <signal name="LayoutUpdated">
+      <arg type="u" name="revision"/>
+      <arg type="i" name="parent"/>
+    </signal>
+  </interface>
+</node>
+"#;
+
+// Menu item ids; 0 is the root
+const ITEM_STATUS: i32 = 1;
+const ITEM_UPGRADE_ALL: i32 = 2;
+const ITEM_CHECK_NOW: i32 = 3;
+const ITEM_SEPARATOR: i32 = 4;
+const ITEM_OPEN: i32 = 5;
+
+struct Tray {
+    app: Application,
+    connection: DBusConnection,
+    open: Box<dyn Fn(&Application)>,
+    /// Outdated packages from the last check; None until it finishes
+    outdated: RefCell<Option<Vec<String>>>,
+    checking: Cell<bool>,
+    upgrading: Cell<bool>,
+    /// Bumped whenever the menu changes, as dbusmenu clients expect
+    revision: Cell<u32>,
+    /// The well-known name that tells the tray host the icon exists; released to hide it
+    owner: RefCell<Option<gio::OwnerId>>,
+    hold: RefCell<Option<gio::ApplicationHoldGuard>>,
+}
+
+thread_local! {
+    static TRAY: RefCell<Option<Rc<Tray>>> = const { RefCell::new(None) };
+}
+
+/// Add the `app.tray-icon` toggle for Preferences and show the icon if it is enabled.
+/// While shown, the icon keeps BrewHouse running and its "Open BrewHouse" item calls `open`.
+pub fn init(app: &Application, connection: &DBusConnection, open: impl Fn(&Application) + 'static) {
+    let tray = Rc::new(Tray {
+        app: app.clone(),
+        connection: connection.clone(),
+        open: Box::new(open),
+        outdated: RefCell::new(None),
+        checking: Cell::new(false),
+        upgrading: Cell::new(false),
+        revision: Cell::new(1),
+        owner: RefCell::new(None),
+        hold: RefCell::new(None),
+    });
+    if let Err(e) = register_objects(&tray) {
+        eprintln!("Could not export the tray icon: {}", e);
+        return;
+    }
+    TRAY.with(|current| *current.borrow_mut() = Some(tray.clone()));
+
+    let enabled = settings::get().show_tray_icon;
+    let action = gio::SimpleAction::new_stateful("tray-icon", None, &enabled.to_variant());
+    action.connect_change_state(|action, state| {
+        let Some(enabled) = state.and_then(|state| state.get::<bool>()) else {
+            return;
+        };
+        action.set_state(&enabled.to_variant());
+        settings::update(|s| s.show_tray_icon = enabled);
+        if let Some(tray) = TRAY.with(|current| current.borrow().clone()) {
+            set_visible(&tray, enabled);
+        }
+    });
+    app.add_action(&action);
+
+    // Installs and upgrades change what is outdated
+    let tray_for_events = Rc::downgrade(&tray);
+    events::subscribe_app(move |_| {
+        if let Some(tray) = tray_for_events.upgrade().filter(|tray| tray.owner.borrow().is_some()) {
+            check(&tray);
+        }
+    });
+    let tray_for_timer = Rc::downgrade(&tray);
+    glib::timeout_add_local(CHECK_INTERVAL, move || {
+        if let Some(tray) = tray_for_timer.upgrade().filter(|tray| tray.owner.borrow().is_some()) {
+            check(&tray);
+        }
+        glib::ControlFlow::Continue
+    });
+
+    if enabled {
+        set_visible(&tray, true);
+    }
+}
+
+fn register_objects(tray: &Rc<Tray>) -> Result<(), glib::Error> {
+    let item_node = gio::DBusNodeInfo::for_xml(ITEM_XML)?;
+    let item = item_node.lookup_interface("org.kde.StatusNotifierItem").expect("Item XML declares the interface");
+    let menu_node = gio::DBusNodeInfo::for_xml(MENU_XML)?;
+    let menu = menu_node.lookup_interface("com.canonical.dbusmenu").expect("Menu XML declares the interface");
+
+    let tray_for_item = Rc::downgrade(tray);
+    let tray_for_properties = Rc::downgrade(tray);
+    tray.connection
+        .register_object(ITEM_PATH, &item)
+        .method_call(move |_, _, _, _, method, _, invocation| {
+            if let (Some(tray), "Activate" | "SecondaryActivate") = (tray_for_item.upgrade(), method) {
+                (tray.open)(&tray.app);
+            }
+            invocation.return_value(None);
+        })
+        .property(move |_, _, _, _, property| {
+            let count = tray_for_properties.upgrade().and_then(|tray| tray.outdated.borrow().as_ref().map(Vec::len));
+            item_property(property, count)
+        })
+        .build()?;
+
+    let tray_for_menu = Rc::downgrade(tray);
+    tray.connection
+        .register_object(MENU_PATH, &menu)
+        .method_call(move |_, _, _, _, method, parameters, invocation| {
+            let Some(tray) = tray_for_menu.upgrade() else {
+                invocation.return_value(None);
+                return;
+            };
+            match method {
+                "GetLayout" => {
+                    let children: Vec<glib::Variant> = menu_items(&tray)
+                        .into_iter()
+                        .map(|(id, properties)| (id, properties, Vec::<glib::Variant>::new()).to_variant())
+                        .collect();
+                    let root = HashMap::from([("children-display".to_string(), "submenu".to_variant())]);
+                    invocation.return_value(Some(&(tray.revision.get(), (0i32, root, children)).to_variant()));
+                }
+                "GetGroupProperties" => {
+                    let (ids, _) = parameters.get::<(Vec<i32>, Vec<String>)>().unwrap_or_default();
+                    let items: Vec<_> = menu_items(&tray).into_iter().filter(|(id, _)| ids.contains(id)).collect();
+                    invocation.return_value(Some(&(items,).to_variant()));
+                }
+                "GetProperty" => {
+                    let (id, name) = parameters.get::<(i32, String)>().unwrap_or_default();
+                    let value = menu_items(&tray)
+                        .into_iter()
+                        .find(|(item, _)| *item == id)
+                        .and_then(|(_, mut properties)| properties.remove(&name))
+                        .unwrap_or_else(|| "".to_variant());
+                    invocation.return_value(Some(&(value,).to_variant()));
+                }
+                "Event" => {
+                    if let Some((id, "clicked")) = parameters
+                        .get::<(i32, String, glib::Variant, u32)>()
+                        .as_ref()
+                        .map(|(id, event, _, _)| (*id, event.as_str()))
+                    {
+                        menu_clicked(&tray, id);
+                    }
+                    invocation.return_value(None);
+                }
+                "AboutToShow" => invocation.return_value(Some(&(false,).to_variant())),
+                _ => invocation.return_dbus_error("org.freedesktop.DBus.Error.UnknownMethod", method),
+            }
+        })
+        .property(move |_, _, _, _, property| match property {
+            "Version" => 3u32.to_variant(),
+            "TextDirection" => "ltr".to_variant(),
+            _ => "normal".to_variant(),
+        })
+        .build()?;
+    Ok(())
+}
+
+fn set_visible(tray: &Rc<Tray>, visible: bool) {
+    if !visible {
+        if let Some(owner) = tray.owner.borrow_mut().take() {
+            gio::bus_unown_name(owner);
+        }
+        tray.hold.borrow_mut().take();
+        return;
+    }
+    if tray.owner.borrow().is_some() {
+        return;
+    }
+
+    // Named the way the StatusNotifierItem spec suggests, so each process gets its own
+    let name = format!("org.kde.StatusNotifierItem-{}-1", std::process::id());
+    let owner = gio::bus_own_name_on_connection(
+        &tray.connection,
+        &name,
+        gio::BusNameOwnerFlags::NONE,
+        |connection, name| register_with_host(&connection, name),
+        |_, name| eprintln!("Lost the tray icon name {}", name),
+    );
+    *tray.owner.borrow_mut() = Some(owner);
+    *tray.hold.borrow_mut() = Some(tray.app.hold());
+    check(tray);
+}
+
+fn register_with_host(connection: &DBusConnection, name: &str) {
+    connection.call(
+        Some("org.kde.StatusNotifierWatcher"),
+        "/StatusNotifierWatcher",
+        "org.kde.StatusNotifierWatcher",
+        "RegisterStatusNotifierItem",
+        Some(&(name,).to_variant()),
+        None,
+        gio::DBusCallFlags::NONE,
+        -1,
+        gio::Cancellable::NONE,
+        |result| {
+            if let Err(e) = result {
+                eprintln!("No tray to show the icon in (is a StatusNotifier host running?): {}", e);
+            }
+        },
+    );
+}
+
+/// Run `brew outdated` and update the icon, tooltip and menu with the result
+fn check(tray: &Rc<Tray>) {
+    if tray.checking.replace(true) {
+        return;
+    }
+    menu_changed(tray);
+
+    let tray = tray.clone();
+    glib::spawn_future_local(async move {
+        let result = gtk4::gio::spawn_blocking(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(brew::get_outdated_packages())
+        })
+        .await
+        .expect("Background task failed");
+
+        tray.checking.set(false);
+        match result {
+            Ok(outdated) => *tray.outdated.borrow_mut() = Some(outdated),
+            Err(e) => eprintln!("Tray update check failed: {}", e.user_message()),
+        }
+        for signal in ["NewIcon", "NewToolTip"] {
+            let _ = tray.connection.emit_signal(None, ITEM_PATH, "org.kde.StatusNotifierItem", signal, None);
+        }
+        menu_changed(&tray);
+    });
+}
+
+fn menu_changed(tray: &Tray) {
+    let revision = tray.revision.get() + 1;
+    tray.revision.set(revision);
+    let _ = tray.connection.emit_signal(
+        None,
+        MENU_PATH,
+        "com.canonical.dbusmenu",
+        "LayoutUpdated",
+        Some(&(revision, 0i32).to_variant()),
+    );
+}
+
+fn menu_clicked(tray: &Rc<Tray>, id: i32) {
+    match id {
+        ITEM_UPGRADE_ALL => {
+            tray.upgrading.set(true);
+            menu_changed(tray);
+            let tray_for_finished = tray.clone();
+            let tray_for_removed = tray.clone();
+            jobs::enqueue_upgrade(
+                None,
+                move |result| {
+                    if let Err(e) = result {
+                        eprintln!("Upgrade from the tray failed: {}", e.user_message());
+                    }
+                    tray_for_finished.upgrading.set(false);
+                    check(&tray_for_finished);
+                },
+                move || {
+                    tray_for_removed.upgrading.set(false);
+                    menu_changed(&tray_for_removed);
+                },
+            );
+        }
+        ITEM_CHECK_NOW => check(tray),
+        ITEM_OPEN => (tray.open)(&tray.app),
+        _ => {}
+    }
+}
+
+fn menu_items(tray: &Tray) -> Vec<(i32, HashMap<String, glib::Variant>)> {
+    let count = tray.outdated.borrow().as_ref().map(Vec::len);
+    let busy = tray.checking.get() || tray.upgrading.get();
+    let item = |label: &str, enabled: bool| {
+        HashMap::from([("label".to_string(), label.to_variant()), ("enabled".to_string(), enabled.to_variant())])
+    };
+    let status = if tray.checking.get() { "Checking for updates...".to_string() } else { status_text(count) };
+    vec![
+        (ITEM_STATUS, item(&status, false)),
+        (ITEM_UPGRADE_ALL, item("Upgrade All", !busy && count.is_some_and(|count| count > 0))),
+        (ITEM_CHECK_NOW, item("Check Now", !busy)),
+        (ITEM_SEPARATOR, HashMap::from([("type".to_string(), "separator".to_variant())])),
+        (ITEM_OPEN, item("Open BrewHouse", true)),
+    ]
+}
+
+fn item_property(property: &str, count: Option<usize>) -> glib::Variant {
+    match property {
+        "Category" => "SystemServices".to_variant(),
+        "Id" => "brewhouse".to_variant(),
+        "Title" => "BrewHouse".to_variant(),
+        "Status" => "Active".to_variant(),
+        "IconName" => icon_name(count).to_variant(),
+        "ToolTip" => {
+            let pixmaps: Vec<(i32, i32, Vec<u8>)> = Vec::new();
+            (String::new(), pixmaps, "BrewHouse".to_string(), status_text(count)).to_variant()
+        }
+        "Menu" => glib::variant::ObjectPath::try_from(MENU_PATH).expect("Valid object path").to_variant(),
+        "ItemIsMenu" => false.to_variant(),
+        _ => "".to_variant(),
+    }
+}
+
+fn icon_name(count: Option<usize>) -> &'static str {
+    match count {
+        Some(count) if count > 0 => "software-update-available",
+        _ => "system-software-install",
+    }
+}
+
+/// e.g. "3 updates available"; None means no check has finished yet
+fn status_text(count: Option<usize>) -> String {
+    match count {
+        None => "Not checked yet".to_string(),
+        Some(0) => "Everything is up to date".to_string(),
+        Some(1) => "1 update available".to_string(),
+        Some(count) => format!("{} updates available", count),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_text() {
+        assert_eq!(status_text(None), "Not checked yet");
+        assert_eq!(status_text(Some(0)), "Everything is up to date");
+        assert_eq!(status_text(Some(1)), "1 update available");
+        assert_eq!(status_text(Some(4)), "4 updates available");
+        assert_eq!(icon_name(Some(4)), "software-update-available");
+        assert_eq!(icon_name(Some(0)), "system-software-install");
+    }
+}
@@ -0,0 +1,1937 @@
+use crate::brew;
+use crate::events::{self, AppEvent};
+use crate::history::{self, Operation};
+use crate::jobs;
+use crate::settings::{self, BrowseSort, SavedSearch};
+use crate::{
+    copy_to_clipboard, create_copy_command_button, create_homepage_link,
+    create_download_progress, create_refresh_button, create_split_view, create_status_label, set_accessible_label,
+    set_homepage_link, set_labelled_by, set_view_busy, show_error_dialog, track_download_progress,
+};
+#[cfg(feature = "terminal")]
+use crate::show_terminal_dialog;
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4::{Box, Button, CheckButton, FlowBox, Label, ListBox, Orientation, ScrolledWindow, SearchEntry, Spinner};
+use libadwaita as adw;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Instant;
+
+// Formulae listed at most in each of the New This Week and Recently Updated sections
+const NEW_FORMULAE_SHOWN: usize = 10;
+
+// A formula counts as new this week, or recently updated, for this long after it first appears
+const NEW_FORMULA_SECS: u64 = 7 * 24 * 60 * 60;
+
+// Cards in the Popular section of the landing page
+const POPULAR_SHOWN: usize = 24;
+
+// Search results added to the list at a time; short queries like "py" match thousands
+const RESULTS_PAGE: usize = 100;
+
+// Entries of the Browse sort dropdown, in order
+const SORT_ORDERS: [(BrowseSort, &str); 3] = [
+    (BrowseSort::Relevance, "Sort by Relevance"),
+    (BrowseSort::Name, "Sort by Name"),
+    (BrowseSort::Popularity, "Sort by Popularity"),
+];
+
+type InstallHandler = Rc<dyn Fn(&Button, &str)>;
+
+/// The search results, shown a page at a time as list rows and as cards. The cards are
+/// shown instead of the list while the grid toggle is on.
+#[derive(Clone)]
+struct ResultViews {
+    list: ListBox,
+    flow: FlowBox,
+    /// Whether the results are casks, found when no formula matched
+    casks: Rc<Cell<bool>>,
+    /// Install the named package from the button on its card
+    on_install: InstallHandler,
+    /// "Showing 100 of 2400" and a Show More button, while some results aren't shown yet
+    footer: Box,
+    footer_label: Label,
+    page: Rc<RefCell<ResultPage>>,
+    /// Packages ticked for Install Selected, with whether each is a cask. Kept across
+    /// searches, so one batch can pick from several.
+    checked: Rc<RefCell<Vec<(String, bool)>>>,
+    /// "3 selected" and the Install Selected and Clear buttons, while anything is ticked
+    selection_bar: Box,
+    selection_label: Label,
+    /// Installed formulae and casks, whose cards say so instead of offering to install
+    installed: Rc<RefCell<HashSet<String>>>,
+}
+
+/// What populate_results was last given, for adding the next page
+#[derive(Default)]
+struct ResultPage {
+    names: Vec<String>,
+    query: String,
+    descriptions: Arc<HashMap<String, String>>,
+    shown: usize,
+    /// Check boxes of the rows shown so far
+    checks: Vec<CheckButton>,
+}
+
+/// What the search status adds to the result count
+enum SearchNote {
+    None,
+    /// The canonical formula and the alias or old name that was searched for
+    AlsoKnownAs(String, String),
+    /// No formula matched, so the results are casks
+    Casks,
+}
+
+pub fn create_browse_view() -> Box {
+    let view = Box::new(Orientation::Vertical, 10);
+    view.set_margin_start(10);
+    view.set_margin_end(10);
+    view.set_margin_top(10);
+    view.set_margin_bottom(10);
+
+    // Search bar
+    let search_box = Box::new(Orientation::Horizontal, 10);
+    let search_entry = SearchEntry::new();
+    search_entry.set_placeholder_text(Some("Search packages..."));
+    search_entry.set_hexpand(true);
+    search_box.append(&search_entry);
+
+    let sort_labels: Vec<&str> = SORT_ORDERS.iter().map(|(_, label)| *label).collect();
+    let sort_dropdown = gtk4::DropDown::from_strings(&sort_labels);
+    sort_dropdown.set_tooltip_text(Some("Popularity uses Homebrew's 30-day install analytics"));
+    let saved_sort = settings::get().browse_sort;
+    if let Some(index) = SORT_ORDERS.iter().position(|(sort, _)| *sort == saved_sort) {
+        sort_dropdown.set_selected(index as u32);
+    }
+    search_box.append(&sort_dropdown);
+
+    let desc_toggle = gtk4::ToggleButton::with_label("Descriptions");
+    desc_toggle.set_tooltip_text(Some("Also match what packages do, e.g. “pdf merge”"));
+    desc_toggle.set_active(settings::get().browse_search_descriptions);
+    search_box.append(&desc_toggle);
+
+    let grid_toggle = gtk4::ToggleButton::new();
+    grid_toggle.set_icon_name("view-grid-symbolic");
+    grid_toggle.set_tooltip_text(Some("Show Results as Cards"));
+    set_accessible_label(&grid_toggle, "Show Results as Cards");
+    grid_toggle.set_active(settings::get().browse_grid);
+    search_box.append(&grid_toggle);
+
+    let search_spinner = Spinner::new();
+    set_accessible_label(&search_spinner, "Searching");
+    search_box.append(&search_spinner);
+
+    let search_status = create_status_label(Some("Enter a search term"));
+    search_status.add_css_class("dim-label");
+    search_box.append(&search_status);
+    let save_search_btn = Button::from_icon_name("bookmark-new-symbolic");
+    save_search_btn.set_tooltip_text(Some("Save This Search"));
+    set_accessible_label(&save_search_btn, "Save This Search");
+    search_box.append(&save_search_btn);
+    search_box.append(&create_refresh_button("browse.refresh"));
+
+    view.append(&search_box);
+
+    // Split view: results | details
+
+    // Left: results, as a list or as cards. The list keeps the selection either way.
+    let list_scroll = ScrolledWindow::new();
+    list_scroll.set_vexpand(true);
+    let results_box = Box::new(Orientation::Vertical, 0);
+    let list_box = ListBox::new();
+    list_box.set_selection_mode(gtk4::SelectionMode::Single);
+    list_box.add_css_class("boxed-list");
+    results_box.append(&list_box);
+    let results_flow = create_card_flow();
+    results_box.append(&results_flow);
+    let results_footer = Box::new(Orientation::Horizontal, 10);
+    results_footer.set_halign(gtk4::Align::Center);
+    results_footer.set_margin_top(6);
+    results_footer.set_margin_bottom(6);
+    results_footer.set_visible(false);
+    let results_footer_label = Label::new(None);
+    results_footer_label.add_css_class("dim-label");
+    results_footer.append(&results_footer_label);
+    let show_more_btn = Button::with_label("Show More");
+    show_more_btn.add_css_class("flat");
+    results_footer.append(&show_more_btn);
+    results_box.append(&results_footer);
+    list_scroll.set_child(Some(&results_box));
+
+    list_box.set_visible(!grid_toggle.is_active());
+    results_flow.set_visible(grid_toggle.is_active());
+    let list_box_for_grid = list_box.clone();
+    let results_flow_for_grid = results_flow.clone();
+    grid_toggle.connect_toggled(move |toggle| {
+        settings::update(|s| s.browse_grid = toggle.is_active());
+        list_box_for_grid.set_visible(!toggle.is_active());
+        results_flow_for_grid.set_visible(toggle.is_active());
+    });
+
+    let list_box_for_cards = list_box.clone();
+    results_flow.connect_child_activated(move |_, child| {
+        list_box_for_cards.select_row(list_box_for_cards.row_at_index(child.index()).as_ref());
+    });
+    let results_flow_for_list = results_flow.clone();
+    list_box.connect_row_selected(move |_, row| {
+        match row.and_then(|row| results_flow_for_list.child_at_index(row.index())) {
+            Some(child) => results_flow_for_list.select_child(&child),
+            None => results_flow_for_list.unselect_all(),
+        }
+    });
+
+    // Saved searches above the results; hidden until there is one
+    let sidebar = Box::new(Orientation::Vertical, 6);
+    let saved_section = Box::new(Orientation::Vertical, 6);
+    let saved_header = Label::new(Some("Saved Searches"));
+    saved_header.add_css_class("heading");
+    saved_header.set_halign(gtk4::Align::Start);
+    saved_section.append(&saved_header);
+    let saved_list = ListBox::new();
+    saved_list.set_selection_mode(gtk4::SelectionMode::None);
+    saved_list.add_css_class("boxed-list");
+    saved_section.append(&saved_list);
+    sidebar.append(&saved_section);
+
+    // Formulae new or updated this week, on the landing page before any search
+    let feed_section = Box::new(Orientation::Vertical, 6);
+    feed_section.set_visible(false);
+    let new_list = create_feed_list(&feed_section, "New This Week");
+    let updated_list = create_feed_list(&feed_section, "Recently Updated");
+    // Shown when formulae.brew.sh has nothing new since the list it was compared with
+    let feed_empty_label = Label::new(Some(
+        "New and updated formulae on formulae.brew.sh show up here once there are any since BrewHouse last looked",
+    ));
+    feed_empty_label.add_css_class("dim-label");
+    feed_empty_label.set_wrap(true);
+    feed_empty_label.set_xalign(0.0);
+    feed_empty_label.set_visible(false);
+    feed_section.append(&feed_empty_label);
+    sidebar.append(&feed_section);
+    load_formula_feed(&new_list, &updated_list, &feed_empty_label, &search_entry);
+
+    // The most installed formulae as cards, in place of the empty results list before any search
+    let popular_scroll = ScrolledWindow::new();
+    popular_scroll.set_vexpand(true);
+    popular_scroll.set_visible(false);
+    let popular_section = Box::new(Orientation::Vertical, 6);
+    let popular_header = Label::new(Some("Popular"));
+    popular_header.add_css_class("heading");
+    popular_header.set_halign(gtk4::Align::Start);
+    popular_header.set_tooltip_text(Some("Most installed formulae over the last 30 days"));
+    popular_section.append(&popular_header);
+    let popular_flow = create_card_flow();
+    popular_flow.set_selection_mode(gtk4::SelectionMode::None);
+    popular_section.append(&popular_flow);
+    popular_scroll.set_child(Some(&popular_section));
+    sidebar.append(&popular_scroll);
+    // Fetching the install counts means a download, so wait until Browse is first shown
+    let loaded = Rc::new(Cell::new(false));
+    let (popular_flow_for_map, popular_scroll_for_map) = (popular_flow.clone(), popular_scroll.clone());
+    let (list_scroll_for_map, search_entry_for_map) = (list_scroll.clone(), search_entry.clone());
+    view.connect_map(move |_| {
+        if !loaded.replace(true) {
+            load_popular_packages(
+                &popular_flow_for_map,
+                &popular_scroll_for_map,
+                &list_scroll_for_map,
+                &search_entry_for_map,
+            );
+        }
+    });
+
+    // Searches replace the feed and popular packages; clearing the search brings them back
+    let new_list_for_clear = new_list.clone();
+    let updated_list_for_clear = updated_list.clone();
+    let feed_section_for_clear = feed_section.clone();
+    let feed_empty_label_for_clear = feed_empty_label.clone();
+    let popular_flow_for_clear = popular_flow.clone();
+    let popular_scroll_for_clear = popular_scroll.clone();
+    let list_scroll_for_clear = list_scroll.clone();
+    search_entry.connect_search_changed(move |entry| {
+        if !entry.text().is_empty() {
+            return;
+        }
+        if new_list_for_clear.first_child().is_some()
+            || updated_list_for_clear.first_child().is_some()
+            || feed_empty_label_for_clear.is_visible()
+        {
+            feed_section_for_clear.set_visible(true);
+        }
+        if popular_flow_for_clear.first_child().is_some() {
+            popular_scroll_for_clear.set_visible(true);
+            list_scroll_for_clear.set_visible(false);
+        }
+    });
+
+    sidebar.append(&list_scroll);
+
+    // Ticked results, installed together as one queued job
+    let selection_bar = Box::new(Orientation::Horizontal, 10);
+    selection_bar.set_visible(false);
+    let selection_label = Label::new(None);
+    selection_label.set_halign(gtk4::Align::Start);
+    selection_label.set_hexpand(true);
+    selection_bar.append(&selection_label);
+    let clear_selection_btn = Button::with_label("Clear");
+    clear_selection_btn.add_css_class("flat");
+    selection_bar.append(&clear_selection_btn);
+    let install_selected_btn = Button::with_label("Install Selected");
+    install_selected_btn.add_css_class("suggested-action");
+    selection_bar.append(&install_selected_btn);
+    sidebar.append(&selection_bar);
+
+    let batch_status = create_status_label(None);
+    batch_status.set_halign(gtk4::Align::Start);
+    sidebar.append(&batch_status);
+    let batch_progress = gtk4::ProgressBar::new();
+    batch_progress.set_show_text(true);
+    batch_progress.set_visible(false);
+    sidebar.append(&batch_progress);
+    populate_saved_searches(&saved_list, &saved_section);
+
+    let search_entry_for_saved = search_entry.clone();
+    saved_list.connect_row_activated(move |_, row| {
+        if let Some(search) = settings::get().saved_searches.get(row.index() as usize) {
+            search_entry_for_saved.set_text(&search.query);
+            search_entry_for_saved.emit_activate();
+        }
+    });
+
+    let search_entry_for_save = search_entry.clone();
+    let saved_list_for_save = saved_list.clone();
+    let saved_section_for_save = saved_section.clone();
+    save_search_btn.connect_clicked(move |_| {
+        let query = search_entry_for_save.text().trim().to_string();
+        if query.is_empty() {
+            return;
+        }
+        settings::update(|s| {
+            if !s.saved_searches.iter().any(|search| search.query == query) {
+                s.saved_searches.push(SavedSearch { query, ..Default::default() });
+            }
+        });
+        populate_saved_searches(&saved_list_for_save, &saved_section_for_save);
+    });
+
+    // Right: details
+    let details_box = Box::new(Orientation::Vertical, 10);
+    details_box.set_margin_start(20);
+    details_box.set_margin_end(20);
+    details_box.set_margin_top(20);
+
+    let details_name = Label::new(Some("Select a package"));
+    details_name.add_css_class("title-1");
+    details_name.set_halign(gtk4::Align::Start);
+    details_box.append(&details_name);
+
+    let details_version = Label::new(None);
+    details_version.set_halign(gtk4::Align::Start);
+    details_version.add_css_class("dim-label");
+    details_box.append(&details_version);
+
+    // Aliases and former names of the formula
+    let details_aliases = Label::new(None);
+    details_aliases.set_halign(gtk4::Align::Start);
+    details_aliases.set_wrap(true);
+    details_aliases.set_max_width_chars(50);
+    details_aliases.add_css_class("dim-label");
+    details_aliases.set_visible(false);
+    details_box.append(&details_aliases);
+
+    // Only shown for packages outside the official taps
+    let details_tap = Label::new(None);
+    details_tap.set_halign(gtk4::Align::Start);
+    details_tap.add_css_class("dim-label");
+    details_tap.set_visible(false);
+    details_box.append(&details_tap);
+
+    let details_desc = Label::new(None);
+    details_desc.set_halign(gtk4::Align::Start);
+    details_desc.set_wrap(true);
+    details_desc.set_max_width_chars(50);
+    details_box.append(&details_desc);
+
+    let details_homepage = create_homepage_link();
+    details_box.append(&details_homepage);
+
+    // Dependencies section
+    let deps_header = Label::new(Some("Dependencies:"));
+    deps_header.set_halign(gtk4::Align::Start);
+    deps_header.add_css_class("heading");
+    deps_header.set_margin_top(15);
+    deps_header.set_visible(false);
+    details_box.append(&deps_header);
+
+    let deps_list = Label::new(None);
+    deps_list.set_halign(gtk4::Align::Start);
+    deps_list.set_wrap(true);
+    deps_list.set_max_width_chars(50);
+    deps_list.add_css_class("dim-label");
+    deps_list.add_css_class("caption");
+    deps_list.set_visible(false);
+    details_box.append(&deps_list);
+
+    // Build dependencies section
+    let build_deps_header = Label::new(Some("Build Dependencies:"));
+    build_deps_header.set_halign(gtk4::Align::Start);
+    build_deps_header.add_css_class("heading");
+    build_deps_header.set_margin_top(10);
+    build_deps_header.set_visible(false);
+    details_box.append(&build_deps_header);
+
+    let build_deps_list = Label::new(None);
+    build_deps_list.set_halign(gtk4::Align::Start);
+    build_deps_list.set_wrap(true);
+    build_deps_list.set_max_width_chars(50);
+    build_deps_list.add_css_class("dim-label");
+    build_deps_list.add_css_class("caption");
+    build_deps_list.set_visible(false);
+    details_box.append(&build_deps_list);
+
+    // Versioned formulae (e.g. python@3.11), each with its own install button
+    let versioned_header = Label::new(Some("Other Versions:"));
+    versioned_header.set_halign(gtk4::Align::Start);
+    versioned_header.add_css_class("heading");
+    versioned_header.set_margin_top(10);
+    versioned_header.set_visible(false);
+    details_box.append(&versioned_header);
+
+    let versioned_flow = gtk4::FlowBox::new();
+    versioned_flow.set_selection_mode(gtk4::SelectionMode::None);
+    versioned_flow.set_max_children_per_line(4);
+    versioned_flow.set_visible(false);
+    versioned_header.bind_property("visible", &versioned_flow, "visible").build();
+    details_box.append(&versioned_flow);
+
+    // Install options (only shown when they apply to the selected formula)
+    let head_check = CheckButton::with_label("Install HEAD");
+    head_check.set_tooltip_text(Some("Build the latest development version from the upstream repository (brew install --HEAD)"));
+    head_check.set_margin_top(15);
+    head_check.set_visible(false);
+    details_box.append(&head_check);
+
+    let source_check = CheckButton::with_label("Build from source");
+    source_check.set_tooltip_text(Some("Compile locally instead of downloading a prebuilt bottle (brew install --build-from-source)"));
+    source_check.set_visible(false);
+    details_box.append(&source_check);
+
+    // Whether a prebuilt bottle exists for this machine
+    let bottle_label = Label::new(None);
+    bottle_label.set_halign(gtk4::Align::Start);
+    bottle_label.set_wrap(true);
+    bottle_label.set_max_width_chars(50);
+    bottle_label.add_css_class("dim-label");
+    bottle_label.add_css_class("caption");
+    bottle_label.set_visible(false);
+    details_box.append(&bottle_label);
+
+    let no_bottle_warning = Label::new(None);
+    no_bottle_warning.set_halign(gtk4::Align::Start);
+    no_bottle_warning.set_wrap(true);
+    no_bottle_warning.set_max_width_chars(50);
+    no_bottle_warning.add_css_class("warning");
+    no_bottle_warning.set_visible(false);
+    details_box.append(&no_bottle_warning);
+
+    // Formula-specific build options, one checkbox per entry of `options`
+    let formula_options_box = Box::new(Orientation::Vertical, 5);
+    formula_options_box.set_visible(false);
+    let formula_options_header = Label::new(Some("Options:"));
+    formula_options_header.set_halign(gtk4::Align::Start);
+    formula_options_header.add_css_class("heading");
+    formula_options_header.set_margin_top(10);
+    formula_options_box.append(&formula_options_header);
+    details_box.append(&formula_options_box);
+
+    let formula_option_checks: Rc<RefCell<Vec<(String, CheckButton)>>> = Rc::new(RefCell::new(Vec::new()));
+    // Whether the selected package is a cask, found when no formula matched the search
+    let selected_is_cask = Rc::new(Cell::new(false));
+
+    // Install button
+    let actions_box = Box::new(Orientation::Horizontal, 10);
+    actions_box.set_margin_top(20);
+
+    // Offered instead of Install while a tap-qualified package's tap is missing
+    let add_tap_btn = Button::with_label("Add Tap");
+    add_tap_btn.add_css_class("suggested-action");
+    add_tap_btn.set_visible(false);
+    actions_box.append(&add_tap_btn);
+
+    let install_btn = Button::with_label("Install");
+    install_btn.add_css_class("suggested-action");
+    install_btn.set_halign(gtk4::Align::Start);
+    install_btn.set_visible(false);
+    actions_box.append(&install_btn);
+
+    let fetch_btn = Button::with_label("Download Only");
+    fetch_btn.set_tooltip_text(Some("Download the package into the Homebrew cache to install later (brew fetch)"));
+    install_btn.bind_property("visible", &fetch_btn, "visible").sync_create().build();
+    actions_box.append(&fetch_btn);
+
+    let copy_cmd_btn = create_copy_command_button();
+    install_btn.bind_property("visible", &copy_cmd_btn, "visible").sync_create().build();
+    actions_box.append(&copy_cmd_btn);
+
+    #[cfg(feature = "terminal")]
+    let terminal_btn = {
+        let btn = Button::with_label("Install in Terminal");
+        install_btn.bind_property("visible", &btn, "visible").sync_create().build();
+        actions_box.append(&btn);
+        btn
+    };
+    details_box.append(&actions_box);
+
+    let install_status = create_status_label(None);
+    install_status.set_halign(gtk4::Align::Start);
+    details_box.append(&install_status);
+
+    let install_progress = create_download_progress();
+    details_box.append(&install_progress);
+
+    view.append(&create_split_view("browse", &sidebar, &list_box, &details_box));
+
+    // Store search results
+    let results_store: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(Vec::new()));
+    // Query the stored results came from, for ranking them by relevance again
+    let results_query: Rc<RefCell<String>> = Rc::new(RefCell::new(String::new()));
+    // Descriptions shown under the results, from brew's API cache
+    let results_descriptions: Rc<RefCell<Arc<HashMap<String, String>>>> = Rc::default();
+    // Package to select once the running search finishes, set by the browse.show action
+    let select_after_search: Rc<RefCell<Option<String>>> = Rc::new(RefCell::new(None));
+
+    // Installing from a card selects it, so the details show the install's progress
+    let list_box_for_card_install = list_box.clone();
+    let results_for_card_install = results_store.clone();
+    let install_status_for_cards = install_status.clone();
+    let install_progress_for_cards = install_progress.clone();
+    let casks = Rc::new(Cell::new(false));
+    let casks_for_install = casks.clone();
+    let views = ResultViews {
+        list: list_box.clone(),
+        flow: results_flow.clone(),
+        casks,
+        on_install: Rc::new(move |btn: &Button, name: &str| {
+            let index = results_for_card_install.borrow().iter().position(|result| result == name);
+            if let Some(row) = index.and_then(|index| list_box_for_card_install.row_at_index(index as i32)) {
+                list_box_for_card_install.select_row(Some(&row));
+            }
+            let options = brew::InstallOptions { cask: casks_for_install.get(), ..Default::default() };
+            run_browse_install(btn, &install_status_for_cards, &install_progress_for_cards, name.to_string(), options);
+        }),
+        footer: results_footer,
+        footer_label: results_footer_label,
+        page: Rc::default(),
+        checked: Rc::default(),
+        selection_bar,
+        selection_label,
+        installed: Rc::default(),
+    };
+    track_installed(&view, &views.installed);
+
+    let views_for_clear_selection = views.clone();
+    clear_selection_btn.connect_clicked(move |_| clear_checked(&views_for_clear_selection));
+
+    let views_for_batch = views.clone();
+    install_selected_btn.connect_clicked(move |_| {
+        let packages: Vec<(String, brew::InstallOptions)> = views_for_batch
+            .checked
+            .borrow()
+            .iter()
+            .map(|(name, cask)| (name.clone(), brew::InstallOptions { cask: *cask, ..Default::default() }))
+            .collect();
+        clear_checked(&views_for_batch);
+
+        batch_status.set_text(if jobs::is_busy() { "Queued..." } else { "" });
+        let progress = batch_progress.clone();
+        let status = batch_status.clone();
+        let on_progress = move |batch: &jobs::BatchProgress| {
+            status.set_text("");
+            progress.set_visible(true);
+            progress.set_fraction(batch.fraction);
+            progress.set_text(Some(&batch.text()));
+        };
+        let progress = batch_progress.clone();
+        let status = batch_status.clone();
+        let on_finished = move |report: &jobs::BatchReport| {
+            progress.set_visible(false);
+            status.set_text(&report.summary());
+        };
+        let status = batch_status.clone();
+        jobs::enqueue_install_batch(packages, on_progress, on_finished, move || status.set_text(""));
+    });
+    let views_for_more = views.clone();
+    show_more_btn.connect_clicked(move |_| show_more_results(&views_for_more, RESULTS_PAGE));
+    // Scrolling to the end of the results loads the next page too
+    let views_for_scroll = views.clone();
+    list_scroll.connect_edge_reached(move |_, edge| {
+        if edge == gtk4::PositionType::Bottom {
+            show_more_results(&views_for_scroll, RESULTS_PAGE);
+        }
+    });
+
+    // Search handler
+    let list_box_for_search = list_box.clone();
+    let views_for_search = views.clone();
+    let search_spinner_clone = search_spinner.clone();
+    let search_status_clone = search_status.clone();
+    let results_store_clone = results_store.clone();
+    let results_query_clone = results_query.clone();
+    let results_descriptions_clone = results_descriptions.clone();
+    let details_name_reset = details_name.clone();
+    let details_version_reset = details_version.clone();
+    let details_desc_reset = details_desc.clone();
+    let install_btn_reset = install_btn.clone();
+    let sort_dropdown_for_search = sort_dropdown.clone();
+    let desc_toggle_for_search = desc_toggle.clone();
+    let feed_section_for_search = feed_section.clone();
+    let popular_scroll_for_search = popular_scroll.clone();
+    let list_scroll_for_search = list_scroll.clone();
+    let select_for_search = select_after_search.clone();
+
+    search_entry.connect_activate(move |entry| {
+        let query = entry.text().to_string();
+        eprintln!("Search activated with query: '{}'", query);
+        if query.is_empty() {
+            eprintln!("Empty query, returning");
+            return;
+        }
+        feed_section_for_search.set_visible(false);
+        popular_scroll_for_search.set_visible(false);
+        list_scroll_for_search.set_visible(true);
+
+        // Clear previous results
+        populate_results(&views_for_search, &[], "", &Arc::default());
+        details_name_reset.set_text("Searching...");
+        details_version_reset.set_text("");
+        details_desc_reset.set_text("");
+        install_btn_reset.set_visible(false);
+
+        search_spinner_clone.set_spinning(true);
+        search_status_clone.set_text("Searching...");
+
+        let list_box_clone = list_box_for_search.clone();
+        let views_clone = views_for_search.clone();
+        let spinner_clone = search_spinner_clone.clone();
+        let status_clone = search_status_clone.clone();
+        let results_clone = results_store_clone.clone();
+        *results_query_clone.borrow_mut() = query.clone();
+        let query_clone = results_query_clone.clone();
+        let descriptions_clone = results_descriptions_clone.clone();
+        let details_name_clone = details_name_reset.clone();
+        let sort = selected_sort(&sort_dropdown_for_search);
+        let by_description = desc_toggle_for_search.is_active();
+        let select_clone = select_for_search.clone();
+
+        eprintln!("Spawning search task...");
+        glib::spawn_future_local(async move {
+            eprintln!("Search task started for query");
+            set_view_busy(&list_box_clone, true);
+            let result = gtk4::gio::spawn_blocking(move || {
+                eprintln!("Running brew search...");
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                let search = async {
+                    if by_description {
+                        let mut packages = brew::search_descriptions(&query).await?;
+                        sort_results(&mut packages, &query, sort).await;
+                        return Ok((packages, SearchNote::None));
+                    }
+                    // An alias or old name finds the formula it stands for, which search may not list
+                    let (packages, canonical) =
+                        tokio::join!(brew::search_packages(&query), brew::canonical_name(&query));
+                    let mut packages = packages?;
+                    // GUI apps such as firefox are casks, which a formula search doesn't find
+                    if packages.is_empty() && canonical.is_none() {
+                        let mut casks = brew::search_casks(&query).await?;
+                        sort_results(&mut casks, &query, sort).await;
+                        return Ok((casks, SearchNote::Casks));
+                    }
+                    sort_results(&mut packages, &query, sort).await;
+                    // The formula the query stands for goes first, whatever the order, followed by
+                    // its other versions, whose names an alias such as "nodejs" doesn't match
+                    if let Some(name) = &canonical {
+                        let versions = match brew::get_package_info(name).await {
+                            Ok(brew::PackageInfo::Formula(formula)) => formula.versioned_formulae.unwrap_or_default(),
+                            _ => Vec::new(),
+                        };
+                        packages.retain(|package| package != name && !versions.contains(package));
+                        let versions = versions.into_iter().filter(|version| version != name);
+                        packages.splice(0..0, std::iter::once(name.clone()).chain(versions));
+                    }
+                    let note = canonical.map_or(SearchNote::None, |name| SearchNote::AlsoKnownAs(name, query));
+                    Ok::<_, brew::BrewError>((packages, note))
+                };
+                rt.block_on(async {
+                    let (found, descriptions) = tokio::join!(search, brew::get_descriptions());
+                    found.map(|(packages, note)| (packages, note, descriptions))
+                })
+            })
+            .await
+            .expect("Background task failed");
+
+            set_view_busy(&list_box_clone, false);
+            eprintln!("Search completed: {:?}", result.as_ref().map(|(v, _, _)| v.len()));
+            spinner_clone.set_spinning(false);
+
+            match result {
+                Ok((packages, note, descriptions)) => {
+                    eprintln!("Found {} packages", packages.len());
+                    views_clone.casks.set(matches!(note, SearchNote::Casks));
+                    status_clone.set_text(&match note {
+                        SearchNote::AlsoKnownAs(name, query) => {
+                            format!("{} results · {} is also known as “{}”", packages.len(), name, query)
+                        }
+                        SearchNote::Casks if !packages.is_empty() => {
+                            format!("No formulae found · Found as a cask: {} results", packages.len())
+                        }
+                        _ => format!("{} results", packages.len()),
+                    });
+                    details_name_clone.set_text("Select a package");
+
+                    let query = query_clone.borrow().clone();
+                    populate_results(&views_clone, &packages, &query, &descriptions);
+                    *descriptions_clone.borrow_mut() = descriptions;
+                    let selected = select_clone.take().and_then(|name| packages.iter().position(|p| *p == name));
+                    // A package further down than the first page gets the pages up to it
+                    let shown = views_clone.page.borrow().shown;
+                    if let Some(index) = selected.filter(|index| *index >= shown) {
+                        show_more_results(&views_clone, index + 1 - shown);
+                    }
+                    *results_clone.borrow_mut() = packages;
+                    if let Some(row) = selected.and_then(|index| list_box_clone.row_at_index(index as i32)) {
+                        list_box_clone.select_row(Some(&row));
+                        row.grab_focus();
+                    }
+                }
+                Err(e) => {
+                    select_clone.take();
+                    status_clone.set_text(&format!("Error: {}", e.user_message()));
+                }
+            }
+        });
+    });
+
+    // Search again with or without descriptions
+    let search_entry_for_desc = search_entry.clone();
+    desc_toggle.connect_toggled(move |toggle| {
+        settings::update(|s| s.browse_search_descriptions = toggle.is_active());
+        if !search_entry_for_desc.text().is_empty() {
+            search_entry_for_desc.emit_activate();
+        }
+    });
+
+    // Re-sort the current results when the sort order changes
+    let results_for_sort = results_store.clone();
+    let views_for_sort = views.clone();
+    let search_status_for_sort = search_status.clone();
+
+    let query_for_sort = results_query.clone();
+    let descriptions_for_sort = results_descriptions.clone();
+
+    sort_dropdown.connect_selected_notify(move |dropdown| {
+        let sort = selected_sort(dropdown);
+        settings::update(|s| s.browse_sort = sort);
+
+        let mut results = results_for_sort.borrow().clone();
+        if results.is_empty() {
+            return;
+        }
+
+        if sort != BrowseSort::Popularity {
+            match sort {
+                BrowseSort::Relevance => brew::sort_by_relevance(&mut results, &query_for_sort.borrow()),
+                _ => results.sort(),
+            }
+            let query = query_for_sort.borrow();
+            populate_results(&views_for_sort, &results, &query, &descriptions_for_sort.borrow());
+            *results_for_sort.borrow_mut() = results;
+            return;
+        }
+
+        let results_store = results_for_sort.clone();
+        let views = views_for_sort.clone();
+        let status = search_status_for_sort.clone();
+        let current_query = query_for_sort.clone();
+        let query = query_for_sort.borrow().clone();
+        let dropdown = dropdown.clone();
+        let descriptions = descriptions_for_sort.borrow().clone();
+
+        glib::spawn_future_local(async move {
+            let result = gtk4::gio::spawn_blocking(move || {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                rt.block_on(brew::get_install_analytics())
+            })
+            .await
+            .expect("Background task failed");
+            // A search or another sort order chosen meanwhile has its own results
+            if *current_query.borrow() != query || selected_sort(&dropdown) != BrowseSort::Popularity {
+                return;
+            }
+
+            match result {
+                Ok(analytics) => {
+                    brew::sort_by_popularity(&mut results, &analytics);
+                    populate_results(&views, &results, &query, &descriptions);
+                    *results_store.borrow_mut() = results;
+                }
+                Err(e) => {
+                    status.set_text(&format!("Error: {}", e.user_message()));
+                }
+            }
+        });
+    });
+
+    // Row selection - fetch package info
+    let results_for_selection = results_store.clone();
+    let details_name_clone = details_name.clone();
+    let details_version_clone = details_version.clone();
+    let details_tap_clone = details_tap.clone();
+    let details_aliases_clone = details_aliases.clone();
+    let details_desc_clone = details_desc.clone();
+    let details_homepage_clone = details_homepage.clone();
+    let deps_header_clone = deps_header.clone();
+    let deps_list_clone = deps_list.clone();
+    let build_deps_header_clone = build_deps_header.clone();
+    let build_deps_list_clone = build_deps_list.clone();
+    let install_btn_clone = install_btn.clone();
+    let add_tap_btn_clone = add_tap_btn.clone();
+    let install_status_clone = install_status.clone();
+    let install_progress_clone = install_progress.clone();
+    let versioned_header_clone = versioned_header.clone();
+    let versioned_flow_clone = versioned_flow.clone();
+    let head_check_clone = head_check.clone();
+    let source_check_clone = source_check.clone();
+    let bottle_label_clone = bottle_label.clone();
+    let no_bottle_warning_clone = no_bottle_warning.clone();
+    let formula_options_box_clone = formula_options_box.clone();
+    let formula_option_checks_clone = formula_option_checks.clone();
+    let selected_is_cask_clone = selected_is_cask.clone();
+
+    list_box.connect_row_selected(move |_, row| {
+        if let Some(row) = row {
+            let idx = row.index() as usize;
+            let results = results_for_selection.borrow();
+            if let Some(pkg_name) = results.get(idx) {
+                let pkg_name = pkg_name.clone();
+                let name_label = details_name_clone.clone();
+                let version_label = details_version_clone.clone();
+                let tap_label = details_tap_clone.clone();
+                let aliases_label = details_aliases_clone.clone();
+                let desc_label = details_desc_clone.clone();
+                let homepage_label = details_homepage_clone.clone();
+                let deps_header = deps_header_clone.clone();
+                let deps_list = deps_list_clone.clone();
+                let build_deps_header = build_deps_header_clone.clone();
+                let build_deps_list = build_deps_list_clone.clone();
+                let btn = install_btn_clone.clone();
+                let add_tap_btn = add_tap_btn_clone.clone();
+                let status = install_status_clone.clone();
+                let progress = install_progress_clone.clone();
+                let versioned_header = versioned_header_clone.clone();
+                let versioned_flow = versioned_flow_clone.clone();
+                let head_check = head_check_clone.clone();
+                let source_check = source_check_clone.clone();
+                let bottle_label = bottle_label_clone.clone();
+                let no_bottle_warning = no_bottle_warning_clone.clone();
+                let formula_options_box = formula_options_box_clone.clone();
+                let formula_option_checks = formula_option_checks_clone.clone();
+                let is_cask = selected_is_cask_clone.clone();
+
+                is_cask.set(false);
+                name_label.set_text("Loading...");
+                version_label.set_text("");
+                tap_label.set_visible(false);
+                aliases_label.set_visible(false);
+                desc_label.set_text("");
+                homepage_label.set_visible(false);
+                deps_header.set_visible(false);
+                deps_list.set_visible(false);
+                build_deps_header.set_visible(false);
+                build_deps_list.set_visible(false);
+                versioned_header.set_visible(false);
+                head_check.set_visible(false);
+                head_check.set_active(false);
+                source_check.set_visible(false);
+                source_check.set_active(false);
+                bottle_label.set_visible(false);
+                no_bottle_warning.set_visible(false);
+                formula_options_box.set_visible(false);
+                for (_, check) in formula_option_checks.borrow_mut().drain(..) {
+                    formula_options_box.remove(&check);
+                }
+                while let Some(child) = versioned_flow.first_child() {
+                    versioned_flow.remove(&child);
+                }
+                btn.set_visible(false);
+                add_tap_btn.set_visible(false);
+                status.set_text("");
+
+                glib::spawn_future_local(async move {
+                    let pkg_for_task = pkg_name.clone();
+                    // None: the package's tap has to be added before brew can describe it
+                    let result = gtk4::gio::spawn_blocking(move || {
+                        let rt = tokio::runtime::Runtime::new().unwrap();
+                        rt.block_on(async {
+                            if brew::split_tap_name(&pkg_for_task).is_some() {
+                                let tapped = brew::get_taps().await?;
+                                if brew::missing_tap(&pkg_for_task, &tapped).is_some() {
+                                    return Ok(None);
+                                }
+                            }
+                            brew::get_package_info(&pkg_for_task).await.map(Some)
+                        })
+                    })
+                    .await
+                    .expect("Background task failed");
+
+                    match result {
+                        Ok(None) => {
+                            let (tap, package) = brew::split_tap_name(&pkg_name).unwrap_or(("", &pkg_name));
+                            name_label.set_text(package);
+                            tap_label.set_text(&format!("From {}, which isn't tapped yet", tap));
+                            tap_label.set_visible(true);
+                            desc_label.set_text("Add the tap to see the details and install it.");
+                            add_tap_btn.set_label(&format!("Add {}", tap));
+                            add_tap_btn.set_visible(true);
+                        }
+                        Ok(Some(info)) => {
+                            let details = info.details();
+                            name_label.set_text(&details.display_name);
+                            version_label.set_text(&format!("Version: {}", details.version.as_deref().unwrap_or("unknown")));
+                            if let Some(tap) = details.tap.as_deref().filter(|tap| !brew::is_tapped(tap, &[])) {
+                                tap_label.set_text(&format!("From {}", tap));
+                                tap_label.set_visible(true);
+                            }
+                            desc_label.set_text(details.desc.as_deref().unwrap_or("No description"));
+                            set_homepage_link(&homepage_label, details.homepage.as_deref());
+
+                            // Show runtime dependencies
+                            if !details.dependencies.is_empty() {
+                                deps_list.set_text(&details.dependencies.join(", "));
+                                deps_header.set_visible(true);
+                                deps_list.set_visible(true);
+                            }
+
+                            // Show build dependencies
+                            if !details.build_dependencies.is_empty() {
+                                build_deps_list.set_text(&details.build_dependencies.join(", "));
+                                build_deps_header.set_visible(true);
+                                build_deps_list.set_visible(true);
+                            }
+
+                            // Versions, bottles and options only exist for formulae
+                            let brew::PackageInfo::Formula(info) = info else {
+                                is_cask.set(true);
+                                btn.set_visible(true);
+                                return;
+                            };
+
+                            let other_names = info.other_names();
+                            if !other_names.is_empty() {
+                                aliases_label.set_text(&format!("Also known as {}", other_names.join(", ")));
+                                aliases_label.set_visible(true);
+                            }
+
+                            // Offer the pinned-major-version formulae
+                            let alternatives: Vec<&String> = info
+                                .versioned_formulae
+                                .iter()
+                                .flatten()
+                                .filter(|name| **name != info.name)
+                                .collect();
+                            for name in &alternatives {
+                                let alt_btn = Button::with_label(&format!("Install {}", name));
+                                let alt_status = status.clone();
+                                let alt_progress = progress.clone();
+                                let alt_name = name.to_string();
+                                alt_btn.connect_clicked(move |alt_btn| {
+                                    let options = brew::InstallOptions::default();
+                                    run_browse_install(alt_btn, &alt_status, &alt_progress, alt_name.clone(), options);
+                                });
+                                versioned_flow.insert(&alt_btn, -1);
+                            }
+                            // Any release from the formula's history, through brew extract
+                            let older_btn = Button::with_label("Older Version…");
+                            older_btn.set_tooltip_text(Some("Install a release from the formula's history"));
+                            let older_status = status.clone();
+                            let older_progress = progress.clone();
+                            let older_name = pkg_name.clone();
+                            older_btn.connect_clicked(move |older_btn| {
+                                show_older_versions_dialog(older_btn, &older_status, &older_progress, &older_name);
+                            });
+                            versioned_flow.insert(&older_btn, -1);
+                            versioned_header.set_visible(true);
+
+                            head_check.set_visible(info.versions.head.is_some());
+
+                            // Source builds should be a choice, not a surprise
+                            let tag = brew::current_bottle_tag();
+                            let has_bottle = match (&info.bottle, &tag) {
+                                (Some(bottle), Some(tag)) => brew::has_bottle_for(bottle, tag),
+                                (None, _) => false,
+                                // Unknown platform: don't warn about something we can't check
+                                (Some(_), None) => true,
+                            };
+                            source_check.set_visible(has_bottle);
+                            let platform = tag.as_deref().map_or("this platform".to_string(), brew::bottle_tag_label);
+                            if has_bottle {
+                                bottle_label.set_text(&format!("✓ Prebuilt bottle available for {}", platform));
+                            } else {
+                                let others = info.bottle.as_ref().map(brew::bottle_tags).unwrap_or_default();
+                                bottle_label.set_text(&if others.is_empty() {
+                                    "No bottles are built for this formula".to_string()
+                                } else {
+                                    let labels: Vec<String> =
+                                        others.iter().map(String::as_str).map(brew::bottle_tag_label).collect();
+                                    format!("Bottles exist for {}", labels.join(", "))
+                                });
+                                no_bottle_warning.set_text(&format!(
+                                    "No prebuilt bottle is available for {}. Installing will build from source, \
+                                     which will likely take {}.",
+                                    platform,
+                                    brew::source_build_estimate(&info)
+                                ));
+                            }
+                            bottle_label.set_visible(tag.is_some());
+                            no_bottle_warning.set_visible(!has_bottle);
+
+                            // --HEAD is already covered by its own checkbox
+                            let mut checks = formula_option_checks.borrow_mut();
+                            for option in info.install_options().into_iter().filter(|o| o.option != "--HEAD") {
+                                let check = CheckButton::with_label(&option.option);
+                                if !option.description.is_empty() {
+                                    check.set_tooltip_text(Some(&option.description));
+                                }
+                                formula_options_box.append(&check);
+                                checks.push((option.option, check));
+                            }
+                            formula_options_box.set_visible(!checks.is_empty());
+
+                            btn.set_visible(true);
+                        }
+                        Err(e) => {
+                            name_label.set_text("Error loading package");
+                            desc_label.set_text(&e.user_message());
+                        }
+                    }
+                });
+            }
+        }
+    });
+
+    // Reads the install option controls for the selected package
+    let head_check_for_options = head_check.clone();
+    let source_check_for_options = source_check.clone();
+    let formula_option_checks_for_options = formula_option_checks.clone();
+    let selected_install_options = move || brew::InstallOptions {
+        head: head_check_for_options.is_visible() && head_check_for_options.is_active(),
+        build_from_source: source_check_for_options.is_visible() && source_check_for_options.is_active(),
+        formula_options: formula_option_checks_for_options
+            .borrow()
+            .iter()
+            .filter(|(_, check)| check.is_active())
+            .map(|(option, _)| option.clone())
+            .collect(),
+        cask: selected_is_cask.get(),
+        ..Default::default()
+    };
+
+    // Copy command handler
+    let results_for_copy = results_store.clone();
+    let list_box_for_copy = list_box.clone();
+    let options_for_copy = selected_install_options.clone();
+
+    copy_cmd_btn.connect_clicked(move |btn| {
+        if let Some(row) = list_box_for_copy.selected_row() {
+            if let Some(pkg_name) = results_for_copy.borrow().get(row.index() as usize) {
+                let args = brew::install_args(pkg_name, &options_for_copy());
+                let args: Vec<&str> = args.iter().map(String::as_str).collect();
+                copy_to_clipboard(btn, &brew::command_line(args[0], &args[1..]));
+            }
+        }
+    });
+
+    #[cfg(feature = "terminal")]
+    {
+        let results_for_terminal = results_store.clone();
+        let list_box_for_terminal = list_box.clone();
+        let install_status_for_terminal = install_status.clone();
+        let options_for_terminal = selected_install_options.clone();
+
+        terminal_btn.connect_clicked(move |btn| {
+            if let Some(row) = list_box_for_terminal.selected_row() {
+                if let Some(pkg_name) = results_for_terminal.borrow().get(row.index() as usize) {
+                    let status = install_status_for_terminal.clone();
+                    let name = pkg_name.clone();
+                    let args = brew::install_args(pkg_name, &options_for_terminal());
+                    show_terminal_dialog(btn, Operation::Install, Some(pkg_name), args, move |success| {
+                        status.set_text(if success { "Installed successfully!" } else { "Install failed" });
+                        if success {
+                            events::emit(AppEvent::PackageInstalled(name.clone()));
+                        }
+                    });
+                }
+            }
+        });
+    }
+
+    // Install button handler
+    let results_for_install = results_store.clone();
+    let list_box_for_install = list_box.clone();
+    let install_status_for_handler = install_status.clone();
+    let install_progress_for_handler = install_progress.clone();
+
+    install_btn.connect_clicked(move |btn| {
+        let selected_row = list_box_for_install.selected_row();
+        if let Some(row) = selected_row {
+            let idx = row.index() as usize;
+            let results = results_for_install.borrow();
+            if let Some(pkg_name) = results.get(idx) {
+                run_browse_install(
+                    btn,
+                    &install_status_for_handler,
+                    &install_progress_for_handler,
+                    pkg_name.clone(),
+                    selected_install_options(),
+                );
+            }
+        }
+    });
+
+    // Add Tap handler: tap, then load the package's details again
+    let results_for_tap = results_store.clone();
+    let list_box_for_tap = list_box.clone();
+    let install_status_for_tap = install_status.clone();
+
+    add_tap_btn.connect_clicked(move |btn| {
+        let Some(row) = list_box_for_tap.selected_row() else {
+            return;
+        };
+        let results = results_for_tap.borrow();
+        if let Some((tap, _)) = results.get(row.index() as usize).and_then(|name| brew::split_tap_name(name)) {
+            run_browse_add_tap(btn, &install_status_for_tap, &row, tap.to_string());
+        }
+    });
+
+    // Download Only handler
+    let results_for_fetch = results_store.clone();
+    let list_box_for_fetch = list_box.clone();
+    let install_status_for_fetch = install_status.clone();
+    let install_progress_for_fetch = install_progress.clone();
+
+    fetch_btn.connect_clicked(move |btn| {
+        if let Some(row) = list_box_for_fetch.selected_row() {
+            let results = results_for_fetch.borrow();
+            if let Some(pkg_name) = results.get(row.index() as usize) {
+                run_browse_fetch(btn, &install_status_for_fetch, &install_progress_for_fetch, pkg_name.clone());
+            }
+        }
+    });
+
+    // browse.show: search for a package and select it, for --package and other deep links
+    let show_action = gtk4::gio::SimpleAction::new("show", Some(glib::VariantTy::STRING));
+    let search_entry_for_show = search_entry.clone();
+    show_action.connect_activate(move |_, name| {
+        if let Some(name) = name.and_then(|name| name.str()) {
+            *select_after_search.borrow_mut() = Some(name.to_string());
+            search_entry_for_show.set_text(name);
+            search_entry_for_show.emit_activate();
+        }
+    });
+
+    // browse.refresh: run the current search again
+    let refresh_action = gtk4::gio::SimpleAction::new("refresh", None);
+    let search_entry_for_refresh = search_entry.clone();
+    refresh_action.connect_activate(move |_, _| {
+        if !search_entry_for_refresh.text().is_empty() {
+            search_entry_for_refresh.emit_activate();
+        }
+    });
+
+    let actions = gtk4::gio::SimpleActionGroup::new();
+    actions.add_action(&show_action);
+    actions.add_action(&refresh_action);
+    view.insert_action_group("browse", Some(&actions));
+
+    view
+}
+
+/// Add a heading and an empty list to `section`, in a box hidden until the list gets a row
+fn create_feed_list(section: &Box, title: &str) -> ListBox {
+    let feed_box = Box::new(Orientation::Vertical, 6);
+    feed_box.set_visible(false);
+    let header = Label::new(Some(title));
+    header.add_css_class("heading");
+    header.set_halign(gtk4::Align::Start);
+    feed_box.append(&header);
+    let list = ListBox::new();
+    list.set_selection_mode(gtk4::SelectionMode::None);
+    list.add_css_class("boxed-list");
+    feed_box.append(&list);
+    section.append(&feed_box);
+    list
+}
+
+/// Fill `new_list` with the formulae new this week and `updated_list` with those that got a
+/// new version, major updates first, or show `empty_label` if neither has any. Their section
+/// is shown if no search has been typed into `search_entry` yet.
+fn load_formula_feed(new_list: &ListBox, updated_list: &ListBox, empty_label: &Label, search_entry: &SearchEntry) {
+    let new_list = new_list.clone();
+    let updated_list = updated_list.clone();
+    let empty_label = empty_label.clone();
+    let Some(section) = empty_label.parent() else {
+        return;
+    };
+    let search_entry = search_entry.clone();
+    glib::spawn_future_local(async move {
+        let (feed, descriptions) = gtk4::gio::spawn_blocking(|| {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async { tokio::join!(brew::get_formula_feed(), brew::get_descriptions()) })
+        })
+        .await
+        .expect("Background task failed");
+
+        let mut new = feed.added_within(NEW_FORMULA_SECS);
+        new.truncate(NEW_FORMULAE_SHOWN);
+        for name in &new {
+            new_list.append(&create_result_row(name, "", descriptions.get(name).map(String::as_str), None));
+        }
+
+        let mut updated = feed.updated_within(NEW_FORMULA_SECS);
+        updated.truncate(NEW_FORMULAE_SHOWN);
+        for change in &updated {
+            let mut versions = format!("{} → {}", change.old, change.new);
+            if change.is_major() {
+                versions.push_str(" · Major update");
+            }
+            updated_list.append(&create_result_row(&change.name, "", Some(&versions), None));
+        }
+        let updated: Vec<String> = updated.into_iter().map(|change| change.name).collect();
+        if new.is_empty() && updated.is_empty() {
+            empty_label.set_visible(true);
+            section.set_visible(search_entry.text().is_empty());
+        }
+
+        for (list, names) in [(new_list, new), (updated_list, updated)] {
+            if let Some(feed_box) = list.parent() {
+                feed_box.set_visible(!names.is_empty());
+            }
+            if !names.is_empty() && search_entry.text().is_empty() {
+                section.set_visible(true);
+            }
+            list.connect_row_activated(move |list, row| {
+                if let Some(name) = names.get(row.index() as usize) {
+                    let _ = list.activate_action("browse.show", Some(&name.to_variant()));
+                }
+            });
+        }
+    });
+}
+
+/// Fill `flow` with cards for the most installed formulae. Until a search is typed into
+/// `search_entry`, `scroll`, which holds them, is shown instead of `results_scroll`.
+fn load_popular_packages(
+    flow: &gtk4::FlowBox,
+    scroll: &ScrolledWindow,
+    results_scroll: &ScrolledWindow,
+    search_entry: &SearchEntry,
+) {
+    let flow = flow.clone();
+    let scroll = scroll.clone();
+    let results_scroll = results_scroll.clone();
+    let search_entry = search_entry.clone();
+    glib::spawn_future_local(async move {
+        let (analytics, descriptions) = gtk4::gio::spawn_blocking(|| {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(async { tokio::join!(brew::get_install_analytics(), brew::get_descriptions()) })
+        })
+        .await
+        .expect("Background task failed");
+        let analytics = match analytics {
+            Ok(analytics) => analytics,
+            Err(e) => {
+                eprintln!("Could not load popular packages: {}", e.user_message());
+                return;
+            }
+        };
+
+        let popular = brew::most_installed(&analytics, POPULAR_SHOWN);
+        for (name, installs) in &popular {
+            let (card, content) = create_package_card(name, descriptions.get(name).map(String::as_str), false);
+            let installs = format!("{} installs this month", brew::format_install_count(*installs));
+            let installs_label = Label::new(Some(&installs));
+            installs_label.add_css_class("dim-label");
+            installs_label.add_css_class("caption");
+            installs_label.set_halign(gtk4::Align::Start);
+            content.append(&installs_label);
+            flow.insert(&card, -1);
+        }
+        if !popular.is_empty() && search_entry.text().is_empty() {
+            scroll.set_visible(true);
+            results_scroll.set_visible(false);
+        }
+
+        flow.connect_child_activated(move |flow, child| {
+            if let Some((name, _)) = popular.get(child.index() as usize) {
+                let _ = flow.activate_action("browse.show", Some(&name.to_variant()));
+            }
+        });
+    });
+}
+
+/// A flow box laying out package cards in up to three columns
+fn create_card_flow() -> FlowBox {
+    let flow = FlowBox::new();
+    flow.set_homogeneous(true);
+    flow.set_max_children_per_line(3);
+    flow.set_row_spacing(6);
+    flow.set_column_spacing(6);
+    flow.set_valign(gtk4::Align::Start);
+    flow
+}
+
+/// A card with a package's icon, name and description, and the box to add more to it
+fn create_package_card(name: &str, desc: Option<&str>, cask: bool) -> (gtk4::FlowBoxChild, Box) {
+    let child = gtk4::FlowBoxChild::new();
+    let card = Box::new(Orientation::Vertical, 4);
+    card.add_css_class("card");
+
+    let content = Box::new(Orientation::Vertical, 4);
+    content.set_margin_start(12);
+    content.set_margin_end(12);
+    content.set_margin_top(10);
+    content.set_margin_bottom(10);
+
+    // Casks are mostly GUI apps, formulae mostly command line tools
+    let header = Box::new(Orientation::Horizontal, 8);
+    let icon_name = if cask { "application-x-executable-symbolic" } else { "utilities-terminal-symbolic" };
+    header.append(&gtk4::Image::from_icon_name(icon_name));
+    let name_label = Label::new(Some(name));
+    name_label.add_css_class("heading");
+    name_label.set_halign(gtk4::Align::Start);
+    name_label.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+    header.append(&name_label);
+    content.append(&header);
+
+    let desc_label = Label::new(desc);
+    desc_label.add_css_class("caption");
+    desc_label.set_halign(gtk4::Align::Start);
+    desc_label.set_xalign(0.0);
+    desc_label.set_wrap(true);
+    desc_label.set_lines(2);
+    desc_label.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+    desc_label.set_vexpand(true);
+    desc_label.set_valign(gtk4::Align::Start);
+    content.append(&desc_label);
+
+    card.append(&content);
+    set_labelled_by(&child, &name_label);
+    child.set_child(Some(&card));
+    (child, content)
+}
+
+/// List the saved searches in `list`, each with a notification toggle and a remove button.
+/// `section` is only shown while there are any.
+fn populate_saved_searches(list: &ListBox, section: &Box) {
+    while let Some(child) = list.first_child() {
+        list.remove(&child);
+    }
+    let saved = settings::get().saved_searches;
+    section.set_visible(!saved.is_empty());
+
+    for search in saved {
+        let row = gtk4::ListBoxRow::new();
+        let hbox = Box::new(Orientation::Horizontal, 6);
+        hbox.set_margin_start(12);
+        hbox.set_margin_end(6);
+        hbox.set_margin_top(4);
+        hbox.set_margin_bottom(4);
+
+        let label = Label::new(Some(&search.query));
+        label.set_halign(gtk4::Align::Start);
+        label.set_hexpand(true);
+        label.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+        hbox.append(&label);
+        set_labelled_by(&row, &label);
+
+        let notify_toggle = gtk4::ToggleButton::new();
+        notify_toggle.set_icon_name("preferences-system-notifications-symbolic");
+        notify_toggle.add_css_class("flat");
+        notify_toggle.set_tooltip_text(Some("Notify in background mode when new formulae match"));
+        set_accessible_label(&notify_toggle, &format!("Notify about new formulae matching {}", search.query));
+        notify_toggle.set_active(search.notify);
+        let query = search.query.clone();
+        notify_toggle.connect_toggled(move |toggle| {
+            let notify = toggle.is_active();
+            settings::update(|s| {
+                if let Some(search) = s.saved_searches.iter_mut().find(|search| search.query == query) {
+                    search.notify = notify;
+                }
+            });
+        });
+        hbox.append(&notify_toggle);
+
+        let remove_btn = Button::from_icon_name("window-close-symbolic");
+        remove_btn.add_css_class("flat");
+        remove_btn.set_tooltip_text(Some("Remove Saved Search"));
+        set_accessible_label(&remove_btn, &format!("Remove saved search {}", search.query));
+        let query = search.query.clone();
+        let list_for_remove = list.clone();
+        let section_for_remove = section.clone();
+        remove_btn.connect_clicked(move |_| {
+            settings::update(|s| s.saved_searches.retain(|search| search.query != query));
+            populate_saved_searches(&list_for_remove, &section_for_remove);
+        });
+        hbox.append(&remove_btn);
+
+        row.set_child(Some(&hbox));
+        list.append(&row);
+    }
+}
+
+/// Replace the results in `views` with `names`, each showing its description, and show the first page
+fn populate_results(views: &ResultViews, names: &[String], query: &str, descriptions: &Arc<HashMap<String, String>>) {
+    while let Some(child) = views.list.first_child() {
+        views.list.remove(&child);
+    }
+    while let Some(child) = views.flow.first_child() {
+        views.flow.remove(&child);
+    }
+    *views.page.borrow_mut() = ResultPage {
+        names: names.to_vec(),
+        query: query.to_string(),
+        descriptions: descriptions.clone(),
+        ..Default::default()
+    };
+    show_more_results(views, RESULTS_PAGE);
+}
+
+/// Add up to `count` more of the results to `views`, in the same order as the results, so a
+/// row's index is still its package's index
+fn show_more_results(views: &ResultViews, count: usize) {
+    let mut page = views.page.borrow_mut();
+    let end = (page.shown + count).min(page.names.len());
+    let mut checks = Vec::new();
+    for name in &page.names[page.shown..end] {
+        let desc = page.descriptions.get(name).map(String::as_str);
+        let check = create_result_check(name, views);
+        views.list.append(&create_result_row(name, &page.query, desc, Some(&check)));
+        views.flow.insert(&create_result_card(name, desc, views, &check), -1);
+        checks.push(check);
+    }
+    page.checks.extend(checks);
+    page.shown = end;
+
+    views.footer.set_visible(page.shown < page.names.len());
+    views.footer_label.set_text(&format!("Showing {} of {}", page.shown, page.names.len()));
+}
+
+/// Check box ticking `name` for Install Selected
+fn create_result_check(name: &str, views: &ResultViews) -> CheckButton {
+    let check = CheckButton::new();
+    check.set_valign(gtk4::Align::Center);
+    check.set_tooltip_text(Some("Select for Install Selected"));
+    set_accessible_label(&check, &format!("Select {}", name));
+    check.set_active(views.checked.borrow().iter().any(|(checked, _)| checked == name));
+
+    let name = name.to_string();
+    let cask = views.casks.get();
+    let views = views.clone();
+    check.connect_toggled(move |check| {
+        {
+            let mut checked = views.checked.borrow_mut();
+            checked.retain(|(checked, _)| *checked != name);
+            if check.is_active() {
+                checked.push((name.clone(), cask));
+            }
+        }
+        update_selection_bar(&views);
+    });
+    check
+}
+
+/// Untick every result, including ones from earlier searches
+fn clear_checked(views: &ResultViews) {
+    let checks = views.page.borrow().checks.clone();
+    for check in checks {
+        check.set_active(false);
+    }
+    views.checked.borrow_mut().clear();
+    update_selection_bar(views);
+}
+
+fn update_selection_bar(views: &ResultViews) {
+    let count = views.checked.borrow().len();
+    views.selection_bar.set_visible(count > 0);
+    views.selection_label.set_text(&format!("{} selected", count));
+}
+
+/// A search result as a card with an install button. Its check box mirrors `list_check`,
+/// the one on the result's list row.
+/// Keep `installed` up to date with what is installed, for as long as `owner` is shown
+fn track_installed(owner: &Box, installed: &Rc<RefCell<HashSet<String>>>) {
+    let installed_for_load = installed.clone();
+    glib::spawn_future_local(async move {
+        let snapshot = gtk4::gio::spawn_blocking(|| {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(brew::get_installed_snapshot())
+        })
+        .await
+        .expect("Background task failed");
+        if let Ok(snapshot) = snapshot {
+            let formulae = snapshot.formulae.iter().map(|formula| formula.name.clone());
+            let casks = snapshot.casks.iter().map(|cask| cask.token.clone());
+            installed_for_load.borrow_mut().extend(formulae.chain(casks));
+        }
+    });
+    let installed = installed.clone();
+    events::subscribe(owner, move |event| match event {
+        AppEvent::PackageInstalled(name) => {
+            installed.borrow_mut().insert(name.clone());
+        }
+        AppEvent::PackageUninstalled(name) => {
+            installed.borrow_mut().remove(name);
+        }
+        _ => {}
+    });
+}
+
+fn create_result_card(
+    name: &str,
+    desc: Option<&str>,
+    views: &ResultViews,
+    list_check: &CheckButton,
+) -> gtk4::FlowBoxChild {
+    let (child, content) = create_package_card(name, desc, views.casks.get());
+
+    let actions = Box::new(Orientation::Horizontal, 6);
+    let check = CheckButton::with_label("Select");
+    set_accessible_label(&check, &format!("Select {}", name));
+    list_check.bind_property("active", &check, "active").bidirectional().sync_create().build();
+    check.set_hexpand(true);
+    actions.append(&check);
+
+    if views.installed.borrow().contains(name) {
+        let installed_label = Label::new(Some("Installed"));
+        installed_label.add_css_class("dim-label");
+        actions.append(&installed_label);
+        content.append(&actions);
+        return child;
+    }
+    let install_btn = Button::with_label("Install");
+    set_accessible_label(&install_btn, &format!("Install {}", name));
+    let name = name.to_string();
+    let on_install = views.on_install.clone();
+    install_btn.connect_clicked(move |btn| on_install(btn, &name));
+    actions.append(&install_btn);
+    content.append(&actions);
+    child
+}
+
+/// A search result: the package name with the part matching `query` in bold, its tap if it
+/// has one, and its description underneath. `check`, if given, goes in front for picking it
+/// for a batch install.
+fn create_result_row(name: &str, query: &str, desc: Option<&str>, check: Option<&CheckButton>) -> gtk4::ListBoxRow {
+    let row = gtk4::ListBoxRow::new();
+    let row_box = Box::new(Orientation::Horizontal, 10);
+    row_box.set_margin_start(12);
+    row_box.set_margin_end(12);
+    row_box.set_margin_top(8);
+    row_box.set_margin_bottom(8);
+    if let Some(check) = check {
+        row_box.append(check);
+    }
+    let vbox = Box::new(Orientation::Vertical, 2);
+    vbox.set_hexpand(true);
+    row_box.append(&vbox);
+
+    let hbox = Box::new(Orientation::Horizontal, 8);
+    let (tap, package) = brew::split_tap_name(name).map_or((None, name), |(tap, package)| (Some(tap), package));
+    let label = Label::new(None);
+    label.set_markup(&highlighted_markup(package, query));
+    label.set_halign(gtk4::Align::Start);
+    hbox.append(&label);
+    if let Some(tap) = tap {
+        let tap_label = Label::new(Some(tap));
+        tap_label.add_css_class("dim-label");
+        tap_label.add_css_class("caption");
+        hbox.append(&tap_label);
+    }
+    vbox.append(&hbox);
+
+    if let Some(desc) = desc {
+        let desc_label = Label::new(Some(desc));
+        desc_label.add_css_class("dim-label");
+        desc_label.add_css_class("caption");
+        desc_label.set_halign(gtk4::Align::Start);
+        desc_label.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+        vbox.append(&desc_label);
+    }
+
+    match tap {
+        Some(tap) => set_accessible_label(&row, &format!("{} from {}", package, tap)),
+        None => set_labelled_by(&row, &label),
+    }
+    row.set_child(Some(&row_box));
+    row
+}
+
+/// Pango markup for `text` with its first match of `query` in bold
+fn highlighted_markup(text: &str, query: &str) -> String {
+    match brew::match_range(text, query) {
+        Some(range) => format!(
+            "{}<b>{}</b>{}",
+            glib::markup_escape_text(&text[..range.start]),
+            glib::markup_escape_text(&text[range.clone()]),
+            glib::markup_escape_text(&text[range.end..])
+        ),
+        None => glib::markup_escape_text(text).to_string(),
+    }
+}
+
+fn selected_sort(dropdown: &gtk4::DropDown) -> BrowseSort {
+    SORT_ORDERS.get(dropdown.selected() as usize).map_or(BrowseSort::default(), |(sort, _)| *sort)
+}
+
+/// Put search results in `sort` order; brew already lists them by name
+async fn sort_results(packages: &mut [String], query: &str, sort: BrowseSort) {
+    match sort {
+        BrowseSort::Relevance => brew::sort_by_relevance(packages, query),
+        BrowseSort::Name => {}
+        BrowseSort::Popularity => {
+            // Keep brew's alphabetical order if analytics are unavailable
+            if let Ok(analytics) = brew::get_install_analytics().await {
+                brew::sort_by_popularity(packages, &analytics);
+            }
+        }
+    }
+}
+
+/// Dialog listing the versions in `pkg_name`'s history; the chosen one is extracted into a
+/// local tap and installed with `run_browse_install_version`
+fn show_older_versions_dialog(btn: &Button, status: &Label, progress: &gtk4::ProgressBar, pkg_name: &str) {
+    use adw::prelude::*;
+
+    let parent = btn.root().and_then(|root| root.downcast::<gtk4::Window>().ok());
+    let dialog = gtk4::Window::builder()
+        .title(format!("Older Versions of {}", pkg_name))
+        .default_width(420)
+        .default_height(480)
+        .modal(true)
+        .build();
+    dialog.set_transient_for(parent.as_ref());
+
+    let vbox = Box::new(Orientation::Vertical, 10);
+    vbox.set_margin_start(20);
+    vbox.set_margin_end(20);
+    vbox.set_margin_top(20);
+    vbox.set_margin_bottom(20);
+
+    let hint = Label::new(Some(&format!(
+        "The chosen version is copied into the local tap {} and installed next to the current one. \
+         Listing versions needs the formula's tap checked out (brew tap --force homebrew/core for core formulae).",
+        brew::EXTRACT_TAP
+    )));
+    hint.set_halign(gtk4::Align::Start);
+    hint.set_wrap(true);
+    hint.set_max_width_chars(50);
+    vbox.append(&hint);
+
+    let loading_status = create_status_label(Some("Reading the formula's history..."));
+    loading_status.set_halign(gtk4::Align::Start);
+    loading_status.add_css_class("dim-label");
+    vbox.append(&loading_status);
+
+    let scroll = ScrolledWindow::new();
+    scroll.set_vexpand(true);
+    let list_box = ListBox::new();
+    list_box.set_selection_mode(gtk4::SelectionMode::Single);
+    list_box.add_css_class("boxed-list");
+    scroll.set_child(Some(&list_box));
+    vbox.append(&scroll);
+
+    let buttons = Box::new(Orientation::Horizontal, 10);
+    buttons.set_halign(gtk4::Align::End);
+    let cancel_btn = Button::with_label("Cancel");
+    buttons.append(&cancel_btn);
+    let install_btn = Button::with_label("Install");
+    install_btn.add_css_class("suggested-action");
+    install_btn.set_sensitive(false);
+    buttons.append(&install_btn);
+    vbox.append(&buttons);
+    dialog.set_child(Some(&vbox));
+
+    let dialog_for_cancel = dialog.clone();
+    cancel_btn.connect_clicked(move |_| dialog_for_cancel.close());
+
+    let versions: Rc<RefCell<Vec<(String, String)>>> = Rc::new(RefCell::new(Vec::new()));
+    let install_for_selection = install_btn.clone();
+    list_box.connect_row_selected(move |_, row| install_for_selection.set_sensitive(row.is_some()));
+
+    let versions_for_install = versions.clone();
+    let list_box_for_install = list_box.clone();
+    let dialog_for_install = dialog.clone();
+    let (browse_btn, status, progress, name) = (btn.clone(), status.clone(), progress.clone(), pkg_name.to_string());
+    install_btn.connect_clicked(move |_| {
+        let Some(row) = list_box_for_install.selected_row() else {
+            return;
+        };
+        if let Some((version, _)) = versions_for_install.borrow().get(row.index() as usize) {
+            run_browse_install_version(&browse_btn, &status, &progress, name.clone(), version.clone());
+            dialog_for_install.close();
+        }
+    });
+
+    let name = pkg_name.to_string();
+    glib::spawn_future_local(async move {
+        let result = gtk4::gio::spawn_blocking(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(brew::get_formula_versions(&name))
+        })
+        .await
+        .expect("Background task failed");
+
+        match result {
+            Ok(found) if found.is_empty() => loading_status.set_text("No versions found in the formula's history"),
+            Ok(found) => {
+                loading_status.set_visible(false);
+                for (version, date) in &found {
+                    let row = adw::ActionRow::builder().title(version).subtitle(date).build();
+                    list_box.append(&row);
+                }
+                *versions.borrow_mut() = found;
+            }
+            Err(e) => loading_status.set_text(&format!("Error: {}", e.user_message())),
+        }
+    });
+
+    dialog.present();
+}
+
+/// Extract `pkg_name` at `version` into the local tap and install it, as one queued job
+fn run_browse_install_version(
+    btn: &Button,
+    status: &Label,
+    progress: &gtk4::ProgressBar,
+    pkg_name: String,
+    version: String,
+) {
+    let status = status.clone();
+    let btn_clone = btn.clone();
+    let progress_tx = track_download_progress(progress);
+
+    status.set_text(if jobs::is_busy() { "Queued..." } else { "Extracting..." });
+
+    let title = format!("Install {} {}", pkg_name, version);
+    let on_removed = {
+        let status = status.clone();
+        move || status.set_text("")
+    };
+    let job = async move {
+        status.set_text(&format!("Extracting {} {}...", pkg_name, version));
+        set_view_busy(&btn_clone, true);
+        let started = Instant::now();
+        let (pkg_for_task, version_for_task) = (pkg_name.clone(), version.clone());
+        let status_for_task = status.clone();
+        let extracted = gtk4::gio::spawn_blocking(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(brew::extract_version(&pkg_for_task, &version_for_task))
+        })
+        .await
+        .expect("Background task failed");
+
+        let result = match extracted {
+            Ok(versioned) => {
+                status_for_task.set_text(&format!("Installing {}...", versioned));
+                let name_for_task = versioned.clone();
+                let result = gtk4::gio::spawn_blocking(move || {
+                    let rt = tokio::runtime::Runtime::new().unwrap();
+                    let options = brew::InstallOptions::default();
+                    rt.block_on(brew::install_package_with_progress(&name_for_task, &options, progress_tx))
+                })
+                .await
+                .expect("Background task failed");
+                result.map(|output| (versioned, output))
+            }
+            Err(e) => Err(e),
+        };
+        set_view_busy(&btn_clone, false);
+        let versioned = result.as_ref().map_or_else(|_| format!("{}@{}", pkg_name, version), |(name, _)| name.clone());
+        let output = result.as_ref().map(|(_, output)| output.as_str());
+        history::record(Operation::Install, Some(&versioned), output, started.elapsed());
+        let outcome = jobs::outcome(&output);
+
+        match result {
+            Ok((versioned, _)) => {
+                status.set_text(&format!("Installed {} successfully!", versioned));
+                events::emit(AppEvent::PackageInstalled(versioned));
+            }
+            Err(e) => {
+                status.set_text("Installation failed");
+                show_error_dialog(&btn_clone, &format!("Could not install {} {}", pkg_name, version), &e);
+            }
+        }
+        outcome
+    };
+    jobs::enqueue(title, job, on_removed);
+}
+
+/// Tap `tap` for the package in `row`, then select the row again to show its details
+fn run_browse_add_tap(btn: &Button, status: &Label, row: &gtk4::ListBoxRow, tap: String) {
+    let status = status.clone();
+    let btn_clone = btn.clone();
+    let row = row.clone();
+
+    btn.set_sensitive(false);
+    status.set_text(if jobs::is_busy() { "Queued..." } else { "Adding tap..." });
+
+    let title = format!("Tap {}", tap);
+    let on_removed = {
+        let status = status.clone();
+        let btn = btn.clone();
+        move || {
+            status.set_text("");
+            btn.set_sensitive(true);
+        }
+    };
+    let job = async move {
+        status.set_text("Adding tap...");
+        set_view_busy(&btn_clone, true);
+        let started = Instant::now();
+        let tap_for_task = tap.clone();
+        let result = gtk4::gio::spawn_blocking(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(brew::add_tap(&tap_for_task))
+        })
+        .await
+        .expect("Background task failed");
+        set_view_busy(&btn_clone, false);
+        btn_clone.set_sensitive(true);
+        history::record(Operation::Tap, Some(&tap), result.as_deref(), started.elapsed());
+        let outcome = jobs::outcome(&result);
+
+        match result {
+            Ok(_) => {
+                status.set_text(&format!("Added {}", tap));
+                if let Some(list_box) = row.parent().and_downcast::<ListBox>() {
+                    list_box.unselect_all();
+                    list_box.select_row(Some(&row));
+                }
+            }
+            Err(e) => {
+                status.set_text("Could not add the tap");
+                show_error_dialog(&btn_clone, &format!("Could not tap {}", tap), &e);
+            }
+        }
+        outcome
+    };
+    jobs::enqueue(title, job, on_removed);
+}
+
+/// Download a package without installing it, reporting progress in `status` and `progress`
+/// like an install
+fn run_browse_fetch(btn: &Button, status: &Label, progress: &gtk4::ProgressBar, pkg_name: String) {
+    let status = status.clone();
+    let btn_clone = btn.clone();
+    let progress_tx = track_download_progress(progress);
+
+    btn.set_sensitive(false);
+    status.set_text(&if jobs::is_busy() { "Queued...".to_string() } else { format!("Downloading {}...", pkg_name) });
+
+    let title = format!("Download {}", pkg_name);
+    let on_removed = {
+        let status = status.clone();
+        let btn = btn.clone();
+        move || {
+            status.set_text("");
+            btn.set_sensitive(true);
+        }
+    };
+    let job = async move {
+        status.set_text(&format!("Downloading {}...", pkg_name));
+        set_view_busy(&btn_clone, true);
+        let started = Instant::now();
+        let pkg_for_task = pkg_name.clone();
+        let result = gtk4::gio::spawn_blocking(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(brew::fetch_package(&pkg_for_task, progress_tx))
+        })
+        .await
+        .expect("Background task failed");
+        set_view_busy(&btn_clone, false);
+        btn_clone.set_sensitive(true);
+        history::record(Operation::Fetch, Some(&pkg_name), result.as_deref(), started.elapsed());
+        let outcome = jobs::outcome(&result);
+
+        match result {
+            Ok(_) => {
+                status.set_text(&format!("Downloaded {}; installing it won't need to download again", pkg_name));
+            }
+            Err(e) => {
+                status.set_text("Download failed");
+                show_error_dialog(&btn_clone, &format!("Could not download {}", pkg_name), &e);
+            }
+        }
+        outcome
+    };
+    jobs::enqueue(title, job, on_removed);
+}
+
+/// Install a package from the Browse view, reporting progress in `status` and `progress`.
+/// `btn` stays insensitive after a successful install.
+fn run_browse_install(
+    btn: &Button,
+    status: &Label,
+    progress: &gtk4::ProgressBar,
+    pkg_name: String,
+    options: brew::InstallOptions,
+) {
+    let status = status.clone();
+    let btn_clone = btn.clone();
+    let progress_tx = track_download_progress(progress);
+
+    btn.set_sensitive(false);
+    status.set_text(if jobs::is_busy() { "Queued..." } else { "Installing..." });
+
+    let title = format!("Install {}", pkg_name);
+    let on_removed = {
+        let status = status.clone();
+        let btn = btn.clone();
+        move || {
+            status.set_text("");
+            btn.set_sensitive(true);
+        }
+    };
+    let job = async move {
+        status.set_text("Installing...");
+        set_view_busy(&btn_clone, true);
+        let started = Instant::now();
+        let pkg_for_task = pkg_name.clone();
+        let result = gtk4::gio::spawn_blocking(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(brew::install_package_with_progress(&pkg_for_task, &options, progress_tx))
+        })
+        .await
+        .expect("Background task failed");
+        set_view_busy(&btn_clone, false);
+        history::record(Operation::Install, Some(&pkg_name), result.as_deref(), started.elapsed());
+        let outcome = jobs::outcome(&result);
+
+        match result {
+            Ok(_) => {
+                status.set_text(&format!("Installed {} successfully!", pkg_name));
+                events::emit(AppEvent::PackageInstalled(pkg_name.clone()));
+            }
+            Err(e) => {
+                status.set_text("Installation failed");
+                show_error_dialog(&btn_clone, &format!("Could not install {}", pkg_name), &e);
+                btn_clone.set_sensitive(true);
+            }
+        }
+        outcome
+    };
+    jobs::enqueue(title, job, on_removed);
+}
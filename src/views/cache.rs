@@ -0,0 +1,266 @@
+use super::history::format_timestamp;
+use crate::brew;
+use crate::{create_refresh_button, create_status_label, set_accessible_label, set_labelled_by, set_view_busy};
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4::{Box, Button, Label, ListBox, ListBoxRow, Orientation, ScrolledWindow};
+use libadwaita as adw;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub fn create_cache_view() -> Box {
+    let view = Box::new(Orientation::Vertical, 10);
+    view.set_margin_start(10);
+    view.set_margin_end(10);
+    view.set_margin_top(10);
+    view.set_margin_bottom(10);
+
+    // Header with status and refresh
+    let header_box = Box::new(Orientation::Horizontal, 10);
+    let header = Label::new(Some("Download Cache"));
+    header.add_css_class("title-2");
+    header_box.append(&header);
+
+    let status_label = create_status_label(None);
+    status_label.set_hexpand(true);
+    status_label.set_halign(gtk4::Align::Start);
+    status_label.add_css_class("dim-label");
+    header_box.append(&status_label);
+
+    header_box.append(&create_refresh_button("cache.refresh"));
+
+    view.append(&header_box);
+
+    // Cached downloads
+    let list_scroll = ScrolledWindow::new();
+    list_scroll.set_vexpand(true);
+    let list_box = ListBox::new();
+    list_box.set_selection_mode(gtk4::SelectionMode::None);
+    list_box.add_css_class("boxed-list");
+    list_box.set_placeholder(Some(&Label::new(Some("The download cache is empty"))));
+    list_scroll.set_child(Some(&list_box));
+    view.append(&list_scroll);
+
+    // Bulk deletion by age
+    let prune_box = Box::new(Orientation::Horizontal, 10);
+    let prune_label = Label::new(Some("Delete downloads older than"));
+    prune_box.append(&prune_label);
+
+    let days_spin = gtk4::SpinButton::with_range(1.0, 365.0, 1.0);
+    days_spin.set_value(30.0);
+    set_labelled_by(&days_spin, &prune_label);
+    prune_box.append(&days_spin);
+
+    let days_label = Label::new(Some("days"));
+    prune_box.append(&days_label);
+
+    let prune_btn = Button::with_label("Delete");
+    prune_btn.add_css_class("destructive-action");
+    prune_box.append(&prune_btn);
+    view.append(&prune_box);
+
+    // Entries currently listed
+    let entries_store: Rc<RefCell<Vec<brew::CacheEntry>>> = Rc::new(RefCell::new(Vec::new()));
+
+    // Rebuild the list from disk. Populate and delete refer to each other, so
+    // the populate closure is stored in a cell that row buttons can reach.
+    type Populate = Rc<RefCell<Option<Rc<dyn Fn()>>>>;
+    let populate_cell: Populate = Rc::new(RefCell::new(None));
+
+    let list_box_for_populate = list_box.clone();
+    let status_for_populate = status_label.clone();
+    let entries_for_populate = entries_store.clone();
+    let populate_cell_for_rows = populate_cell.clone();
+    let populate: Rc<dyn Fn()> = Rc::new(move || {
+        let list_box = list_box_for_populate.clone();
+        let status = status_for_populate.clone();
+        let entries_store = entries_for_populate.clone();
+        let populate_cell = populate_cell_for_rows.clone();
+        status.set_text("Loading...");
+
+        glib::spawn_future_local(async move {
+            set_view_busy(&list_box, true);
+            let result = gtk4::gio::spawn_blocking(move || {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                rt.block_on(brew::get_cache_entries())
+            })
+            .await
+            .expect("Background task failed");
+            set_view_busy(&list_box, false);
+
+            while let Some(child) = list_box.first_child() {
+                list_box.remove(&child);
+            }
+
+            match result {
+                Ok(entries) => {
+                    let total: u64 = entries.iter().map(|e| e.size).sum();
+                    status.set_text(&format!("{} downloads, {}", entries.len(), glib::format_size(total)));
+                    for entry in &entries {
+                        let populate = populate_cell.borrow().clone();
+                        list_box.append(&create_cache_row(entry, &status, populate));
+                    }
+                    *entries_store.borrow_mut() = entries;
+                }
+                Err(e) => {
+                    status.set_text(&format!("Error: {}", e.user_message()));
+                }
+            }
+        });
+    });
+    *populate_cell.borrow_mut() = Some(populate.clone());
+
+    // Load the first time the page is shown
+    let loaded = Rc::new(std::cell::Cell::new(false));
+    let populate_on_map = populate.clone();
+    view.connect_map(move |_| {
+        if !loaded.replace(true) {
+            populate_on_map();
+        }
+    });
+
+    let refresh_action = gtk4::gio::SimpleAction::new("refresh", None);
+    let populate_on_refresh = populate.clone();
+    refresh_action.connect_activate(move |_, _| populate_on_refresh());
+    let actions = gtk4::gio::SimpleActionGroup::new();
+    actions.add_action(&refresh_action);
+    view.insert_action_group("cache", Some(&actions));
+
+    // Delete everything older than the chosen number of days
+    let entries_for_prune = entries_store.clone();
+    let status_for_prune = status_label.clone();
+    prune_btn.connect_clicked(move |btn| {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let old = brew::cache_entries_older_than(&entries_for_prune.borrow(), days_spin.value() as u64, now);
+        if old.is_empty() {
+            status_for_prune.set_text("No downloads are that old");
+            return;
+        }
+        confirm_prune(btn, old, &status_for_prune, populate.clone());
+    });
+
+    view
+}
+
+/// Ask before deleting `entries`, listing each with its size
+fn confirm_prune(
+    widget: &impl IsA<gtk4::Widget>,
+    entries: Vec<brew::CacheEntry>,
+    status: &Label,
+    populate: Rc<dyn Fn()>,
+) {
+    use adw::prelude::MessageDialogExt;
+
+    let total: u64 = entries.iter().map(|entry| entry.size).sum();
+    let parent = widget.root().and_then(|root| root.downcast::<gtk4::Window>().ok());
+    let dialog = adw::MessageDialog::new(
+        parent.as_ref(),
+        Some(&match entries.len() {
+            1 => "Delete 1 Old Download?".to_string(),
+            count => format!("Delete {} Old Downloads?", count),
+        }),
+        Some(&format!("This frees {}. Brew downloads them again if they are needed.", glib::format_size(total))),
+    );
+
+    let list = entries
+        .iter()
+        .map(|entry| format!("{} ({})", entry.name, glib::format_size(entry.size)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let label = Label::new(Some(&list));
+    label.set_xalign(0.0);
+    label.set_selectable(true);
+    let scroll = ScrolledWindow::new();
+    scroll.set_max_content_height(240);
+    scroll.set_propagate_natural_height(true);
+    scroll.set_child(Some(&label));
+    dialog.set_extra_child(Some(&scroll));
+
+    dialog.add_response("cancel", "Cancel");
+    dialog.add_response("delete", "Delete");
+    dialog.set_response_appearance("delete", adw::ResponseAppearance::Destructive);
+    dialog.set_default_response(Some("cancel"));
+    dialog.set_close_response("cancel");
+    let status = status.clone();
+    dialog.connect_response(None, move |_, response| {
+        if response == "delete" {
+            delete_cache_entries(entries.clone(), &status, Some(populate.clone()));
+        }
+    });
+    dialog.present();
+}
+
+fn create_cache_row(entry: &brew::CacheEntry, status: &Label, populate: Option<Rc<dyn Fn()>>) -> ListBoxRow {
+    let row = ListBoxRow::new();
+
+    let hbox = Box::new(Orientation::Horizontal, 12);
+    hbox.set_margin_start(12);
+    hbox.set_margin_end(12);
+    hbox.set_margin_top(8);
+    hbox.set_margin_bottom(8);
+
+    let info_box = Box::new(Orientation::Vertical, 2);
+    info_box.set_hexpand(true);
+
+    let name_label = Label::new(Some(&entry.name));
+    name_label.set_halign(gtk4::Align::Start);
+    name_label.set_ellipsize(gtk4::pango::EllipsizeMode::Middle);
+    name_label.add_css_class("heading");
+    info_box.append(&name_label);
+
+    let meta_label = Label::new(Some(&format!(
+        "{} · {}",
+        glib::format_size(entry.size),
+        format_timestamp(entry.modified)
+    )));
+    meta_label.set_halign(gtk4::Align::Start);
+    meta_label.add_css_class("dim-label");
+    meta_label.add_css_class("caption");
+    info_box.append(&meta_label);
+
+    hbox.append(&info_box);
+    set_labelled_by(&row, &name_label);
+
+    let delete_btn = Button::from_icon_name("user-trash-symbolic");
+    delete_btn.set_tooltip_text(Some("Delete this download"));
+    set_accessible_label(&delete_btn, &format!("Delete {}", entry.name));
+    delete_btn.set_valign(gtk4::Align::Center);
+    let entry_for_delete = entry.clone();
+    let status_for_delete = status.clone();
+    delete_btn.connect_clicked(move |_| {
+        delete_cache_entries(vec![entry_for_delete.clone()], &status_for_delete, populate.clone());
+    });
+    hbox.append(&delete_btn);
+
+    row.set_child(Some(&hbox));
+    row
+}
+
+/// Delete cache entries in the background, then reload the list
+fn delete_cache_entries(entries: Vec<brew::CacheEntry>, status: &Label, populate: Option<Rc<dyn Fn()>>) {
+    let status = status.clone();
+    status.set_text("Deleting...");
+
+    glib::spawn_future_local(async move {
+        let result = gtk4::gio::spawn_blocking(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(brew::delete_cache_entries(&entries))
+        })
+        .await
+        .expect("Background task failed");
+
+        match result {
+            Ok(_) => {
+                if let Some(populate) = populate {
+                    populate();
+                }
+            }
+            Err(e) => {
+                status.set_text(&format!("Error: {}", e.user_message()));
+            }
+        }
+    });
+}
@@ -0,0 +1,247 @@
+use crate::brew;
+use crate::history;
+use crate::settings;
+use crate::{
+    create_log_view, create_refresh_button, create_split_view, set_accessible_label, set_labelled_by, set_output_text,
+    show_toast,
+};
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4::{Box, Button, Label, ListBox, ListBoxRow, Orientation, ScrolledWindow, SearchEntry, Window};
+use libadwaita as adw;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub fn create_history_view() -> Box {
+    let view = Box::new(Orientation::Vertical, 10);
+    view.set_margin_start(10);
+    view.set_margin_end(10);
+    view.set_margin_top(10);
+    view.set_margin_bottom(10);
+
+    // Header with search
+    let header_box = Box::new(Orientation::Horizontal, 10);
+    let header = Label::new(Some("Operation History"));
+    header.add_css_class("title-2");
+    header_box.append(&header);
+
+    let search_entry = SearchEntry::new();
+    search_entry.set_placeholder_text(Some("Search packages and output..."));
+    search_entry.set_hexpand(true);
+    header_box.append(&search_entry);
+
+    // Retention limit
+    let keep_label = Label::new(Some("Keep last"));
+    keep_label.add_css_class("dim-label");
+    header_box.append(&keep_label);
+
+    let retention_spin = gtk4::SpinButton::with_range(10.0, 10000.0, 10.0);
+    retention_spin.set_value(settings::get().history_max_entries as f64);
+    retention_spin.set_tooltip_text(Some("Older operations are removed from the history"));
+    set_labelled_by(&retention_spin, &keep_label);
+    header_box.append(&retention_spin);
+
+    let entries_label = Label::new(Some("entries"));
+    entries_label.add_css_class("dim-label");
+    header_box.append(&entries_label);
+    header_box.append(&create_refresh_button("history.refresh"));
+
+    view.append(&header_box);
+
+    // Split view: operations | output
+    let list_scroll = ScrolledWindow::new();
+    list_scroll.set_vexpand(true);
+    let list_box = ListBox::new();
+    list_box.set_selection_mode(gtk4::SelectionMode::Single);
+    list_box.add_css_class("boxed-list");
+    list_box.set_placeholder(Some(&Label::new(Some("No operations recorded yet"))));
+    list_scroll.set_child(Some(&list_box));
+
+    let details_box = Box::new(Orientation::Vertical, 10);
+    details_box.set_margin_start(20);
+    details_box.set_margin_end(20);
+    details_box.set_margin_top(20);
+    details_box.set_margin_bottom(20);
+    details_box.set_hexpand(true);
+
+    let details_title = Label::new(Some("Select an operation"));
+    details_title.add_css_class("title-1");
+    details_title.set_halign(gtk4::Align::Start);
+    details_box.append(&details_title);
+
+    let details_meta = Label::new(None);
+    details_meta.set_halign(gtk4::Align::Start);
+    details_meta.add_css_class("dim-label");
+    details_box.append(&details_meta);
+
+    let (log_view, output_view) = create_log_view();
+    details_box.append(&log_view);
+
+    let save_btn = Button::with_label("Save Log…");
+    save_btn.set_tooltip_text(Some("Write the whole output of this operation to a file"));
+    save_btn.set_halign(gtk4::Align::End);
+    save_btn.set_sensitive(false);
+    details_box.append(&save_btn);
+
+    view.append(&create_split_view("history", &list_scroll, &list_box, &details_box));
+
+    // Entries currently shown, in list order
+    let entries_store: Rc<RefCell<Vec<history::HistoryEntry>>> = Rc::new(RefCell::new(Vec::new()));
+
+    // Rebuild the list from the global history using the current search text
+    let list_box_for_populate = list_box.clone();
+    let entries_for_populate = entries_store.clone();
+    let search_for_populate = search_entry.clone();
+    let populate = Rc::new(move || {
+        let entries = history::search(&search_for_populate.text());
+        while let Some(child) = list_box_for_populate.first_child() {
+            list_box_for_populate.remove(&child);
+        }
+        for entry in &entries {
+            list_box_for_populate.append(&create_history_row(entry));
+        }
+        *entries_for_populate.borrow_mut() = entries;
+    });
+
+    // Refresh whenever the page is shown, so new operations appear
+    let populate_on_map = populate.clone();
+    view.connect_map(move |_| populate_on_map());
+
+    let populate_on_search = populate.clone();
+    search_entry.connect_search_changed(move |_| populate_on_search());
+
+    let refresh_action = gtk4::gio::SimpleAction::new("refresh", None);
+    let populate_on_refresh = populate.clone();
+    refresh_action.connect_activate(move |_, _| {
+        history::init();
+        populate_on_refresh();
+    });
+    let actions = gtk4::gio::SimpleActionGroup::new();
+    actions.add_action(&refresh_action);
+    view.insert_action_group("history", Some(&actions));
+
+    let populate_on_retention = populate.clone();
+    retention_spin.connect_value_changed(move |spin| {
+        settings::update(|s| s.history_max_entries = spin.value() as usize);
+        history::apply_retention();
+        populate_on_retention();
+    });
+
+    // Row selection shows the captured output
+    let selected: Rc<RefCell<Option<history::HistoryEntry>>> = Rc::new(RefCell::new(None));
+    let entries_for_selection = entries_store.clone();
+    let selected_for_selection = selected.clone();
+    let save_btn_for_selection = save_btn.clone();
+    list_box.connect_row_selected(move |_, row| {
+        let entry = row.and_then(|row| entries_for_selection.borrow().get(row.index() as usize).cloned());
+        save_btn_for_selection.set_sensitive(entry.is_some());
+        if let Some(entry) = &entry {
+            details_title.set_text(&entry.title());
+            details_meta.set_text(&format!(
+                "{} · {} · {}",
+                format_timestamp(entry.timestamp),
+                entry.duration_label(),
+                if entry.success { "Succeeded" } else { "Failed" }
+            ));
+
+            let output = if entry.success {
+                entry.full_output()
+            } else {
+                brew::sanitize_error(&entry.full_output())
+            };
+            if output.trim().is_empty() {
+                output_view.buffer().set_text("(no output)");
+            } else {
+                set_output_text(&output_view, &output);
+            }
+        }
+        *selected_for_selection.borrow_mut() = entry;
+    });
+
+    save_btn.connect_clicked(move |btn| {
+        if let Some(entry) = selected.borrow().as_ref() {
+            save_log(btn, entry);
+        }
+    });
+
+    view
+}
+
+/// Ask for a file and write the whole output of `entry` to it
+fn save_log(widget: &impl IsA<gtk4::Widget>, entry: &history::HistoryEntry) {
+    let parent = widget.root().and_then(|root| root.downcast::<Window>().ok());
+    let chooser = gtk4::FileChooserNative::new(
+        Some("Save Log"),
+        parent.as_ref(),
+        gtk4::FileChooserAction::Save,
+        Some("Save"),
+        Some("Cancel"),
+    );
+    chooser.set_current_name(&entry.export_file_name());
+
+    let widget = widget.clone().upcast::<gtk4::Widget>();
+    let output = entry.full_output();
+    // The handler holds a reference so the native dialog stays alive while open
+    let chooser_for_response = chooser.clone();
+    chooser.connect_response(move |_, response| {
+        if response != gtk4::ResponseType::Accept {
+            return;
+        }
+        let Some(path) = chooser_for_response.file().and_then(|f| f.path()) else {
+            return;
+        };
+        let toast = match std::fs::write(&path, &output) {
+            Ok(()) => adw::Toast::new(&format!("Saved log to {}", path.display())),
+            Err(e) => adw::Toast::new(&format!("Could not save the log: {}", e)),
+        };
+        show_toast(&widget, toast);
+    });
+    chooser.show();
+}
+
+fn create_history_row(entry: &history::HistoryEntry) -> ListBoxRow {
+    let row = ListBoxRow::new();
+
+    let hbox = Box::new(Orientation::Horizontal, 12);
+    hbox.set_margin_start(12);
+    hbox.set_margin_end(12);
+    hbox.set_margin_top(8);
+    hbox.set_margin_bottom(8);
+
+    let outcome = if entry.success { "succeeded" } else { "failed" };
+    set_accessible_label(&row, &format!("{}, {}, {}", entry.title(), outcome, format_timestamp(entry.timestamp)));
+
+    let status_icon = Label::new(Some(if entry.success { "✔" } else { "✖" }));
+    status_icon.add_css_class(if entry.success { "success" } else { "error" });
+    hbox.append(&status_icon);
+
+    let info_box = Box::new(Orientation::Vertical, 2);
+    info_box.set_hexpand(true);
+
+    let title_label = Label::new(Some(&entry.title()));
+    title_label.set_halign(gtk4::Align::Start);
+    title_label.add_css_class("heading");
+    info_box.append(&title_label);
+
+    let meta_label = Label::new(Some(&format!(
+        "{} · {}",
+        format_timestamp(entry.timestamp),
+        entry.duration_label()
+    )));
+    meta_label.set_halign(gtk4::Align::Start);
+    meta_label.add_css_class("dim-label");
+    meta_label.add_css_class("caption");
+    info_box.append(&meta_label);
+
+    hbox.append(&info_box);
+    row.set_child(Some(&hbox));
+    row
+}
+
+/// Format a Unix timestamp in local time, e.g. "2025-12-26 14:03"
+pub fn format_timestamp(timestamp: u64) -> String {
+    glib::DateTime::from_unix_local(timestamp as i64)
+        .and_then(|dt| dt.format("%Y-%m-%d %H:%M"))
+        .map(|s| s.to_string())
+        .unwrap_or_default()
+}
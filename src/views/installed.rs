@@ -0,0 +1,1331 @@
+use crate::app_state::AppState;
+use crate::brew;
+use crate::events::{self, AppEvent};
+use crate::history::{self, Operation};
+use crate::inventory::{self, ExportFormat};
+use crate::jobs;
+use crate::notes;
+use crate::package_object::PackageObject;
+use crate::{
+    copy_to_clipboard, create_copy_command_button, create_homepage_link, create_refresh_button, create_split_view,
+    create_status_label, set_accessible_label, set_homepage_link, set_labelled_by, set_view_busy, show_error_dialog,
+    show_toast, stream_into_text_view,
+};
+#[cfg(feature = "terminal")]
+use crate::show_terminal_dialog;
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4::{
+    Box, Button, CheckButton, Label, ListBox, ListBoxRow, Orientation, ScrolledWindow, Spinner, TextView, Window,
+};
+use libadwaita as adw;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+// Index of the "Sort by Size" entry in the Installed sort dropdown
+const SORT_BY_SIZE: u32 = 1;
+// Index of the "Sort by Recently Changed" entry
+const SORT_BY_CHANGED: u32 = 2;
+
+// Entries of the dropdown that narrows the list to recently installed or upgraded packages;
+// the first shows everything
+const CHANGED_WITHIN: [(&str, Option<i64>); 3] =
+    [("Any Time", None), ("Last 7 Days", Some(7)), ("Last 30 Days", Some(30))];
+
+pub fn create_installed_view(state: &AppState) -> Box {
+    let view = Box::new(Orientation::Vertical, 10);
+    view.set_margin_start(10);
+    view.set_margin_end(10);
+    view.set_margin_top(10);
+    view.set_margin_bottom(10);
+
+    // Header with status
+    let header_box = Box::new(Orientation::Horizontal, 10);
+    let header = Label::new(Some("Installed Packages"));
+    header.add_css_class("title-2");
+    header_box.append(&header);
+
+    let spinner = Spinner::new();
+    spinner.set_spinning(true);
+    set_accessible_label(&spinner, "Loading installed packages");
+    header_box.append(&spinner);
+
+    let status_label = create_status_label(Some("Loading..."));
+    status_label.set_hexpand(true);
+    status_label.set_halign(gtk4::Align::Start);
+    header_box.append(&status_label);
+
+    let filter_entry = gtk4::SearchEntry::new();
+    filter_entry.set_placeholder_text(Some("Filter by name, description or note..."));
+    filter_entry.set_width_chars(30);
+    header_box.append(&filter_entry);
+
+    let changed_labels: Vec<&str> = CHANGED_WITHIN.iter().map(|(label, _)| *label).collect();
+    let changed_dropdown = gtk4::DropDown::from_strings(&changed_labels);
+    changed_dropdown.set_tooltip_text(Some("Only show packages installed or upgraded recently"));
+    header_box.append(&changed_dropdown);
+
+    let sort_dropdown = gtk4::DropDown::from_strings(&["Sort by Name", "Sort by Size", "Sort by Recently Changed"]);
+    sort_dropdown.set_tooltip_text(Some("Size is the disk space used by all installed versions"));
+    header_box.append(&sort_dropdown);
+
+    let export_btn = Button::from_icon_name("document-save-as-symbolic");
+    export_btn.set_tooltip_text(Some("Export Package List…"));
+    set_accessible_label(&export_btn, "Export Package List");
+    header_box.append(&export_btn);
+    header_box.append(&create_refresh_button("installed.refresh"));
+
+    view.append(&header_box);
+
+    // Split view: list | details
+
+    // Left: package list
+    let list_scroll = ScrolledWindow::new();
+    list_scroll.set_vexpand(true);
+    let list_box = ListBox::new();
+    list_box.set_selection_mode(gtk4::SelectionMode::Single);
+    list_box.add_css_class("boxed-list");
+    list_scroll.set_child(Some(&list_box));
+
+    // Right: details panel
+    let details_box = Box::new(Orientation::Vertical, 10);
+    details_box.set_margin_start(20);
+    details_box.set_margin_end(20);
+    details_box.set_margin_top(20);
+    details_box.set_margin_bottom(20);
+    details_box.set_hexpand(true);
+
+    let details_name = Label::new(Some("Select a package"));
+    details_name.add_css_class("title-1");
+    details_name.set_halign(gtk4::Align::Start);
+    details_box.append(&details_name);
+
+    let details_version = Label::new(None);
+    details_version.set_halign(gtk4::Align::Start);
+    details_version.add_css_class("dim-label");
+    details_box.append(&details_version);
+
+    let details_desc = Label::new(None);
+    details_desc.set_halign(gtk4::Align::Start);
+    details_desc.set_wrap(true);
+    details_desc.set_max_width_chars(50);
+    details_box.append(&details_desc);
+
+    let details_changed = Label::new(None);
+    details_changed.set_halign(gtk4::Align::Start);
+    details_changed.add_css_class("dim-label");
+    details_box.append(&details_changed);
+
+    let details_homepage = create_homepage_link();
+    details_box.append(&details_homepage);
+
+    // The user's own note on the package, saved when Enter is pressed or the entry is left
+    let note_entry = gtk4::Entry::new();
+    note_entry.set_placeholder_text(Some("Add a note, e.g. why this is installed"));
+    note_entry.set_visible(false);
+    set_accessible_label(&note_entry, "Note");
+    details_box.append(&note_entry);
+    // Package the note entry is showing the note of
+    let note_package: Rc<RefCell<Option<String>>> = Rc::new(RefCell::new(None));
+
+    let link_controls = LinkControls::new();
+    details_box.append(&link_controls.container);
+
+    // Installed kegs; old ones can be removed with brew cleanup
+    let versions_box = Box::new(Orientation::Horizontal, 10);
+    versions_box.set_visible(false);
+    let details_versions = Label::new(None);
+    details_versions.set_halign(gtk4::Align::Start);
+    details_versions.set_wrap(true);
+    versions_box.append(&details_versions);
+    let cleanup_btn = Button::with_label("Remove Old Versions");
+    cleanup_btn.set_tooltip_text(Some("Remove every installed version except the current one (brew cleanup)"));
+    versions_box.append(&cleanup_btn);
+    details_box.append(&versions_box);
+
+    // Files installed by the package, loaded when the expander is opened
+    let files_expander = gtk4::Expander::new(Some("Files"));
+    files_expander.set_visible(false);
+    let files_view = TextView::new();
+    files_view.set_editable(false);
+    files_view.set_monospace(true);
+    let files_scroll = ScrolledWindow::new();
+    files_scroll.set_min_content_height(200);
+    files_scroll.set_child(Some(&files_view));
+    files_expander.set_child(Some(&files_scroll));
+    details_box.append(&files_expander);
+
+    // Recent commits to the formula in its tap, loaded when the expander is opened
+    let history_expander = gtk4::Expander::new(Some("History"));
+    files_expander.bind_property("visible", &history_expander, "visible").sync_create().build();
+    let history_box = Box::new(Orientation::Vertical, 6);
+    history_box.set_margin_top(6);
+    let history_scroll = ScrolledWindow::new();
+    history_scroll.set_min_content_height(200);
+    history_scroll.set_child(Some(&history_box));
+    history_expander.set_child(Some(&history_scroll));
+    details_box.append(&history_expander);
+
+    // The formula's own test (brew test), run on demand with its output below
+    let test_expander = gtk4::Expander::new(Some("Test"));
+    files_expander.bind_property("visible", &test_expander, "visible").sync_create().build();
+    let test_box = Box::new(Orientation::Vertical, 6);
+    test_box.set_margin_top(6);
+    let test_controls = Box::new(Orientation::Horizontal, 10);
+    let test_btn = Button::with_label("Run Test");
+    test_btn.set_tooltip_text(Some("Run the test the formula ships with (brew test)"));
+    test_controls.append(&test_btn);
+    let test_status = create_status_label(None);
+    test_status.set_halign(gtk4::Align::Start);
+    test_controls.append(&test_status);
+    test_box.append(&test_controls);
+    let test_view = TextView::new();
+    test_view.set_editable(false);
+    test_view.set_monospace(true);
+    test_view.set_wrap_mode(gtk4::WrapMode::WordChar);
+    let test_scroll = ScrolledWindow::new();
+    test_scroll.set_min_content_height(200);
+    test_scroll.set_child(Some(&test_view));
+    test_box.append(&test_scroll);
+    test_expander.set_child(Some(&test_box));
+    details_box.append(&test_expander);
+
+    // Uninstall button (hidden until package selected)
+    let actions_box = Box::new(Orientation::Horizontal, 10);
+    actions_box.set_margin_top(20);
+
+    let uninstall_btn = Button::with_label("Uninstall");
+    uninstall_btn.add_css_class("destructive-action");
+    uninstall_btn.set_halign(gtk4::Align::Start);
+    uninstall_btn.set_visible(false);
+    actions_box.append(&uninstall_btn);
+
+    let copy_cmd_btn = create_copy_command_button();
+    uninstall_btn.bind_property("visible", &copy_cmd_btn, "visible").sync_create().build();
+    actions_box.append(&copy_cmd_btn);
+
+    #[cfg(feature = "terminal")]
+    let terminal_btn = {
+        let btn = Button::with_label("Uninstall in Terminal");
+        uninstall_btn.bind_property("visible", &btn, "visible").sync_create().build();
+        actions_box.append(&btn);
+        btn
+    };
+    details_box.append(&actions_box);
+
+    let uninstall_status = create_status_label(None);
+    uninstall_status.set_halign(gtk4::Align::Start);
+    details_box.append(&uninstall_status);
+
+    // Spacer
+    let spacer = Box::new(Orientation::Vertical, 0);
+    spacer.set_vexpand(true);
+    details_box.append(&spacer);
+
+    view.append(&create_split_view("installed", &list_scroll, &list_box, &details_box));
+
+    // Installed packages; the list shows them through a model sorted by the dropdown
+    let sort_dropdown_for_sorter = sort_dropdown.clone();
+    let sorter = gtk4::CustomSorter::new(move |a, b| {
+        let (Some(a), Some(b)) = (a.downcast_ref::<PackageObject>(), b.downcast_ref::<PackageObject>()) else {
+            return gtk4::Ordering::Equal;
+        };
+        compare_installed_packages(a, b, sort_dropdown_for_sorter.selected()).into()
+    });
+    let sorted_packages = gtk4::SortListModel::new(Some(state.installed_packages().clone()), Some(sorter.clone()));
+    // Only packages whose name, description or note contain the filter text
+    // and that changed within the chosen period
+    let filter_entry_for_filter = filter_entry.clone();
+    let changed_dropdown_for_filter = changed_dropdown.clone();
+    let filter = gtk4::CustomFilter::new(move |item| {
+        let Some(package) = item.downcast_ref::<PackageObject>() else {
+            return false;
+        };
+        if let Some((_, Some(days))) = CHANGED_WITHIN.get(changed_dropdown_for_filter.selected() as usize) {
+            let now = glib::DateTime::now_utc().map_or(0, |now| now.to_unix());
+            if package.changed() < now - days * 24 * 60 * 60 {
+                return false;
+            }
+        }
+        let query = filter_entry_for_filter.text().trim().to_lowercase();
+        query.is_empty()
+            || package.name().to_lowercase().contains(&query)
+            || package.desc().is_some_and(|desc| desc.to_lowercase().contains(&query))
+            || notes::matches(&package.name(), &query)
+    });
+    let shown_packages = gtk4::FilterListModel::new(Some(sorted_packages), Some(filter.clone()));
+    let filter_for_entry = filter.clone();
+    filter_entry.connect_search_changed(move |_| filter_for_entry.changed(gtk4::FilterChange::Different));
+    let filter_for_changed = filter.clone();
+    changed_dropdown.connect_selected_notify(move |_| filter_for_changed.changed(gtk4::FilterChange::Different));
+
+    list_box.bind_model(Some(&shown_packages), |item| {
+        let package = item.downcast_ref::<PackageObject>().expect("Installed list holds PackageObjects");
+        create_package_row(package).upcast()
+    });
+
+    // Re-sort the list when the sort order changes
+    let sorter_for_sort = sorter.clone();
+    sort_dropdown.connect_selected_notify(move |_| {
+        sorter_for_sort.changed(gtk4::SorterChange::Different);
+    });
+
+    // Row selection handler
+    // Keep what was typed into the note; the filter may match the package differently now
+    let note_entry_for_save = note_entry.clone();
+    let note_package_for_save = note_package.clone();
+    let filter_for_note = filter.clone();
+    let save_note = Rc::new(move || {
+        if let Some(name) = note_package_for_save.borrow().as_deref() {
+            if notes::get(name).unwrap_or_default() != note_entry_for_save.text().trim() {
+                notes::set(name, &note_entry_for_save.text());
+                filter_for_note.changed(gtk4::FilterChange::Different);
+            }
+        }
+    });
+    let save_note_for_activate = save_note.clone();
+    note_entry.connect_activate(move |_| save_note_for_activate());
+    let note_focus = gtk4::EventControllerFocus::new();
+    let save_note_for_leave = save_note.clone();
+    note_focus.connect_leave(move |_| save_note_for_leave());
+    note_entry.add_controller(note_focus);
+
+    let sorted_for_selection = shown_packages.clone();
+    let note_entry_clone = note_entry.clone();
+    let note_package_clone = note_package.clone();
+    let details_name_clone = details_name.clone();
+    let details_version_clone = details_version.clone();
+    let details_desc_clone = details_desc.clone();
+    let details_changed_clone = details_changed.clone();
+    let details_homepage_clone = details_homepage.clone();
+    let uninstall_btn_clone = uninstall_btn.clone();
+    let link_controls_clone = link_controls.clone();
+    let versions_box_clone = versions_box.clone();
+    let details_versions_clone = details_versions.clone();
+    let files_expander_clone = files_expander.clone();
+    let history_expander_clone = history_expander.clone();
+    let test_expander_clone = test_expander.clone();
+    let test_status_clone = test_status.clone();
+    let test_view_clone = test_view.clone();
+    // The package whose test output is showing; a run only updates the expander while it is
+    let test_package: Rc<RefCell<Option<String>>> = Rc::new(RefCell::new(None));
+    let test_package_clone = test_package.clone();
+
+    list_box.connect_row_selected(move |_, row| {
+        if let Some(package) = row.and_then(|row| package_for_row(&sorted_for_selection, row)) {
+            let pkg = package.package();
+            details_name_clone.set_text(&pkg.name);
+            details_version_clone.set_text(&format!("Version: {}", pkg.version.as_deref().unwrap_or("unknown")));
+            details_desc_clone.set_text(pkg.desc.as_deref().unwrap_or("No description available"));
+            let changed = glib::DateTime::from_unix_local(package.changed()).and_then(|time| time.format("%x"));
+            match changed {
+                Ok(date) if package.changed() > 0 => {
+                    details_changed_clone.set_text(&format!("Last installed or upgraded on {}", date));
+                    details_changed_clone.set_visible(true);
+                }
+                _ => details_changed_clone.set_visible(false),
+            }
+            set_homepage_link(&details_homepage_clone, pkg.homepage.as_deref());
+            save_note();
+            note_entry_clone.set_text(&notes::get(&pkg.name).unwrap_or_default());
+            note_entry_clone.set_visible(true);
+            *note_package_clone.borrow_mut() = Some(pkg.name.clone());
+            uninstall_btn_clone.set_visible(true);
+            // Casks aren't kegs, so there is nothing to link
+            link_controls_clone.show(Some(&pkg).filter(|pkg| !pkg.cask));
+            show_installed_versions(&versions_box_clone, &details_versions_clone, Some(&pkg));
+            files_expander_clone.set_label(Some("Files"));
+            files_expander_clone.set_expanded(false);
+            files_expander_clone.set_visible(!pkg.cask);
+            history_expander_clone.set_label(Some("History"));
+            history_expander_clone.set_expanded(false);
+            test_expander_clone.set_expanded(false);
+            if test_package_clone.borrow().as_deref() != Some(pkg.name.as_str()) {
+                *test_package_clone.borrow_mut() = None;
+                test_status_clone.set_text("");
+                test_view_clone.buffer().set_text("");
+            }
+        }
+    });
+
+    // Run Test handler: stream brew test into the expander and record it in the history
+    let sorted_for_test = shown_packages.clone();
+    let list_box_for_test = list_box.clone();
+
+    test_btn.connect_clicked(move |btn| {
+        let Some(package) = selected_package(&list_box_for_test, &sorted_for_test) else {
+            return;
+        };
+        let pkg_name = package.name();
+        let btn = btn.clone();
+        let status_label = test_status.clone();
+        let test_package = test_package.clone();
+        test_view.buffer().set_text("");
+        *test_package.borrow_mut() = Some(pkg_name.clone());
+        let is_shown = {
+            let test_package = test_package.clone();
+            let pkg_name = pkg_name.clone();
+            move || test_package.borrow().as_deref() == Some(pkg_name.as_str())
+        };
+
+        // Lines go both to the text view, while it still shows this package, and into the
+        // log kept for the history
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+        let (view_tx, view_rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+        stream_into_text_view(&test_view, view_rx);
+        let log = Rc::new(RefCell::new(String::new()));
+        let log_for_lines = log.clone();
+        let is_shown_for_lines = is_shown.clone();
+        let forwarder = glib::spawn_future_local(async move {
+            while let Some(line) = rx.recv().await {
+                log_for_lines.borrow_mut().push_str(&line);
+                log_for_lines.borrow_mut().push('\n');
+                if is_shown_for_lines() {
+                    let _ = view_tx.send(line);
+                }
+            }
+        });
+
+        btn.set_sensitive(false);
+        status_label.set_text(&format!("Testing {}...", pkg_name));
+        glib::spawn_future_local(async move {
+            set_view_busy(&btn, true);
+            let started = Instant::now();
+            let pkg_for_task = pkg_name.clone();
+            let result = gtk4::gio::spawn_blocking(move || {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                rt.block_on(brew::test_formula(&pkg_for_task, tx))
+            })
+            .await
+            .expect("Background task failed");
+            // The sender is gone once the test has finished, so this only waits for the last lines
+            let _ = forwarder.await;
+            set_view_busy(&btn, false);
+            btn.set_sensitive(true);
+            let log = log.borrow().clone();
+            let logged = result.as_ref().map(|_| log.as_str()).map_err(|e| format!("{}\n{}", log, e));
+            history::record(Operation::Test, Some(&pkg_name), logged, started.elapsed());
+
+            if !is_shown() {
+                return;
+            }
+            status_label.remove_css_class("success");
+            status_label.remove_css_class("error");
+            match result {
+                Ok(()) => {
+                    status_label.set_text("✓ Test passed");
+                    status_label.add_css_class("success");
+                }
+                Err(e) => {
+                    status_label.set_text(&format!("Test failed: {}", e.user_message()));
+                    status_label.add_css_class("error");
+                }
+            }
+        });
+    });
+
+    // Files expander - list the selected package's files each time it is expanded, so they are current
+    // after an upgrade; selecting another package collapses it
+    let sorted_for_files = shown_packages.clone();
+    let list_box_for_files = list_box.clone();
+    let files_view_clone = files_view.clone();
+
+    files_expander.connect_expanded_notify(move |expander| {
+        if !expander.is_expanded() {
+            return;
+        }
+        if let Some(package) = selected_package(&list_box_for_files, &sorted_for_files) {
+            let pkg_name = package.name();
+            let expander = expander.clone();
+            let files_view = files_view_clone.clone();
+            files_view.buffer().set_text("Loading...");
+
+            glib::spawn_future_local(async move {
+                let result = gtk4::gio::spawn_blocking(move || {
+                    let rt = tokio::runtime::Runtime::new().unwrap();
+                    rt.block_on(brew::get_package_files(&pkg_name))
+                })
+                .await
+                .expect("Background task failed");
+
+                match result {
+                    Ok(files) => {
+                        expander.set_label(Some(&format!(
+                            "Files ({} files, {})",
+                            files.paths.len(),
+                            glib::format_size(files.total_bytes)
+                        )));
+                        files_view.buffer().set_text(&files.paths.join("\n"));
+                    }
+                    Err(e) => {
+                        files_view.buffer().set_text(&format!("Error: {}", e.user_message()));
+                    }
+                }
+            });
+        }
+    });
+
+    // History expander - list recent formula commits on first expand
+    let sorted_for_history = shown_packages.clone();
+    let list_box_for_history = list_box.clone();
+    let history_box_clone = history_box.clone();
+
+    history_expander.connect_expanded_notify(move |expander| {
+        if !expander.is_expanded() {
+            return;
+        }
+        if let Some(package) = selected_package(&list_box_for_history, &sorted_for_history) {
+            let pkg_name = package.name();
+            let expander = expander.clone();
+            let history_box = history_box_clone.clone();
+            while let Some(child) = history_box.first_child() {
+                history_box.remove(&child);
+            }
+            history_box.append(&history_label("Loading...", false));
+
+            glib::spawn_future_local(async move {
+                let result = gtk4::gio::spawn_blocking(move || {
+                    let rt = tokio::runtime::Runtime::new().unwrap();
+                    rt.block_on(brew::get_formula_log(&pkg_name))
+                })
+                .await
+                .expect("Background task failed");
+
+                while let Some(child) = history_box.first_child() {
+                    history_box.remove(&child);
+                }
+                match result {
+                    Ok(commits) if commits.is_empty() => {
+                        history_box.append(&history_label("No commits found", false));
+                    }
+                    Ok(commits) => {
+                        expander.set_label(Some(&format!(
+                            "History ({} recent commits, latest {})",
+                            commits.len(),
+                            commits[0].short_date()
+                        )));
+                        for commit in &commits {
+                            let byline = format!("{} · {}", commit.short_date(), commit.author);
+                            history_box.append(&history_label(&commit.subject, true));
+                            history_box.append(&history_label(&byline, false));
+                        }
+                    }
+                    Err(e) => {
+                        history_box.append(&history_label(&format!("Error: {}", e.user_message()), false));
+                    }
+                }
+            });
+        }
+    });
+
+    // Remove old versions handler
+    let sorted_for_cleanup = shown_packages.clone();
+    let list_box_for_cleanup = list_box.clone();
+    let versions_box_for_cleanup = versions_box.clone();
+    let details_versions_for_cleanup = details_versions.clone();
+    let uninstall_status_for_cleanup = uninstall_status.clone();
+    let state_for_cleanup = state.clone();
+
+    cleanup_btn.connect_clicked(move |btn| {
+        if let Some(package) = selected_package(&list_box_for_cleanup, &sorted_for_cleanup) {
+            let pkg_name = package.name();
+            let (state, list_box, shown) =
+                (state_for_cleanup.clone(), list_box_for_cleanup.clone(), sorted_for_cleanup.clone());
+            let btn_clone = btn.clone();
+            let versions_box = versions_box_for_cleanup.clone();
+            let details_versions = details_versions_for_cleanup.clone();
+            let status_label = uninstall_status_for_cleanup.clone();
+
+            btn.set_sensitive(false);
+            status_label.set_text(if jobs::is_busy() { "Queued..." } else { "Removing old versions..." });
+            let title = format!("Remove old versions of {}", pkg_name);
+            let on_removed = {
+                let status_label = status_label.clone();
+                let btn = btn.clone();
+                move || {
+                    status_label.set_text("");
+                    btn.set_sensitive(true);
+                }
+            };
+
+            let job = async move {
+                status_label.set_text("Removing old versions...");
+                set_view_busy(&btn_clone, true);
+                let started = Instant::now();
+                let pkg_for_task = pkg_name.clone();
+                let result = gtk4::gio::spawn_blocking(move || {
+                    let rt = tokio::runtime::Runtime::new().unwrap();
+                    rt.block_on(brew::cleanup_package(&pkg_for_task))
+                })
+                .await
+                .expect("Background task failed");
+                set_view_busy(&btn_clone, false);
+                btn_clone.set_sensitive(true);
+                history::record(Operation::Cleanup, Some(&pkg_name), result.as_deref(), started.elapsed());
+                let outcome = jobs::outcome(&result);
+
+                match result {
+                    Ok(_) => {
+                        status_label.set_text("Old versions removed");
+                        events::emit(AppEvent::PackageChanged(pkg_name.clone()));
+                        if let Some(package) = state.find_installed(&pkg_name) {
+                            package.update(|pkg| {
+                                let current = pkg.current_version().map(str::to_string);
+                                pkg.installed_versions.retain(|v| Some(v) == current.as_ref());
+                            });
+                            if is_selected(&list_box, &shown, &pkg_name) {
+                                show_installed_versions(&versions_box, &details_versions, Some(&package.package()));
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        status_label.set_text("Removing old versions failed");
+                        show_error_dialog(&btn_clone, &format!("Could not clean up {}", pkg_name), &e);
+                    }
+                }
+                outcome
+            };
+            jobs::enqueue(title, job, on_removed);
+        }
+    });
+
+    // Link / unlink handlers
+    for link in [true, false] {
+        let btn = if link { &link_controls.link_btn } else { &link_controls.unlink_btn };
+        let sorted_for_link = shown_packages.clone();
+        let list_box_for_link = list_box.clone();
+        let link_controls_for_link = link_controls.clone();
+        let uninstall_status_for_link = uninstall_status.clone();
+        let state_for_link = state.clone();
+
+        btn.connect_clicked(move |btn| {
+            if let Some(package) = selected_package(&list_box_for_link, &sorted_for_link) {
+                let pkg_name = package.name();
+                let (state, list_box, shown) =
+                    (state_for_link.clone(), list_box_for_link.clone(), sorted_for_link.clone());
+                let force = link_controls_for_link.force_check.is_active();
+                let btn_clone = btn.clone();
+                let link_controls = link_controls_for_link.clone();
+                let status_label = uninstall_status_for_link.clone();
+
+                let running_text = if link { "Linking..." } else { "Unlinking..." };
+                btn.set_sensitive(false);
+                status_label.set_text(if jobs::is_busy() { "Queued..." } else { running_text });
+                let title = format!("{} {}", if link { "Link" } else { "Unlink" }, pkg_name);
+                let on_removed = {
+                    let status_label = status_label.clone();
+                    let btn = btn.clone();
+                    move || {
+                        status_label.set_text("");
+                        btn.set_sensitive(true);
+                    }
+                };
+
+                let job = async move {
+                    status_label.set_text(running_text);
+                    set_view_busy(&btn_clone, true);
+                    let started = Instant::now();
+                    let pkg_for_task = pkg_name.clone();
+                    let result = gtk4::gio::spawn_blocking(move || {
+                        let rt = tokio::runtime::Runtime::new().unwrap();
+                        if link {
+                            rt.block_on(brew::link_package(&pkg_for_task, force))
+                        } else {
+                            rt.block_on(brew::unlink_package(&pkg_for_task))
+                        }
+                    })
+                    .await
+                    .expect("Background task failed");
+                    set_view_busy(&btn_clone, false);
+                    btn_clone.set_sensitive(true);
+                    let operation = if link { Operation::Link } else { Operation::Unlink };
+                    history::record(operation, Some(&pkg_name), result.as_deref(), started.elapsed());
+                    let outcome = jobs::outcome(&result);
+
+                    match result {
+                        Ok(_) => {
+                            status_label.set_text(if link { "Linked successfully!" } else { "Unlinked successfully!" });
+                            events::emit(AppEvent::PackageChanged(pkg_name.clone()));
+                            // The list may have been reloaded or another package selected since
+                            if let Some(package) = state.find_installed(&pkg_name) {
+                                package.update(|pkg| {
+                                    pkg.linked_keg = if link { pkg.version.clone() } else { None };
+                                });
+                                if is_selected(&list_box, &shown, &pkg_name) {
+                                    link_controls.show(Some(&package.package()));
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            let action = if link { "link" } else { "unlink" };
+                            status_label.set_text(&format!("Could not {} {}", action, pkg_name));
+                            show_error_dialog(&btn_clone, &format!("Could not {} {}", action, pkg_name), &e);
+                        }
+                    }
+                    outcome
+                };
+                jobs::enqueue(title, job, on_removed);
+            }
+        });
+    }
+
+    // Copy command handler
+    let sorted_for_copy = shown_packages.clone();
+    let list_box_for_copy = list_box.clone();
+
+    copy_cmd_btn.connect_clicked(move |btn| {
+        if let Some(package) = selected_package(&list_box_for_copy, &sorted_for_copy) {
+            let args = brew::uninstall_args(&package.name(), package.package().cask, false);
+            let args: Vec<&str> = args.iter().map(String::as_str).collect();
+            copy_to_clipboard(btn, &brew::command_line(args[0], &args[1..]));
+        }
+    });
+
+    // Uninstall in Terminal asks for confirmation (and whether to zap a cask) like Uninstall
+    #[cfg(feature = "terminal")]
+    let uninstall_in_terminal = Rc::new(std::cell::Cell::new(false));
+    #[cfg(feature = "terminal")]
+    {
+        let uninstall_btn = uninstall_btn.clone();
+        let uninstall_in_terminal = uninstall_in_terminal.clone();
+        terminal_btn.connect_clicked(move |_| {
+            uninstall_in_terminal.set(true);
+            uninstall_btn.emit_clicked();
+        });
+    }
+
+    // Uninstall button handler
+    let state_for_uninstall = state.clone();
+    let sorted_for_uninstall = shown_packages.clone();
+    let list_box_for_uninstall = list_box.clone();
+    let uninstall_status_clone = uninstall_status.clone();
+    let details_name_for_uninstall = details_name.clone();
+    let uninstall_btn_for_handler = uninstall_btn.clone();
+    let link_controls_for_uninstall = link_controls.clone();
+    let versions_box_for_uninstall = versions_box.clone();
+    let files_expander_for_uninstall = files_expander.clone();
+
+    uninstall_btn.connect_clicked(move |btn| {
+        if let Some(package) = selected_package(&list_box_for_uninstall, &sorted_for_uninstall) {
+            let pkg_for_undo = package.package();
+            let pkg_name = pkg_for_undo.name.clone();
+            let is_cask = pkg_for_undo.cask;
+            let state_clone = state_for_uninstall.clone();
+            let status_label = uninstall_status_clone.clone();
+            let btn_clone = btn.clone();
+            let package_clone = package.clone();
+            let list_box_clone = list_box_for_uninstall.clone();
+            let details_name_clone = details_name_for_uninstall.clone();
+            let uninstall_btn_clone = uninstall_btn_for_handler.clone();
+            let link_controls_clone = link_controls_for_uninstall.clone();
+            let versions_box_clone = versions_box_for_uninstall.clone();
+            let files_expander_clone = files_expander_for_uninstall.clone();
+            #[cfg(feature = "terminal")]
+            let in_terminal = uninstall_in_terminal.replace(false);
+
+            // Show confirmation dialog
+            let parent_window = btn.root()
+                .and_then(|r| r.downcast::<gtk4::Window>().ok());
+
+            let dialog = Window::builder()
+                .title("Confirm Uninstall")
+                .default_width(400)
+                .default_height(150)
+                .modal(true)
+                .build();
+
+            if let Some(ref parent) = parent_window {
+                dialog.set_transient_for(Some(parent));
+            }
+
+            let dialog_box = Box::new(Orientation::Vertical, 10);
+            dialog_box.set_margin_start(20);
+            dialog_box.set_margin_end(20);
+            dialog_box.set_margin_top(20);
+            dialog_box.set_margin_bottom(20);
+
+            let title_label = Label::new(Some(&format!("Uninstall {}?", pkg_name)));
+            title_label.add_css_class("title-3");
+            dialog_box.append(&title_label);
+
+            let detail_label = Label::new(Some(&format!(
+                "Are you sure you want to uninstall {}?\nThis action cannot be undone.",
+                pkg_name
+            )));
+            detail_label.set_wrap(true);
+            dialog_box.append(&detail_label);
+
+            // Casks can also be zapped, removing their preferences and support files
+            let zap_check = CheckButton::with_label("Zap (remove all associated files)");
+            zap_check.set_visible(is_cask);
+            dialog_box.append(&zap_check);
+
+            let zap_preview = Label::new(None);
+            zap_preview.set_halign(gtk4::Align::Start);
+            zap_preview.set_wrap(true);
+            zap_preview.set_selectable(true);
+            zap_preview.add_css_class("dim-label");
+            zap_preview.add_css_class("caption");
+            zap_check.bind_property("active", &zap_preview, "visible").sync_create().build();
+            dialog_box.append(&zap_preview);
+
+            let zap_preview_loaded = Rc::new(std::cell::Cell::new(false));
+            let zap_preview_clone = zap_preview.clone();
+            let token_for_preview = pkg_name.clone();
+            zap_check.connect_toggled(move |check| {
+                if !check.is_active() || zap_preview_loaded.replace(true) {
+                    return;
+                }
+                zap_preview_clone.set_text("Loading files to remove...");
+
+                let preview = zap_preview_clone.clone();
+                let token = token_for_preview.clone();
+                glib::spawn_future_local(async move {
+                    let result = gtk4::gio::spawn_blocking(move || {
+                        let rt = tokio::runtime::Runtime::new().unwrap();
+                        rt.block_on(brew::get_cask_zap_paths(&token))
+                    })
+                    .await
+                    .expect("Background task failed");
+
+                    match result {
+                        Ok(paths) if paths.is_empty() => {
+                            preview.set_text("This cask has no zap stanza; only the app itself will be removed.");
+                        }
+                        Ok(paths) => preview.set_text(&format!("Will also remove:\n{}", paths.join("\n"))),
+                        Err(e) => preview.set_text(&format!("Error: {}", e.user_message())),
+                    }
+                });
+            });
+
+            let button_box = Box::new(Orientation::Horizontal, 10);
+            button_box.set_halign(gtk4::Align::End);
+            button_box.set_margin_top(20);
+
+            let cancel_btn = Button::with_label("Cancel");
+            let confirm_btn = Button::with_label("Uninstall");
+            confirm_btn.add_css_class("destructive-action");
+            #[cfg(feature = "terminal")]
+            if in_terminal {
+                confirm_btn.set_label("Uninstall in Terminal");
+            }
+
+            button_box.append(&cancel_btn);
+            button_box.append(&confirm_btn);
+            dialog_box.append(&button_box);
+
+            dialog.set_child(Some(&dialog_box));
+
+            // Cancel button closes dialog
+            let dialog_for_cancel = dialog.clone();
+            cancel_btn.connect_clicked(move |_| {
+                dialog_for_cancel.close();
+            });
+
+            // Confirm button triggers uninstall
+            let dialog_for_confirm = dialog.clone();
+            let pkg_name_for_dialog = pkg_name.clone();
+            confirm_btn.connect_clicked(move |_| {
+                dialog_for_confirm.close();
+                let zap = zap_check.is_active();
+
+                #[cfg(feature = "terminal")]
+                if in_terminal {
+                    let status = status_label.clone();
+                    let name = pkg_name_for_dialog.clone();
+                    let args = brew::uninstall_args(&name, is_cask, zap);
+                    let package = Some(pkg_name_for_dialog.as_str());
+                    show_terminal_dialog(&btn_clone, Operation::Uninstall, package, args, move |success| {
+                        status.set_text(if success { "Uninstalled successfully!" } else { "Uninstall failed" });
+                        if success {
+                            events::emit(AppEvent::PackageUninstalled(name.clone()));
+                        }
+                    });
+                    return;
+                }
+
+                let pkg_name = pkg_name_for_dialog.clone();
+                let status_label = status_label.clone();
+                let btn_clone = btn_clone.clone();
+                let package_clone = package_clone.clone();
+                let list_box_clone = list_box_clone.clone();
+                let details_name_clone = details_name_clone.clone();
+                let uninstall_btn_clone = uninstall_btn_clone.clone();
+                let link_controls_clone = link_controls_clone.clone();
+                let versions_box_clone = versions_box_clone.clone();
+                let files_expander_clone = files_expander_clone.clone();
+                let state_clone = state_clone.clone();
+                let pkg_for_undo = pkg_for_undo.clone();
+
+                btn_clone.set_sensitive(false);
+                status_label.set_text(if jobs::is_busy() { "Queued..." } else { "Uninstalling..." });
+                let title = format!("Uninstall {}", pkg_name);
+                let on_removed = {
+                    let status_label = status_label.clone();
+                    let btn = btn_clone.clone();
+                    move || {
+                        status_label.set_text("");
+                        btn.set_sensitive(true);
+                    }
+                };
+
+                let job = async move {
+                    status_label.set_text("Uninstalling...");
+                    set_view_busy(&btn_clone, true);
+                    let started = Instant::now();
+                    let pkg_for_task = pkg_name.clone();
+                    let result = gtk4::gio::spawn_blocking(move || {
+                        let rt = tokio::runtime::Runtime::new().unwrap();
+                        if is_cask {
+                            rt.block_on(brew::uninstall_cask(&pkg_for_task, zap))
+                        } else {
+                            rt.block_on(brew::uninstall_package(&pkg_for_task))
+                        }
+                    })
+                    .await
+                    .expect("Background task failed");
+                    set_view_busy(&btn_clone, false);
+                    history::record(Operation::Uninstall, Some(&pkg_name), result.as_deref(), started.elapsed());
+                    let outcome = jobs::outcome(&result);
+
+                    match result {
+                        Ok(_) => {
+                            status_label.set_text("Uninstalled successfully!");
+                            events::emit(AppEvent::PackageUninstalled(pkg_name.clone()));
+                            state_clone.remove_installed(&package_clone);
+                            details_name_clone.set_text("Package uninstalled");
+                            uninstall_btn_clone.set_visible(false);
+                            link_controls_clone.show(None);
+                            versions_box_clone.set_visible(false);
+                            files_expander_clone.set_visible(false);
+                            show_undo_uninstall_toast(&list_box_clone, &state_clone, pkg_for_undo);
+                        }
+                        Err(e) => {
+                            status_label.set_text("Uninstall failed");
+                            show_error_dialog(&btn_clone, &format!("Could not uninstall {}", pkg_name), &e);
+                            btn_clone.set_sensitive(true);
+                        }
+                    }
+                    outcome
+                };
+                jobs::enqueue(title, job, on_removed);
+            });
+
+            dialog.present();
+        }
+    });
+
+    // Load packages async; runs again after installs, uninstalls and upgrades
+    // (link/unlink and cleanup update the selected package in place)
+    let list_box_for_load = list_box.clone();
+    let state_for_load = state.clone();
+    let load_packages = Rc::new(move || {
+        let list_box_clone = list_box_for_load.clone();
+        let spinner_clone = spinner.clone();
+        let status_label_clone = status_label.clone();
+        let state_clone = state_for_load.clone();
+        let sorter_clone = sorter.clone();
+        let sort_dropdown_clone = sort_dropdown.clone();
+        spinner_clone.set_visible(true);
+        spinner_clone.set_spinning(true);
+
+        glib::spawn_future_local(async move {
+            set_view_busy(&list_box_clone, true);
+            let result = gtk4::gio::spawn_blocking(move || {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                rt.block_on(brew::get_installed_packages())
+            })
+            .await
+            .expect("Background task failed");
+            set_view_busy(&list_box_clone, false);
+
+            match result {
+                Ok(packages) => {
+                    spinner_clone.set_spinning(false);
+                    spinner_clone.set_visible(false);
+                    status_label_clone.set_text(&format!("{} packages", packages.len()));
+
+                    let names: Vec<String> = packages.iter().map(|p| p.name.clone()).collect();
+                    state_clone.set_installed_packages(packages);
+
+                    // Measuring kegs walks the whole Cellar, so do it after the list is shown
+                    let sizes = gtk4::gio::spawn_blocking(move || {
+                        let rt = tokio::runtime::Runtime::new().unwrap();
+                        rt.block_on(brew::get_keg_sizes(&names))
+                    })
+                    .await
+                    .expect("Background task failed");
+
+                    if let Ok(sizes) = sizes {
+                        let total: u64 = sizes.values().sum();
+                        status_label_clone.set_text(&format!(
+                            "{} packages, {}",
+                            state_clone.installed_count(),
+                            glib::format_size(total)
+                        ));
+                        state_clone.set_keg_sizes(&sizes);
+
+                        // Re-sorting rebuilds the rows, so only do it when the order depends on size
+                        if sort_dropdown_clone.selected() == SORT_BY_SIZE {
+                            sorter_clone.changed(gtk4::SorterChange::Different);
+                        }
+                    }
+                }
+                Err(e) => {
+                    spinner_clone.set_spinning(false);
+                    spinner_clone.set_visible(false);
+                    status_label_clone.set_text(&format!("Error: {}", e.user_message()));
+                }
+            }
+        });
+    });
+    load_packages();
+    let load_packages_for_events = load_packages.clone();
+    events::subscribe(&list_box, move |event| {
+        if !matches!(event, AppEvent::PackageChanged(_)) {
+            load_packages_for_events();
+        }
+    });
+
+    // installed.select: select a package by name, for --package and other deep links
+    let select_action = gtk4::gio::SimpleAction::new("select", Some(glib::VariantTy::STRING));
+    let sorted_for_select = shown_packages.clone();
+    let list_box_for_select = list_box.clone();
+    let filter_entry_for_select = filter_entry.clone();
+    let changed_dropdown_for_select = changed_dropdown.clone();
+    let filter_for_select = filter.clone();
+    select_action.connect_activate(move |_, name| {
+        let Some(name) = name.and_then(|name| name.str()) else {
+            return;
+        };
+        // The package may be filtered out of the list, by name or by when it last changed
+        filter_entry_for_select.set_text("");
+        changed_dropdown_for_select.set_selected(0);
+        filter_for_select.changed(gtk4::FilterChange::LessStrict);
+        let position = sorted_for_select.iter::<PackageObject>().flatten().position(|p| p.name() == name);
+        if let Some(row) = position.and_then(|index| list_box_for_select.row_at_index(index as i32)) {
+            list_box_for_select.select_row(Some(&row));
+            row.grab_focus();
+        }
+    });
+
+    let refresh_action = gtk4::gio::SimpleAction::new("refresh", None);
+    refresh_action.connect_activate(move |_, _| {
+        brew::forget_installed_snapshot();
+        load_packages();
+    });
+
+    // Delete uninstalls, but only from the list so it still deletes text elsewhere
+    let uninstall_action = gtk4::gio::SimpleAction::new("uninstall", None);
+    let uninstall_btn_for_action = uninstall_btn.clone();
+    uninstall_action.connect_activate(move |_, _| {
+        if uninstall_btn_for_action.is_visible() && uninstall_btn_for_action.is_sensitive() {
+            uninstall_btn_for_action.emit_clicked();
+        }
+    });
+    let shortcuts = gtk4::ShortcutController::new();
+    shortcuts.add_shortcut(gtk4::Shortcut::new(
+        gtk4::ShortcutTrigger::parse_string("Delete"),
+        Some(gtk4::NamedAction::new("installed.uninstall")),
+    ));
+    list_box.add_controller(shortcuts);
+
+    let actions = gtk4::gio::SimpleActionGroup::new();
+    actions.add_action(&select_action);
+    actions.add_action(&refresh_action);
+    actions.add_action(&uninstall_action);
+    view.insert_action_group("installed", Some(&actions));
+
+    let state_for_export = state.clone();
+    export_btn.connect_clicked(move |btn| {
+        let store = state_for_export.installed_packages();
+        let packages: Vec<brew::Package> = (0..store.n_items())
+            .filter_map(|i| store.item(i).and_downcast::<PackageObject>())
+            .map(|package| package.package())
+            .collect();
+        export_installed(btn, packages);
+    });
+
+    view
+}
+
+/// Ask for a file and a format, and write `packages` to it
+fn export_installed(widget: &impl IsA<gtk4::Widget>, packages: Vec<brew::Package>) {
+    let parent = widget.root().and_then(|root| root.downcast::<Window>().ok());
+    let chooser = gtk4::FileChooserNative::new(
+        Some("Export Package List"),
+        parent.as_ref(),
+        gtk4::FileChooserAction::Save,
+        Some("Export"),
+        Some("Cancel"),
+    );
+    let formats: Vec<(&str, &str)> =
+        ExportFormat::ALL.iter().map(|format| (format.extension(), format.label())).collect();
+    chooser.add_choice("format", "Format", &formats);
+    chooser.set_choice("format", ExportFormat::Text.extension());
+    chooser.set_current_name("installed-packages.txt");
+
+    let widget = widget.clone().upcast::<gtk4::Widget>();
+    // The handler holds a reference so the native dialog stays alive while open
+    let chooser_for_response = chooser.clone();
+    chooser.connect_response(move |_, response| {
+        if response != gtk4::ResponseType::Accept {
+            return;
+        }
+        let Some(mut path) = chooser_for_response.file().and_then(|f| f.path()) else {
+            return;
+        };
+        let choice = chooser_for_response.choice("format");
+        let format = ExportFormat::ALL
+            .into_iter()
+            .find(|format| choice.as_deref() == Some(format.extension()))
+            .unwrap_or(ExportFormat::Text);
+        // The name was typed for the default format; give it the chosen one's extension
+        if path.extension().is_none_or(|extension| ExportFormat::ALL.iter().any(|f| extension == f.extension())) {
+            path.set_extension(format.extension());
+        }
+
+        let toast = match std::fs::write(&path, inventory::export(&packages, format)) {
+            Ok(()) => adw::Toast::new(&format!("Exported {} packages to {}", packages.len(), path.display())),
+            Err(e) => adw::Toast::new(&format!("Could not export the package list: {}", e)),
+        };
+        show_toast(&widget, toast);
+    });
+    chooser.show();
+}
+
+/// Order installed packages by name, keg size (largest first) or change time (newest first),
+/// following the index selected in the sort dropdown
+fn compare_installed_packages(a: &PackageObject, b: &PackageObject, sort: u32) -> std::cmp::Ordering {
+    match sort {
+        SORT_BY_SIZE => b.size().cmp(&a.size()).then_with(|| a.name().cmp(&b.name())),
+        SORT_BY_CHANGED => b.changed().cmp(&a.changed()).then_with(|| a.name().cmp(&b.name())),
+        _ => a.name().cmp(&b.name()),
+    }
+}
+
+/// The package shown by `row` in a list bound to `model`
+fn package_for_row(model: &impl IsA<gtk4::gio::ListModel>, row: &ListBoxRow) -> Option<PackageObject> {
+    let index = u32::try_from(row.index()).ok()?;
+    model.item(index).and_downcast()
+}
+
+/// The package in the selected row of a list bound to `model`
+fn selected_package(list_box: &ListBox, model: &impl IsA<gtk4::gio::ListModel>) -> Option<PackageObject> {
+    package_for_row(model, &list_box.selected_row()?)
+}
+
+/// Whether the package called `name` is the one selected in a list bound to `model`
+fn is_selected(list_box: &ListBox, model: &impl IsA<gtk4::gio::ListModel>, name: &str) -> bool {
+    selected_package(list_box, model).is_some_and(|package| package.name() == name)
+}
+
+/// Link state and link/unlink buttons in the Installed details pane
+#[derive(Clone)]
+struct LinkControls {
+    container: Box,
+    state_label: Label,
+    link_btn: Button,
+    unlink_btn: Button,
+    force_check: CheckButton,
+}
+
+impl LinkControls {
+    fn new() -> Self {
+        let container = Box::new(Orientation::Horizontal, 10);
+        container.set_visible(false);
+
+        let state_label = Label::new(None);
+        state_label.add_css_class("dim-label");
+        container.append(&state_label);
+
+        let link_btn = Button::with_label("Link");
+        container.append(&link_btn);
+
+        let unlink_btn = Button::with_label("Unlink");
+        container.append(&unlink_btn);
+
+        let force_check = CheckButton::with_label("Force");
+        force_check.set_tooltip_text(Some("Link a keg-only formula into the Homebrew prefix (brew link --force)"));
+        container.append(&force_check);
+
+        Self { container, state_label, link_btn, unlink_btn, force_check }
+    }
+
+    /// Update the controls for the selected package, or hide them when there is none
+    fn show(&self, package: Option<&brew::Package>) {
+        self.container.set_visible(package.is_some());
+        if let Some(pkg) = package {
+            let linked = pkg.linked_keg.is_some();
+            self.state_label.set_text(&pkg.link_state_label());
+            self.link_btn.set_visible(!linked);
+            self.unlink_btn.set_visible(linked);
+            self.force_check.set_visible(!linked && pkg.keg_only);
+            self.force_check.set_active(false);
+        }
+    }
+}
+
+/// List the installed kegs of a package. The box with the cleanup button is only
+/// shown when more than one version is installed.
+fn show_installed_versions(versions_box: &Box, label: &Label, package: Option<&brew::Package>) {
+    let old_versions = package.map(|pkg| pkg.old_versions()).unwrap_or_default();
+    versions_box.set_visible(!old_versions.is_empty());
+
+    if let Some(pkg) = package {
+        let current = pkg.current_version().unwrap_or("unknown");
+        label.set_text(&format!(
+            "Installed versions: {} (current), {} (old)",
+            current,
+            old_versions.join(", ")
+        ));
+    }
+}
+
+/// A line of the History expander; commit subjects are emphasized, everything else dimmed
+fn history_label(text: &str, subject: bool) -> Label {
+    let label = Label::new(Some(text));
+    label.set_halign(gtk4::Align::Start);
+    label.set_xalign(0.0);
+    label.set_wrap(true);
+    label.add_css_class(if subject { "heading" } else { "dim-label" });
+    label
+}
+
+/// Show a toast offering to reinstall a package that was just uninstalled.
+/// On success the package is put back into the list, which keeps it in sort order.
+fn show_undo_uninstall_toast(
+    list_box: &ListBox,
+    state: &AppState,
+    package: brew::Package,
+) {
+    let toast = adw::Toast::builder()
+        .title(format!("Uninstalled {}", package.name))
+        .button_label("Undo")
+        .timeout(10)
+        .build();
+
+    let list_box_for_undo = list_box.clone();
+    let state_for_undo = state.clone();
+
+    toast.connect_button_clicked(move |_| {
+        let list_box = list_box_for_undo.clone();
+        let state = state_for_undo.clone();
+        let package = package.clone();
+        let title = format!("Reinstall {}", package.name);
+
+        let job = async move {
+            set_view_busy(&list_box, true);
+
+            // The uninstall has just used the install rate limit; wait it out rather than fail
+            let wait_ms = brew::INSTALL_RATE_LIMITER.remaining_ms();
+            if wait_ms > 0 {
+                glib::timeout_future(Duration::from_millis(wait_ms)).await;
+            }
+
+            let started = Instant::now();
+            let pkg_for_task = package.name.clone();
+            let options = brew::InstallOptions { cask: package.cask, ..Default::default() };
+            let result = gtk4::gio::spawn_blocking(move || {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                rt.block_on(brew::install_package(&pkg_for_task, &options))
+            })
+            .await
+            .expect("Background task failed");
+            set_view_busy(&list_box, false);
+            history::record(Operation::Install, Some(&package.name), result.as_deref(), started.elapsed());
+            let outcome = jobs::outcome(&result);
+
+            match result {
+                Ok(_) => {
+                    show_toast(&list_box, adw::Toast::new(&format!("Reinstalled {}", package.name)));
+                    events::emit(AppEvent::PackageInstalled(package.name.clone()));
+                    state.add_installed(package);
+                }
+                Err(e) => {
+                    let message = format!(
+                        "Could not reinstall {}: {}",
+                        package.name,
+                        e.user_message()
+                    );
+                    show_toast(&list_box, adw::Toast::new(&glib::markup_escape_text(&message)));
+                }
+            }
+            outcome
+        };
+        jobs::enqueue(title, job, || {});
+    });
+
+    show_toast(list_box, toast);
+}
+
+/// Row for an installed package; the labels follow the package's properties
+fn create_package_row(package: &PackageObject) -> ListBoxRow {
+    let row = ListBoxRow::new();
+
+    let hbox = Box::new(Orientation::Horizontal, 12);
+    hbox.set_margin_start(12);
+    hbox.set_margin_end(12);
+    hbox.set_margin_top(8);
+    hbox.set_margin_bottom(8);
+
+    let info_box = Box::new(Orientation::Vertical, 2);
+    info_box.set_hexpand(true);
+
+    let name_label = Label::new(Some(&package.name()));
+    name_label.set_halign(gtk4::Align::Start);
+    name_label.add_css_class("heading");
+    info_box.append(&name_label);
+
+    let version_label = Label::new(None);
+    version_label.set_halign(gtk4::Align::Start);
+    version_label.add_css_class("dim-label");
+    version_label.add_css_class("caption");
+    bind_optional_label(package, "version", &version_label);
+    info_box.append(&version_label);
+
+    let desc_label = Label::new(None);
+    desc_label.set_halign(gtk4::Align::Start);
+    desc_label.set_wrap(true);
+    desc_label.set_max_width_chars(50);
+    desc_label.add_css_class("caption");
+    bind_optional_label(package, "desc", &desc_label);
+    info_box.append(&desc_label);
+
+    hbox.append(&info_box);
+    set_labelled_by(&row, &name_label);
+
+    // Sizes arrive after the list is shown; hide the label until then
+    let size_label = Label::new(None);
+    size_label.add_css_class("dim-label");
+    size_label.add_css_class("numeric");
+    package
+        .bind_property("size", &size_label, "label")
+        .transform_to(|_, bytes: u64| Some(glib::format_size(bytes).to_string()))
+        .sync_create()
+        .build();
+    package
+        .bind_property("size", &size_label, "visible")
+        .transform_to(|_, bytes: u64| Some(bytes > 0))
+        .sync_create()
+        .build();
+    hbox.append(&size_label);
+
+    row.set_child(Some(&hbox));
+    row
+}
+
+/// Show an optional string property of `package` in `label`, hiding the label when it is unset
+fn bind_optional_label(package: &PackageObject, property: &str, label: &Label) {
+    package
+        .bind_property(property, label, "label")
+        .transform_to(|_, value: Option<String>| Some(value.unwrap_or_default()))
+        .sync_create()
+        .build();
+    package
+        .bind_property(property, label, "visible")
+        .transform_to(|_, value: Option<String>| Some(value.is_some()))
+        .sync_create()
+        .build();
+}
@@ -0,0 +1,147 @@
+use crate::jobs::{self, Job, JobState};
+use crate::{ansi, copy_to_clipboard, set_output_text};
+use gtk4::prelude::*;
+use gtk4::{Box, Button, Label, ListBox, ListBoxRow, Orientation, Revealer, ScrolledWindow, TextView, ToggleButton};
+use libadwaita as adw;
+
+/// Button for the header bar and the bottom panel it slides up: queued, running and finished jobs
+pub fn create_jobs_panel() -> (ToggleButton, Revealer) {
+    let toggle = ToggleButton::with_label("Jobs");
+    toggle.set_tooltip_text(Some("Show queued and finished operations"));
+
+    let revealer = Revealer::new();
+    revealer.set_transition_type(gtk4::RevealerTransitionType::SlideUp);
+    toggle.bind_property("active", &revealer, "reveal-child").sync_create().build();
+
+    let panel = Box::new(Orientation::Vertical, 6);
+    panel.set_margin_start(10);
+    panel.set_margin_end(10);
+    panel.set_margin_top(6);
+    panel.set_margin_bottom(10);
+
+    let header_box = Box::new(Orientation::Horizontal, 10);
+    let header = Label::new(Some("Jobs"));
+    header.add_css_class("heading");
+    header.set_halign(gtk4::Align::Start);
+    header.set_hexpand(true);
+    header_box.append(&header);
+
+    let clear_btn = Button::with_label("Clear Finished");
+    clear_btn.add_css_class("flat");
+    clear_btn.connect_clicked(|_| jobs::clear_finished());
+    header_box.append(&clear_btn);
+    panel.append(&header_box);
+
+    let scroll = ScrolledWindow::new();
+    scroll.set_min_content_height(160);
+    let list_box = ListBox::new();
+    list_box.set_selection_mode(gtk4::SelectionMode::None);
+    list_box.add_css_class("boxed-list");
+    scroll.set_child(Some(&list_box));
+    panel.append(&scroll);
+    revealer.set_child(Some(&panel));
+
+    let toggle_for_refresh = toggle.clone();
+    let list_box_for_refresh = list_box.clone();
+    let refresh = move || {
+        while let Some(child) = list_box_for_refresh.first_child() {
+            list_box_for_refresh.remove(&child);
+        }
+
+        let all = jobs::jobs();
+        if all.is_empty() {
+            let empty = Label::new(Some("No jobs yet"));
+            empty.add_css_class("dim-label");
+            empty.set_margin_top(12);
+            empty.set_margin_bottom(12);
+            list_box_for_refresh.append(&empty);
+        }
+        // Newest first, so the job just started is on top
+        for job in all.iter().rev() {
+            list_box_for_refresh.append(&create_job_row(job));
+        }
+
+        let active = all.iter().filter(|job| !job.state.is_finished()).count();
+        toggle_for_refresh.set_label(&if active > 0 { format!("Jobs ({})", active) } else { "Jobs".to_string() });
+    };
+    refresh();
+    jobs::connect_changed(&list_box, refresh);
+
+    (toggle, revealer)
+}
+
+fn create_job_row(job: &Job) -> ListBoxRow {
+    let row = ListBoxRow::new();
+    let hbox = Box::new(Orientation::Horizontal, 12);
+    hbox.set_margin_start(12);
+    hbox.set_margin_end(12);
+    hbox.set_margin_top(6);
+    hbox.set_margin_bottom(6);
+
+    let title = Label::new(Some(&job.title));
+    title.set_halign(gtk4::Align::Start);
+    title.set_hexpand(true);
+    hbox.append(&title);
+
+    let state = Label::new(Some(job.state.label()));
+    state.add_css_class(match job.state {
+        JobState::Succeeded => "success",
+        JobState::Failed => "error",
+        JobState::Queued | JobState::Running => "dim-label",
+    });
+    hbox.append(&state);
+
+    if job.state == JobState::Queued {
+        let remove_btn = Button::with_label("Remove");
+        remove_btn.add_css_class("flat");
+        remove_btn.set_tooltip_text(Some("Take this job out of the queue"));
+        let id = job.id;
+        remove_btn.connect_clicked(move |_| {
+            jobs::remove(id);
+        });
+        hbox.append(&remove_btn);
+    }
+
+    if job.state.is_finished() {
+        let output_btn = Button::with_label("Output");
+        output_btn.add_css_class("flat");
+        let title = job.title.clone();
+        let output = job.output.clone();
+        output_btn.connect_clicked(move |btn| show_job_output(btn, &title, &output));
+        hbox.append(&output_btn);
+    }
+
+    row.set_child(Some(&hbox));
+    row
+}
+
+fn show_job_output(widget: &impl IsA<gtk4::Widget>, title: &str, output: &str) {
+    use adw::prelude::MessageDialogExt;
+
+    let parent = widget.root().and_then(|root| root.downcast::<gtk4::Window>().ok());
+    let dialog = adw::MessageDialog::new(parent.as_ref(), Some(title), None);
+    dialog.add_response("close", "Close");
+    dialog.set_close_response("close");
+
+    let details_box = Box::new(Orientation::Vertical, 6);
+    let scroll = ScrolledWindow::new();
+    scroll.set_min_content_height(160);
+    scroll.set_max_content_height(320);
+    scroll.set_propagate_natural_height(true);
+    let text_view = TextView::new();
+    text_view.set_editable(false);
+    text_view.set_wrap_mode(gtk4::WrapMode::WordChar);
+    text_view.set_monospace(true);
+    set_output_text(&text_view, if output.trim().is_empty() { "(no output)" } else { output });
+    scroll.set_child(Some(&text_view));
+    details_box.append(&scroll);
+
+    let copy_btn = Button::with_label("Copy to Clipboard");
+    copy_btn.set_halign(gtk4::Align::End);
+    let output_for_copy = ansi::strip(output);
+    copy_btn.connect_clicked(move |btn| copy_to_clipboard(btn, &output_for_copy));
+    details_box.append(&copy_btn);
+
+    dialog.set_extra_child(Some(&details_box));
+    dialog.present();
+}
@@ -0,0 +1,293 @@
+//! Migrate Machine: save this machine's taps, formulae, casks and pins as a bundle, or import one
+
+use crate::brew;
+use crate::inventory;
+use crate::jobs;
+use crate::create_status_label;
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4::{Box, Button, Label, Orientation, Window};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+// Takes the text read from a bundle's path, or why it couldn't be read
+type OpenBundle = Rc<dyn Fn(&std::path::Path, std::io::Result<String>)>;
+
+/// Carry this setup to another machine: save the taps, formulae, casks and pins as one bundle,
+/// or import a bundle (or any Brewfile), seeing what it will add before it starts and a report
+/// of what it did afterwards. A `bundle`, the path of one with its text, is opened for
+/// import straight away.
+pub fn show_migrate_dialog(parent: &impl IsA<Window>, bundle: Option<(&std::path::Path, String)>) {
+    let dialog = Window::builder()
+        .title("Migrate Machine")
+        .default_width(520)
+        .modal(true)
+        .transient_for(parent)
+        .build();
+
+    let vbox = Box::new(Orientation::Vertical, 10);
+    vbox.set_margin_start(20);
+    vbox.set_margin_end(20);
+    vbox.set_margin_top(20);
+    vbox.set_margin_bottom(20);
+
+    let export_title = Label::new(Some("Moving from this machine"));
+    export_title.set_halign(gtk4::Align::Start);
+    export_title.add_css_class("heading");
+    vbox.append(&export_title);
+    let export_hint = Label::new(Some(
+        "Save the taps, the formulae you installed, which of them are pinned, and the casks as one file.",
+    ));
+    export_hint.set_halign(gtk4::Align::Start);
+    export_hint.set_wrap(true);
+    vbox.append(&export_hint);
+    let export_btn = Button::with_label("Save Bundle…");
+    export_btn.set_halign(gtk4::Align::Start);
+    vbox.append(&export_btn);
+
+    vbox.append(&gtk4::Separator::new(Orientation::Horizontal));
+
+    let import_title = Label::new(Some("Moving to this machine"));
+    import_title.set_halign(gtk4::Align::Start);
+    import_title.add_css_class("heading");
+    vbox.append(&import_title);
+    let import_hint = Label::new(Some(
+        "Open a bundle saved on the other machine, or a Brewfile, to set up everything it lists.",
+    ));
+    import_hint.set_halign(gtk4::Align::Start);
+    import_hint.set_wrap(true);
+    vbox.append(&import_hint);
+    let import_btn = Button::with_label("Open Bundle…");
+    import_btn.set_halign(gtk4::Align::Start);
+    vbox.append(&import_btn);
+
+    let plan_label = Label::new(None);
+    plan_label.set_halign(gtk4::Align::Start);
+    plan_label.set_wrap(true);
+    plan_label.set_selectable(true);
+    plan_label.set_visible(false);
+    vbox.append(&plan_label);
+
+    let progress = gtk4::ProgressBar::new();
+    progress.set_show_text(true);
+    progress.set_visible(false);
+    vbox.append(&progress);
+
+    let status_label = create_status_label(None);
+    status_label.set_halign(gtk4::Align::Start);
+    status_label.set_wrap(true);
+    status_label.set_selectable(true);
+    vbox.append(&status_label);
+
+    let start_btn = Button::with_label("Start Import");
+    start_btn.add_css_class("suggested-action");
+    start_btn.set_halign(gtk4::Align::End);
+    start_btn.set_visible(false);
+    vbox.append(&start_btn);
+    dialog.set_child(Some(&vbox));
+
+    let (dialog_for_export, status_for_export) = (dialog.clone(), status_label.clone());
+    export_btn.connect_clicked(move |btn| {
+        save_machine_bundle(&dialog_for_export, btn, &status_for_export);
+    });
+
+    // The plan for the opened bundle, until the import starts
+    let plan: Rc<RefCell<Option<inventory::MigrationPlan>>> = Rc::default();
+
+    // Parses the text of the bundle at a path and shows what importing it will do
+    let open_bundle: OpenBundle = {
+        let (plan, status_label, plan_label, start_btn) =
+            (plan.clone(), status_label.clone(), plan_label.clone(), start_btn.clone());
+        Rc::new(move |path, text| {
+            let bundle = text.map_err(|e| e.to_string()).and_then(|text| inventory::MachineBundle::parse(&text));
+            let bundle = match bundle {
+                Ok(bundle) => bundle,
+                Err(e) => {
+                    status_label.set_text(&format!("Could not open {}: {}", path.display(), e));
+                    return;
+                }
+            };
+
+            plan.take();
+            plan_label.set_visible(false);
+            start_btn.set_visible(false);
+            status_label.set_text("Checking what is installed...");
+            let plan = plan.clone();
+            let (status_label, plan_label, start_btn) = (status_label.clone(), plan_label.clone(), start_btn.clone());
+            glib::spawn_future_local(async move {
+                let result = gtk4::gio::spawn_blocking(move || {
+                    let rt = tokio::runtime::Runtime::new().unwrap();
+                    rt.block_on(inventory::plan_import(&bundle))
+                })
+                .await
+                .expect("Background task failed");
+                let planned = match result {
+                    Ok(planned) => planned,
+                    Err(e) => {
+                        status_label.set_text(&format!("Error: {}", e.user_message()));
+                        return;
+                    }
+                };
+
+                status_label.set_text("");
+                let mut lines = vec![planned.summary()];
+                for (title, names) in [
+                    ("Taps", &planned.taps),
+                    ("Formulae", &planned.formulae),
+                    ("Casks", &planned.casks),
+                    ("Pin", &planned.pins),
+                ] {
+                    if !names.is_empty() {
+                        lines.push(format!("{}: {}", title, names.join(", ")));
+                    }
+                }
+                plan_label.set_text(&lines.join("\n"));
+                plan_label.set_visible(true);
+                start_btn.set_visible(!planned.is_empty());
+                start_btn.set_sensitive(true);
+                *plan.borrow_mut() = Some(planned);
+            });
+        })
+    };
+
+    let (dialog_for_open, open_bundle_for_chooser) = (dialog.clone(), open_bundle.clone());
+    import_btn.connect_clicked(move |_| {
+        let chooser = gtk4::FileChooserNative::new(
+            Some("Open a Bundle"),
+            Some(&dialog_for_open),
+            gtk4::FileChooserAction::Open,
+            Some("Open"),
+            Some("Cancel"),
+        );
+        let open_bundle = open_bundle_for_chooser.clone();
+        // The handler holds a reference so the native dialog stays alive while open
+        let chooser_for_response = chooser.clone();
+        chooser.connect_response(move |_, response| {
+            if response != gtk4::ResponseType::Accept {
+                return;
+            }
+            let Some(path) = chooser_for_response.file().and_then(|f| f.path()) else {
+                return;
+            };
+            let open_bundle = open_bundle.clone();
+            glib::spawn_future_local(async move {
+                let path_for_task = path.clone();
+                let text = gtk4::gio::spawn_blocking(move || std::fs::read_to_string(path_for_task))
+                    .await
+                    .expect("Background task failed");
+                open_bundle(&path, text);
+            });
+        });
+        chooser.show();
+    });
+
+    // Taps first so formulae from them can install, and pins last so their formulae are there
+    start_btn.connect_clicked(move |btn| {
+        let Some(planned) = plan.take() else {
+            return;
+        };
+        let cask_options = brew::InstallOptions { cask: true, ..Default::default() };
+        let steps: Vec<jobs::BatchStep> = planned
+            .taps
+            .into_iter()
+            .map(jobs::BatchStep::Tap)
+            .chain(planned.formulae.into_iter().map(|name| jobs::BatchStep::Install(name, Default::default())))
+            .chain(planned.casks.into_iter().map(|name| jobs::BatchStep::Install(name, cask_options.clone())))
+            .chain(planned.pins.into_iter().map(jobs::BatchStep::Pin))
+            .collect();
+
+        btn.set_sensitive(false);
+        status_label.set_text(if jobs::is_busy() { "Queued..." } else { "" });
+        let (progress_for_batch, status_for_batch) = (progress.clone(), status_label.clone());
+        let on_progress = move |batch: &jobs::BatchProgress| {
+            status_for_batch.set_text("");
+            progress_for_batch.set_visible(true);
+            progress_for_batch.set_fraction(batch.fraction);
+            progress_for_batch.set_text(Some(&batch.text()));
+        };
+        let (progress_for_finished, status_for_finished) = (progress.clone(), status_label.clone());
+        let (plan_label_for_finished, btn_for_finished) = (plan_label.clone(), btn.clone());
+        let on_finished = move |report: &jobs::BatchReport| {
+            progress_for_finished.set_visible(false);
+            plan_label_for_finished.set_visible(false);
+            btn_for_finished.set_visible(false);
+            let mut lines = vec![format!("Import finished. {}", report.summary())];
+            lines.extend(report.failed.iter().map(|(name, error)| format!("{}: {}", name, error)));
+            status_for_finished.set_text(&lines.join("\n"));
+        };
+        let (status_for_removed, btn_for_removed) = (status_label.clone(), btn.clone());
+        jobs::enqueue_batch("Migrate machine", steps, on_progress, on_finished, move || {
+            status_for_removed.set_text("");
+            btn_for_removed.set_visible(false);
+        });
+    });
+
+    dialog.present();
+    if let Some((path, text)) = bundle {
+        open_bundle(path, Ok(text));
+    }
+}
+
+/// Ask where to save a bundle of this machine's setup, as JSON or a Brewfile, and write it
+fn save_machine_bundle(parent: &Window, btn: &Button, status_label: &Label) {
+    let chooser = gtk4::FileChooserNative::new(
+        Some("Save Bundle"),
+        Some(parent),
+        gtk4::FileChooserAction::Save,
+        Some("Save"),
+        Some("Cancel"),
+    );
+    chooser.add_choice("format", "Format", &[("json", "BrewHouse bundle (JSON)"), ("brewfile", "Brewfile")]);
+    chooser.set_choice("format", "json");
+    chooser.set_current_name("brewhouse-bundle.json");
+
+    let (btn, status_label) = (btn.clone(), status_label.clone());
+    // The handler holds a reference so the native dialog stays alive while open
+    let chooser_for_response = chooser.clone();
+    chooser.connect_response(move |_, response| {
+        if response != gtk4::ResponseType::Accept {
+            return;
+        }
+        let Some(mut path) = chooser_for_response.file().and_then(|f| f.path()) else {
+            return;
+        };
+        let as_brewfile = chooser_for_response.choice("format").as_deref() == Some("brewfile");
+        // The name was typed for the default format; a Brewfile goes by that name
+        if as_brewfile && path.extension().is_some_and(|extension| extension == "json") {
+            path.set_file_name("Brewfile");
+        }
+
+        btn.set_sensitive(false);
+        status_label.set_text("Collecting what is installed...");
+        let (btn, status_label) = (btn.clone(), status_label.clone());
+        glib::spawn_future_local(async move {
+            let result = gtk4::gio::spawn_blocking(|| {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                rt.block_on(inventory::machine_bundle())
+            })
+            .await
+            .expect("Background task failed");
+            btn.set_sensitive(true);
+
+            let bundle = match result {
+                Ok(bundle) => bundle,
+                Err(e) => {
+                    status_label.set_text(&format!("Error: {}", e.user_message()));
+                    return;
+                }
+            };
+            let text = if as_brewfile { bundle.to_brewfile() } else { bundle.to_json() };
+            match std::fs::write(&path, text) {
+                Ok(()) => status_label.set_text(&format!(
+                    "Saved {} taps, {} formulae and {} casks to {}",
+                    bundle.taps.len(),
+                    bundle.formulae.len(),
+                    bundle.casks.len(),
+                    path.display()
+                )),
+                Err(e) => status_label.set_text(&format!("Could not save the bundle: {}", e)),
+            }
+        });
+    });
+    chooser.show();
+}
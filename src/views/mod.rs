@@ -0,0 +1,13 @@
+//! One module per page of the main window, plus the jobs panel below them, the first-run setup,
+//! Preferences and the Tools dialogs
+
+pub mod browse;
+pub mod cache;
+pub mod history;
+pub mod installed;
+pub mod jobs;
+pub mod migrate;
+pub mod preferences;
+pub mod setup;
+pub mod tools;
+pub mod updates;
@@ -0,0 +1,184 @@
+//! The Preferences window and the Homebrew environment variables it sets for every brew command
+
+use crate::brew;
+use crate::runner;
+use crate::settings;
+use crate::views::tools::{create_brewfile_row, show_brew_path_dialog};
+use crate::{apply_color_scheme, COLOR_SCHEMES};
+use gtk4::prelude::*;
+use gtk4::Window;
+use libadwaita as adw;
+
+/// Settings that aren't tied to one page: where brew is, and what runs in the background
+pub fn show_preferences_window(parent: &impl IsA<Window>) {
+    use adw::prelude::*;
+
+    let preferences = adw::PreferencesWindow::builder()
+        .transient_for(parent)
+        .modal(true)
+        .search_enabled(false)
+        .build();
+    let page = adw::PreferencesPage::new();
+
+    let appearance_group = adw::PreferencesGroup::new();
+    appearance_group.set_title("Appearance");
+    let scheme_names: Vec<&str> = COLOR_SCHEMES.iter().map(|(_, name)| *name).collect();
+    let scheme_row = adw::ComboRow::builder()
+        .title("Style")
+        .model(&gtk4::StringList::new(&scheme_names))
+        .build();
+    let current = settings::get().color_scheme;
+    if let Some(index) = COLOR_SCHEMES.iter().position(|(scheme, _)| *scheme == current) {
+        scheme_row.set_selected(index as u32);
+    }
+    scheme_row.connect_selected_notify(|row| {
+        if let Some((scheme, _)) = COLOR_SCHEMES.get(row.selected() as usize) {
+            apply_color_scheme(*scheme);
+            settings::update(|s| s.color_scheme = *scheme);
+        }
+    });
+    appearance_group.add(&scheme_row);
+    page.add(&appearance_group);
+
+    let homebrew_group = adw::PreferencesGroup::new();
+    homebrew_group.set_title("Homebrew");
+    let location_row = adw::ActionRow::builder()
+        .title("Homebrew Location")
+        .subtitle(brew::brew_program().display().to_string())
+        .activatable(true)
+        .build();
+    location_row.add_suffix(&gtk4::Image::from_icon_name("go-next-symbolic"));
+    let preferences_for_location = preferences.clone();
+    location_row.connect_activated(move |_| show_brew_path_dialog(&preferences_for_location));
+    homebrew_group.add(&location_row);
+    homebrew_group.add(&create_brewfile_row(parent));
+    homebrew_group.add(&create_switch_row(
+        "Verbose Output",
+        "Run installs and upgrades with --verbose, to see every build step in their output",
+        "app.verbose",
+    ));
+    homebrew_group.add(&create_switch_row(
+        "Skip Quarantine for Casks",
+        "Install apps with --no-quarantine, so macOS doesn't ask before opening them the first time",
+        "app.no-quarantine",
+    ));
+    homebrew_group.add(&create_switch_row(
+        "Simulate Only",
+        "Preview installs, upgrades and cleanups with --dry-run instead of changing anything",
+        "app.dry-run",
+    ));
+    page.add(&homebrew_group);
+
+    let env_group = adw::PreferencesGroup::new();
+    env_group.set_title("Environment");
+    env_group.set_description(Some("Set for every brew command BrewHouse runs"));
+    for (title, name, subtitle) in HOMEBREW_FLAGS {
+        env_group.add(&create_env_switch_row(title, name, subtitle));
+    }
+    for (title, name) in HOMEBREW_MIRRORS {
+        env_group.add(&create_env_entry_row(title, name));
+    }
+    page.add(&env_group);
+
+    let background_group = adw::PreferencesGroup::new();
+    background_group.set_title("Background");
+    background_group.add(&create_switch_row(
+        "Show Tray Icon",
+        "The number of outdated packages, with a menu to upgrade them",
+        "app.tray-icon",
+    ));
+    background_group.add(&create_switch_row(
+        "Check for Updates at Login",
+        "Start brewhouse --daemon when you log in",
+        "app.autostart",
+    ));
+    page.add(&background_group);
+
+    preferences.add(&page);
+    preferences.present();
+}
+
+// Homebrew switches offered in Preferences: (title, variable, what it does)
+const HOMEBREW_FLAGS: &[(&str, &str, &str)] = &[
+    ("No Auto-Update", "HOMEBREW_NO_AUTO_UPDATE", "Don't run brew update before installs and upgrades"),
+    ("No Analytics", "HOMEBREW_NO_ANALYTICS", "Don't send anonymous usage statistics to Homebrew"),
+    ("No Cleanup After Installs", "HOMEBREW_NO_INSTALL_CLEANUP", "Keep old versions until you clean up yourself"),
+];
+
+// Download locations that can point at a mirror instead: (title, variable)
+const HOMEBREW_MIRRORS: &[(&str, &str)] = &[
+    ("Bottle Mirror (HOMEBREW_BOTTLE_DOMAIN)", "HOMEBREW_BOTTLE_DOMAIN"),
+    ("API Mirror (HOMEBREW_API_DOMAIN)", "HOMEBREW_API_DOMAIN"),
+];
+
+/// Pass the environment variables from settings to every brew command
+pub fn apply_homebrew_env() {
+    runner::set_env(settings::get().homebrew_env.into_iter().collect());
+}
+
+/// Preferences row with a switch that sets `name`=1 for every brew command while it is on
+fn create_env_switch_row(title: &str, name: &str, subtitle: &str) -> adw::ActionRow {
+    use adw::prelude::*;
+
+    let switch = gtk4::Switch::new();
+    switch.set_valign(gtk4::Align::Center);
+    switch.set_active(settings::get().homebrew_env.contains_key(name));
+    let name = name.to_string();
+    let row = adw::ActionRow::builder()
+        .title(title)
+        .subtitle(format!("{}: {}", name, subtitle))
+        .activatable_widget(&switch)
+        .build();
+    switch.connect_active_notify(move |switch| {
+        let active = switch.is_active();
+        settings::update(|s| {
+            if active {
+                s.homebrew_env.insert(name.clone(), "1".to_string());
+            } else {
+                s.homebrew_env.remove(&name);
+            }
+        });
+        apply_homebrew_env();
+    });
+    row.add_suffix(&switch);
+    row
+}
+
+/// Preferences row for a URL that `name` is set to for every brew command; empty unsets it.
+/// The API mirror is also used for the analytics BrewHouse downloads itself.
+fn create_env_entry_row(title: &str, name: &str) -> adw::EntryRow {
+    use adw::prelude::*;
+
+    let row = adw::EntryRow::builder().title(title).show_apply_button(true).build();
+    row.set_text(settings::get().homebrew_env.get(name).map_or("", String::as_str));
+    let name = name.to_string();
+    row.connect_apply(move |row| {
+        let url = row.text().trim().to_string();
+        if !url.is_empty() && !url.starts_with("https://") && !url.starts_with("http://") {
+            row.add_css_class("error");
+            return;
+        }
+        row.remove_css_class("error");
+        settings::update(|s| {
+            if url.is_empty() {
+                s.homebrew_env.remove(&name);
+            } else {
+                s.homebrew_env.insert(name.clone(), url.clone());
+            }
+        });
+        apply_homebrew_env();
+    });
+    row
+}
+
+/// Preferences row with a switch bound to the boolean state of `action`
+fn create_switch_row(title: &str, subtitle: &str, action: &str) -> adw::ActionRow {
+    use adw::prelude::*;
+
+    let switch = gtk4::Switch::new();
+    switch.set_valign(gtk4::Align::Center);
+    switch.set_action_name(Some(action));
+    let row = adw::ActionRow::builder().title(title).subtitle(subtitle).activatable_widget(&switch).build();
+    row.add_suffix(&switch);
+    row
+}
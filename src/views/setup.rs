@@ -0,0 +1,599 @@
+//! First launch: installing Homebrew when it's missing, and the setup wizard shown before the main window
+
+use crate::brew;
+use crate::history::{self, Operation};
+use crate::jobs;
+use crate::settings;
+use crate::views;
+use crate::stream_into_text_view;
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4::{Application, Box, Button, CheckButton, Label, Orientation, ScrolledWindow, Spinner, TextView, Window};
+use libadwaita as adw;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Instant;
+
+/// First-run dialog shown when brew can't be found. Offers to run the official
+/// install script and calls `on_installed` once brew works.
+pub fn show_install_brew_dialog<F: Fn() + 'static>(app: &Application, on_installed: F) {
+    let dialog = Window::builder()
+        .application(app)
+        .title("BrewHouse - Homebrew Not Found")
+        .default_width(600)
+        .default_height(400)
+        .modal(true)
+        .build();
+
+    let vbox = Box::new(Orientation::Vertical, 10);
+    vbox.set_margin_start(20);
+    vbox.set_margin_end(20);
+    vbox.set_margin_top(20);
+    vbox.set_margin_bottom(20);
+
+    let header_box = Box::new(Orientation::Horizontal, 10);
+    let spinner = Spinner::new();
+    spinner.set_visible(false);
+    header_box.append(&spinner);
+
+    let status_label = Label::new(Some("Homebrew is not installed"));
+    status_label.add_css_class("title-3");
+    header_box.append(&status_label);
+    vbox.append(&header_box);
+
+    let explanation = Label::new(Some(&format!(
+        "BrewHouse manages packages through Homebrew, but no brew executable was found on your PATH. \
+         BrewHouse can run the official install script for you:\n\n{}\n\n\
+         The installer needs administrator rights. If it asks for a password, run the command \
+         above in a terminal instead, then choose Check Again.",
+        brew::install_script_command()
+    )));
+    explanation.set_wrap(true);
+    explanation.set_selectable(true);
+    explanation.set_halign(gtk4::Align::Start);
+    vbox.append(&explanation);
+
+    let scroll = ScrolledWindow::new();
+    scroll.set_vexpand(true);
+    scroll.set_hexpand(true);
+    scroll.set_visible(false);
+
+    let text_view = TextView::new();
+    text_view.set_editable(false);
+    text_view.set_wrap_mode(gtk4::WrapMode::Word);
+    text_view.set_monospace(true);
+    scroll.set_child(Some(&text_view));
+    vbox.append(&scroll);
+
+    let button_box = Box::new(Orientation::Horizontal, 10);
+    button_box.set_halign(gtk4::Align::End);
+
+    let quit_btn = Button::with_label("Quit");
+    let check_btn = Button::with_label("Check Again");
+    let install_btn = Button::with_label("Install Homebrew");
+    install_btn.add_css_class("suggested-action");
+    button_box.append(&quit_btn);
+    button_box.append(&check_btn);
+    button_box.append(&install_btn);
+    vbox.append(&button_box);
+
+    dialog.set_child(Some(&vbox));
+    dialog.present();
+
+    let on_installed = Rc::new(on_installed);
+
+    let dialog_for_quit = dialog.clone();
+    quit_btn.connect_clicked(move |_| dialog_for_quit.close());
+
+    let dialog_for_check = dialog.clone();
+    let status_for_check = status_label.clone();
+    let on_installed_for_check = on_installed.clone();
+    check_btn.connect_clicked(move |_| {
+        if locate_installed_brew() {
+            dialog_for_check.close();
+            on_installed_for_check();
+        } else {
+            status_for_check.set_text("Homebrew is still not installed");
+        }
+    });
+
+    let dialog_for_install = dialog.clone();
+    install_btn.connect_clicked(move |btn| {
+        btn.set_sensitive(false);
+        check_btn.set_sensitive(false);
+        spinner.set_visible(true);
+        spinner.set_spinning(true);
+        status_label.set_text("Installing Homebrew...");
+        scroll.set_visible(true);
+        let buffer = text_view.buffer();
+        buffer.set_text("");
+
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+        stream_into_text_view(&text_view, rx);
+
+        let btn = btn.clone();
+        let check_btn = check_btn.clone();
+        let spinner = spinner.clone();
+        let status_label = status_label.clone();
+        let buffer = buffer.clone();
+        let dialog = dialog_for_install.clone();
+        let on_installed = on_installed.clone();
+        glib::spawn_future_local(async move {
+            let result = gtk4::gio::spawn_blocking(move || {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                rt.block_on(brew::install_homebrew(tx))
+            })
+            .await
+            .expect("Background task failed");
+
+            spinner.set_spinning(false);
+            spinner.set_visible(false);
+            btn.set_sensitive(true);
+            check_btn.set_sensitive(true);
+
+            match result {
+                Ok(()) if locate_installed_brew() => {
+                    dialog.close();
+                    on_installed();
+                }
+                Ok(()) => {
+                    status_label.set_text("Installed, but brew could not be found");
+                    let mut end = buffer.end_iter();
+                    buffer.insert(
+                        &mut end,
+                        "\nFollow the installer's \"Next steps\" to add brew to your PATH, then choose Check Again.\n",
+                    );
+                }
+                Err(e) => {
+                    status_label.set_text("Installation failed");
+                    let mut end = buffer.end_iter();
+                    buffer.insert(&mut end, &format!("\nError: {}\n", e.user_message()));
+                }
+            }
+        });
+    });
+}
+
+/// Onboarding shown before the main window on first launch: checks brew and the
+/// shell setup, offers recommended taps and a Brewfile import.
+pub fn show_setup_wizard<F: Fn() + 'static>(app: &Application, on_complete: F) {
+    let dialog = Window::builder()
+        .application(app)
+        .title("Welcome to BrewHouse")
+        .default_width(600)
+        .default_height(460)
+        .modal(true)
+        .build();
+
+    let vbox = Box::new(Orientation::Vertical, 10);
+    vbox.set_margin_start(20);
+    vbox.set_margin_end(20);
+    vbox.set_margin_top(20);
+    vbox.set_margin_bottom(20);
+
+    let carousel = adw::Carousel::new();
+    carousel.set_vexpand(true);
+    carousel.set_hexpand(true);
+    carousel.set_allow_scroll_wheel(false);
+    carousel.append(&create_setup_welcome_page());
+    carousel.append(&create_setup_shell_page());
+    carousel.append(&create_setup_taps_page());
+    carousel.append(&create_setup_brewfile_page(&dialog));
+    vbox.append(&carousel);
+
+    let dots = adw::CarouselIndicatorDots::new();
+    dots.set_carousel(Some(&carousel));
+    vbox.append(&dots);
+
+    let button_box = Box::new(Orientation::Horizontal, 10);
+    let skip_btn = Button::with_label("Skip Setup");
+    button_box.append(&skip_btn);
+
+    let spacer = Box::new(Orientation::Horizontal, 0);
+    spacer.set_hexpand(true);
+    button_box.append(&spacer);
+
+    let back_btn = Button::with_label("Back");
+    back_btn.set_sensitive(false);
+    let next_btn = Button::with_label("Next");
+    next_btn.add_css_class("suggested-action");
+    button_box.append(&back_btn);
+    button_box.append(&next_btn);
+    vbox.append(&button_box);
+
+    dialog.set_child(Some(&vbox));
+    dialog.present();
+
+    let back_for_page = back_btn.clone();
+    let next_for_page = next_btn.clone();
+    let skip_for_page = skip_btn.clone();
+    carousel.connect_page_changed(move |carousel, index| {
+        let last = index + 1 == carousel.n_pages();
+        back_for_page.set_sensitive(index > 0);
+        next_for_page.set_label(if last { "Start Using BrewHouse" } else { "Next" });
+        skip_for_page.set_visible(!last);
+    });
+
+    let carousel_for_back = carousel.clone();
+    back_btn.connect_clicked(move |_| {
+        let index = carousel_for_back.position().round() as u32;
+        if index > 0 {
+            carousel_for_back.scroll_to(&carousel_for_back.nth_page(index - 1), true);
+        }
+    });
+
+    let finish = Rc::new(move |dialog: &Window| {
+        settings::update(|s| s.onboarding_complete = true);
+        dialog.close();
+        on_complete();
+    });
+
+    let dialog_for_skip = dialog.clone();
+    let finish_for_skip = finish.clone();
+    skip_btn.connect_clicked(move |_| finish_for_skip(&dialog_for_skip));
+
+    let dialog_for_next = dialog.clone();
+    let carousel_for_next = carousel.clone();
+    next_btn.connect_clicked(move |_| {
+        let index = carousel_for_next.position().round() as u32;
+        if index + 1 < carousel_for_next.n_pages() {
+            carousel_for_next.scroll_to(&carousel_for_next.nth_page(index + 1), true);
+        } else {
+            finish(&dialog_for_next);
+        }
+    });
+}
+
+/// Page layout shared by the setup wizard: title, explanation, then page-specific content
+fn create_setup_page(title: &str, description: &str) -> Box {
+    let page = Box::new(Orientation::Vertical, 12);
+    page.set_hexpand(true);
+    page.set_valign(gtk4::Align::Start);
+    page.set_margin_start(10);
+    page.set_margin_end(10);
+
+    let title_label = Label::new(Some(title));
+    title_label.add_css_class("title-2");
+    title_label.set_halign(gtk4::Align::Start);
+    page.append(&title_label);
+
+    let description_label = Label::new(Some(description));
+    description_label.set_wrap(true);
+    description_label.set_halign(gtk4::Align::Start);
+    description_label.set_xalign(0.0);
+    page.append(&description_label);
+
+    page
+}
+
+fn create_setup_welcome_page() -> Box {
+    let page = create_setup_page(
+        "Welcome to BrewHouse",
+        "A few quick steps to make sure Homebrew is ready to use. You can skip any of them.",
+    );
+
+    let status_label = Label::new(Some("Checking Homebrew..."));
+    status_label.add_css_class("heading");
+    status_label.set_halign(gtk4::Align::Start);
+    status_label.set_wrap(true);
+    page.append(&status_label);
+
+    let program = brew::resolved_brew_program();
+    glib::spawn_future_local(async move {
+        let program_for_task = program.clone();
+        let result = gtk4::gio::spawn_blocking(move || brew::validate_brew_path(&program_for_task))
+            .await
+            .expect("Background task failed");
+
+        match result {
+            Ok(version) => status_label.set_text(&format!("✓ {} at {}", version, program.display())),
+            Err(e) => status_label.set_text(&format!("Error: {}", e.user_message())),
+        }
+    });
+
+    page
+}
+
+fn create_setup_shell_page() -> Box {
+    let page = create_setup_page(
+        "Shell Setup",
+        "Homebrew's commands are only available in a terminal once `brew shellenv` runs in your shell's startup file.",
+    );
+
+    let home = std::path::PathBuf::from(std::env::var_os("HOME").unwrap_or_default());
+    let shell = std::env::var("SHELL").unwrap_or_default();
+
+    let status_label = Label::new(None);
+    status_label.add_css_class("heading");
+    status_label.set_halign(gtk4::Align::Start);
+    status_label.set_wrap(true);
+    page.append(&status_label);
+
+    if let Some(config) = brew::find_shellenv_config(&home) {
+        status_label.set_text(&format!("✓ Already set up in {}", config.display()));
+        return page;
+    }
+
+    let config = brew::shell_config_file(&shell, &home);
+    let line = brew::shellenv_line(&brew::resolved_brew_program(), &shell);
+    status_label.set_text(&format!("Not found in your shell config. BrewHouse can add this line to {}:", config.display()));
+
+    let line_label = Label::new(Some(&line));
+    line_label.add_css_class("monospace");
+    line_label.set_selectable(true);
+    line_label.set_halign(gtk4::Align::Start);
+    line_label.set_wrap(true);
+    page.append(&line_label);
+
+    let add_btn = Button::with_label(&format!(
+        "Add to {}",
+        config.file_name().unwrap_or_default().to_string_lossy()
+    ));
+    add_btn.set_halign(gtk4::Align::Start);
+    page.append(&add_btn);
+
+    add_btn.connect_clicked(move |btn| match brew::add_shellenv(&config, &line) {
+        Ok(()) => {
+            btn.set_visible(false);
+            status_label.set_text(&format!("✓ Added to {}. Open a new terminal to use brew.", config.display()));
+        }
+        Err(e) => status_label.set_text(&format!("Error: {}", e)),
+    });
+
+    page
+}
+
+fn create_setup_taps_page() -> Box {
+    let page = create_setup_page(
+        "Recommended Taps",
+        "Taps are extra repositories of formulae and commands. These are optional.",
+    );
+
+    let checks: Vec<(&'static str, CheckButton)> = brew::RECOMMENDED_TAPS
+        .iter()
+        .map(|(name, description)| {
+            let check = CheckButton::with_label(&format!("{} — {}", name, description));
+            check.set_sensitive(false);
+            page.append(&check);
+            (*name, check)
+        })
+        .collect();
+
+    let add_btn = Button::with_label("Add Selected Taps");
+    add_btn.set_halign(gtk4::Align::Start);
+    add_btn.set_sensitive(false);
+    page.append(&add_btn);
+
+    let status_label = Label::new(None);
+    status_label.set_halign(gtk4::Align::Start);
+    status_label.set_wrap(true);
+    status_label.add_css_class("dim-label");
+    page.append(&status_label);
+
+    // Already tapped repositories show as checked and can't be toggled
+    let checks = Rc::new(checks);
+    let checks_for_load = checks.clone();
+    let add_for_load = add_btn.clone();
+    glib::spawn_future_local(async move {
+        let result = gtk4::gio::spawn_blocking(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(brew::get_taps())
+        })
+        .await
+        .expect("Background task failed");
+
+        let tapped = result.unwrap_or_default();
+        for (name, check) in checks_for_load.iter() {
+            let is_tapped = tapped.iter().any(|t| t == name);
+            check.set_active(is_tapped);
+            check.set_sensitive(!is_tapped);
+        }
+        add_for_load.set_sensitive(checks_for_load.iter().any(|(_, check)| check.is_sensitive()));
+    });
+
+    add_btn.connect_clicked(move |btn| {
+        let selected: Vec<String> = checks
+            .iter()
+            .filter(|(_, check)| check.is_sensitive() && check.is_active())
+            .map(|(name, _)| name.to_string())
+            .collect();
+        if selected.is_empty() {
+            return;
+        }
+
+        btn.set_sensitive(false);
+        status_label.set_text(if jobs::is_busy() { "Queued..." } else { "Adding taps..." });
+        let title = format!("Add {}", selected.join(", "));
+
+        let btn = btn.clone();
+        let checks = checks.clone();
+        let status_label = status_label.clone();
+        let on_removed = {
+            let btn = btn.clone();
+            let status_label = status_label.clone();
+            move || {
+                status_label.set_text("");
+                btn.set_sensitive(true);
+            }
+        };
+        let job = async move {
+            status_label.set_text("Adding taps...");
+            let mut failed = Vec::new();
+            for name in selected {
+                let started = Instant::now();
+                let name_for_task = name.clone();
+                let result = gtk4::gio::spawn_blocking(move || {
+                    let rt = tokio::runtime::Runtime::new().unwrap();
+                    rt.block_on(brew::add_tap(&name_for_task))
+                })
+                .await
+                .expect("Background task failed");
+                history::record(Operation::Tap, Some(&name), result.as_deref(), started.elapsed());
+
+                match result {
+                    Ok(_) => {
+                        if let Some((_, check)) = checks.iter().find(|(n, _)| *n == name) {
+                            check.set_sensitive(false);
+                        }
+                    }
+                    Err(e) => failed.push(format!("{}: {}", name, e.user_message())),
+                }
+            }
+
+            btn.set_sensitive(checks.iter().any(|(_, check)| check.is_sensitive()));
+            if failed.is_empty() {
+                status_label.set_text("✓ Taps added");
+                Ok(String::new())
+            } else {
+                status_label.set_text(&format!("Error: {}", failed.join("\n")));
+                Err(failed.join("\n"))
+            }
+        };
+        jobs::enqueue(title, job, on_removed);
+    });
+
+    page
+}
+
+fn create_setup_brewfile_page(parent: &Window) -> Box {
+    let page = create_setup_page(
+        "Import a Brewfile",
+        "Coming from another machine? Choose a Brewfile (from `brew bundle dump`) to install everything it lists, \
+         or a text file with a package name on each line.",
+    );
+
+    let choose_box = Box::new(Orientation::Horizontal, 10);
+    let choose_btn = Button::with_label("Choose Brewfile...");
+    choose_box.append(&choose_btn);
+    let list_btn = Button::with_label("Choose Package List...");
+    choose_box.append(&list_btn);
+    page.append(&choose_box);
+
+    let status_label = Label::new(None);
+    status_label.set_halign(gtk4::Align::Start);
+    status_label.set_wrap(true);
+    page.append(&status_label);
+
+    let scroll = ScrolledWindow::new();
+    scroll.set_min_content_height(180);
+    scroll.set_vexpand(true);
+    scroll.set_visible(false);
+
+    let text_view = TextView::new();
+    text_view.set_editable(false);
+    text_view.set_wrap_mode(gtk4::WrapMode::Word);
+    text_view.set_monospace(true);
+    scroll.set_child(Some(&text_view));
+    page.append(&scroll);
+
+    let parent_for_list = parent.clone();
+    let status_for_list = status_label.clone();
+    list_btn.connect_clicked(move |_| {
+        let (parent, status_label) = (parent_for_list.clone(), status_for_list.clone());
+        views::tools::choose_package_list_file(&parent_for_list, move |text| match text {
+            Ok(text) => views::tools::show_install_list_dialog(&parent, &text),
+            Err(e) => status_label.set_text(&format!("Error: {}", e)),
+        });
+    });
+
+    let parent = parent.clone();
+    choose_btn.connect_clicked(move |btn| {
+        let chooser = gtk4::FileChooserNative::new(
+            Some("Choose a Brewfile"),
+            Some(&parent),
+            gtk4::FileChooserAction::Open,
+            Some("Import"),
+            Some("Cancel"),
+        );
+
+        let btn = btn.clone();
+        let status_label = status_label.clone();
+        let scroll = scroll.clone();
+        let text_view = text_view.clone();
+        // The handler holds a reference so the native dialog stays alive while open
+        let chooser_for_response = chooser.clone();
+        chooser.connect_response(move |_, response| {
+            if response != gtk4::ResponseType::Accept {
+                return;
+            }
+            let Some(path) = chooser_for_response.file().and_then(|f| f.path()) else {
+                return;
+            };
+
+            btn.set_sensitive(false);
+            let running_text = format!("Installing from {}...", path.display());
+            status_label.set_text(if jobs::is_busy() { "Queued..." } else { &running_text });
+            scroll.set_visible(true);
+            text_view.buffer().set_text("");
+
+            // Keep a copy of the output for the history log
+            let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+            let (log_tx, log_rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+            stream_into_text_view(&text_view, log_rx);
+            let output = Rc::new(RefCell::new(String::new()));
+            let output_for_log = output.clone();
+            glib::spawn_future_local(async move {
+                while let Some(line) = rx.recv().await {
+                    output_for_log.borrow_mut().push_str(&line);
+                    output_for_log.borrow_mut().push('\n');
+                    let _ = log_tx.send(line);
+                }
+            });
+
+            let btn = btn.clone();
+            let status_label = status_label.clone();
+            let title = format!("Install from {}", path.file_name().unwrap_or_default().to_string_lossy());
+            let on_removed = {
+                let btn = btn.clone();
+                let status_label = status_label.clone();
+                move || {
+                    status_label.set_text("");
+                    btn.set_sensitive(true);
+                }
+            };
+            let job = async move {
+                status_label.set_text(&running_text);
+                let started = Instant::now();
+                let path_for_task = path.clone();
+                let result = gtk4::gio::spawn_blocking(move || {
+                    let rt = tokio::runtime::Runtime::new().unwrap();
+                    rt.block_on(brew::bundle_install(&path_for_task, tx))
+                })
+                .await
+                .expect("Background task failed");
+                let path_name = path.to_string_lossy().to_string();
+                let log = output.borrow().clone();
+                history::record(Operation::Bundle, Some(&path_name), result.as_ref().map(|_| log.as_str()), started.elapsed());
+
+                btn.set_sensitive(true);
+                let outcome = jobs::outcome(&result.as_ref().map(|_| log.as_str()));
+                match result {
+                    Ok(()) => status_label.set_text("✓ Brewfile installed"),
+                    Err(e) => status_label.set_text(&format!("Error: {}", e.user_message())),
+                }
+                outcome
+            };
+            jobs::enqueue(title, job, on_removed);
+        });
+        chooser.show();
+    });
+
+    page
+}
+
+/// Re-check for brew after installing it. A fresh install isn't on this process's
+/// PATH yet, so fall back to the standard install locations and remember the one found.
+fn locate_installed_brew() -> bool {
+    if brew::is_brew_installed() {
+        return true;
+    }
+    let Some(path) = brew::detect_brew_installations().into_iter().next() else {
+        return false;
+    };
+    if brew::validate_brew_path(&path).is_err() {
+        return false;
+    }
+    brew::set_brew_path(Some(path.clone()));
+    settings::update(|s| s.brew_path = Some(path.to_string_lossy().to_string()));
+    true
+}
@@ -0,0 +1,971 @@
+//! Dialogs from the Tools menu for getting packages onto this machine: writing a formula,
+//! installing from a list of names, and comparing with a Brewfile. Also the ones for looking
+//! into the Homebrew install: which package owns a file, where brew is, and `brew config`
+
+use crate::brew;
+use crate::inventory;
+use crate::jobs;
+use crate::settings;
+use crate::{copy_to_clipboard, create_status_label, set_accessible_label, set_labelled_by};
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4::{Box, Button, Label, Orientation, ScrolledWindow, Spinner, Window};
+use libadwaita as adw;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Dialog that writes a new formula for an upstream source archive into a tap (`brew create`)
+/// and opens it in the default editor
+pub fn show_create_formula_dialog(parent: &impl IsA<Window>) {
+    let dialog = Window::builder()
+        .title("Create a Formula")
+        .default_width(500)
+        .modal(true)
+        .transient_for(parent)
+        .build();
+
+    let vbox = Box::new(Orientation::Vertical, 10);
+    vbox.set_margin_start(20);
+    vbox.set_margin_end(20);
+    vbox.set_margin_top(20);
+    vbox.set_margin_bottom(20);
+
+    let hint = Label::new(Some(
+        "Homebrew downloads the source archive, guesses the build system and writes a formula to finish by hand. \
+         The tap is created locally if it doesn't exist yet.",
+    ));
+    hint.set_halign(gtk4::Align::Start);
+    hint.set_wrap(true);
+    vbox.append(&hint);
+
+    let grid = gtk4::Grid::new();
+    grid.set_row_spacing(8);
+    grid.set_column_spacing(10);
+    let entries: Vec<gtk4::Entry> = [
+        ("Source URL", "https://example.com/hello-2.12.tar.gz"),
+        ("Name", "Guessed from the URL"),
+        ("Tap", "yourname/tap"),
+    ]
+    .iter()
+    .enumerate()
+    .map(|(row, (title, placeholder))| {
+        let label = Label::new(Some(title));
+        label.set_halign(gtk4::Align::End);
+        grid.attach(&label, 0, row as i32, 1, 1);
+        let entry = gtk4::Entry::new();
+        entry.set_placeholder_text(Some(placeholder));
+        entry.set_hexpand(true);
+        set_labelled_by(&entry, &label);
+        grid.attach(&entry, 1, row as i32, 1, 1);
+        entry
+    })
+    .collect();
+    vbox.append(&grid);
+    let (url_entry, name_entry, tap_entry) = (entries[0].clone(), entries[1].clone(), entries[2].clone());
+
+    let status_label = create_status_label(None);
+    status_label.set_halign(gtk4::Align::Start);
+    status_label.set_wrap(true);
+    status_label.set_selectable(true);
+    vbox.append(&status_label);
+
+    let create_btn = Button::with_label("Create");
+    create_btn.add_css_class("suggested-action");
+    create_btn.set_halign(gtk4::Align::End);
+    vbox.append(&create_btn);
+    dialog.set_child(Some(&vbox));
+
+    // Both the URL and the tap are needed
+    let refresh_sensitive = {
+        let (url_entry, tap_entry, create_btn) = (url_entry.clone(), tap_entry.clone(), create_btn.clone());
+        move || create_btn.set_sensitive(!url_entry.text().trim().is_empty() && !tap_entry.text().trim().is_empty())
+    };
+    refresh_sensitive();
+    for entry in [&url_entry, &tap_entry] {
+        let refresh_sensitive = refresh_sensitive.clone();
+        entry.connect_changed(move |_| refresh_sensitive());
+    }
+
+    create_btn.connect_clicked(move |btn| {
+        let url = url_entry.text().trim().to_string();
+        let name = Some(name_entry.text().trim().to_string()).filter(|name| !name.is_empty());
+        let tap = tap_entry.text().trim().to_string();
+
+        btn.set_sensitive(false);
+        status_label.set_text("Downloading the source archive...");
+        let btn = btn.clone();
+        let status_label = status_label.clone();
+        glib::spawn_future_local(async move {
+            let result = gtk4::gio::spawn_blocking(move || {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                rt.block_on(brew::create_formula(&url, name.as_deref(), &tap))
+            })
+            .await
+            .expect("Background task failed");
+            btn.set_sensitive(true);
+
+            match result {
+                Ok(path) => {
+                    status_label.set_text(&format!("Created {}", path.display()));
+                    let uri = gtk4::gio::File::for_path(&path).uri();
+                    let window = btn.root().and_then(|root| root.downcast::<Window>().ok());
+                    gtk4::show_uri(window.as_ref(), &uri, gtk4::gdk::CURRENT_TIME);
+                }
+                Err(e) => status_label.set_text(&format!("Error: {}", e.user_message())),
+            }
+        });
+    });
+
+    dialog.present();
+}
+
+/// Install packages from a list of names, pasted (e.g. from a blog post) or read from a text
+/// file, starting with `text`. Checking the list shows which names are packages and which
+/// are installed already; the rest are installed as one queued job.
+pub fn show_install_list_dialog(parent: &impl IsA<Window>, text: &str) {
+    let dialog = Window::builder()
+        .title("Install from List")
+        .default_width(500)
+        .modal(true)
+        .transient_for(parent)
+        .build();
+
+    let vbox = Box::new(Orientation::Vertical, 10);
+    vbox.set_margin_start(20);
+    vbox.set_margin_end(20);
+    vbox.set_margin_top(20);
+    vbox.set_margin_bottom(20);
+
+    let hint = Label::new(Some(
+        "Paste package names, one per line or separated by spaces, or open a text file of them. \
+         Commands such as “brew install --cask firefox” work too.",
+    ));
+    hint.set_halign(gtk4::Align::Start);
+    hint.set_wrap(true);
+    vbox.append(&hint);
+
+    let scroll = ScrolledWindow::new();
+    scroll.set_min_content_height(160);
+    scroll.set_vexpand(true);
+    let text_view = gtk4::TextView::new();
+    text_view.set_monospace(true);
+    text_view.set_wrap_mode(gtk4::WrapMode::WordChar);
+    set_labelled_by(&text_view, &hint);
+    scroll.set_child(Some(&text_view));
+    vbox.append(&scroll);
+
+    let check_label = Label::new(None);
+    check_label.set_halign(gtk4::Align::Start);
+    check_label.set_wrap(true);
+    check_label.set_selectable(true);
+    check_label.set_visible(false);
+    vbox.append(&check_label);
+
+    let status_label = create_status_label(None);
+    status_label.set_halign(gtk4::Align::Start);
+    status_label.set_wrap(true);
+    vbox.append(&status_label);
+
+    let progress = gtk4::ProgressBar::new();
+    progress.set_show_text(true);
+    progress.set_visible(false);
+    vbox.append(&progress);
+
+    let button_box = Box::new(Orientation::Horizontal, 10);
+    let open_btn = Button::with_label("Open File…");
+    open_btn.set_hexpand(true);
+    open_btn.set_halign(gtk4::Align::Start);
+    button_box.append(&open_btn);
+    let check_btn = Button::with_label("Check");
+    button_box.append(&check_btn);
+    let install_btn = Button::with_label("Install");
+    install_btn.add_css_class("suggested-action");
+    install_btn.set_sensitive(false);
+    button_box.append(&install_btn);
+    vbox.append(&button_box);
+    dialog.set_child(Some(&vbox));
+
+    // The last check, until the list is edited
+    let checked: Rc<RefCell<Option<brew::PackageListCheck>>> = Rc::default();
+
+    let checked_for_edit = checked.clone();
+    let (check_label_for_edit, install_btn_for_edit) = (check_label.clone(), install_btn.clone());
+    text_view.buffer().connect_changed(move |_| {
+        checked_for_edit.take();
+        check_label_for_edit.set_visible(false);
+        install_btn_for_edit.set_label("Install");
+        install_btn_for_edit.set_sensitive(false);
+    });
+
+    let checked_for_check = checked.clone();
+    let (text_view_for_check, install_btn_for_check) = (text_view.clone(), install_btn.clone());
+    let status_for_check = status_label.clone();
+    check_btn.connect_clicked(move |btn| {
+        let (start, end) = text_view_for_check.buffer().bounds();
+        let names = brew::parse_package_list(&text_view_for_check.buffer().text(&start, &end, false));
+        if names.is_empty() {
+            status_for_check.set_text("No package names found");
+            return;
+        }
+
+        btn.set_sensitive(false);
+        status_for_check.set_text(&format!("Checking {} names...", names.len()));
+        let btn = btn.clone();
+        let checked = checked_for_check.clone();
+        let check_label = check_label.clone();
+        let install_btn = install_btn_for_check.clone();
+        let status_label = status_for_check.clone();
+        glib::spawn_future_local(async move {
+            let result = gtk4::gio::spawn_blocking(move || {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                rt.block_on(brew::check_package_list(&names))
+            })
+            .await
+            .expect("Background task failed");
+            btn.set_sensitive(true);
+
+            let check = match result {
+                Ok(check) => check,
+                Err(e) => {
+                    status_label.set_text(&format!("Error: {}", e.user_message()));
+                    return;
+                }
+            };
+            status_label.set_text("");
+            let mut lines = Vec::new();
+            for (title, names) in [
+                ("Formulae to install", &check.formulae),
+                ("Casks to install", &check.casks),
+                ("Already installed", &check.installed),
+                ("Not found", &check.unknown),
+            ] {
+                if !names.is_empty() {
+                    lines.push(format!("{}: {}", title, names.join(", ")));
+                }
+            }
+            check_label.set_text(&lines.join("\n"));
+            check_label.set_visible(true);
+
+            let count = check.formulae.len() + check.casks.len();
+            install_btn.set_label(&match count {
+                1 => "Install 1 Package".to_string(),
+                count => format!("Install {} Packages", count),
+            });
+            install_btn.set_sensitive(count > 0);
+            *checked.borrow_mut() = Some(check);
+        });
+    });
+
+    // A file replaces the list and is checked straight away
+    let (dialog_for_open, text_view_for_open) = (dialog.clone(), text_view.clone());
+    let (check_btn_for_open, status_for_open) = (check_btn.clone(), status_label.clone());
+    open_btn.connect_clicked(move |_| {
+        let (text_view, check_btn, status_label) =
+            (text_view_for_open.clone(), check_btn_for_open.clone(), status_for_open.clone());
+        choose_package_list_file(&dialog_for_open, move |text| match text {
+            Ok(text) => {
+                text_view.buffer().set_text(&text);
+                check_btn.emit_clicked();
+            }
+            Err(e) => status_label.set_text(&format!("Error: {}", e)),
+        });
+    });
+
+    install_btn.connect_clicked(move |btn| {
+        let Some(check) = checked.borrow().clone() else {
+            return;
+        };
+        let formulae = check.formulae.into_iter().map(|name| (name, brew::InstallOptions::default()));
+        let casks = check
+            .casks
+            .into_iter()
+            .map(|name| (name, brew::InstallOptions { cask: true, ..Default::default() }));
+        let packages: Vec<(String, brew::InstallOptions)> = formulae.chain(casks).collect();
+
+        btn.set_sensitive(false);
+        status_label.set_text(if jobs::is_busy() { "Queued..." } else { "" });
+        let (progress_for_batch, status_for_batch) = (progress.clone(), status_label.clone());
+        let on_progress = move |batch: &jobs::BatchProgress| {
+            status_for_batch.set_text("");
+            progress_for_batch.set_visible(true);
+            progress_for_batch.set_fraction(batch.fraction);
+            progress_for_batch.set_text(Some(&batch.text()));
+        };
+        let (progress_for_finished, status_for_finished) = (progress.clone(), status_label.clone());
+        let btn_for_finished = btn.clone();
+        let on_finished = move |report: &jobs::BatchReport| {
+            progress_for_finished.set_visible(false);
+            status_for_finished.set_text(&report.summary());
+            // Lets packages that failed be tried again
+            btn_for_finished.set_sensitive(true);
+        };
+        let (status_for_removed, btn_for_removed) = (status_label.clone(), btn.clone());
+        jobs::enqueue_install_batch(packages, on_progress, on_finished, move || {
+            status_for_removed.set_text("");
+            btn_for_removed.set_sensitive(true);
+        });
+    });
+
+    dialog.present();
+    if !text.trim().is_empty() {
+        text_view.buffer().set_text(text);
+        check_btn.emit_clicked();
+    }
+}
+
+/// Ask for a text file of package names and pass its contents to `on_loaded`
+pub fn choose_package_list_file(parent: &impl IsA<Window>, on_loaded: impl Fn(std::io::Result<String>) + 'static) {
+    let chooser = gtk4::FileChooserNative::new(
+        Some("Open a Package List"),
+        Some(parent),
+        gtk4::FileChooserAction::Open,
+        Some("Open"),
+        Some("Cancel"),
+    );
+    let text_filter = gtk4::FileFilter::new();
+    text_filter.set_name(Some("Text files"));
+    text_filter.add_mime_type("text/plain");
+    chooser.add_filter(&text_filter);
+    let all_filter = gtk4::FileFilter::new();
+    all_filter.set_name(Some("All files"));
+    all_filter.add_pattern("*");
+    chooser.add_filter(&all_filter);
+
+    // The handler holds a reference so the native dialog stays alive while open
+    let chooser_for_response = chooser.clone();
+    chooser.connect_response(move |_, response| {
+        if response != gtk4::ResponseType::Accept {
+            return;
+        }
+        if let Some(path) = chooser_for_response.file().and_then(|f| f.path()) {
+            on_loaded(std::fs::read_to_string(path));
+        }
+    });
+    chooser.show();
+}
+
+/// Ask for a Brewfile and compare it with what is installed
+pub fn choose_brewfile_to_compare(parent: &impl IsA<Window>) {
+    let chooser = gtk4::FileChooserNative::new(
+        Some("Compare with a Brewfile"),
+        Some(parent),
+        gtk4::FileChooserAction::Open,
+        Some("Compare"),
+        Some("Cancel"),
+    );
+
+    let parent = parent.upcast_ref::<Window>().clone();
+    // The handler holds a reference so the native dialog stays alive while open
+    let chooser_for_response = chooser.clone();
+    chooser.connect_response(move |_, response| {
+        if response != gtk4::ResponseType::Accept {
+            return;
+        }
+        if let Some(path) = chooser_for_response.file().and_then(|f| f.path()) {
+            show_brewfile_compare_dialog(&parent, path);
+        }
+    });
+    chooser.show();
+}
+
+/// What the Brewfile at `path` lists that isn't installed, what is installed that it doesn't
+/// list, and what matches. Missing entries can be installed in one go, and the extras added
+/// to the end of the Brewfile; either compares again once it is done.
+pub fn show_brewfile_compare_dialog(parent: &Window, path: std::path::PathBuf) {
+    let dialog = Window::builder()
+        .title("Compare with Brewfile")
+        .default_width(560)
+        .default_height(520)
+        .modal(true)
+        .transient_for(parent)
+        .build();
+
+    let vbox = Box::new(Orientation::Vertical, 10);
+    vbox.set_margin_start(20);
+    vbox.set_margin_end(20);
+    vbox.set_margin_top(20);
+    vbox.set_margin_bottom(20);
+
+    let path_label = Label::new(Some(&path.display().to_string()));
+    path_label.set_halign(gtk4::Align::Start);
+    path_label.set_ellipsize(gtk4::pango::EllipsizeMode::Middle);
+    path_label.add_css_class("dim-label");
+    vbox.append(&path_label);
+
+    let summary_label = create_status_label(Some("Comparing..."));
+    summary_label.set_halign(gtk4::Align::Start);
+    summary_label.set_wrap(true);
+    vbox.append(&summary_label);
+
+    let scroll = ScrolledWindow::new();
+    scroll.set_vexpand(true);
+    let sections_box = Box::new(Orientation::Vertical, 12);
+    let sections: Vec<(gtk4::Expander, Label)> = [true, true, false]
+        .into_iter()
+        .map(|expanded| {
+            let expander = gtk4::Expander::new(None);
+            expander.set_expanded(expanded);
+            let entries_label = Label::new(None);
+            entries_label.set_halign(gtk4::Align::Start);
+            entries_label.set_selectable(true);
+            entries_label.set_margin_start(20);
+            entries_label.add_css_class("monospace");
+            expander.set_child(Some(&entries_label));
+            sections_box.append(&expander);
+            (expander, entries_label)
+        })
+        .collect();
+    scroll.set_child(Some(&sections_box));
+    vbox.append(&scroll);
+
+    let status_label = create_status_label(None);
+    status_label.set_halign(gtk4::Align::Start);
+    status_label.set_wrap(true);
+    vbox.append(&status_label);
+
+    let progress = gtk4::ProgressBar::new();
+    progress.set_show_text(true);
+    progress.set_visible(false);
+    vbox.append(&progress);
+
+    let button_box = Box::new(Orientation::Horizontal, 10);
+    button_box.set_halign(gtk4::Align::End);
+    let add_extras_btn = Button::with_label("Add Extras to Brewfile");
+    add_extras_btn.set_sensitive(false);
+    button_box.append(&add_extras_btn);
+    let install_btn = Button::with_label("Install Missing");
+    install_btn.add_css_class("suggested-action");
+    install_btn.set_sensitive(false);
+    button_box.append(&install_btn);
+    vbox.append(&button_box);
+    dialog.set_child(Some(&vbox));
+
+    // The last comparison, which the buttons act on
+    let diff: Rc<RefCell<inventory::BrewfileDiff>> = Rc::default();
+
+    let diff_for_compare = diff.clone();
+    let path_for_compare = path.clone();
+    let (summary_for_compare, install_btn_for_compare) = (summary_label.clone(), install_btn.clone());
+    let add_extras_btn_for_compare = add_extras_btn.clone();
+    let compare: Rc<dyn Fn()> = Rc::new(move || {
+        let text = match std::fs::read_to_string(&path_for_compare) {
+            Ok(text) => text,
+            Err(e) => {
+                summary_for_compare.set_text(&format!("Error: {}", e));
+                return;
+            }
+        };
+        install_btn_for_compare.set_sensitive(false);
+        add_extras_btn_for_compare.set_sensitive(false);
+        summary_for_compare.set_text("Comparing...");
+        let diff = diff_for_compare.clone();
+        let sections = sections.clone();
+        let summary_label = summary_for_compare.clone();
+        let (install_btn, add_extras_btn) = (install_btn_for_compare.clone(), add_extras_btn_for_compare.clone());
+        glib::spawn_future_local(async move {
+            let result = gtk4::gio::spawn_blocking(move || {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                rt.block_on(inventory::compare_with_installed(&text))
+            })
+            .await
+            .expect("Background task failed");
+            let compared = match result {
+                Ok(compared) => compared,
+                Err(e) => {
+                    summary_label.set_text(&format!("Error: {}", e.user_message()));
+                    return;
+                }
+            };
+
+            let (missing, extra) = (compared.missing.len(), compared.extra.len());
+            summary_label.set_text(&if missing == 0 && extra == 0 {
+                "Everything in the Brewfile is installed, and nothing else is".to_string()
+            } else {
+                format!("{} missing, {} extra, {} matching", missing, extra, compared.matching.len())
+            });
+            let titles = [
+                "Missing: in the Brewfile, not installed",
+                "Extra: installed, not in the Brewfile",
+                "Matching",
+            ];
+            for (((expander, entries_label), title), entries) in
+                sections.iter().zip(titles).zip([&compared.missing, &compared.extra, &compared.matching])
+            {
+                expander.set_label(Some(&format!("{} ({})", title, entries.len())));
+                let lines: Vec<String> = entries.iter().map(inventory::BrewfileEntry::line).collect();
+                entries_label.set_text(&if lines.is_empty() { "None".to_string() } else { lines.join("\n") });
+            }
+            install_btn.set_sensitive(!compared.missing.is_empty());
+            add_extras_btn.set_sensitive(!compared.extra.is_empty());
+            *diff.borrow_mut() = compared;
+        });
+    });
+
+    // Taps come first so formulae from them can install
+    let (diff_for_install, compare_for_install) = (diff.clone(), compare.clone());
+    let status_for_install = status_label.clone();
+    install_btn.connect_clicked(move |btn| {
+        let mut missing = diff_for_install.borrow().missing.clone();
+        missing.sort_by_key(|entry| entry.kind != inventory::BrewfileKind::Tap);
+        let steps: Vec<jobs::BatchStep> = missing
+            .into_iter()
+            .map(|entry| match entry.kind {
+                inventory::BrewfileKind::Tap => jobs::BatchStep::Tap(entry.name),
+                kind => {
+                    let cask = kind == inventory::BrewfileKind::Cask;
+                    jobs::BatchStep::Install(entry.name, brew::InstallOptions { cask, ..Default::default() })
+                }
+            })
+            .collect();
+
+        btn.set_sensitive(false);
+        status_for_install.set_text(if jobs::is_busy() { "Queued..." } else { "" });
+        let progress_for_batch = progress.clone();
+        let on_progress = move |batch: &jobs::BatchProgress| {
+            progress_for_batch.set_visible(true);
+            progress_for_batch.set_fraction(batch.fraction);
+            progress_for_batch.set_text(Some(&batch.text()));
+        };
+        let (progress_for_finished, status_for_finished) = (progress.clone(), status_for_install.clone());
+        let compare_for_finished = compare_for_install.clone();
+        let on_finished = move |report: &jobs::BatchReport| {
+            progress_for_finished.set_visible(false);
+            compare_for_finished();
+            status_for_finished.set_text(&report.summary());
+        };
+        let (status_for_removed, btn_for_removed) = (status_for_install.clone(), btn.clone());
+        jobs::enqueue_batch("Install from Brewfile", steps, on_progress, on_finished, move || {
+            status_for_removed.set_text("");
+            btn_for_removed.set_sensitive(true);
+        });
+    });
+
+    let compare_for_extras = compare.clone();
+    let status_for_extras = status_label.clone();
+    add_extras_btn.connect_clicked(move |_| {
+        let extra = diff.borrow().extra.clone();
+        let written = std::fs::read_to_string(&path)
+            .and_then(|text| std::fs::write(&path, inventory::append_to_brewfile(&text, &extra)));
+        match written {
+            Ok(()) => {
+                compare_for_extras();
+                status_for_extras.set_text(&match extra.len() {
+                    1 => "Added 1 line to the Brewfile".to_string(),
+                    count => format!("Added {} lines to the Brewfile", count),
+                });
+            }
+            Err(e) => status_for_extras.set_text(&format!("Could not update the Brewfile: {}", e)),
+        }
+    });
+
+    dialog.present();
+    compare();
+}
+
+/// What the last `brew bundle check` of the Brewfile at `path` found, with a way into
+/// Compare with Brewfile to install what is missing
+pub fn show_brewfile_check_dialog(
+    parent: &adw::ApplicationWindow,
+    path: std::path::PathBuf,
+    check: Option<&brew::BundleCheck>,
+) {
+    use adw::prelude::MessageDialogExt;
+
+    let body = match check {
+        Some(check) if check.is_satisfied() => "Everything in the Brewfile is installed.".to_string(),
+        Some(check) => check.missing.join("\n"),
+        None => "The Brewfile hasn't been checked yet, or brew bundle check failed.".to_string(),
+    };
+    let dialog = adw::MessageDialog::new(Some(parent), Some(&path.display().to_string()), Some(&body));
+    dialog.add_response("close", "Close");
+    dialog.add_response("compare", "Compare…");
+    dialog.set_default_response(Some("close"));
+    dialog.set_close_response("close");
+    let parent = parent.clone();
+    dialog.connect_response(None, move |_, response| {
+        if response == "compare" {
+            show_brewfile_compare_dialog(parent.upcast_ref(), path.clone());
+        }
+    });
+    dialog.present();
+}
+
+/// Preferences row choosing the Brewfile the Status panel checks, with a button to stop
+/// checking one. `window` is told to check again whenever it changes.
+pub fn create_brewfile_row(window: &impl IsA<Window>) -> adw::ActionRow {
+    use adw::prelude::*;
+
+    fn subtitle(path: Option<&str>) -> String {
+        path.map_or_else(|| "None; choose one to see whether it is satisfied in Status".to_string(), str::to_string)
+    }
+
+    let path = settings::get().brewfile_path;
+    let row = adw::ActionRow::builder()
+        .title("Brewfile")
+        .subtitle(subtitle(path.as_deref()))
+        .activatable(true)
+        .build();
+    let clear_btn = Button::from_icon_name("edit-clear-symbolic");
+    clear_btn.add_css_class("flat");
+    clear_btn.set_valign(gtk4::Align::Center);
+    clear_btn.set_tooltip_text(Some("Stop Checking a Brewfile"));
+    set_accessible_label(&clear_btn, "Stop Checking a Brewfile");
+    clear_btn.set_visible(path.is_some());
+    row.add_suffix(&clear_btn);
+    row.add_suffix(&gtk4::Image::from_icon_name("go-next-symbolic"));
+
+    let window = window.upcast_ref::<Window>().clone();
+    let set_path = {
+        let (row, clear_btn, window) = (row.clone(), clear_btn.clone(), window.clone());
+        move |path: Option<String>| {
+            row.set_subtitle(&subtitle(path.as_deref()));
+            clear_btn.set_visible(path.is_some());
+            settings::update(|s| s.brewfile_path = path);
+            let _ = window.activate_action("win.check-brewfile", None);
+        }
+    };
+
+    let set_path_for_clear = set_path.clone();
+    clear_btn.connect_clicked(move |_| set_path_for_clear(None));
+
+    row.connect_activated(move |row| {
+        let parent = row.root().and_downcast::<Window>();
+        let chooser = gtk4::FileChooserNative::new(
+            Some("Choose a Brewfile"),
+            parent.as_ref(),
+            gtk4::FileChooserAction::Open,
+            Some("Choose"),
+            Some("Cancel"),
+        );
+        let set_path = set_path.clone();
+        // The handler holds a reference so the native dialog stays alive while open
+        let chooser_for_response = chooser.clone();
+        chooser.connect_response(move |_, response| {
+            if response != gtk4::ResponseType::Accept {
+                return;
+            }
+            if let Some(path) = chooser_for_response.file().and_then(|f| f.path()) {
+                set_path(Some(path.to_string_lossy().to_string()));
+            }
+        });
+        chooser.show();
+    });
+    row
+}
+
+/// Dialog that resolves which keg a file on disk belongs to
+pub fn show_file_owner_dialog(parent: &impl IsA<Window>) {
+    let dialog = Window::builder()
+        .title("Which Package Owns a File?")
+        .default_width(500)
+        .modal(true)
+        .transient_for(parent)
+        .build();
+
+    let vbox = Box::new(Orientation::Vertical, 10);
+    vbox.set_margin_start(20);
+    vbox.set_margin_end(20);
+    vbox.set_margin_top(20);
+    vbox.set_margin_bottom(20);
+
+    let hint = Label::new(Some("Enter or choose a path, e.g. a binary found on your PATH."));
+    hint.set_halign(gtk4::Align::Start);
+    hint.set_wrap(true);
+    vbox.append(&hint);
+
+    let path_box = Box::new(Orientation::Horizontal, 10);
+    let path_entry = gtk4::Entry::new();
+    path_entry.set_placeholder_text(Some("/home/linuxbrew/.linuxbrew/bin/wget"));
+    path_entry.set_hexpand(true);
+    path_box.append(&path_entry);
+
+    let choose_btn = Button::with_label("Choose...");
+    path_box.append(&choose_btn);
+
+    let lookup_btn = Button::with_label("Look Up");
+    lookup_btn.add_css_class("suggested-action");
+    path_box.append(&lookup_btn);
+    vbox.append(&path_box);
+
+    let result_label = Label::new(None);
+    result_label.set_halign(gtk4::Align::Start);
+    result_label.set_wrap(true);
+    result_label.set_selectable(true);
+    vbox.append(&result_label);
+
+    dialog.set_child(Some(&vbox));
+
+    // File chooser fills in the entry and looks the file up straight away
+    let dialog_for_choose = dialog.clone();
+    let entry_for_choose = path_entry.clone();
+    choose_btn.connect_clicked(move |_| {
+        let chooser = gtk4::FileChooserNative::new(
+            Some("Choose a File"),
+            Some(&dialog_for_choose),
+            gtk4::FileChooserAction::Open,
+            Some("Choose"),
+            Some("Cancel"),
+        );
+        let entry = entry_for_choose.clone();
+        // The handler holds a reference so the native dialog stays alive while open
+        let chooser_for_response = chooser.clone();
+        chooser.connect_response(move |_, response| {
+            if response == gtk4::ResponseType::Accept {
+                if let Some(path) = chooser_for_response.file().and_then(|f| f.path()) {
+                    entry.set_text(&path.to_string_lossy());
+                    entry.emit_activate();
+                }
+            }
+        });
+        chooser.show();
+    });
+
+    let result_for_lookup = result_label.clone();
+    let lookup = move |path: String| {
+        let path = path.trim().to_string();
+        if path.is_empty() {
+            return;
+        }
+        result_for_lookup.set_text("Looking up...");
+
+        let result_label = result_for_lookup.clone();
+        glib::spawn_future_local(async move {
+            let path_for_task = path.clone();
+            let result = gtk4::gio::spawn_blocking(move || {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                rt.block_on(brew::find_file_owner(&path_for_task))
+            })
+            .await
+            .expect("Background task failed");
+
+            match result {
+                Ok(Some(owner)) => {
+                    let kind = if owner.cask { "cask" } else { "formula" };
+                    result_label.set_text(&format!(
+                        "{} belongs to the {} {} (version {})",
+                        path, kind, owner.package, owner.version
+                    ));
+                }
+                Ok(None) => {
+                    result_label.set_text(&format!("{} is not managed by Homebrew", path));
+                }
+                Err(e) => {
+                    result_label.set_text(&format!("Error: {}", e.user_message()));
+                }
+            }
+        });
+    };
+
+    let lookup_for_entry = lookup.clone();
+    path_entry.connect_activate(move |entry| {
+        lookup_for_entry(entry.text().to_string());
+    });
+
+    let entry_for_btn = path_entry.clone();
+    lookup_btn.connect_clicked(move |_| {
+        lookup(entry_for_btn.text().to_string());
+    });
+
+    dialog.present();
+}
+
+/// Dialog for choosing the brew executable, for installs outside PATH.
+/// The path is validated before it is saved.
+pub fn show_brew_path_dialog(parent: &impl IsA<Window>) {
+    let dialog = Window::builder()
+        .title("Homebrew Location")
+        .default_width(500)
+        .modal(true)
+        .transient_for(parent)
+        .build();
+
+    let vbox = Box::new(Orientation::Vertical, 10);
+    vbox.set_margin_start(20);
+    vbox.set_margin_end(20);
+    vbox.set_margin_top(20);
+    vbox.set_margin_bottom(20);
+
+    let hint = Label::new(Some(
+        "Path to the brew executable. Leave empty to use the brew found on your PATH.",
+    ));
+    hint.set_halign(gtk4::Align::Start);
+    hint.set_wrap(true);
+    vbox.append(&hint);
+
+    let path_entry = gtk4::Entry::new();
+    path_entry.set_placeholder_text(Some("/home/linuxbrew/.linuxbrew/bin/brew"));
+    path_entry.set_text(&settings::get().brew_path.unwrap_or_default());
+    vbox.append(&path_entry);
+
+    let status_label = Label::new(Some(&format!("Currently using: {}", brew::brew_program().display())));
+    status_label.set_halign(gtk4::Align::Start);
+    status_label.set_wrap(true);
+    status_label.add_css_class("dim-label");
+    vbox.append(&status_label);
+
+    let button_box = Box::new(Orientation::Horizontal, 10);
+    button_box.set_halign(gtk4::Align::End);
+    button_box.set_margin_top(10);
+
+    let cancel_btn = Button::with_label("Cancel");
+    let save_btn = Button::with_label("Save");
+    save_btn.add_css_class("suggested-action");
+    button_box.append(&cancel_btn);
+    button_box.append(&save_btn);
+    vbox.append(&button_box);
+
+    dialog.set_child(Some(&vbox));
+
+    let dialog_for_cancel = dialog.clone();
+    cancel_btn.connect_clicked(move |_| dialog_for_cancel.close());
+
+    let dialog_for_save = dialog.clone();
+    let entry_for_save = path_entry.clone();
+    save_btn.connect_clicked(move |btn| {
+        let path = entry_for_save.text().trim().to_string();
+        if path.is_empty() {
+            settings::update(|s| s.brew_path = None);
+            brew::set_brew_path(None);
+            dialog_for_save.close();
+            return;
+        }
+
+        btn.set_sensitive(false);
+        status_label.set_text("Checking...");
+
+        let btn = btn.clone();
+        let dialog = dialog_for_save.clone();
+        let status_label = status_label.clone();
+        glib::spawn_future_local(async move {
+            let path_for_task = path.clone();
+            let result = gtk4::gio::spawn_blocking(move || {
+                brew::validate_brew_path(std::path::Path::new(&path_for_task))
+            })
+            .await
+            .expect("Background task failed");
+            btn.set_sensitive(true);
+
+            match result {
+                Ok(_) => {
+                    brew::set_brew_path(Some(std::path::PathBuf::from(&path)));
+                    settings::update(|s| s.brew_path = Some(path));
+                    dialog.close();
+                }
+                Err(e) => {
+                    status_label.set_text(&format!("Error: {}", e.user_message()));
+                }
+            }
+        });
+    });
+
+    dialog.present();
+}
+
+// Keys from `brew config` shown at the top of the About Homebrew dialog
+const BREW_CONFIG_HIGHLIGHTS: &[&str] = &["HOMEBREW_VERSION", "HOMEBREW_PREFIX", "Core tap HEAD", "CPU", "Clang"];
+
+/// Dialog showing parsed `brew config` output, for bug reports
+pub fn show_brew_config_dialog(parent: &impl IsA<Window>) {
+    let dialog = Window::builder()
+        .title("About Homebrew")
+        .default_width(600)
+        .default_height(500)
+        .modal(true)
+        .transient_for(parent)
+        .build();
+
+    let vbox = Box::new(Orientation::Vertical, 10);
+    vbox.set_margin_start(20);
+    vbox.set_margin_end(20);
+    vbox.set_margin_top(20);
+    vbox.set_margin_bottom(20);
+
+    let header_box = Box::new(Orientation::Horizontal, 10);
+    let header = Label::new(Some("Homebrew Configuration"));
+    header.add_css_class("title-2");
+    header.set_hexpand(true);
+    header.set_halign(gtk4::Align::Start);
+    header_box.append(&header);
+
+    let spinner = Spinner::new();
+    spinner.set_spinning(true);
+    set_accessible_label(&spinner, "Loading the configuration");
+    header_box.append(&spinner);
+
+    let copy_btn = Button::with_label("Copy");
+    copy_btn.set_tooltip_text(Some("Copy the full brew config output, e.g. for a bug report"));
+    copy_btn.set_sensitive(false);
+    header_box.append(&copy_btn);
+    vbox.append(&header_box);
+
+    let scroll = ScrolledWindow::new();
+    scroll.set_vexpand(true);
+    let grid = gtk4::Grid::new();
+    grid.set_row_spacing(6);
+    grid.set_column_spacing(16);
+    scroll.set_child(Some(&grid));
+    vbox.append(&scroll);
+
+    dialog.set_child(Some(&vbox));
+    dialog.present();
+
+    let config_text: Rc<RefCell<String>> = Rc::new(RefCell::new(String::new()));
+
+    let config_for_copy = config_text.clone();
+    copy_btn.connect_clicked(move |btn| {
+        copy_to_clipboard(btn, &config_for_copy.borrow());
+    });
+
+    glib::spawn_future_local(async move {
+        let result = gtk4::gio::spawn_blocking(move || {
+            let rt = tokio::runtime::Runtime::new().unwrap();
+            rt.block_on(brew::get_brew_config())
+        })
+        .await
+        .expect("Background task failed");
+        spinner.set_spinning(false);
+        spinner.set_visible(false);
+
+        match result {
+            Ok(config) => {
+                // Highlighted keys first, then everything else in brew's order
+                let mut ordered: Vec<&(String, String)> = BREW_CONFIG_HIGHLIGHTS
+                    .iter()
+                    .filter_map(|key| config.iter().find(|(k, _)| k == key))
+                    .collect();
+                ordered.extend(config.iter().filter(|(k, _)| !BREW_CONFIG_HIGHLIGHTS.contains(&k.as_str())));
+
+                for (row, (key, value)) in ordered.iter().enumerate() {
+                    let key_label = Label::new(Some(key));
+                    key_label.set_halign(gtk4::Align::Start);
+                    key_label.set_valign(gtk4::Align::Start);
+                    key_label.add_css_class("dim-label");
+                    if BREW_CONFIG_HIGHLIGHTS.contains(&key.as_str()) {
+                        key_label.add_css_class("heading");
+                    }
+
+                    let value_label = Label::new(Some(value));
+                    value_label.set_halign(gtk4::Align::Start);
+                    value_label.set_hexpand(true);
+                    value_label.set_wrap(true);
+                    value_label.set_selectable(true);
+
+                    grid.attach(&key_label, 0, row as i32, 1, 1);
+                    grid.attach(&value_label, 1, row as i32, 1, 1);
+                }
+
+                *config_text.borrow_mut() = config
+                    .iter()
+                    .map(|(k, v)| format!("{}: {}", k, v))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                copy_btn.set_sensitive(true);
+            }
+            Err(e) => {
+                let error_label = Label::new(Some(&format!("Error: {}", e.user_message())));
+                error_label.set_wrap(true);
+                grid.attach(&error_label, 0, 0, 2, 1);
+            }
+        }
+    });
+}
@@ -0,0 +1,672 @@
+use crate::app_state::AppState;
+use crate::brew;
+use crate::events::{self, AppEvent};
+use crate::history::{self, Operation};
+use crate::jobs;
+use crate::{
+    copy_to_clipboard, create_copy_command_button, create_download_progress, create_refresh_button,
+    create_status_label, set_accessible_label, set_labelled_by, set_view_busy, show_error_dialog, show_error_details,
+    track_download_progress,
+};
+#[cfg(feature = "terminal")]
+use crate::show_terminal_dialog;
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4::{Box, Button, CheckButton, Expander, Label, ListBox, ListBoxRow, Orientation, ScrolledWindow, Spinner};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+pub fn create_updates_view(state: &AppState) -> Box {
+    let view = Box::new(Orientation::Vertical, 10);
+    view.set_margin_start(10);
+    view.set_margin_end(10);
+    view.set_margin_top(10);
+    view.set_margin_bottom(10);
+
+    // Header
+    let header_box = Box::new(Orientation::Horizontal, 10);
+    let header = Label::new(Some("Available Updates"));
+    header.add_css_class("title-2");
+    header_box.append(&header);
+
+    let spinner = Spinner::new();
+    spinner.set_spinning(true);
+    set_accessible_label(&spinner, "Checking for updates");
+    header_box.append(&spinner);
+
+    let status_label = create_status_label(Some("Checking for updates..."));
+    status_label.set_hexpand(true);
+    status_label.set_halign(gtk4::Align::Start);
+    header_box.append(&status_label);
+    header_box.append(&create_refresh_button("updates.refresh"));
+
+    // Upgrade Selected button
+    let upgrade_selected_btn = Button::with_label("Upgrade Selected");
+    upgrade_selected_btn.add_css_class("suggested-action");
+    upgrade_selected_btn.set_visible(false);
+    header_box.append(&upgrade_selected_btn);
+
+    // Upgrade All button
+    let upgrade_all_btn = Button::with_label("Upgrade All");
+    upgrade_all_btn.set_visible(false);
+    header_box.append(&upgrade_all_btn);
+
+    // Copies `brew upgrade` for the selected packages, or for everything if none are selected
+    let copy_cmd_btn = create_copy_command_button();
+    upgrade_all_btn.bind_property("visible", &copy_cmd_btn, "visible").sync_create().build();
+    header_box.append(&copy_cmd_btn);
+
+    #[cfg(feature = "terminal")]
+    let terminal_btn = {
+        let btn = Button::with_label("Upgrade in Terminal");
+        btn.set_tooltip_text(Some("Run brew upgrade interactively, e.g. for casks that ask for a password"));
+        upgrade_all_btn.bind_property("visible", &btn, "visible").sync_create().build();
+        header_box.append(&btn);
+        btn
+    };
+
+    view.append(&header_box);
+
+    // List of outdated packages
+    let scroll = ScrolledWindow::new();
+    scroll.set_vexpand(true);
+    let list_box = ListBox::new();
+    list_box.set_selection_mode(gtk4::SelectionMode::None);
+    list_box.add_css_class("boxed-list");
+    scroll.set_child(Some(&list_box));
+    view.append(&scroll);
+
+    // Upgrade status, with a retry button after a batch upgrade had failures
+    let footer_box = Box::new(Orientation::Horizontal, 10);
+    let upgrade_status = create_status_label(None);
+    upgrade_status.set_halign(gtk4::Align::Start);
+    upgrade_status.set_hexpand(true);
+    footer_box.append(&upgrade_status);
+
+    let retry_failed_btn = Button::with_label("Retry Failed");
+    retry_failed_btn.set_visible(false);
+    footer_box.append(&retry_failed_btn);
+    view.append(&footer_box);
+
+    let upgrade_progress = create_download_progress();
+    view.append(&upgrade_progress);
+
+    // Progress of an "Upgrade Selected" batch, shown while it runs
+    let batch_box = Box::new(Orientation::Vertical, 4);
+    batch_box.set_visible(false);
+    let batch_progress = gtk4::ProgressBar::new();
+    batch_progress.set_show_text(true);
+    batch_box.append(&batch_progress);
+    let batch_eta = Label::new(None);
+    batch_eta.set_halign(gtk4::Align::Start);
+    batch_eta.add_css_class("dim-label");
+    batch_eta.add_css_class("caption");
+    batch_box.append(&batch_eta);
+    view.append(&batch_box);
+
+    // Store checkboxes for access
+    let checkboxes: Rc<RefCell<Vec<(String, CheckButton)>>> = Rc::new(RefCell::new(Vec::new()));
+
+    // Load outdated packages; runs again when another view installs or removes something
+    let list_box_for_load = list_box.clone();
+    let spinner_for_load = spinner.clone();
+    let status_label_for_load = status_label.clone();
+    let upgrade_all_btn_for_load = upgrade_all_btn.clone();
+    let upgrade_selected_btn_for_load = upgrade_selected_btn.clone();
+    let checkboxes_for_load = checkboxes.clone();
+    let state_for_load = state.clone();
+    let load_outdated = Rc::new(move || {
+        let list_box_clone = list_box_for_load.clone();
+        let spinner_clone = spinner_for_load.clone();
+        let status_label_clone = status_label_for_load.clone();
+        let upgrade_all_btn_clone = upgrade_all_btn_for_load.clone();
+        let upgrade_selected_btn_clone = upgrade_selected_btn_for_load.clone();
+        let checkboxes_clone = checkboxes_for_load.clone();
+        let state_clone = state_for_load.clone();
+        spinner_clone.set_visible(true);
+        spinner_clone.set_spinning(true);
+
+        glib::spawn_future_local(async move {
+            set_view_busy(&list_box_clone, true);
+            let result = gtk4::gio::spawn_blocking(move || {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                rt.block_on(brew::get_outdated_packages())
+            })
+            .await
+            .expect("Background task failed");
+            set_view_busy(&list_box_clone, false);
+
+            spinner_clone.set_spinning(false);
+            spinner_clone.set_visible(false);
+
+            match result {
+                Ok(packages) => {
+                    while let Some(child) = list_box_clone.first_child() {
+                        list_box_clone.remove(&child);
+                    }
+                    let mut cbs = checkboxes_clone.borrow_mut();
+                    cbs.clear();
+
+                    upgrade_all_btn_clone.set_visible(!packages.is_empty());
+                    upgrade_selected_btn_clone.set_visible(!packages.is_empty());
+                    if packages.is_empty() {
+                        status_label_clone.set_text("All packages are up to date!");
+                    } else {
+                        status_label_clone.set_text(&format!("{} updates available", packages.len()));
+
+                        for pkg_name in packages {
+                            let error = state_clone.upgrade_error(&pkg_name);
+                            let (row, checkbox) = create_update_row_with_checkbox(&pkg_name, error.as_deref());
+                            cbs.push((pkg_name, checkbox));
+                            list_box_clone.append(&row);
+                        }
+                    }
+                }
+                Err(e) => {
+                    status_label_clone.set_text(&format!("Error: {}", e.user_message()));
+                }
+            }
+        });
+    });
+    load_outdated();
+    let load_outdated_for_events = load_outdated.clone();
+    events::subscribe(&list_box, move |event| {
+        if matches!(event, AppEvent::PackageInstalled(_) | AppEvent::PackageUninstalled(_)) {
+            load_outdated_for_events();
+        }
+    });
+
+    #[cfg(feature = "terminal")]
+    {
+        let upgrade_status_for_terminal = upgrade_status.clone();
+        terminal_btn.connect_clicked(move |btn| {
+            let status = upgrade_status_for_terminal.clone();
+            show_terminal_dialog(btn, Operation::Upgrade, None, brew::upgrade_args(None), move |success| {
+                status.set_text(if success { "All packages upgraded successfully!" } else { "Upgrade failed" });
+                if success {
+                    events::emit(AppEvent::PackagesUpgraded);
+                }
+            });
+        });
+    }
+
+    // Copy command handler
+    let checkboxes_for_copy = checkboxes.clone();
+
+    copy_cmd_btn.connect_clicked(move |btn| {
+        let cbs = checkboxes_for_copy.borrow();
+        let selected: Vec<&str> = cbs
+            .iter()
+            .filter(|(_, cb)| cb.is_active())
+            .map(|(name, _)| name.as_str())
+            .collect();
+        copy_to_clipboard(btn, &brew::command_line("upgrade", &selected));
+    });
+
+    // Upgrade Selected handler
+    let checkboxes_for_selected = checkboxes.clone();
+    let upgrade_status_selected = upgrade_status.clone();
+    let list_box_for_selected = list_box.clone();
+    let status_for_selected = status_label.clone();
+    let upgrade_all_for_selected = upgrade_all_btn.clone();
+    let upgrade_selected_for_handler = upgrade_selected_btn.clone();
+    let state_for_selected = state.clone();
+    let retry_for_selected = retry_failed_btn.clone();
+    let batch_box_for_selected = batch_box.clone();
+    let batch_progress_for_selected = batch_progress.clone();
+    let batch_eta_for_selected = batch_eta.clone();
+
+    upgrade_selected_btn.connect_clicked(move |btn| {
+        // Debug: show all checkbox states
+        {
+            let cbs = checkboxes_for_selected.borrow();
+            eprintln!("Checkbox states ({} total):", cbs.len());
+            for (name, cb) in cbs.iter() {
+                eprintln!("  {} = {}", name, cb.is_active());
+            }
+        }
+
+        let selected: Vec<String> = checkboxes_for_selected
+            .borrow()
+            .iter()
+            .filter(|(_, cb)| cb.is_active())
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        eprintln!("Selected for upgrade: {:?}", selected);
+
+        if selected.is_empty() {
+            upgrade_status_selected.set_text("No packages selected");
+            return;
+        }
+
+        btn.set_sensitive(false);
+        retry_for_selected.set_visible(false);
+        let total = selected.len();
+        let selected_for_title = selected.clone();
+
+        let status = upgrade_status_selected.clone();
+        let list_box = list_box_for_selected.clone();
+        let header_status = status_for_selected.clone();
+        let btn_clone = btn.clone();
+        let checkboxes_clone = checkboxes_for_selected.clone();
+        let upgrade_all_clone = upgrade_all_for_selected.clone();
+        let upgrade_selected_clone = upgrade_selected_for_handler.clone();
+        let state = state_for_selected.clone();
+        let retry_btn = retry_for_selected.clone();
+        let batch_box = batch_box_for_selected.clone();
+        let batch_progress = batch_progress_for_selected.clone();
+        let batch_eta = batch_eta_for_selected.clone();
+        upgrade_status_selected.set_text(if jobs::is_busy() { "Queued..." } else { "" });
+        let on_removed = {
+            let status = status.clone();
+            let btn = btn.clone();
+            move || {
+                status.set_text("");
+                btn.set_sensitive(true);
+            }
+        };
+
+        // Upgrade packages one by one with progress updates
+        let job = async move {
+            set_view_busy(&btn_clone, true);
+            let mut succeeded = Vec::new();
+            let mut failed: Vec<(String, String)> = Vec::new();
+            let mut report = brew::UpgradeReport::default();
+
+            // Share of the batch done so far, counting the current package's download
+            let done = Rc::new(Cell::new(0.0));
+            let running = Rc::new(Cell::new(true));
+            let batch_started = Instant::now();
+            status.set_text("");
+            batch_progress.set_fraction(0.0);
+            batch_eta.set_text(&eta_text(Duration::ZERO, 0.0));
+            batch_box.set_visible(true);
+            {
+                let batch_eta = batch_eta.clone();
+                let done = done.clone();
+                let running = running.clone();
+                glib::timeout_add_seconds_local(1, move || {
+                    if !running.get() {
+                        return glib::ControlFlow::Break;
+                    }
+                    batch_eta.set_text(&eta_text(batch_started.elapsed(), done.get()));
+                    glib::ControlFlow::Continue
+                });
+            }
+
+            for (i, pkg) in selected.iter().enumerate() {
+                batch_progress.set_text(Some(&format!("Upgrading {} ({}/{})", pkg, i + 1, total)));
+
+                let (progress_tx, mut progress_rx) = tokio::sync::mpsc::unbounded_channel::<f64>();
+                let batch_progress_clone = batch_progress.clone();
+                let done_clone = done.clone();
+                glib::spawn_future_local(async move {
+                    while let Some(percent) = progress_rx.recv().await {
+                        // Each dependency's download starts again from 0%, which mustn't move the bar back
+                        let fraction = (i as f64 + percent / 100.0) / total as f64;
+                        done_clone.set(done_clone.get().max(fraction.min((i + 1) as f64 / total as f64)));
+                        batch_progress_clone.set_fraction(done_clone.get());
+                    }
+                });
+
+                let started = Instant::now();
+                let pkg_clone = pkg.clone();
+                let result = gtk4::gio::spawn_blocking(move || {
+                    let rt = tokio::runtime::Runtime::new().unwrap();
+                    rt.block_on(brew::upgrade_packages_with_progress(Some(&pkg_clone), progress_tx))
+                })
+                .await
+                .expect("Background task failed");
+                history::record(
+                    Operation::Upgrade,
+                    Some(pkg),
+                    result.as_ref().map(|r| r.output.as_str()),
+                    started.elapsed(),
+                );
+
+                match result {
+                    Ok(pkg_report) => {
+                        // A pinned package stays outdated, so it is neither a success nor a failure
+                        if !pkg_report.pinned.contains(pkg) {
+                            succeeded.push(pkg.clone());
+                        }
+                        report.upgraded.extend(pkg_report.upgraded);
+                        report.skipped.extend(pkg_report.skipped);
+                        report.pinned.extend(pkg_report.pinned);
+                    }
+                    Err(e) => failed.push((pkg.clone(), brew::sanitize_error(&e.to_string()))),
+                }
+                done.set(done.get().max((i + 1) as f64 / total as f64));
+                batch_progress.set_fraction(done.get());
+            }
+            running.set(false);
+            batch_box.set_visible(false);
+
+            // Clear the list UI
+            while let Some(child) = list_box.first_child() {
+                list_box.remove(&child);
+            }
+
+            // Get remaining packages (failed ones + ones not attempted)
+            let remaining: Vec<String> = {
+                let cbs = checkboxes_clone.borrow();
+                cbs.iter()
+                    .filter(|(name, _)| !succeeded.contains(name))
+                    .map(|(name, _)| name.clone())
+                    .collect()
+            };
+
+            state.record_upgrade_results(&succeeded, &failed);
+
+            if !succeeded.is_empty() {
+                events::emit(AppEvent::PackagesUpgraded);
+            }
+
+            // Show results
+            if failed.is_empty() {
+                status.set_text(&report.summary());
+            } else {
+                let failed_names: Vec<&str> = failed.iter().map(|(n, _)| n.as_str()).collect();
+                let error_msg = failed.iter().map(|(n, e)| format!("{}: {}", n, e)).collect::<Vec<_>>().join("\n\n");
+                status.set_text(&format!(
+                    "{}; {} failed: {}",
+                    report.summary(),
+                    failed.len(),
+                    failed_names.join(", ")
+                ));
+                show_error_details(
+                    &btn_clone,
+                    &format!("{} of {} upgrades failed", failed.len(), total),
+                    &format!("Could not upgrade {}.", failed_names.join(", ")),
+                    &error_msg,
+                );
+                retry_btn.set_label(&format!("Retry Failed ({})", failed.len()));
+                retry_btn.set_visible(true);
+            }
+
+            // Rebuild checkboxes store and UI
+            {
+                let mut cbs = checkboxes_clone.borrow_mut();
+                cbs.clear();
+
+                if remaining.is_empty() {
+                    header_status.set_text("All packages are up to date!");
+                    upgrade_all_clone.set_visible(false);
+                    upgrade_selected_clone.set_visible(false);
+                } else {
+                    header_status.set_text(&format!("{} updates available", remaining.len()));
+                    for name in remaining {
+                        let (row, new_cb) = create_update_row_with_checkbox(&name, state.upgrade_error(&name).as_deref());
+                        list_box.append(&row);
+                        cbs.push((name, new_cb));
+                    }
+                }
+            }
+            btn_clone.set_sensitive(true);
+            set_view_busy(&btn_clone, false);
+            if failed.is_empty() {
+                Ok(report.summary())
+            } else {
+                Err(failed.iter().map(|(n, e)| format!("{}: {}", n, e)).collect::<Vec<_>>().join("\n\n"))
+            }
+        };
+        let title = match selected_for_title.as_slice() {
+            [name] => format!("Upgrade {}", name),
+            _ => format!("Upgrade {} packages", total),
+        };
+        jobs::enqueue(title, job, on_removed);
+    });
+
+    // Retry Failed: select only the packages that failed last time and upgrade them again
+    let checkboxes_for_retry = checkboxes.clone();
+    let state_for_retry = state.clone();
+    let upgrade_selected_for_retry = upgrade_selected_btn.clone();
+    retry_failed_btn.connect_clicked(move |_| {
+        for (name, cb) in checkboxes_for_retry.borrow().iter() {
+            cb.set_active(state_for_retry.upgrade_error(name).is_some());
+        }
+        upgrade_selected_for_retry.emit_clicked();
+    });
+
+    // Upgrade All handler
+    let upgrade_status_clone = upgrade_status.clone();
+    let list_box_for_upgrade = list_box.clone();
+    let status_for_upgrade = status_label.clone();
+    let upgrade_selected_for_all = upgrade_selected_btn.clone();
+    let retry_for_all = retry_failed_btn.clone();
+    let load_outdated_for_all = load_outdated.clone();
+    let progress_for_all = upgrade_progress.clone();
+
+    upgrade_all_btn.connect_clicked(move |btn| {
+        btn.set_sensitive(false);
+        retry_for_all.set_visible(false);
+        upgrade_status_clone.set_text(if jobs::is_busy() { "Queued..." } else { "Upgrading all packages..." });
+
+        let status = upgrade_status_clone.clone();
+        let list_box = list_box_for_upgrade.clone();
+        let header_status = status_for_upgrade.clone();
+        let btn_clone = btn.clone();
+        let upgrade_selected_clone = upgrade_selected_for_all.clone();
+        let load_outdated = load_outdated_for_all.clone();
+        let progress_tx = track_download_progress(&progress_for_all);
+        let on_removed = {
+            let status = status.clone();
+            let btn = btn.clone();
+            move || {
+                status.set_text("");
+                btn.set_sensitive(true);
+            }
+        };
+
+        let job = async move {
+            status.set_text("Upgrading all packages...");
+            set_view_busy(&btn_clone, true);
+            let started = Instant::now();
+            let result = gtk4::gio::spawn_blocking(move || {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                rt.block_on(brew::upgrade_packages_with_progress(None, progress_tx))
+            })
+            .await
+            .expect("Background task failed");
+            set_view_busy(&btn_clone, false);
+            history::record(
+                Operation::Upgrade,
+                None,
+                result.as_ref().map(|r| r.output.as_str()),
+                started.elapsed(),
+            );
+            let outcome = jobs::outcome(&result.as_ref().map(|r| r.output.as_str()));
+
+            match result {
+                Ok(report) => {
+                    status.set_text(&report.summary());
+                    events::emit(AppEvent::PackagesUpgraded);
+                    if report.pinned.is_empty() {
+                        while let Some(child) = list_box.first_child() {
+                            list_box.remove(&child);
+                        }
+                        header_status.set_text("All packages are up to date!");
+                        btn_clone.set_visible(false);
+                        upgrade_selected_clone.set_visible(false);
+                    } else {
+                        // Pinned packages are still outdated; list what is left
+                        btn_clone.set_sensitive(true);
+                        load_outdated();
+                    }
+                }
+                Err(e) => {
+                    status.set_text("Upgrade failed");
+                    show_error_dialog(&btn_clone, "Could not upgrade packages", &e);
+                    btn_clone.set_sensitive(true);
+                }
+            }
+            outcome
+        };
+        jobs::enqueue("Upgrade all packages", job, on_removed);
+    });
+
+    let refresh_action = gtk4::gio::SimpleAction::new("refresh", None);
+    refresh_action.connect_activate(move |_, _| load_outdated());
+    let actions = gtk4::gio::SimpleActionGroup::new();
+    actions.add_action(&refresh_action);
+    view.insert_action_group("updates", Some(&actions));
+
+    view
+}
+
+/// Elapsed time of a batch and, once part of it is `done` (0..1), an estimate of the rest
+fn eta_text(elapsed: Duration, done: f64) -> String {
+    let elapsed_label = history::format_duration(elapsed);
+    if done <= 0.0 {
+        return format!("Elapsed {}", elapsed_label);
+    }
+    let remaining = Duration::from_secs_f64(elapsed.as_secs_f64() * (1.0 - done) / done);
+    format!("Elapsed {} · about {} left", elapsed_label, history::format_duration(remaining))
+}
+
+/// Row for an outdated package. `error` is the output of its last failed upgrade, if any,
+/// and adds a button to inspect it.
+fn create_update_row_with_checkbox(name: &str, error: Option<&str>) -> (ListBoxRow, CheckButton) {
+    let row = ListBoxRow::new();
+
+    let vbox = Box::new(Orientation::Vertical, 6);
+    vbox.set_margin_start(12);
+    vbox.set_margin_end(12);
+    vbox.set_margin_top(8);
+    vbox.set_margin_bottom(8);
+
+    let hbox = Box::new(Orientation::Horizontal, 12);
+    vbox.append(&hbox);
+
+    let checkbox = CheckButton::new();
+    hbox.append(&checkbox);
+
+    let label = Label::new(Some(name));
+    label.set_halign(gtk4::Align::Start);
+    label.set_hexpand(true);
+    label.add_css_class("heading");
+    hbox.append(&label);
+    set_labelled_by(&row, &label);
+    set_labelled_by(&checkbox, &label);
+
+    if let Some(error) = error {
+        let error_btn = Button::with_label("⚠ Failed");
+        error_btn.add_css_class("flat");
+        error_btn.set_tooltip_text(Some("Show the output of the failed upgrade"));
+        set_accessible_label(&error_btn, "Last upgrade failed, show its output");
+        let heading = format!("Could not upgrade {}", name);
+        let error = error.to_string();
+        error_btn.connect_clicked(move |btn| {
+            show_error_details(btn, &heading, "The last upgrade attempt failed.", &error);
+        });
+        hbox.append(&error_btn);
+    }
+
+    let update_icon = Label::new(Some("⬆"));
+    update_icon.add_css_class("dim-label");
+    hbox.append(&update_icon);
+
+    vbox.append(&create_release_notes_expander(name));
+
+    row.set_child(Some(&vbox));
+    (row, checkbox)
+}
+
+/// Expander with the upstream release notes for an update, fetched from GitHub when first opened
+fn create_release_notes_expander(name: &str) -> Expander {
+    let expander = Expander::new(Some("Release notes"));
+    let content = Box::new(Orientation::Vertical, 6);
+    content.set_margin_start(24);
+    content.set_margin_top(6);
+    expander.set_child(Some(&content));
+
+    let name = name.to_string();
+    let loaded = Rc::new(Cell::new(false));
+    expander.connect_expanded_notify(move |expander| {
+        if !expander.is_expanded() || loaded.replace(true) {
+            return;
+        }
+
+        while let Some(child) = content.first_child() {
+            content.remove(&child);
+        }
+        let loading = Label::new(Some("Loading release notes..."));
+        loading.set_halign(gtk4::Align::Start);
+        loading.add_css_class("dim-label");
+        content.append(&loading);
+
+        let content_clone = content.clone();
+        let loaded_clone = loaded.clone();
+        let name_clone = name.clone();
+        glib::spawn_future_local(async move {
+            let result = gtk4::gio::spawn_blocking(move || {
+                let rt = tokio::runtime::Runtime::new().unwrap();
+                rt.block_on(brew::get_release_notes(&name_clone))
+            })
+            .await
+            .expect("Background task failed");
+            content_clone.remove(&loading);
+
+            match result {
+                Ok(Some(notes)) => show_release_notes(&content_clone, &notes),
+                Ok(None) => content_clone.append(&dim_label(
+                    "This package isn't hosted on GitHub, so no release notes are available.",
+                )),
+                Err(e) => {
+                    content_clone.append(&dim_label(&format!("Error: {}", e.user_message())));
+                    // Let the next expand try again
+                    loaded_clone.set(false);
+                }
+            }
+        });
+    });
+
+    expander
+}
+
+fn show_release_notes(content: &Box, notes: &brew::ReleaseNotes) {
+    if notes.releases.is_empty() {
+        let range = match &notes.installed {
+            Some(installed) => format!("between {} and {}", installed, notes.available),
+            None => format!("for {}", notes.available),
+        };
+        content.append(&dim_label(&format!("{} has no GitHub releases {}.", notes.repo, range)));
+    }
+
+    for release in &notes.releases {
+        let heading = match &release.name {
+            Some(name) if name != &release.tag => format!("{} — {}", release.tag, name),
+            _ => release.tag.clone(),
+        };
+        let heading_label = Label::new(Some(&heading));
+        heading_label.set_halign(gtk4::Align::Start);
+        heading_label.add_css_class("heading");
+        content.append(&heading_label);
+
+        let body = if release.body.is_empty() { "No notes for this release." } else { &release.body };
+        let body_label = Label::new(Some(body));
+        body_label.set_halign(gtk4::Align::Start);
+        body_label.set_xalign(0.0);
+        body_label.set_wrap(true);
+        body_label.set_selectable(true);
+        content.append(&body_label);
+
+        let link = gtk4::LinkButton::with_label(&release.url, "View on GitHub");
+        link.set_halign(gtk4::Align::Start);
+        content.append(&link);
+    }
+
+    let all_link = gtk4::LinkButton::with_label(&notes.releases_url(), "All releases");
+    all_link.set_halign(gtk4::Align::Start);
+    content.append(&all_link);
+}
+
+fn dim_label(text: &str) -> Label {
+    let label = Label::new(Some(text));
+    label.set_halign(gtk4::Align::Start);
+    label.set_wrap(true);
+    label.add_css_class("dim-label");
+    label
+}